@@ -155,12 +155,19 @@ pub async fn activate_user(
 
   user.status = Set(status);
 
-  match user.update(&state.db).await {
-    Ok(_) => (),
+  let user = match user.update(&state.db).await {
+    Ok(user) => user,
     Err(e) => {
       log::error!("Error: {:?}", e);
       return StatusCode::INTERNAL_SERVER_ERROR;
     }
+  };
+
+  // a newly active tenant may match fee assignment rules
+  if user.status == UserStatus::Active {
+    if let Err(e) = crate::manager::targets::sync_rules(&state).await {
+      log::error!("Failed to sync fee assignment rules: {:?}", e);
+    }
   }
 
   StatusCode::OK
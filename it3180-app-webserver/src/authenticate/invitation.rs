@@ -0,0 +1,160 @@
+use crate::prelude::*;
+
+use crate::entities::{room_invitations, users};
+use crate::household::members::add_resident;
+use crate::manager::{invitations::find_usable_invitation, occupancy::room_accepts, targets};
+
+use sea_orm::TransactionTrait;
+
+use tags::AUTH;
+
+/// Register with a room invitation.
+#[utoipa::path(
+  post,
+  path = "/register/invitation",
+  description = "Đăng ký tài khoản người thuê bằng token trong liên kết mời do quản lý tạo. Tài khoản được kích hoạt và chuyển vào phòng
+  với vai trò ghi trong liên kết ngay lập tức. Mỗi liên kết chỉ dùng được một lần, trước khi hết hạn hoặc bị thu hồi.",
+  tag = AUTH,
+  responses(
+    (status = CREATED, description = "Registration successful"),
+    (status = BAD_REQUEST, description = "Registration failed, or the email does not match the invitation"),
+    (status = UNAUTHORIZED, description = "Invalid, expired, used or revoked invitation"),
+    (status = CONFLICT, description = "The household of the room changed since the invitation was created"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+  )
+)]
+pub(crate) async fn account_register_invitation(
+  State(state): State<AppState>,
+  Json(register_info): Json<InvitationRegisterInfo>,
+) -> Result<impl IntoResponse, impl IntoResponse> {
+  let db = &state.db;
+
+  let server_err = (StatusCode::INTERNAL_SERVER_ERROR, "Server error");
+
+  let InvitationRegisterInfo {
+    token,
+    name,
+    username,
+    email,
+    phone,
+    password,
+  } = register_info;
+
+  let invitation = find_usable_invitation(&state, &token)
+    .await
+    .map_err(|e| {
+      log::error!("Error: {:?}", e);
+      server_err
+    })?
+    .ok_or((StatusCode::UNAUTHORIZED, "Invalid invitation"))?;
+  if invitation
+    .email
+    .as_ref()
+    .is_some_and(|invited| !invited.eq_ignore_ascii_case(email.trim()))
+  {
+    return Err((
+      StatusCode::BAD_REQUEST,
+      "Email does not match the invitation",
+    ));
+  }
+
+  let user_exists = Users::find()
+    .filter(
+      Condition::any()
+        .add(users::Column::Username.eq(&username))
+        .add(users::Column::Email.eq(&email)),
+    )
+    .one(db)
+    .await
+    .map_err(|e| {
+      log::error!("Error: {:?}", e);
+      server_err
+    })?;
+  if user_exists.is_some() {
+    log::error!("User already exists");
+    return Err((StatusCode::BAD_REQUEST, "User already exists"));
+  }
+
+  // the room may have changed hands since the invitation was made
+  let room = Rooms::find_by_id(invitation.room_number)
+    .one(db)
+    .await
+    .map_err(|e| {
+      log::error!("Error: {:?}", e);
+      server_err
+    })?
+    .ok_or((StatusCode::UNAUTHORIZED, "Invalid invitation"))?;
+  if !room_accepts(&room, &invitation.role) {
+    return Err((StatusCode::CONFLICT, "Room is not available"));
+  }
+
+  let (salt, password) = super::hash_password(&password).map_err(|e| {
+    log::error!("Error hashing password: {:?}", e);
+    server_err
+  })?;
+  let new_user = users::ActiveModel {
+    name: Set(name),
+    username: Set(username.clone()),
+    email: Set(email),
+    phone: Set(phone),
+    salt: Set(salt),
+    password: Set(password),
+    role: Set(UserRole::Tenant),
+    status: Set(UserStatus::Active),
+    ..Default::default()
+  };
+
+  let now = chrono::Utc::now().naive_utc();
+  let txn = db.begin().await.map_err(|e| {
+    log::error!("Error: {:?}", e);
+    server_err
+  })?;
+  let user = new_user.insert(&txn).await.map_err(|e| {
+    log::error!("Error: {:?}", e);
+    server_err
+  })?;
+
+  // an invitation registers one account, even with concurrent requests
+  let used = RoomInvitations::update_many()
+    .col_expr(room_invitations::Column::UsedBy, Expr::value(user.id))
+    .col_expr(room_invitations::Column::UsedAt, Expr::value(now))
+    .filter(room_invitations::Column::Id.eq(invitation.id))
+    .filter(room_invitations::Column::UsedAt.is_null())
+    .exec(&txn)
+    .await
+    .map_err(|e| {
+      log::error!("Error: {:?}", e);
+      server_err
+    })?;
+  if used.rows_affected == 0 {
+    return Err((StatusCode::UNAUTHORIZED, "Invalid invitation"));
+  }
+
+  add_resident(&txn, room, user.id, invitation.role.clone(), now)
+    .await
+    .map_err(|e| {
+      log::error!("Error: {:?}", e);
+      server_err
+    })?;
+  txn.commit().await.map_err(|e| {
+    log::error!("Error: {:?}", e);
+    server_err
+  })?;
+
+  log::info!(
+    "User registered with invitation {}: {} moved into room {} as {:?}",
+    invitation.id,
+    username,
+    invitation.room_number,
+    invitation.role
+  );
+
+  // the room may now match the targets of fee assignment rules
+  if invitation.role == RoomMemberRole::Householder {
+    if let Err(e) = targets::sync_rules(&state).await {
+      log::error!("Failed to sync fee assignment rules: {:?}", e);
+    }
+  }
+
+  Ok(StatusCode::CREATED)
+}
@@ -1,9 +1,12 @@
+pub mod invitation;
 pub mod recover_password;
 pub mod validate_token;
 
 use crate::prelude::*;
 
-use crate::entities::{rooms, users};
+use crate::entities::{room_claims, users};
+
+use sea_orm::TransactionTrait;
 
 use axum::Json;
 use serde_json::json;
@@ -155,16 +158,25 @@ pub(crate) async fn account_login(
   Ok((StatusCode::OK, headers, json!(response).to_string()))
 }
 
+/// Hash a new password with a random salt. Returns the salt and the hash.
+pub(crate) fn hash_password(password: &str) -> Result<(Vec<u8>, Vec<u8>), argon2::Error> {
+  let argon2_config = argon2::Config::default();
+  let mut rng = fastrand::Rng::new();
+  let salt = (0..16).map(|_| rng.u8(..)).collect::<Vec<u8>>();
+  let password = argon2::hash_raw(password.as_bytes(), &salt, &argon2_config)?;
+  Ok((salt, password))
+}
+
 /// Register command.
 #[utoipa::path(
   post,
   path = "/register",
   description = "Đăng ký tài khoản mới với thông tin cần thiết (đưa vào dưới dạng JSON). Trả về trạng thái CREATED và thêm người dùng mới vào database
-  nếu đăng ký thành công.",
+  nếu đăng ký thành công. Người thuê có `room_id` gửi yêu cầu nhận phòng đó, phòng phải có sẵn và chỉ được giao khi quản lý chấp nhận yêu cầu.",
   tag = AUTH,
   responses(
     (status = CREATED, description = "Registration successful"),
-    (status = BAD_REQUEST, description = "Registration failed"),
+    (status = BAD_REQUEST, description = "Registration failed, or the room does not exist"),
   )
 )]
 pub(crate) async fn account_register(
@@ -201,11 +213,21 @@ pub(crate) async fn account_register(
     return Err((StatusCode::BAD_REQUEST, "User already exists"));
   }
 
+  // tenants claim an existing room, managers set rooms up
+  let room_number = room_id.filter(|_| role == UserRole::Tenant);
+  if let Some(room_number) = room_number {
+    let room = Rooms::find_by_id(room_number).one(db).await.map_err(|e| {
+      log::error!("Error: {:?}", e);
+      server_err
+    })?;
+    if room.is_none() {
+      log::error!("Room {} not found", room_number);
+      return Err((StatusCode::BAD_REQUEST, "Room not found"));
+    }
+  }
+
   // Hash the password
-  let argon2_config = argon2::Config::default();
-  let mut rng = fastrand::Rng::new();
-  let salt = (0..16).map(|_| rng.u8(..)).collect::<Vec<u8>>();
-  let password = argon2::hash_raw(password.as_bytes(), &salt, &argon2_config).map_err(|e| {
+  let (salt, password) = hash_password(&password).map_err(|e| {
     log::error!("Error hashing password: {:?}", e);
     (StatusCode::INTERNAL_SERVER_ERROR, "Server error")
   })?;
@@ -219,43 +241,37 @@ pub(crate) async fn account_register(
     role: Set(role.clone()),
     ..Default::default()
   };
-  let res = Users::insert(new_user).exec(db).await.map_err(|e| {
+  let txn = db.begin().await.map_err(|e| {
+    log::error!("Error: {:?}", e);
+    server_err
+  })?;
+  let res = Users::insert(new_user).exec(&txn).await.map_err(|e| {
     log::error!("Error: {:?}", e);
     (StatusCode::INTERNAL_SERVER_ERROR, "Server error")
   })?;
 
   log::info!("User registered: {}", username);
 
-  let user_id = res.last_insert_id;
-  // add new room to db and assign to user
-  if room_id.is_some() && role == UserRole::Tenant {
-    let room_id = room_id.unwrap();
-
-    // check if room exists
-    let room = Rooms::find()
-      .filter(rooms::Column::RoomNumber.eq(room_id))
-      .one(db)
-      .await
-      .map_err(|e| {
-        log::error!("Error: {:?}", e);
-        server_err.clone()
-      })?;
-    if room.is_some() {
-      return Err(server_err.clone());
-    }
-
-    // create new room
-    let new_room = rooms::ActiveModel {
-      room_number: Set(room_id.clone()),
-      tenant_id: Set(user_id),
+  // the room is only given once a manager approves the claim
+  if let Some(room_number) = room_number {
+    let claim = room_claims::ActiveModel {
+      user_id: Set(res.last_insert_id),
+      room_number: Set(room_number),
+      status: Set(RoomClaimStatus::Pending),
       ..Default::default()
     };
-    new_room.insert(db).await.map_err(|e| {
+    claim.insert(&txn).await.map_err(|e| {
       log::error!("Error: {:?}", e);
-      server_err.clone()
+      server_err
     })?;
+    log::info!("Room {} claimed by {}", room_number, username);
   }
 
+  txn.commit().await.map_err(|e| {
+    log::error!("Error: {:?}", e);
+    server_err
+  })?;
+
   Ok(StatusCode::CREATED)
 }
 
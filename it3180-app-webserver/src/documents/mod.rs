@@ -0,0 +1,560 @@
+//! Invoices and receipts. Generated documents are stored in the `documents` table so the
+//! exact same file can be downloaded again later.
+
+pub mod pdf;
+
+use axum_extra::extract::Query;
+use sea_orm::{DerivePartialModel, FromQueryResult, Order, QueryOrder};
+
+use crate::{
+  entities::{documents, fees_room_assignment, room_members},
+  household::members::find_membership,
+  prelude::*,
+};
+use pdf::{InvoiceData, InvoiceLine, ReceiptData};
+
+/// Building details printed at the top of every document.
+#[derive(Debug, Clone)]
+pub struct BuildingInfo {
+  pub name: String,
+  pub address: Option<String>,
+  pub phone: Option<String>,
+}
+
+#[derive(
+  Debug, Clone, serde::Serialize, serde::Deserialize, ToSchema, DerivePartialModel, FromQueryResult,
+)]
+#[sea_orm(entity = "Documents")]
+pub struct DocumentInfo {
+  pub id: i32,
+  #[schema(value_type = String, examples("invoice", "receipt"))]
+  pub kind: DocumentKind,
+  pub room_number: i32,
+  pub period: Option<String>,
+  pub transaction_id: Option<i32>,
+  pub file_name: String,
+  pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ToSchema)]
+pub struct GenerateInvoicesInfo {
+  /// Rooms to bill. All rooms with unpaid fees if not given.
+  pub room_numbers: Option<Vec<i32>>,
+  /// Billing month as `YYYY-MM`, defaults to the current month
+  pub period: Option<String>,
+}
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct DocumentFilter {
+  room_number: Option<i32>,
+  kind: Option<String>,
+}
+
+fn pdf_response(document: documents::Model) -> impl IntoResponse {
+  (
+    StatusCode::OK,
+    [
+      (header::CONTENT_TYPE, "application/pdf".to_string()),
+      (
+        header::CONTENT_DISPOSITION,
+        format!("attachment; filename=\"{}\"", document.file_name),
+      ),
+    ],
+    document.content,
+  )
+}
+
+/// Find the room a user lives in.
+async fn find_user_room(state: &AppState, user_id: i32) -> Result<room_members::Model, StatusCode> {
+  let room = find_membership(&state.db, user_id).await.map_err(|e| {
+    log::error!("Error: {:?}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+  })?;
+
+  room.ok_or(StatusCode::NOT_FOUND)
+}
+
+/// The first moment of a `YYYY-MM` billing month and of the month after it.
+fn period_range(period: &str) -> Option<(DateTime, DateTime)> {
+  let start = chrono::NaiveDate::parse_from_str(&format!("{}-01", period), "%Y-%m-%d").ok()?;
+  let end = start.checked_add_months(chrono::Months::new(1))?;
+  Some((start.and_hms_opt(0, 0, 0)?, end.and_hms_opt(0, 0, 0)?))
+}
+
+/// Build the invoice of a room from its unpaid fees due in the period and store it. A room is
+/// invoiced once per period: the invoice it already has is returned as is.
+/// Returns `None` if the room has nothing to pay.
+async fn create_invoice(
+  state: &AppState,
+  room_number: i32,
+  period: &str,
+  (start, end): (DateTime, DateTime),
+) -> Result<Option<DocumentInfo>, StatusCode> {
+  let room = Rooms::find_by_id(room_number)
+    .find_also_related(Users)
+    .one(&state.db)
+    .await
+    .map_err(|e| {
+      log::error!("Error: {:?}", e);
+      StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+  let Some((room, tenant)) = room else {
+    return Err(StatusCode::NOT_FOUND);
+  };
+
+  let existing = Documents::find()
+    .filter(documents::Column::Kind.eq(DocumentKind::Invoice))
+    .filter(documents::Column::RoomNumber.eq(room.room_number))
+    .filter(documents::Column::Period.eq(period))
+    .into_partial_model::<DocumentInfo>()
+    .one(&state.db)
+    .await
+    .map_err(|e| {
+      log::error!("Error: {:?}", e);
+      StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+  if existing.is_some() {
+    return Ok(existing);
+  }
+
+  let assignments = FeesRoomAssignment::find()
+    .filter(fees_room_assignment::Column::RoomNumber.eq(room.room_number))
+    .filter(fees_room_assignment::Column::IsPaid.eq(false))
+    .filter(fees_room_assignment::Column::DueDate.gte(start))
+    .filter(fees_room_assignment::Column::DueDate.lt(end))
+    .order_by(fees_room_assignment::Column::DueDate, Order::Asc)
+    .all(&state.db)
+    .await
+    .map_err(|e| {
+      log::error!("Error: {:?}", e);
+      StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+  if assignments.is_empty() {
+    return Ok(None);
+  }
+
+  let invoice = InvoiceData {
+    room_number: room.room_number,
+    tenant_name: tenant.map(|tenant| tenant.name).unwrap_or_default(),
+    period: period.to_string(),
+    lines: assignments
+      .into_iter()
+      .map(|assignment| InvoiceLine {
+        assignment_id: assignment.assignment_id,
+        fee_name: assignment.fee_name,
+        due_date: assignment.due_date,
+        amount: assignment.amount,
+      })
+      .collect(),
+  };
+
+  let content = pdf::render_invoice(&state.building, &invoice).map_err(|e| {
+    log::error!("Failed to render invoice: {:?}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+  })?;
+
+  let document = documents::ActiveModel {
+    kind: Set(DocumentKind::Invoice),
+    room_number: Set(room.room_number),
+    period: Set(Some(period.to_string())),
+    file_name: Set(format!("hoa-don-{}-{}.pdf", room.room_number, period)),
+    content: Set(content),
+    created_at: Set(chrono::Utc::now().naive_utc()),
+    ..Default::default()
+  };
+  let document = document.insert(&state.db).await.map_err(|e| {
+    log::error!("Error: {:?}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+  })?;
+
+  Ok(Some(DocumentInfo {
+    id: document.id,
+    kind: document.kind,
+    room_number: document.room_number,
+    period: document.period,
+    transaction_id: document.transaction_id,
+    file_name: document.file_name,
+    created_at: document.created_at,
+  }))
+}
+
+/// Get the stored receipt of a transaction, generating it on first use.
+async fn get_or_create_receipt(
+  state: &AppState,
+  transaction_id: i32,
+) -> Result<documents::Model, StatusCode> {
+  let server_error = |e: DbErr| {
+    log::error!("Error: {:?}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+  };
+
+  let existing = Documents::find()
+    .filter(documents::Column::TransactionId.eq(transaction_id))
+    .one(&state.db)
+    .await
+    .map_err(server_error)?;
+  if let Some(existing) = existing {
+    return Ok(existing);
+  }
+
+  let transaction = Transactions::find_by_id(transaction_id)
+    .find_also_related(FeesRoomAssignment)
+    .one(&state.db)
+    .await
+    .map_err(server_error)?;
+  let Some((transaction, Some(assignment))) = transaction else {
+    return Err(StatusCode::NOT_FOUND);
+  };
+  let tenant = Rooms::find_by_id(assignment.room_number)
+    .find_also_related(Users)
+    .one(&state.db)
+    .await
+    .map_err(server_error)?
+    .and_then(|(_, tenant)| tenant);
+
+  let receipt = ReceiptData {
+    transaction_id: transaction.id,
+    room_number: assignment.room_number,
+    tenant_name: tenant.map(|tenant| tenant.name).unwrap_or_default(),
+    fee_name: assignment.fee_name,
+    amount: transaction.amount,
+    paid_at: transaction.created_at,
+  };
+  let content = pdf::render_receipt(&state.building, &receipt).map_err(|e| {
+    log::error!("Failed to render receipt: {:?}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+  })?;
+
+  let document = documents::ActiveModel {
+    kind: Set(DocumentKind::Receipt),
+    room_number: Set(assignment.room_number),
+    transaction_id: Set(Some(transaction.id)),
+    file_name: Set(format!("bien-lai-{:06}.pdf", transaction.id)),
+    content: Set(content),
+    created_at: Set(chrono::Utc::now().naive_utc()),
+    ..Default::default()
+  };
+
+  document.insert(&state.db).await.map_err(server_error)
+}
+
+#[utoipa::path(
+  post,
+  path = "/documents/invoices",
+  description = "Tạo hóa đơn tháng cho các phòng, liệt kê các khoản phí chưa thanh toán của phòng có hạn nộp trong tháng, yêu cầu request có role là Manager.
+  Mỗi phòng chỉ có một hóa đơn cho mỗi tháng, phòng đã có hóa đơn được trả về hóa đơn cũ. Trả về danh sách hóa đơn.",
+  tag = tags::MANAGER,
+  responses(
+    (status = CREATED, description = "Invoices generated", body = Vec<DocumentInfo>),
+    (status = BAD_REQUEST, description = "Invalid period"),
+    (status = NOT_FOUND, description = "Room not found"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn generate_invoices(
+  State(state): State<AppState>,
+  Json(info): Json<GenerateInvoicesInfo>,
+) -> Result<impl IntoResponse, StatusCode> {
+  let period = info
+    .period
+    .unwrap_or_else(|| chrono::Local::now().format("%Y-%m").to_string());
+  let range = period_range(&period).ok_or(StatusCode::BAD_REQUEST)?;
+
+  let room_numbers = match info.room_numbers {
+    Some(room_numbers) => room_numbers,
+    None => FeesRoomAssignment::find()
+      .filter(fees_room_assignment::Column::IsPaid.eq(false))
+      .filter(fees_room_assignment::Column::DueDate.gte(range.0))
+      .filter(fees_room_assignment::Column::DueDate.lt(range.1))
+      .all(&state.db)
+      .await
+      .map_err(|e| {
+        log::error!("Error: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+      })?
+      .into_iter()
+      .map(|assignment| assignment.room_number)
+      .collect::<std::collections::BTreeSet<_>>()
+      .into_iter()
+      .collect(),
+  };
+
+  let mut invoices = vec![];
+  for room_number in room_numbers {
+    if let Some(invoice) = create_invoice(&state, room_number, &period, range).await? {
+      invoices.push(invoice);
+    }
+  }
+
+  log::info!("{} invoices generated for {}", invoices.len(), period);
+
+  Ok((StatusCode::CREATED, Json(invoices)))
+}
+
+#[utoipa::path(
+  get,
+  path = "/documents",
+  description = "Lấy danh sách hóa đơn và biên lai đã tạo, có thể lọc theo phòng và loại, yêu cầu request có role là Manager.",
+  tag = tags::MANAGER,
+  params(
+    DocumentFilter
+  ),
+  responses(
+    (status = OK, description = "Documents retrieved", body = Vec<DocumentInfo>),
+    (status = BAD_REQUEST, description = "Invalid kind"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn get_documents(
+  State(state): State<AppState>,
+  Query(DocumentFilter { room_number, kind }): Query<DocumentFilter>,
+) -> Result<Json<Vec<DocumentInfo>>, StatusCode> {
+  let mut query = Documents::find().order_by(documents::Column::CreatedAt, Order::Desc);
+  if let Some(room_number) = room_number {
+    query = query.filter(documents::Column::RoomNumber.eq(room_number));
+  }
+  if let Some(kind) = kind {
+    let kind = match kind.as_str() {
+      "invoice" => DocumentKind::Invoice,
+      "receipt" => DocumentKind::Receipt,
+      _ => return Err(StatusCode::BAD_REQUEST),
+    };
+    query = query.filter(documents::Column::Kind.eq(kind));
+  }
+
+  let documents = query
+    .into_partial_model::<DocumentInfo>()
+    .all(&state.db)
+    .await
+    .map_err(|e| {
+      log::error!("Error: {:?}", e);
+      StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+  Ok(Json(documents))
+}
+
+#[utoipa::path(
+  get,
+  path = "/documents/{id}",
+  description = "Tải về một hóa đơn hoặc biên lai dưới dạng PDF, yêu cầu request có role là Manager.",
+  tag = tags::MANAGER,
+  responses(
+    (status = OK, description = "PDF document", content_type = "application/pdf"),
+    (status = NOT_FOUND, description = "Document not found"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn download_document(
+  State(state): State<AppState>,
+  Path(id): Path<i32>,
+) -> Result<impl IntoResponse, StatusCode> {
+  let document = Documents::find_by_id(id)
+    .one(&state.db)
+    .await
+    .map_err(|e| {
+      log::error!("Error: {:?}", e);
+      StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+  Ok(pdf_response(document))
+}
+
+#[utoipa::path(
+  get,
+  path = "/receipts/{transaction_id}",
+  description = "Tải về biên lai của một giao dịch dưới dạng PDF, yêu cầu request có role là Manager. Biên lai được tạo ở lần tải đầu tiên.",
+  tag = tags::MANAGER,
+  responses(
+    (status = OK, description = "PDF receipt", content_type = "application/pdf"),
+    (status = NOT_FOUND, description = "Transaction not found"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn download_receipt(
+  State(state): State<AppState>,
+  Path(transaction_id): Path<i32>,
+) -> Result<impl IntoResponse, StatusCode> {
+  let document = get_or_create_receipt(&state, transaction_id).await?;
+
+  Ok(pdf_response(document))
+}
+
+#[utoipa::path(
+  get,
+  path = "/household/documents",
+  description = "Lấy danh sách hóa đơn và biên lai của phòng mà người dùng đang thuê.",
+  tag = tags::HOUSEHOLD,
+  responses(
+    (status = OK, description = "Documents retrieved", body = Vec<DocumentInfo>),
+    (status = NOT_FOUND, description = "Room not found"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn get_household_documents(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+) -> Result<Json<Vec<DocumentInfo>>, StatusCode> {
+  let claims = state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+  let room = find_user_room(&state, claims.custom.id).await?;
+
+  let documents = Documents::find()
+    .filter(documents::Column::RoomNumber.eq(room.room_number))
+    .order_by(documents::Column::CreatedAt, Order::Desc)
+    .into_partial_model::<DocumentInfo>()
+    .all(&state.db)
+    .await
+    .map_err(|e| {
+      log::error!("Error: {:?}", e);
+      StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+  Ok(Json(documents))
+}
+
+#[utoipa::path(
+  get,
+  path = "/household/documents/{id}",
+  description = "Tải về một hóa đơn hoặc biên lai của phòng mà người dùng đang thuê dưới dạng PDF.",
+  tag = tags::HOUSEHOLD,
+  responses(
+    (status = OK, description = "PDF document", content_type = "application/pdf"),
+    (status = NOT_FOUND, description = "Document not found"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn download_household_document(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+  Path(id): Path<i32>,
+) -> Result<impl IntoResponse, StatusCode> {
+  let claims = state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+  let room = find_user_room(&state, claims.custom.id).await?;
+
+  let document = Documents::find_by_id(id)
+    .filter(documents::Column::RoomNumber.eq(room.room_number))
+    .one(&state.db)
+    .await
+    .map_err(|e| {
+      log::error!("Error: {:?}", e);
+      StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+  Ok(pdf_response(document))
+}
+
+#[utoipa::path(
+  get,
+  path = "/household/receipts/{transaction_id}",
+  description = "Tải về biên lai của một khoản phí đã thanh toán của phòng mà người dùng đang thuê dưới dạng PDF.",
+  tag = tags::HOUSEHOLD,
+  responses(
+    (status = OK, description = "PDF receipt", content_type = "application/pdf"),
+    (status = NOT_FOUND, description = "Transaction not found"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn download_household_receipt(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+  Path(transaction_id): Path<i32>,
+) -> Result<impl IntoResponse, StatusCode> {
+  let claims = state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+  let room = find_user_room(&state, claims.custom.id).await?;
+
+  // make sure the transaction paid for a fee of the user's room
+  let assignment = Transactions::find_by_id(transaction_id)
+    .find_also_related(FeesRoomAssignment)
+    .one(&state.db)
+    .await
+    .map_err(|e| {
+      log::error!("Error: {:?}", e);
+      StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .and_then(|(_, assignment)| assignment);
+  match assignment {
+    Some(assignment) if assignment.room_number == room.room_number => {}
+    _ => return Err(StatusCode::NOT_FOUND),
+  }
+
+  let document = get_or_create_receipt(&state, transaction_id).await?;
+
+  Ok(pdf_response(document))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn midnight(y: i32, m: u32, d: u32) -> DateTime {
+    chrono::NaiveDate::from_ymd_opt(y, m, d)
+      .unwrap()
+      .and_hms_opt(0, 0, 0)
+      .unwrap()
+  }
+
+  #[test]
+  fn period_is_a_month() {
+    assert_eq!(
+      period_range("2024-02"),
+      Some((midnight(2024, 2, 1), midnight(2024, 3, 1)))
+    );
+    assert_eq!(
+      period_range("2024-12"),
+      Some((midnight(2024, 12, 1), midnight(2025, 1, 1)))
+    );
+  }
+
+  #[test]
+  fn invalid_period() {
+    assert_eq!(period_range("2024-13"), None);
+    assert_eq!(period_range("2024"), None);
+    assert_eq!(period_range("tháng 2"), None);
+  }
+}
@@ -0,0 +1,282 @@
+//! PDF rendering for invoices and receipts.
+//!
+//! The built-in PDF fonts cannot show Vietnamese, so DejaVu Sans is embedded in every document.
+
+use std::io::Cursor;
+
+use printpdf::{
+  IndirectFontRef, Line, Mm, PdfDocument, PdfDocumentReference, PdfLayerReference, Point,
+};
+
+use super::BuildingInfo;
+
+const FONT_REGULAR: &[u8] = include_bytes!("../../assets/fonts/DejaVuSans.ttf");
+const FONT_BOLD: &[u8] = include_bytes!("../../assets/fonts/DejaVuSans-Bold.ttf");
+
+const PAGE_WIDTH: f32 = 210.0;
+const PAGE_HEIGHT: f32 = 297.0;
+const MARGIN: f32 = 20.0;
+
+/// One fee on an invoice.
+#[derive(Debug, Clone)]
+pub struct InvoiceLine {
+  pub assignment_id: i32,
+  pub fee_name: String,
+  pub due_date: chrono::NaiveDateTime,
+  pub amount: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct InvoiceData {
+  pub room_number: i32,
+  pub tenant_name: String,
+  /// Billing month, `YYYY-MM`
+  pub period: String,
+  pub lines: Vec<InvoiceLine>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReceiptData {
+  pub transaction_id: i32,
+  pub room_number: i32,
+  pub tenant_name: String,
+  pub fee_name: String,
+  pub amount: i64,
+  pub paid_at: chrono::NaiveDateTime,
+}
+
+/// Writes text top to bottom, starting a new page when the current one is full.
+struct Writer {
+  doc: PdfDocumentReference,
+  layer: PdfLayerReference,
+  regular: IndirectFontRef,
+  bold: IndirectFontRef,
+  /// Distance from the bottom of the page, in mm
+  y: f32,
+}
+
+impl Writer {
+  fn new(title: &str) -> anyhow::Result<Self> {
+    let (doc, page, layer) = PdfDocument::new(title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+    let regular = doc.add_external_font(Cursor::new(FONT_REGULAR))?;
+    let bold = doc.add_external_font(Cursor::new(FONT_BOLD))?;
+    let layer = doc.get_page(page).get_layer(layer);
+
+    Ok(Self {
+      doc,
+      layer,
+      regular,
+      bold,
+      y: PAGE_HEIGHT - MARGIN,
+    })
+  }
+
+  /// Move down by `height` mm, breaking the page if needed.
+  fn advance(&mut self, height: f32) {
+    self.y -= height;
+    if self.y < MARGIN {
+      let (page, layer) = self
+        .doc
+        .add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+      self.layer = self.doc.get_page(page).get_layer(layer);
+      self.y = PAGE_HEIGHT - MARGIN - height;
+    }
+  }
+
+  fn text(&self, text: &str, size: f32, x: f32, bold: bool) {
+    let font = if bold { &self.bold } else { &self.regular };
+    self.layer.use_text(text, size, Mm(x), Mm(self.y), font);
+  }
+
+  fn line(&mut self, size: f32, text: &str, bold: bool) {
+    self.advance(size * 0.5);
+    self.text(text, size, MARGIN, bold);
+  }
+
+  /// Write cells at the given x positions on one line.
+  fn row(&mut self, size: f32, cells: &[(f32, &str)], bold: bool) {
+    self.advance(size * 0.55);
+    for (x, text) in cells {
+      self.text(text, size, *x, bold);
+    }
+  }
+
+  fn rule(&mut self) {
+    self.advance(2.5);
+    self.layer.add_line(Line {
+      points: vec![
+        (Point::new(Mm(MARGIN), Mm(self.y)), false),
+        (Point::new(Mm(PAGE_WIDTH - MARGIN), Mm(self.y)), false),
+      ],
+      is_closed: false,
+    });
+    self.advance(1.5);
+  }
+
+  fn header(&mut self, building: &BuildingInfo) {
+    self.line(16.0, &building.name.to_uppercase(), true);
+    if let Some(address) = &building.address {
+      self.line(10.0, &format!("Địa chỉ: {}", address), false);
+    }
+    if let Some(phone) = &building.phone {
+      self.line(10.0, &format!("Điện thoại: {}", phone), false);
+    }
+    self.rule();
+  }
+
+  fn finish(self) -> anyhow::Result<Vec<u8>> {
+    Ok(self.doc.save_to_bytes()?)
+  }
+}
+
+/// Format an amount as `1.000.000 VND`.
+pub fn format_vnd(amount: i64) -> String {
+  let digits = amount.unsigned_abs().to_string();
+  let mut grouped = String::new();
+  for (i, c) in digits.chars().enumerate() {
+    if i > 0 && (digits.len() - i).is_multiple_of(3) {
+      grouped.push('.');
+    }
+    grouped.push(c);
+  }
+  let sign = if amount < 0 { "-" } else { "" };
+  format!("{}{} VND", sign, grouped)
+}
+
+pub fn render_invoice(building: &BuildingInfo, invoice: &InvoiceData) -> anyhow::Result<Vec<u8>> {
+  let title = format!("Hóa đơn phòng {} - {}", invoice.room_number, invoice.period);
+  let mut w = Writer::new(&title)?;
+
+  w.header(building);
+  w.advance(4.0);
+  w.line(18.0, "HÓA ĐƠN THU PHÍ", true);
+  w.line(11.0, &format!("Kỳ thu: {}", invoice.period), false);
+  w.advance(2.0);
+  w.line(11.0, &format!("Phòng: {}", invoice.room_number), false);
+  w.line(11.0, &format!("Chủ hộ: {}", invoice.tenant_name), false);
+  w.line(
+    11.0,
+    &format!(
+      "Ngày lập: {}",
+      chrono::Local::now().naive_local().format("%d/%m/%Y")
+    ),
+    false,
+  );
+  w.advance(4.0);
+
+  let columns = [MARGIN, MARGIN + 12.0, MARGIN + 85.0, MARGIN + 115.0];
+  w.row(
+    10.0,
+    &[
+      (columns[0], "STT"),
+      (columns[1], "Khoản phí"),
+      (columns[2], "Hạn nộp"),
+      (columns[3], "Số tiền"),
+    ],
+    true,
+  );
+  w.rule();
+  for (i, line) in invoice.lines.iter().enumerate() {
+    let index = (i + 1).to_string();
+    let due_date = line.due_date.format("%d/%m/%Y").to_string();
+    let amount = format_vnd(line.amount);
+    w.row(
+      10.0,
+      &[
+        (columns[0], &index),
+        (columns[1], &line.fee_name),
+        (columns[2], &due_date),
+        (columns[3], &amount),
+      ],
+      false,
+    );
+    w.row(
+      8.0,
+      &[(
+        columns[1],
+        &format!("Nội dung chuyển khoản: FLATAPP{}", line.assignment_id),
+      )],
+      false,
+    );
+  }
+  w.rule();
+  let total = invoice.lines.iter().map(|line| line.amount).sum::<i64>();
+  w.row(
+    11.0,
+    &[(columns[1], "Tổng cộng"), (columns[3], &format_vnd(total))],
+    true,
+  );
+
+  w.advance(8.0);
+  w.line(
+    9.0,
+    "Vui lòng chuyển khoản riêng cho từng khoản phí với nội dung như trên để hệ thống tự động ghi nhận.",
+    false,
+  );
+
+  w.finish()
+}
+
+pub fn render_receipt(building: &BuildingInfo, receipt: &ReceiptData) -> anyhow::Result<Vec<u8>> {
+  let title = format!("Biên lai BL-{:06}", receipt.transaction_id);
+  let mut w = Writer::new(&title)?;
+
+  w.header(building);
+  w.advance(4.0);
+  w.line(18.0, "BIÊN LAI THU TIỀN", true);
+  w.line(
+    11.0,
+    &format!("Số: BL-{:06}", receipt.transaction_id),
+    false,
+  );
+  w.advance(4.0);
+  w.line(11.0, &format!("Phòng: {}", receipt.room_number), false);
+  w.line(11.0, &format!("Người nộp: {}", receipt.tenant_name), false);
+  w.line(11.0, &format!("Nội dung: {}", receipt.fee_name), false);
+  w.line(
+    11.0,
+    &format!("Số tiền: {}", format_vnd(receipt.amount)),
+    true,
+  );
+  w.line(
+    11.0,
+    &format!(
+      "Ngày thanh toán: {}",
+      receipt.paid_at.format("%d/%m/%Y %H:%M")
+    ),
+    false,
+  );
+  w.advance(8.0);
+  w.line(
+    9.0,
+    &format!("{} xác nhận đã nhận đủ số tiền trên.", building.name),
+    false,
+  );
+
+  w.finish()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn zero() {
+    assert_eq!(format_vnd(0), "0 VND");
+  }
+
+  #[test]
+  fn thousands_are_separated_by_dots() {
+    assert_eq!(format_vnd(999), "999 VND");
+    assert_eq!(format_vnd(1000), "1.000 VND");
+    assert_eq!(format_vnd(250000), "250.000 VND");
+    assert_eq!(format_vnd(1234567), "1.234.567 VND");
+  }
+
+  #[test]
+  fn negative_amounts() {
+    assert_eq!(format_vnd(-500), "-500 VND");
+    assert_eq!(format_vnd(-1500000), "-1.500.000 VND");
+    assert_eq!(format_vnd(i64::MIN), "-9.223.372.036.854.775.808 VND");
+  }
+}
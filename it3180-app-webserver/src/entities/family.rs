@@ -1,5 +1,6 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.0.1
 
+use super::sea_orm_active_enums::Gender;
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
@@ -14,10 +15,18 @@ pub struct Model {
   pub name: String,
   pub birthday: Date,
   pub account_id: i32,
+  pub relationship: Option<String>,
+  pub gender: Option<Gender>,
+  #[sea_orm(unique)]
+  pub national_id: Option<String>,
+  pub occupation: Option<String>,
+  pub phone: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+  #[sea_orm(has_many = "super::residence_declarations::Entity")]
+  ResidenceDeclarations,
   #[sea_orm(
     belongs_to = "super::users::Entity",
     from = "Column::AccountId",
@@ -28,6 +37,12 @@ pub enum Relation {
   Users,
 }
 
+impl Related<super::residence_declarations::Entity> for Entity {
+  fn to() -> RelationDef {
+    Relation::ResidenceDeclarations.def()
+  }
+}
+
 impl Related<super::users::Entity> for Entity {
   fn to() -> RelationDef {
     Relation::Users.def()
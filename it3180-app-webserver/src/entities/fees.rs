@@ -1,5 +1,6 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.0.1
 
+use super::sea_orm_active_enums::FeeCategory;
 use super::sea_orm_active_enums::RecurrenceType;
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
@@ -18,14 +19,40 @@ pub struct Model {
   pub is_recurring: bool,
   pub due_date: DateTime,
   pub recurrence_type: Option<RecurrenceType>,
+  pub archived_at: Option<DateTime>,
+  pub category: FeeCategory,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+  #[sea_orm(has_many = "super::fee_assignment_rules::Entity")]
+  FeeAssignmentRules,
+  #[sea_orm(has_many = "super::fee_tags::Entity")]
+  FeeTags,
+  #[sea_orm(has_many = "super::fee_versions::Entity")]
+  FeeVersions,
   #[sea_orm(has_many = "super::fees_room_assignment::Entity")]
   FeesRoomAssignment,
 }
 
+impl Related<super::fee_assignment_rules::Entity> for Entity {
+  fn to() -> RelationDef {
+    Relation::FeeAssignmentRules.def()
+  }
+}
+
+impl Related<super::fee_tags::Entity> for Entity {
+  fn to() -> RelationDef {
+    Relation::FeeTags.def()
+  }
+}
+
+impl Related<super::fee_versions::Entity> for Entity {
+  fn to() -> RelationDef {
+    Relation::FeeVersions.def()
+  }
+}
+
 impl Related<super::fees_room_assignment::Entity> for Entity {
   fn to() -> RelationDef {
     Relation::FeesRoomAssignment.def()
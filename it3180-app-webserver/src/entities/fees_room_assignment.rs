@@ -15,6 +15,10 @@ pub struct Model {
   pub due_date: DateTime,
   pub payment_date: Option<DateTime>,
   pub is_paid: bool,
+  pub fee_name: String,
+  pub amount: i64,
+  /// Price of one unit, e.g. one vehicle, when the room is billed per unit
+  pub unit_price: Option<i64>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -27,6 +31,8 @@ pub enum Relation {
     on_delete = "Cascade"
   )]
   Fees,
+  #[sea_orm(has_many = "super::payment_reminders::Entity")]
+  PaymentReminders,
   #[sea_orm(
     belongs_to = "super::rooms::Entity",
     from = "Column::RoomNumber",
@@ -45,6 +51,12 @@ impl Related<super::fees::Entity> for Entity {
   }
 }
 
+impl Related<super::payment_reminders::Entity> for Entity {
+  fn to() -> RelationDef {
+    Relation::PaymentReminders.def()
+  }
+}
+
 impl Related<super::rooms::Entity> for Entity {
   fn to() -> RelationDef {
     Relation::Rooms.def()
@@ -2,13 +2,32 @@
 
 pub mod prelude;
 
+pub mod documents;
 pub mod family;
+pub mod fee_assignment_rules;
 pub mod fee_recurrence;
+pub mod fee_tags;
+pub mod fee_versions;
 pub mod fees;
 pub mod fees_room_assignment;
+pub mod ledger_accounts;
+pub mod ledger_entries;
+pub mod ledger_postings;
+pub mod notification_deliveries;
+pub mod notification_preferences;
+pub mod notification_templates;
 pub mod notifications;
+pub mod payment_reminders;
+pub mod residence_declarations;
+pub mod room_claims;
+pub mod room_group_members;
+pub mod room_groups;
+pub mod room_invitations;
+pub mod room_members;
+pub mod room_occupancies;
 pub mod rooms;
 pub mod sea_orm_active_enums;
 pub mod transaction_logs;
 pub mod transactions;
 pub mod users;
+pub mod vehicles;
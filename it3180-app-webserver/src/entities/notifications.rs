@@ -17,10 +17,13 @@ pub struct Model {
   pub created_at: DateTime,
   pub from_user: i32,
   pub to_user: i32,
+  pub read_at: Option<DateTime>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+  #[sea_orm(has_many = "super::notification_deliveries::Entity")]
+  NotificationDeliveries,
   #[sea_orm(
     belongs_to = "super::users::Entity",
     from = "Column::FromUser",
@@ -39,4 +42,10 @@ pub enum Relation {
   Users1,
 }
 
+impl Related<super::notification_deliveries::Entity> for Entity {
+  fn to() -> RelationDef {
+    Relation::NotificationDeliveries.def()
+  }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
@@ -1,11 +1,30 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.0.1
 
+pub use super::documents::Entity as Documents;
 pub use super::family::Entity as Family;
+pub use super::fee_assignment_rules::Entity as FeeAssignmentRules;
 pub use super::fee_recurrence::Entity as FeeRecurrence;
+pub use super::fee_tags::Entity as FeeTags;
+pub use super::fee_versions::Entity as FeeVersions;
 pub use super::fees::Entity as Fees;
 pub use super::fees_room_assignment::Entity as FeesRoomAssignment;
+pub use super::ledger_accounts::Entity as LedgerAccounts;
+pub use super::ledger_entries::Entity as LedgerEntries;
+pub use super::ledger_postings::Entity as LedgerPostings;
+pub use super::notification_deliveries::Entity as NotificationDeliveries;
+pub use super::notification_preferences::Entity as NotificationPreferences;
+pub use super::notification_templates::Entity as NotificationTemplates;
 pub use super::notifications::Entity as Notifications;
+pub use super::payment_reminders::Entity as PaymentReminders;
+pub use super::residence_declarations::Entity as ResidenceDeclarations;
+pub use super::room_claims::Entity as RoomClaims;
+pub use super::room_group_members::Entity as RoomGroupMembers;
+pub use super::room_groups::Entity as RoomGroups;
+pub use super::room_invitations::Entity as RoomInvitations;
+pub use super::room_members::Entity as RoomMembers;
+pub use super::room_occupancies::Entity as RoomOccupancies;
 pub use super::rooms::Entity as Rooms;
 pub use super::transaction_logs::Entity as TransactionLogs;
 pub use super::transactions::Entity as Transactions;
 pub use super::users::Entity as Users;
+pub use super::vehicles::Entity as Vehicles;
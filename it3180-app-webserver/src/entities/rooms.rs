@@ -1,23 +1,44 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.0.1
 
+use super::sea_orm_active_enums::RoomStatus;
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
 #[derive(
-  Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize, utoipa :: ToSchema,
+  Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize, utoipa :: ToSchema,
 )]
 #[sea_orm(table_name = "rooms")]
 pub struct Model {
   #[sea_orm(primary_key, auto_increment = false)]
   pub room_number: i32,
   #[sea_orm(unique)]
-  pub tenant_id: i32,
+  pub tenant_id: Option<i32>,
+  pub building: Option<String>,
+  pub floor: Option<i32>,
+  #[sea_orm(column_type = "Double", nullable)]
+  pub area: Option<f64>,
+  pub room_type: Option<String>,
+  pub status: RoomStatus,
+  #[sea_orm(column_type = "Text", nullable)]
+  pub notes: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
   #[sea_orm(has_many = "super::fees_room_assignment::Entity")]
   FeesRoomAssignment,
+  #[sea_orm(has_many = "super::residence_declarations::Entity")]
+  ResidenceDeclarations,
+  #[sea_orm(has_many = "super::room_claims::Entity")]
+  RoomClaims,
+  #[sea_orm(has_many = "super::room_group_members::Entity")]
+  RoomGroupMembers,
+  #[sea_orm(has_many = "super::room_invitations::Entity")]
+  RoomInvitations,
+  #[sea_orm(has_many = "super::room_members::Entity")]
+  RoomMembers,
+  #[sea_orm(has_many = "super::room_occupancies::Entity")]
+  RoomOccupancies,
   #[sea_orm(
     belongs_to = "super::users::Entity",
     from = "Column::TenantId",
@@ -26,6 +47,8 @@ pub enum Relation {
     on_delete = "NoAction"
   )]
   Users,
+  #[sea_orm(has_many = "super::vehicles::Entity")]
+  Vehicles,
 }
 
 impl Related<super::fees_room_assignment::Entity> for Entity {
@@ -34,10 +57,52 @@ impl Related<super::fees_room_assignment::Entity> for Entity {
   }
 }
 
+impl Related<super::residence_declarations::Entity> for Entity {
+  fn to() -> RelationDef {
+    Relation::ResidenceDeclarations.def()
+  }
+}
+
+impl Related<super::room_claims::Entity> for Entity {
+  fn to() -> RelationDef {
+    Relation::RoomClaims.def()
+  }
+}
+
+impl Related<super::room_group_members::Entity> for Entity {
+  fn to() -> RelationDef {
+    Relation::RoomGroupMembers.def()
+  }
+}
+
+impl Related<super::room_invitations::Entity> for Entity {
+  fn to() -> RelationDef {
+    Relation::RoomInvitations.def()
+  }
+}
+
+impl Related<super::room_members::Entity> for Entity {
+  fn to() -> RelationDef {
+    Relation::RoomMembers.def()
+  }
+}
+
+impl Related<super::room_occupancies::Entity> for Entity {
+  fn to() -> RelationDef {
+    Relation::RoomOccupancies.def()
+  }
+}
+
 impl Related<super::users::Entity> for Entity {
   fn to() -> RelationDef {
     Relation::Users.def()
   }
 }
 
+impl Related<super::vehicles::Entity> for Entity {
+  fn to() -> RelationDef {
+    Relation::Vehicles.def()
+  }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
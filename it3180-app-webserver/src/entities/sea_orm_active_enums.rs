@@ -3,6 +3,224 @@
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
+#[derive(
+  Debug,
+  Clone,
+  PartialEq,
+  Eq,
+  EnumIter,
+  DeriveActiveEnum,
+  Serialize,
+  Deserialize,
+  utoipa :: ToSchema,
+)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "declaration_kind")]
+#[serde(rename_all = "snake_case")]
+pub enum DeclarationKind {
+  #[sea_orm(string_value = "temporary_residence")]
+  TemporaryResidence,
+  #[sea_orm(string_value = "temporary_absence")]
+  TemporaryAbsence,
+}
+#[derive(
+  Debug,
+  Clone,
+  PartialEq,
+  Eq,
+  EnumIter,
+  DeriveActiveEnum,
+  Serialize,
+  Deserialize,
+  utoipa :: ToSchema,
+)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "declaration_status")]
+#[serde(rename_all = "snake_case")]
+pub enum DeclarationStatus {
+  #[sea_orm(string_value = "pending")]
+  Pending,
+  #[sea_orm(string_value = "approved")]
+  Approved,
+  #[sea_orm(string_value = "rejected")]
+  Rejected,
+}
+#[derive(
+  Debug,
+  Clone,
+  PartialEq,
+  Eq,
+  EnumIter,
+  DeriveActiveEnum,
+  Serialize,
+  Deserialize,
+  utoipa :: ToSchema,
+)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "delivery_status")]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+  #[sea_orm(string_value = "queued")]
+  Queued,
+  #[sea_orm(string_value = "sent")]
+  Sent,
+  #[sea_orm(string_value = "failed")]
+  Failed,
+}
+#[derive(
+  Debug,
+  Clone,
+  PartialEq,
+  Eq,
+  EnumIter,
+  DeriveActiveEnum,
+  Serialize,
+  Deserialize,
+  utoipa :: ToSchema,
+)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "document_kind")]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentKind {
+  #[sea_orm(string_value = "invoice")]
+  Invoice,
+  #[sea_orm(string_value = "receipt")]
+  Receipt,
+}
+#[derive(
+  Debug,
+  Clone,
+  Default,
+  PartialEq,
+  Eq,
+  Hash,
+  EnumIter,
+  DeriveActiveEnum,
+  Serialize,
+  Deserialize,
+  utoipa :: ToSchema,
+)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "fee_category")]
+#[serde(rename_all = "snake_case")]
+pub enum FeeCategory {
+  #[default]
+  #[sea_orm(string_value = "service")]
+  Service,
+  #[sea_orm(string_value = "utilities")]
+  Utilities,
+  #[sea_orm(string_value = "parking")]
+  Parking,
+  #[sea_orm(string_value = "contribution")]
+  Contribution,
+  #[sea_orm(string_value = "penalty")]
+  Penalty,
+}
+#[derive(
+  Debug,
+  Clone,
+  PartialEq,
+  Eq,
+  EnumIter,
+  DeriveActiveEnum,
+  Serialize,
+  Deserialize,
+  utoipa :: ToSchema,
+)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "gender")]
+#[serde(rename_all = "snake_case")]
+pub enum Gender {
+  #[sea_orm(string_value = "male")]
+  Male,
+  #[sea_orm(string_value = "female")]
+  Female,
+  #[sea_orm(string_value = "other")]
+  Other,
+}
+#[derive(
+  Debug,
+  Clone,
+  PartialEq,
+  Eq,
+  EnumIter,
+  DeriveActiveEnum,
+  Serialize,
+  Deserialize,
+  utoipa :: ToSchema,
+)]
+#[sea_orm(
+  rs_type = "String",
+  db_type = "Enum",
+  enum_name = "ledger_account_type"
+)]
+#[serde(rename_all = "snake_case")]
+pub enum LedgerAccountType {
+  #[sea_orm(string_value = "asset")]
+  Asset,
+  #[sea_orm(string_value = "liability")]
+  Liability,
+  #[sea_orm(string_value = "income")]
+  Income,
+  #[sea_orm(string_value = "expense")]
+  Expense,
+}
+#[derive(
+  Debug,
+  Clone,
+  PartialEq,
+  Eq,
+  EnumIter,
+  DeriveActiveEnum,
+  Serialize,
+  Deserialize,
+  utoipa :: ToSchema,
+)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "ledger_entry_kind")]
+#[serde(rename_all = "snake_case")]
+pub enum LedgerEntryKind {
+  #[sea_orm(string_value = "charge")]
+  Charge,
+  #[sea_orm(string_value = "payment")]
+  Payment,
+  #[sea_orm(string_value = "adjustment")]
+  Adjustment,
+  #[sea_orm(string_value = "refund")]
+  Refund,
+}
+#[derive(
+  Debug,
+  Clone,
+  PartialEq,
+  Eq,
+  EnumIter,
+  DeriveActiveEnum,
+  Serialize,
+  Deserialize,
+  utoipa :: ToSchema,
+)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "notification_event")]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationEvent {
+  #[sea_orm(string_value = "fee_assigned")]
+  FeeAssigned,
+  #[sea_orm(string_value = "payment_reminder_upcoming")]
+  PaymentReminderUpcoming,
+  #[sea_orm(string_value = "payment_reminder_due")]
+  PaymentReminderDue,
+  #[sea_orm(string_value = "payment_reminder_overdue")]
+  PaymentReminderOverdue,
+  #[sea_orm(string_value = "room_invitation")]
+  RoomInvitation,
+  #[sea_orm(string_value = "room_claim_approved")]
+  RoomClaimApproved,
+  #[sea_orm(string_value = "room_claim_rejected")]
+  RoomClaimRejected,
+  #[sea_orm(string_value = "declaration_approved")]
+  DeclarationApproved,
+  #[sea_orm(string_value = "declaration_rejected")]
+  DeclarationRejected,
+  #[sea_orm(string_value = "declaration_expiring")]
+  DeclarationExpiring,
+  #[sea_orm(string_value = "vehicle_approved")]
+  VehicleApproved,
+  #[sea_orm(string_value = "vehicle_rejected")]
+  VehicleRejected,
+}
 #[derive(
   Debug,
   Clone,
@@ -35,6 +253,73 @@ pub enum RecurrenceType {
   Deserialize,
   utoipa :: ToSchema,
 )]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "room_claim_status")]
+#[serde(rename_all = "snake_case")]
+pub enum RoomClaimStatus {
+  #[sea_orm(string_value = "pending")]
+  Pending,
+  #[sea_orm(string_value = "approved")]
+  Approved,
+  #[sea_orm(string_value = "rejected")]
+  Rejected,
+}
+#[derive(
+  Debug,
+  Clone,
+  PartialEq,
+  Eq,
+  EnumIter,
+  DeriveActiveEnum,
+  Serialize,
+  Deserialize,
+  utoipa :: ToSchema,
+)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "room_member_role")]
+#[serde(rename_all = "snake_case")]
+pub enum RoomMemberRole {
+  #[sea_orm(string_value = "householder")]
+  Householder,
+  #[sea_orm(string_value = "co_resident")]
+  CoResident,
+  #[sea_orm(string_value = "owner")]
+  Owner,
+  #[sea_orm(string_value = "renter")]
+  Renter,
+}
+#[derive(
+  Debug,
+  Clone,
+  Default,
+  PartialEq,
+  Eq,
+  EnumIter,
+  DeriveActiveEnum,
+  Serialize,
+  Deserialize,
+  utoipa :: ToSchema,
+)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "room_status")]
+#[serde(rename_all = "snake_case")]
+pub enum RoomStatus {
+  #[default]
+  #[sea_orm(string_value = "vacant")]
+  Vacant,
+  #[sea_orm(string_value = "occupied")]
+  Occupied,
+  #[sea_orm(string_value = "under_renovation")]
+  UnderRenovation,
+}
+#[derive(
+  Debug,
+  Clone,
+  PartialEq,
+  Eq,
+  EnumIter,
+  DeriveActiveEnum,
+  Serialize,
+  Deserialize,
+  utoipa :: ToSchema,
+)]
 #[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "user_role")]
 #[serde(rename_all = "snake_case")]
 pub enum UserRole {
@@ -64,3 +349,49 @@ pub enum UserStatus {
   #[sea_orm(string_value = "inactive")]
   Inactive,
 }
+#[derive(
+  Debug,
+  Clone,
+  PartialEq,
+  Eq,
+  EnumIter,
+  DeriveActiveEnum,
+  Serialize,
+  Deserialize,
+  utoipa :: ToSchema,
+)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "vehicle_status")]
+#[serde(rename_all = "snake_case")]
+pub enum VehicleStatus {
+  #[sea_orm(string_value = "pending")]
+  Pending,
+  #[sea_orm(string_value = "approved")]
+  Approved,
+  #[sea_orm(string_value = "rejected")]
+  Rejected,
+  #[sea_orm(string_value = "removed")]
+  Removed,
+}
+#[derive(
+  Debug,
+  Clone,
+  PartialEq,
+  Eq,
+  EnumIter,
+  DeriveActiveEnum,
+  Serialize,
+  Deserialize,
+  utoipa :: ToSchema,
+)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "vehicle_type")]
+#[serde(rename_all = "snake_case")]
+pub enum VehicleType {
+  #[sea_orm(string_value = "bicycle")]
+  Bicycle,
+  #[sea_orm(string_value = "electric_bicycle")]
+  ElectricBicycle,
+  #[sea_orm(string_value = "motorbike")]
+  Motorbike,
+  #[sea_orm(string_value = "car")]
+  Car,
+}
@@ -8,7 +8,7 @@ use serde::{Deserialize, Serialize};
 )]
 #[sea_orm(table_name = "transaction_logs")]
 pub struct Model {
-  #[sea_orm(primary_key, auto_increment = false)]
+  #[sea_orm(primary_key)]
   pub id: i32,
   pub gateway: String,
   pub transaction_date: DateTime,
@@ -20,6 +20,8 @@ pub struct Model {
   pub content: String,
   pub reference_code: Option<String>,
   pub description: Option<String>,
+  pub provider: String,
+  pub external_id: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
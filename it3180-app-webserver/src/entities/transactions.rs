@@ -22,7 +22,7 @@ pub enum Relation {
     from = "Column::AssignmentId",
     to = "super::fees_room_assignment::Column::AssignmentId",
     on_update = "NoAction",
-    on_delete = "Cascade"
+    on_delete = "Restrict"
   )]
   FeesRoomAssignment,
 }
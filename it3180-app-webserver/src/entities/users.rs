@@ -31,8 +31,22 @@ pub struct Model {
 pub enum Relation {
   #[sea_orm(has_many = "super::family::Entity")]
   Family,
+  #[sea_orm(has_one = "super::notification_preferences::Entity")]
+  NotificationPreferences,
+  #[sea_orm(has_many = "super::notification_templates::Entity")]
+  NotificationTemplates,
+  #[sea_orm(has_many = "super::residence_declarations::Entity")]
+  ResidenceDeclarations,
+  #[sea_orm(has_many = "super::room_claims::Entity")]
+  RoomClaims,
+  #[sea_orm(has_many = "super::room_members::Entity")]
+  RoomMembers,
+  #[sea_orm(has_many = "super::room_occupancies::Entity")]
+  RoomOccupancies,
   #[sea_orm(has_one = "super::rooms::Entity")]
   Rooms,
+  #[sea_orm(has_many = "super::vehicles::Entity")]
+  Vehicles,
 }
 
 impl Related<super::family::Entity> for Entity {
@@ -41,10 +55,52 @@ impl Related<super::family::Entity> for Entity {
   }
 }
 
+impl Related<super::notification_preferences::Entity> for Entity {
+  fn to() -> RelationDef {
+    Relation::NotificationPreferences.def()
+  }
+}
+
+impl Related<super::notification_templates::Entity> for Entity {
+  fn to() -> RelationDef {
+    Relation::NotificationTemplates.def()
+  }
+}
+
+impl Related<super::residence_declarations::Entity> for Entity {
+  fn to() -> RelationDef {
+    Relation::ResidenceDeclarations.def()
+  }
+}
+
+impl Related<super::room_claims::Entity> for Entity {
+  fn to() -> RelationDef {
+    Relation::RoomClaims.def()
+  }
+}
+
+impl Related<super::room_members::Entity> for Entity {
+  fn to() -> RelationDef {
+    Relation::RoomMembers.def()
+  }
+}
+
+impl Related<super::room_occupancies::Entity> for Entity {
+  fn to() -> RelationDef {
+    Relation::RoomOccupancies.def()
+  }
+}
+
 impl Related<super::rooms::Entity> for Entity {
   fn to() -> RelationDef {
     Relation::Rooms.def()
   }
 }
 
+impl Related<super::vehicles::Entity> for Entity {
+  fn to() -> RelationDef {
+    Relation::Vehicles.def()
+  }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
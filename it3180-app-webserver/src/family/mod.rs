@@ -4,16 +4,97 @@ use crate::{entities::family, prelude::*};
 pub struct AddFamilyMemberInfo {
   pub name: String,
   pub birthday: chrono::NaiveDate,
+  /// Relationship to the householder, e.g. "vợ", "con"
+  pub relationship: Option<String>,
+  pub gender: Option<Gender>,
+  /// National ID (CCCD/CMND) number, 9 or 12 digits, unique in the building
+  pub national_id: Option<String>,
+  pub occupation: Option<String>,
+  pub phone: Option<String>,
+}
+
+fn server_error(e: DbErr) -> StatusCode {
+  log::error!("Error: {:?}", e);
+  StatusCode::INTERNAL_SERVER_ERROR
+}
+
+/// Trim an optional text field, treating a blank value as missing.
+pub(crate) fn optional_text(value: Option<String>) -> Option<String> {
+  value
+    .map(|value| value.trim().to_string())
+    .filter(|value| !value.is_empty())
+}
+
+/// A national ID is 9 (CMND) or 12 (CCCD) digits.
+pub(crate) fn is_valid_national_id(national_id: &str) -> bool {
+  matches!(national_id.len(), 9 | 12) && national_id.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Check the member info and fill in the fields of the family member.
+///
+/// A national ID must be valid, and must not be used by anybody else in the building.
+async fn apply_member_info(
+  db: &DatabaseConnection,
+  member: &mut family::ActiveModel,
+  member_info: AddFamilyMemberInfo,
+  member_id: Option<i32>,
+) -> Result<(), StatusCode> {
+  let name = member_info.name.trim().to_string();
+  if name.is_empty() {
+    return Err(StatusCode::BAD_REQUEST);
+  }
+
+  let national_id = optional_text(member_info.national_id);
+  if let Some(national_id) = &national_id {
+    if !is_valid_national_id(national_id) {
+      return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let mut query = family::Entity::find().filter(family::Column::NationalId.eq(national_id));
+    if let Some(member_id) = member_id {
+      query = query.filter(family::Column::Id.ne(member_id));
+    }
+    if query.one(db).await.map_err(server_error)?.is_some() {
+      log::info!("National ID already registered: {}", national_id);
+      return Err(StatusCode::CONFLICT);
+    }
+  }
+
+  member.name = Set(name);
+  member.birthday = Set(member_info.birthday);
+  member.relationship = Set(optional_text(member_info.relationship));
+  member.gender = Set(member_info.gender);
+  member.national_id = Set(national_id);
+  member.occupation = Set(optional_text(member_info.occupation));
+  member.phone = Set(optional_text(member_info.phone));
+  Ok(())
+}
+
+/// Find a family member of the account.
+async fn find_family_member(
+  db: &DatabaseConnection,
+  account_id: i32,
+  id: i32,
+) -> Result<family::Model, StatusCode> {
+  family::Entity::find_by_id(id)
+    .filter(family::Column::AccountId.eq(account_id))
+    .one(db)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)
 }
 
 /// Thêm thông tin của một thành viên gia đình
 #[utoipa::path(
   post,
   path = "/family",
-  description = "Thêm thông tin của một thành viên gia đình",
+  description = "Thêm thông tin của một thành viên gia đình, gồm quan hệ với chủ hộ, giới tính, số CCCD, nghề nghiệp và số điện thoại.
+  Số CCCD gồm 9 hoặc 12 chữ số và không được trùng với người khác trong tòa nhà.",
   tag = tags::FAMILY,
   responses(
-    (status = OK, description = "OK"),
+    (status = CREATED, description = "Created"),
+    (status = BAD_REQUEST, description = "Missing name or invalid national ID"),
+    (status = CONFLICT, description = "National ID already registered"),
     (status = INTERNAL_SERVER_ERROR, description = "Server error"),
     (status = UNAUTHORIZED, description = "Unauthorized"),
     (status = FORBIDDEN, description = "Forbidden"),
@@ -35,12 +116,13 @@ pub async fn add_family_member(
     Err(_) => return StatusCode::UNAUTHORIZED,
   };
 
-  let family_member = family::ActiveModel {
-    name: Set(member_info.name),
-    birthday: Set(member_info.birthday),
+  let mut family_member = family::ActiveModel {
     account_id: Set(user_info.custom.id),
     ..Default::default()
   };
+  if let Err(status) = apply_member_info(&state.db, &mut family_member, member_info, None).await {
+    return status;
+  }
 
   match family::Entity::insert(family_member).exec(&state.db).await {
     Ok(_) => StatusCode::CREATED,
@@ -83,3 +165,79 @@ pub async fn get_family_members(
     Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
   }
 }
+
+#[utoipa::path(
+  put,
+  path = "/family/{id}",
+  description = "Sửa thông tin của một thành viên gia đình. Tất cả các trường được thay thế bằng thông tin mới.",
+  tag = tags::FAMILY,
+  request_body = AddFamilyMemberInfo,
+  responses(
+    (status = OK, description = "OK", body = family::Model),
+    (status = BAD_REQUEST, description = "Missing name or invalid national ID"),
+    (status = NOT_FOUND, description = "Not found"),
+    (status = CONFLICT, description = "National ID already registered"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn update_family_member(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+  Path(id): Path<i32>,
+  Json(member_info): Json<AddFamilyMemberInfo>,
+) -> Result<Json<family::Model>, StatusCode> {
+  let user_info = state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+  let family_member = find_family_member(&state.db, user_info.custom.id, id).await?;
+  let mut family_member: family::ActiveModel = family_member.into();
+  apply_member_info(&state.db, &mut family_member, member_info, Some(id)).await?;
+
+  let family_member = family_member
+    .update(&state.db)
+    .await
+    .map_err(server_error)?;
+  Ok(Json(family_member))
+}
+
+#[utoipa::path(
+  delete,
+  path = "/family/{id}",
+  description = "Xóa thông tin của một thành viên gia đình",
+  tag = tags::FAMILY,
+  responses(
+    (status = NO_CONTENT, description = "Deleted"),
+    (status = NOT_FOUND, description = "Not found"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn remove_family_member(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+  Path(id): Path<i32>,
+) -> Result<StatusCode, StatusCode> {
+  let user_info = state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+  let family_member = find_family_member(&state.db, user_info.custom.id, id).await?;
+  family::Entity::delete_by_id(family_member.id)
+    .exec(&state.db)
+    .await
+    .map_err(server_error)?;
+
+  Ok(StatusCode::NO_CONTENT)
+}
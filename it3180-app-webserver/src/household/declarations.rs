@@ -0,0 +1,258 @@
+//! Tạm trú / tạm vắng declarations made by the residents of a room.
+//!
+//! A declaration is about a family member of one of the residents, or about a guest staying in
+//! the room. It waits for a manager's approval before it enters the register.
+
+use sea_orm::{Order, QueryOrder};
+
+use super::members::find_membership;
+use crate::{
+  entities::{family, residence_declarations},
+  family::{is_valid_national_id, optional_text},
+  prelude::*,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DeclarationRequest {
+  pub kind: DeclarationKind,
+  /// The family member the declaration is about, the person fields are then taken from it
+  pub family_id: Option<i32>,
+  /// Name of a guest, required without `family_id`
+  pub full_name: Option<String>,
+  pub birthday: Option<chrono::NaiveDate>,
+  pub gender: Option<Gender>,
+  pub national_id: Option<String>,
+  pub start_date: chrono::NaiveDate,
+  pub end_date: chrono::NaiveDate,
+  pub reason: Option<String>,
+  /// Where the person comes from (tạm trú) or goes to (tạm vắng)
+  pub address: Option<String>,
+}
+
+fn server_error(e: DbErr) -> StatusCode {
+  log::error!("Error: {:?}", e);
+  StatusCode::INTERNAL_SERVER_ERROR
+}
+
+/// Find the family member a declaration is about. It must belong to a resident of the room.
+async fn find_room_family_member(
+  db: &DatabaseConnection,
+  room_number: i32,
+  family_id: i32,
+) -> Result<family::Model, StatusCode> {
+  let member = family::Entity::find_by_id(family_id)
+    .one(db)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+  let membership = find_membership(db, member.account_id)
+    .await
+    .map_err(server_error)?;
+  if membership.is_none_or(|membership| membership.room_number != room_number) {
+    return Err(StatusCode::NOT_FOUND);
+  }
+  Ok(member)
+}
+
+#[utoipa::path(
+  get,
+  path = "/household/declarations",
+  description = "Lấy danh sách các khai báo tạm trú, tạm vắng của phòng mà người dùng đang ở.",
+  tag = tags::HOUSEHOLD,
+  responses(
+    (status = OK, description = "Declarations", body = Vec<residence_declarations::Model>),
+    (status = NOT_FOUND, description = "User has no room"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn get_declarations(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+) -> Result<Json<Vec<residence_declarations::Model>>, StatusCode> {
+  let claims = state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+  let membership = find_membership(&state.db, claims.custom.id)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+  let declarations = ResidenceDeclarations::find()
+    .filter(residence_declarations::Column::RoomNumber.eq(membership.room_number))
+    .order_by(residence_declarations::Column::StartDate, Order::Desc)
+    .order_by(residence_declarations::Column::Id, Order::Desc)
+    .all(&state.db)
+    .await
+    .map_err(server_error)?;
+
+  Ok(Json(declarations))
+}
+
+#[utoipa::path(
+  post,
+  path = "/household/declarations",
+  description = "Khai báo tạm trú hoặc tạm vắng cho một thành viên gia đình (`family_id`) hoặc tạm trú cho khách (`full_name`).
+  Khai báo cần được quản lý duyệt. Không thể khai báo trùng thời gian với một khai báo cùng loại của cùng một người.",
+  tag = tags::HOUSEHOLD,
+  request_body = DeclarationRequest,
+  responses(
+    (status = CREATED, description = "Declaration created", body = residence_declarations::Model),
+    (status = BAD_REQUEST, description = "Invalid dates, missing name or invalid national ID"),
+    (status = NOT_FOUND, description = "User has no room, or family member not found"),
+    (status = CONFLICT, description = "Overlaps another declaration of the same person"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn add_declaration(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+  Json(request): Json<DeclarationRequest>,
+) -> Result<(StatusCode, Json<residence_declarations::Model>), StatusCode> {
+  let claims = state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+  let membership = find_membership(&state.db, claims.custom.id)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+  if request.end_date < request.start_date {
+    return Err(StatusCode::BAD_REQUEST);
+  }
+
+  let mut declaration = residence_declarations::ActiveModel {
+    room_number: Set(membership.room_number),
+    declared_by: Set(claims.custom.id),
+    kind: Set(request.kind.clone()),
+    start_date: Set(request.start_date),
+    end_date: Set(request.end_date),
+    reason: Set(optional_text(request.reason)),
+    address: Set(optional_text(request.address)),
+    status: Set(DeclarationStatus::Pending),
+    ..Default::default()
+  };
+
+  // the register keeps the person as declared
+  let mut same_person = Condition::any();
+  match request.family_id {
+    Some(family_id) => {
+      let member = find_room_family_member(&state.db, membership.room_number, family_id).await?;
+      same_person = same_person.add(residence_declarations::Column::FamilyId.eq(member.id));
+      if let Some(national_id) = &member.national_id {
+        same_person = same_person.add(residence_declarations::Column::NationalId.eq(national_id));
+      }
+      declaration.family_id = Set(Some(member.id));
+      declaration.full_name = Set(member.name);
+      declaration.birthday = Set(Some(member.birthday));
+      declaration.gender = Set(member.gender);
+      declaration.national_id = Set(member.national_id);
+      declaration.relationship = Set(member.relationship);
+    }
+    // a guest stays in the room, only residents can be away from it
+    None => {
+      let full_name = optional_text(request.full_name).ok_or(StatusCode::BAD_REQUEST)?;
+      let national_id = optional_text(request.national_id);
+      if request.kind == DeclarationKind::TemporaryAbsence
+        || national_id
+          .as_ref()
+          .is_some_and(|national_id| !is_valid_national_id(national_id))
+      {
+        return Err(StatusCode::BAD_REQUEST);
+      }
+      same_person = match &national_id {
+        Some(national_id) => {
+          same_person.add(residence_declarations::Column::NationalId.eq(national_id))
+        }
+        None => same_person.add(
+          Condition::all()
+            .add(residence_declarations::Column::RoomNumber.eq(membership.room_number))
+            .add(residence_declarations::Column::FullName.eq(&full_name)),
+        ),
+      };
+      declaration.full_name = Set(full_name);
+      declaration.birthday = Set(request.birthday);
+      declaration.gender = Set(request.gender);
+      declaration.national_id = Set(national_id);
+    }
+  }
+
+  let overlapping = ResidenceDeclarations::find()
+    .filter(same_person)
+    .filter(residence_declarations::Column::Kind.eq(request.kind))
+    .filter(residence_declarations::Column::Status.ne(DeclarationStatus::Rejected))
+    .filter(residence_declarations::Column::StartDate.lte(request.end_date))
+    .filter(residence_declarations::Column::EndDate.gte(request.start_date))
+    .one(&state.db)
+    .await
+    .map_err(server_error)?;
+  if overlapping.is_some() {
+    return Err(StatusCode::CONFLICT);
+  }
+
+  let declaration = declaration.insert(&state.db).await.map_err(server_error)?;
+  log::info!(
+    "Residence declaration {} added for room {}",
+    declaration.id,
+    declaration.room_number
+  );
+
+  Ok((StatusCode::CREATED, Json(declaration)))
+}
+
+#[utoipa::path(
+  delete,
+  path = "/household/declarations/{id}",
+  description = "Hủy một khai báo tạm trú, tạm vắng của phòng chưa được quản lý duyệt.",
+  tag = tags::HOUSEHOLD,
+  responses(
+    (status = NO_CONTENT, description = "Declaration cancelled"),
+    (status = NOT_FOUND, description = "Declaration not found"),
+    (status = CONFLICT, description = "Declaration already reviewed"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn cancel_declaration(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+  Path(id): Path<i32>,
+) -> Result<StatusCode, StatusCode> {
+  let claims = state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+  let membership = find_membership(&state.db, claims.custom.id)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+  let declaration = ResidenceDeclarations::find_by_id(id)
+    .filter(residence_declarations::Column::RoomNumber.eq(membership.room_number))
+    .one(&state.db)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+  if declaration.status != DeclarationStatus::Pending {
+    return Err(StatusCode::CONFLICT);
+  }
+
+  ResidenceDeclarations::delete_by_id(declaration.id)
+    .exec(&state.db)
+    .await
+    .map_err(server_error)?;
+
+  Ok(StatusCode::NO_CONTENT)
+}
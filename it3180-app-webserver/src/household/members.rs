@@ -0,0 +1,610 @@
+//! The residents of a room. Every account living in a room is a member with a role, and they all
+//! see the room's fees. The householder is also the room's tenant, the one who gets billed.
+//!
+//! The householder invites other accounts, which join the room once they accept. An account lives
+//! in one room at a time.
+
+use sea_orm::{Order, QueryOrder, TransactionTrait};
+
+use crate::{
+  entities::{room_members, room_occupancies, rooms, users},
+  notify::templates::Notice,
+  prelude::*,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RoomMemberInfo {
+  pub id: i32,
+  pub room_number: i32,
+  pub user_id: i32,
+  pub name: String,
+  pub email: String,
+  pub phone: String,
+  pub role: RoomMemberRole,
+  pub invited_by: Option<i32>,
+  /// Empty while the invitation has not been accepted
+  pub joined_at: Option<DateTime>,
+}
+
+impl RoomMemberInfo {
+  fn new(member: room_members::Model, user: users::Model) -> Self {
+    Self {
+      id: member.id,
+      room_number: member.room_number,
+      user_id: member.user_id,
+      name: user.name,
+      email: user.email,
+      phone: user.phone,
+      role: member.role,
+      invited_by: member.invited_by,
+      joined_at: member.joined_at,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct InviteMemberInfo {
+  /// Username or email of the account to invite
+  pub login: String,
+  pub role: RoomMemberRole,
+}
+
+fn server_error(e: DbErr) -> StatusCode {
+  log::error!("Error: {:?}", e);
+  StatusCode::INTERNAL_SERVER_ERROR
+}
+
+/// The room a user lives in, if they joined one.
+pub(crate) async fn find_membership<C: ConnectionTrait>(
+  db: &C,
+  user_id: i32,
+) -> Result<Option<room_members::Model>, DbErr> {
+  RoomMembers::find()
+    .filter(room_members::Column::UserId.eq(user_id))
+    .filter(room_members::Column::JoinedAt.is_not_null())
+    .one(db)
+    .await
+}
+
+/// The members of a room, householder first. Pending invitations are left out unless asked for.
+pub(crate) async fn load_members<C: ConnectionTrait>(
+  db: &C,
+  room_number: i32,
+  include_pending: bool,
+) -> Result<Vec<RoomMemberInfo>, DbErr> {
+  let mut query = RoomMembers::find()
+    .find_also_related(Users)
+    .filter(room_members::Column::RoomNumber.eq(room_number))
+    .order_by(room_members::Column::Id, Order::Asc);
+  if !include_pending {
+    query = query.filter(room_members::Column::JoinedAt.is_not_null());
+  }
+
+  let mut members = query
+    .all(db)
+    .await?
+    .into_iter()
+    .filter_map(|(member, user)| Some(RoomMemberInfo::new(member, user?)))
+    .collect::<Vec<_>>();
+  members.sort_by_key(|member| member.role != RoomMemberRole::Householder);
+  Ok(members)
+}
+
+/// Record that a user started living in a room.
+async fn start_occupancy<C: ConnectionTrait>(
+  db: &C,
+  room_number: i32,
+  user_id: i32,
+  role: RoomMemberRole,
+  at: DateTime,
+) -> Result<(), DbErr> {
+  room_occupancies::ActiveModel {
+    room_number: Set(room_number),
+    user_id: Set(user_id),
+    role: Set(role),
+    moved_in_at: Set(at),
+    moved_out_at: Set(None),
+    ..Default::default()
+  }
+  .insert(db)
+  .await?;
+  Ok(())
+}
+
+/// Record that a user stopped living in a room, or in the role they had.
+async fn end_occupancy<C: ConnectionTrait>(
+  db: &C,
+  room_number: i32,
+  user_id: i32,
+  at: DateTime,
+) -> Result<(), DbErr> {
+  RoomOccupancies::update_many()
+    .col_expr(room_occupancies::Column::MovedOutAt, Expr::value(at))
+    .filter(room_occupancies::Column::RoomNumber.eq(room_number))
+    .filter(room_occupancies::Column::UserId.eq(user_id))
+    .filter(room_occupancies::Column::MovedOutAt.is_null())
+    .exec(db)
+    .await?;
+  Ok(())
+}
+
+/// Make a user live in a room with a role, from `at`. A new householder also becomes the tenant
+/// of the room, and the previous one stays as a co-resident. The caller checks that the user does
+/// not live in another room.
+pub(crate) async fn add_resident<C: ConnectionTrait>(
+  db: &C,
+  room: rooms::Model,
+  user_id: i32,
+  role: RoomMemberRole,
+  at: DateTime,
+) -> Result<(), DbErr> {
+  let room_number = room.room_number;
+
+  if role == RoomMemberRole::Householder {
+    let previous = RoomMembers::find()
+      .filter(room_members::Column::RoomNumber.eq(room_number))
+      .filter(room_members::Column::Role.eq(RoomMemberRole::Householder))
+      .filter(room_members::Column::UserId.ne(user_id))
+      .one(db)
+      .await?;
+    if let Some(previous) = previous {
+      end_occupancy(db, room_number, previous.user_id, at).await?;
+      start_occupancy(
+        db,
+        room_number,
+        previous.user_id,
+        RoomMemberRole::CoResident,
+        at,
+      )
+      .await?;
+      room_members::ActiveModel {
+        role: Set(RoomMemberRole::CoResident),
+        ..previous.into()
+      }
+      .update(db)
+      .await?;
+    }
+  }
+
+  let existing = RoomMembers::find()
+    .filter(room_members::Column::RoomNumber.eq(room_number))
+    .filter(room_members::Column::UserId.eq(user_id))
+    .one(db)
+    .await?;
+  match existing {
+    // already living here, only the role changes
+    Some(member) if member.joined_at.is_some() => {
+      if member.role != role {
+        end_occupancy(db, room_number, user_id, at).await?;
+        start_occupancy(db, room_number, user_id, role.clone(), at).await?;
+        room_members::ActiveModel {
+          role: Set(role.clone()),
+          ..member.into()
+        }
+        .update(db)
+        .await?;
+      }
+    }
+    Some(member) => {
+      start_occupancy(db, room_number, user_id, role.clone(), at).await?;
+      room_members::ActiveModel {
+        role: Set(role.clone()),
+        joined_at: Set(Some(at)),
+        ..member.into()
+      }
+      .update(db)
+      .await?;
+    }
+    None => {
+      start_occupancy(db, room_number, user_id, role.clone(), at).await?;
+      room_members::ActiveModel {
+        room_number: Set(room_number),
+        user_id: Set(user_id),
+        role: Set(role.clone()),
+        invited_by: Set(None),
+        joined_at: Set(Some(at)),
+        created_at: Set(chrono::Utc::now().naive_utc()),
+        ..Default::default()
+      }
+      .insert(db)
+      .await?;
+    }
+  }
+
+  // the user joined this room, the other invitations are no longer needed
+  RoomMembers::delete_many()
+    .filter(room_members::Column::UserId.eq(user_id))
+    .filter(room_members::Column::JoinedAt.is_null())
+    .exec(db)
+    .await?;
+
+  if role == RoomMemberRole::Householder {
+    rooms::ActiveModel {
+      tenant_id: Set(Some(user_id)),
+      status: Set(RoomStatus::Occupied),
+      ..room.into()
+    }
+    .update(db)
+    .await?;
+  }
+
+  Ok(())
+}
+
+/// Remove a member or an invitation from a room. A householder leaving also leaves the room
+/// without a tenant, and vacant.
+pub(crate) async fn remove_resident<C: ConnectionTrait>(
+  db: &C,
+  member: &room_members::Model,
+  at: DateTime,
+) -> Result<(), DbErr> {
+  if member.joined_at.is_some() {
+    end_occupancy(db, member.room_number, member.user_id, at).await?;
+  }
+  RoomMembers::delete_by_id(member.id).exec(db).await?;
+
+  if member.role == RoomMemberRole::Householder {
+    if let Some(room) = Rooms::find_by_id(member.room_number).one(db).await? {
+      rooms::ActiveModel {
+        tenant_id: Set(None),
+        status: Set(RoomStatus::Vacant),
+        ..room.into()
+      }
+      .update(db)
+      .await?;
+    }
+  }
+
+  log::info!("Room member removed: {:?}", member);
+  Ok(())
+}
+
+#[utoipa::path(
+  get,
+  path = "/household/members",
+  description = "Lấy danh sách các thành viên của phòng mà người dùng đang ở, bao gồm cả các lời mời chưa được chấp nhận.",
+  tag = tags::HOUSEHOLD,
+  responses(
+    (status = OK, description = "Room members", body = Vec<RoomMemberInfo>),
+    (status = NOT_FOUND, description = "User has no room"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn get_room_members(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+) -> Result<Json<Vec<RoomMemberInfo>>, StatusCode> {
+  let claims = state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+  let membership = find_membership(&state.db, claims.custom.id)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+  let members = load_members(&state.db, membership.room_number, true)
+    .await
+    .map_err(server_error)?;
+
+  Ok(Json(members))
+}
+
+#[utoipa::path(
+  post,
+  path = "/household/members",
+  description = "Mời một tài khoản khác vào phòng, chỉ chủ hộ mới được mời. Tài khoản được mời trở thành thành viên của phòng
+  sau khi chấp nhận lời mời. Không thể mời thêm chủ hộ.",
+  tag = tags::HOUSEHOLD,
+  request_body = InviteMemberInfo,
+  responses(
+    (status = CREATED, description = "Member invited", body = RoomMemberInfo),
+    (status = BAD_REQUEST, description = "Invalid role or account"),
+    (status = FORBIDDEN, description = "User is not the householder"),
+    (status = NOT_FOUND, description = "User has no room or account not found"),
+    (status = CONFLICT, description = "Account is already a member or lives in another room"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn invite_room_member(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+  Json(invite): Json<InviteMemberInfo>,
+) -> Result<(StatusCode, Json<RoomMemberInfo>), StatusCode> {
+  let claims = state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+  let user_id = claims.custom.id;
+
+  let membership = find_membership(&state.db, user_id)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+  if membership.role != RoomMemberRole::Householder {
+    return Err(StatusCode::FORBIDDEN);
+  }
+  if invite.role == RoomMemberRole::Householder {
+    return Err(StatusCode::BAD_REQUEST);
+  }
+
+  let login = invite.login.trim();
+  let invitee = Users::find()
+    .filter(
+      Condition::any()
+        .add(users::Column::Username.eq(login))
+        .add(users::Column::Email.eq(login)),
+    )
+    .one(&state.db)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+  if invitee.role != UserRole::Tenant || invitee.id == user_id {
+    return Err(StatusCode::BAD_REQUEST);
+  }
+
+  let already_member = RoomMembers::find()
+    .filter(room_members::Column::RoomNumber.eq(membership.room_number))
+    .filter(room_members::Column::UserId.eq(invitee.id))
+    .one(&state.db)
+    .await
+    .map_err(server_error)?;
+  let elsewhere = find_membership(&state.db, invitee.id)
+    .await
+    .map_err(server_error)?;
+  if already_member.is_some() || elsewhere.is_some() {
+    return Err(StatusCode::CONFLICT);
+  }
+
+  let member = room_members::ActiveModel {
+    room_number: Set(membership.room_number),
+    user_id: Set(invitee.id),
+    role: Set(invite.role),
+    invited_by: Set(Some(user_id)),
+    joined_at: Set(None),
+    created_at: Set(chrono::Utc::now().naive_utc()),
+    ..Default::default()
+  }
+  .insert(&state.db)
+  .await
+  .map_err(server_error)?;
+  log::info!("Room member invited: {:?}", member);
+
+  if let Err(e) = state
+    .notifier
+    .notify_event(
+      &state.db,
+      user_id,
+      &invitee,
+      &Notice::new(NotificationEvent::RoomInvitation).text("room", member.room_number),
+    )
+    .await
+  {
+    log::error!("Failed to notify invited member: {:?}", e);
+  }
+
+  Ok((
+    StatusCode::CREATED,
+    Json(RoomMemberInfo::new(member, invitee)),
+  ))
+}
+
+#[utoipa::path(
+  delete,
+  path = "/household/members/{user_id}",
+  description = "Xóa một thành viên hoặc hủy lời mời, chỉ chủ hộ mới được xóa thành viên khác. Thành viên có thể tự rời khỏi phòng,
+  trừ chủ hộ.",
+  tag = tags::HOUSEHOLD,
+  responses(
+    (status = NO_CONTENT, description = "Member removed"),
+    (status = FORBIDDEN, description = "User is not the householder"),
+    (status = NOT_FOUND, description = "User has no room or member not found"),
+    (status = CONFLICT, description = "The householder cannot be removed"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn remove_room_member(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+  Path(user_id): Path<i32>,
+) -> Result<StatusCode, StatusCode> {
+  let claims = state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+  let membership = find_membership(&state.db, claims.custom.id)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+  if user_id != claims.custom.id && membership.role != RoomMemberRole::Householder {
+    return Err(StatusCode::FORBIDDEN);
+  }
+
+  let member = RoomMembers::find()
+    .filter(room_members::Column::RoomNumber.eq(membership.room_number))
+    .filter(room_members::Column::UserId.eq(user_id))
+    .one(&state.db)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+  if member.role == RoomMemberRole::Householder {
+    return Err(StatusCode::CONFLICT);
+  }
+
+  let txn = state.db.begin().await.map_err(server_error)?;
+  remove_resident(&txn, &member, chrono::Utc::now().naive_utc())
+    .await
+    .map_err(server_error)?;
+  txn.commit().await.map_err(server_error)?;
+
+  Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+  get,
+  path = "/household/invitations",
+  description = "Lấy danh sách các lời mời vào phòng mà người dùng chưa trả lời.",
+  tag = tags::HOUSEHOLD,
+  responses(
+    (status = OK, description = "Pending invitations", body = Vec<RoomMemberInfo>),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn get_invitations(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+) -> Result<Json<Vec<RoomMemberInfo>>, StatusCode> {
+  let claims = state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+  let invitations = RoomMembers::find()
+    .find_also_related(Users)
+    .filter(room_members::Column::UserId.eq(claims.custom.id))
+    .filter(room_members::Column::JoinedAt.is_null())
+    .order_by(room_members::Column::Id, Order::Asc)
+    .all(&state.db)
+    .await
+    .map_err(server_error)?;
+
+  Ok(Json(
+    invitations
+      .into_iter()
+      .filter_map(|(member, user)| Some(RoomMemberInfo::new(member, user?)))
+      .collect(),
+  ))
+}
+
+/// Find a pending invitation of a user.
+async fn find_invitation(
+  state: &AppState,
+  id: i32,
+  user_id: i32,
+) -> Result<room_members::Model, StatusCode> {
+  RoomMembers::find_by_id(id)
+    .filter(room_members::Column::UserId.eq(user_id))
+    .filter(room_members::Column::JoinedAt.is_null())
+    .one(&state.db)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)
+}
+
+#[utoipa::path(
+  post,
+  path = "/household/invitations/{id}/accept",
+  description = "Chấp nhận lời mời vào phòng. Người dùng đang là thành viên của một phòng khác không thể chấp nhận.",
+  tag = tags::HOUSEHOLD,
+  responses(
+    (status = OK, description = "Invitation accepted", body = RoomMemberInfo),
+    (status = NOT_FOUND, description = "Invitation not found"),
+    (status = CONFLICT, description = "User already lives in a room"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn accept_invitation(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+  Path(id): Path<i32>,
+) -> Result<Json<RoomMemberInfo>, StatusCode> {
+  let claims = state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+  let user_id = claims.custom.id;
+
+  let invitation = find_invitation(&state, id, user_id).await?;
+  let txn = state.db.begin().await.map_err(server_error)?;
+  if find_membership(&txn, user_id)
+    .await
+    .map_err(server_error)?
+    .is_some()
+  {
+    return Err(StatusCode::CONFLICT);
+  }
+
+  let room = Rooms::find_by_id(invitation.room_number)
+    .one(&txn)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+  add_resident(
+    &txn,
+    room,
+    user_id,
+    invitation.role.clone(),
+    chrono::Utc::now().naive_utc(),
+  )
+  .await
+  .map_err(server_error)?;
+  let member = RoomMembers::find_by_id(invitation.id)
+    .one(&txn)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+  txn.commit().await.map_err(server_error)?;
+  log::info!("Room invitation accepted: {:?}", member);
+
+  let user = Users::find_by_id(user_id)
+    .one(&state.db)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+  Ok(Json(RoomMemberInfo::new(member, user)))
+}
+
+#[utoipa::path(
+  delete,
+  path = "/household/invitations/{id}",
+  description = "Từ chối lời mời vào phòng.",
+  tag = tags::HOUSEHOLD,
+  responses(
+    (status = NO_CONTENT, description = "Invitation declined"),
+    (status = NOT_FOUND, description = "Invitation not found"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn decline_invitation(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+  Path(id): Path<i32>,
+) -> Result<StatusCode, StatusCode> {
+  let claims = state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+  let invitation = find_invitation(&state, id, claims.custom.id).await?;
+  RoomMembers::delete_by_id(invitation.id)
+    .exec(&state.db)
+    .await
+    .map_err(server_error)?;
+  log::info!("Room invitation declined: {:?}", invitation);
+
+  Ok(StatusCode::NO_CONTENT)
+}
@@ -1,8 +1,14 @@
+pub mod declarations;
+pub mod members;
+pub mod vehicles;
+
 use axum_extra::extract::Query;
-use sea_orm::{FromQueryResult, IntoActiveModel, QuerySelect};
+use sea_orm::{FromQueryResult, IntoActiveModel, QuerySelect, TransactionTrait};
 
 use crate::{
   entities::{fee_recurrence, fees, fees_room_assignment, transactions},
+  ledger,
+  manager::{categories, history},
   prelude::*,
 };
 
@@ -12,27 +18,52 @@ pub struct FeesRoomInfo {
   pub room_number: i32,
   pub fee_id: i32,
   pub fee_name: String,
+  pub fee_category: FeeCategory,
   pub fee_amount: Option<i64>,
   pub due_date: DateTime,
   pub payment_date: Option<DateTime>,
   pub is_paid: bool,
+  /// Not paid and past the due date
+  pub overdue: bool,
+}
+
+/// Check if an unpaid fee is past its due date.
+pub(crate) fn is_overdue(due_date: DateTime, is_paid: bool) -> bool {
+  !is_paid && due_date < chrono::Utc::now().naive_utc()
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default, ToSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct PersonalHouseholdInfo {
   room_number: i32,
   tenant_id: i32,
   tenant_name: String,
   tenant_email: String,
   tenant_phone: String,
+  /// Role of the user in the room
+  role: RoomMemberRole,
+  members: Vec<members::RoomMemberInfo>,
   fees: Vec<FeesRoomInfo>,
 }
 
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct HouseholdFeesParams {
+  /// Only the fees of this category
+  #[param(inline)]
+  pub category: Option<FeeCategory>,
+  /// Only the fees with this tag
+  pub tag: Option<String>,
+}
+
 #[utoipa::path(
   get,
   path = "/household",
-  description = "Lấy thông tin về hộ gia đình của người dùng. Nếu người dùng hợp lệ, trả về thông tin người dùng và phòng mà người dùng đang thuê dưới dạng JSON.",
+  description = "Lấy thông tin về hộ gia đình của người dùng. Nếu người dùng hợp lệ, trả về thông tin người dùng, phòng mà người dùng đang ở
+  và các thành viên của phòng dưới dạng JSON. Mọi thành viên của phòng đều xem được các khoản phí của phòng.
+  Có thể lọc các khoản phí theo danh mục `category` và nhãn `tag`.",
   tag = tags::HOUSEHOLD,
+  params(
+    HouseholdFeesParams
+  ),
   responses(
     (status = OK, description = "Household info"),
     (status = UNAUTHORIZED, description = "Unauthorized"),
@@ -46,6 +77,7 @@ pub struct PersonalHouseholdInfo {
 pub async fn get_household_info(
   State(state): State<AppState>,
   TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+  Query(HouseholdFeesParams { category, tag }): Query<HouseholdFeesParams>,
 ) -> Result<Json<PersonalHouseholdInfo>, StatusCode> {
   let jwt_access_secret = &state.jwt_access_secret;
 
@@ -58,9 +90,8 @@ pub async fn get_household_info(
 
   let user_id = claims.custom.id;
 
-  // find user by id and the room that the user is renting
+  // find user by id and the room that the user lives in
   let user = Users::find_by_id(user_id)
-    .find_also_related(Rooms)
     .one(&state.db)
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
@@ -72,9 +103,15 @@ pub async fn get_household_info(
     }
   };
 
-  if user.1.is_none() {
-    return Err(StatusCode::NOT_FOUND);
-  }
+  let membership = match members::find_membership(&state.db, user_id)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+  {
+    Some(membership) => membership,
+    None => {
+      return Err(StatusCode::NOT_FOUND);
+    }
+  };
 
   #[derive(Debug, Clone, Serialize, Deserialize, Default, FromQueryResult)]
   struct FeesAssignmentDetail {
@@ -87,6 +124,7 @@ pub async fn get_household_info(
 
     // fees
     fee_name: String,
+    fee_category: FeeCategory,
     fee_amount: i64,
 
     // transactions
@@ -94,15 +132,16 @@ pub async fn get_household_info(
   }
 
   // find fees that the user has to pay
-  let fees = FeesRoomAssignment::find()
+  let mut query = FeesRoomAssignment::find()
     .column_as(fees_room_assignment::Column::AssignmentId, "assignment_id")
     .column_as(fees_room_assignment::Column::RoomNumber, "room_number")
     .column_as(fees_room_assignment::Column::FeeId, "fee_id")
     .column_as(fees_room_assignment::Column::DueDate, "due_date")
     .column_as(fees_room_assignment::Column::PaymentDate, "payment_date")
     .column_as(fees_room_assignment::Column::IsPaid, "is_paid")
-    .column_as(fees::Column::Name, "fee_name")
-    .column_as(fees::Column::Amount, "fee_amount")
+    .column_as(fees_room_assignment::Column::FeeName, "fee_name")
+    .column_as(fees::Column::Category, "fee_category")
+    .column_as(fees_room_assignment::Column::Amount, "fee_amount")
     .column_as(transactions::Column::Amount, "transaction_amount")
     .join(
       sea_orm::JoinType::Join,
@@ -112,7 +151,17 @@ pub async fn get_household_info(
       sea_orm::JoinType::LeftJoin,
       fees_room_assignment::Relation::Transactions.def(),
     )
-    .filter(fees_room_assignment::Column::RoomNumber.eq(user.1.as_ref().unwrap().room_number))
+    .filter(fees_room_assignment::Column::RoomNumber.eq(membership.room_number));
+  if let Some(category) = category {
+    query = query.filter(fees::Column::Category.eq(category));
+  }
+  if let Some(tag) = tag {
+    let fee_ids = categories::fee_ids_with_tag(&state.db, &tag)
+      .await
+      .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    query = query.filter(fees_room_assignment::Column::FeeId.is_in(fee_ids));
+  }
+  let fees = query
     .into_model::<FeesAssignmentDetail>()
     .all(&state.db)
     .await
@@ -125,6 +174,7 @@ pub async fn get_household_info(
       room_number: fee.room_number,
       fee_id: fee.fee_id,
       fee_name: fee.fee_name,
+      fee_category: fee.fee_category,
       fee_amount: match fee.is_paid {
         true => fee.transaction_amount,
         false => Some(fee.fee_amount),
@@ -132,15 +182,31 @@ pub async fn get_household_info(
       due_date: fee.due_date,
       payment_date: fee.payment_date,
       is_paid: fee.is_paid,
+      overdue: is_overdue(fee.due_date, fee.is_paid),
     })
     .collect::<Vec<_>>();
 
+  let members = members::load_members(&state.db, membership.room_number, false)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+  // the tenant is the householder, whoever of the household asks
+  let tenant = Rooms::find_by_id(membership.room_number)
+    .find_also_related(Users)
+    .one(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .and_then(|(_, tenant)| tenant)
+    .unwrap_or(user);
+
   let household_info = PersonalHouseholdInfo {
-    room_number: user.1.as_ref().unwrap().room_number,
-    tenant_id: user.0.id,
-    tenant_name: user.0.name.clone(),
-    tenant_email: user.0.email.clone(),
-    tenant_phone: user.0.phone.clone(),
+    room_number: membership.room_number,
+    tenant_id: tenant.id,
+    tenant_name: tenant.name.clone(),
+    tenant_email: tenant.email.clone(),
+    tenant_phone: tenant.phone.clone(),
+    role: membership.role,
+    members,
     fees,
     // fees: vec![],
   };
@@ -220,39 +286,57 @@ pub async fn pay_fee(
   if fee.0.is_paid {
     return Ok(StatusCode::OK);
   }
+  let amount = fee.0.amount;
+  let room_number = fee.0.room_number;
+  let server_error = |e: DbErr| {
+    log::error!("Error: {:?}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+  };
+  // the payment, its ledger entry and the next fees are written together
+  let txn = state.db.begin().await.map_err(server_error)?;
   // mark the fee as paid
   let mut fee_assignment = fee.0.into_active_model();
   fee_assignment.is_paid = Set(true);
   fee_assignment.payment_date = Set(Some(chrono::Utc::now().naive_utc()));
   let insert_result = fees_room_assignment::Entity::update(fee_assignment)
-    .exec(&state.db)
+    .exec(&txn)
     .await
     .map_err(|e| {
       log::error!("Failed to mark fee as paid: {}", e);
       StatusCode::INTERNAL_SERVER_ERROR
     })?;
   // fee
-  //   .save(&state.db)
+  //   .save(&txn)
   //   .await
   //   .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
   // add a new transaction
   let new_transaction = transactions::ActiveModel {
-    amount: Set(fee.1.as_ref().unwrap().amount),
+    amount: Set(amount),
     created_at: Set(chrono::Utc::now().naive_utc()),
     assignment_id: Set(insert_result.assignment_id),
     ..Default::default()
   };
-  new_transaction
-    .save(&state.db)
+  let new_transaction = new_transaction
+    .insert(&txn)
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+  ledger::post_payment(
+    &txn,
+    new_transaction.id,
+    new_transaction.assignment_id,
+    room_number,
+    amount,
+    ledger::BANK,
+  )
+  .await
+  .map_err(server_error)?;
 
   // check if the fee is in a recurrence chain and assign the next fee to the room
   let next_recurrence = match FeeRecurrence::find()
     .filter(fee_recurrence::Column::PreviousFeeId.eq(fee_id))
     .filter(fee_recurrence::Column::FeeId.ne(fee_id))
-    .one(&state.db)
+    .one(&txn)
     .await
   {
     Ok(entry) => entry,
@@ -262,24 +346,37 @@ pub async fn pay_fee(
     }
   };
   if let Some(next_recurrence) = next_recurrence {
+    let next_fee = Fees::find_by_id(next_recurrence.fee_id)
+      .one(&txn)
+      .await
+      .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let Some(next_fee) = next_fee else {
+      return Err(StatusCode::NOT_FOUND);
+    };
     // assign the next fee to the current room
     let new_fee = fees_room_assignment::ActiveModel {
       room_number: Set(user.1.as_ref().unwrap().room_number),
       fee_id: Set(next_recurrence.fee_id),
       due_date: Set(next_recurrence.due_date),
+      fee_name: Set(next_fee.name.clone()),
+      amount: Set(next_fee.amount),
       ..Default::default()
     };
-    new_fee.save(&state.db).await.map_err(|e| {
+    let new_assignment = new_fee.insert(&txn).await.map_err(|e| {
       log::error!("Failed to save new fee assignment: {}", e);
       StatusCode::INTERNAL_SERVER_ERROR
     })?;
+    ledger::post_charge(&txn, &new_assignment, &next_fee.category, None)
+      .await
+      .map_err(server_error)?;
+    txn.commit().await.map_err(server_error)?;
     return Ok(StatusCode::OK);
   }
 
   // check if the fee is the last in a recurrence chain and create a new fee for the next due date
   let recurrence_entry = match FeeRecurrence::find()
     .filter(fee_recurrence::Column::FeeId.eq(fee_id))
-    .one(&state.db)
+    .one(&txn)
     .await
   {
     Ok(entry) => entry,
@@ -291,7 +388,7 @@ pub async fn pay_fee(
   if recurrence_entry.is_some() {
     // find the fee that is being paid
     let old_fee = Fees::find_by_id(fee_id)
-      .one(&state.db)
+      .one(&txn)
       .await
       .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     let old_fee = match old_fee {
@@ -315,24 +412,42 @@ pub async fn pay_fee(
       is_recurring: Set(true),
       due_date: Set(new_due_date),
       recurrence_type: Set(old_fee.recurrence_type.clone()),
+      category: Set(old_fee.category.clone()),
       ..Default::default()
     };
-    let new_fee = new_fee.save(&state.db).await.map_err(|e| {
+    let new_fee = new_fee.insert(&txn).await.map_err(|e| {
       log::error!("Failed to save new fee: {}", e);
       StatusCode::INTERNAL_SERVER_ERROR
     })?;
-    let new_fee_id = new_fee.id.unwrap();
+    history::record_fee_version(&txn, &new_fee, None)
+      .await
+      .map_err(|e| {
+        log::error!("Failed to save fee version: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+      })?;
+    categories::copy_fee_tags(&txn, fee_id, new_fee.id)
+      .await
+      .map_err(|e| {
+        log::error!("Failed to copy fee tags: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+      })?;
+    let new_fee_id = new_fee.id;
     // assign the new fee to the room
     let new_assignment = fees_room_assignment::ActiveModel {
       room_number: Set(user.1.as_ref().unwrap().room_number),
       fee_id: Set(new_fee_id),
       due_date: Set(new_due_date),
+      fee_name: Set(new_fee.name.clone()),
+      amount: Set(new_fee.amount),
       ..Default::default()
     };
-    new_assignment.save(&state.db).await.map_err(|e| {
+    let new_assignment = new_assignment.insert(&txn).await.map_err(|e| {
       log::error!("Failed to save new fee assignment: {}", e);
       StatusCode::INTERNAL_SERVER_ERROR
     })?;
+    ledger::post_charge(&txn, &new_assignment, &new_fee.category, None)
+      .await
+      .map_err(server_error)?;
     // create a new recurrence chain
     let new_recurrence = fee_recurrence::ActiveModel {
       fee_id: Set(new_fee_id),
@@ -340,11 +455,13 @@ pub async fn pay_fee(
       due_date: Set(new_due_date),
       ..Default::default()
     };
-    new_recurrence.save(&state.db).await.map_err(|e| {
+    new_recurrence.save(&txn).await.map_err(|e| {
       log::error!("Failed to save new recurrence: {}", e);
       StatusCode::INTERNAL_SERVER_ERROR
     })?;
   }
 
+  txn.commit().await.map_err(server_error)?;
+
   Ok(StatusCode::OK)
 }
@@ -0,0 +1,198 @@
+//! Vehicles registered by the residents of a room for parking in the building. A vehicle is
+//! parked, and billed, once a manager approves it.
+
+use sea_orm::{Order, QueryOrder};
+
+use super::members::find_membership;
+use crate::{
+  entities::vehicles,
+  family::optional_text,
+  manager::vehicles::{deregister_vehicle, normalize_plate},
+  prelude::*,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct VehicleRequest {
+  pub vehicle_type: VehicleType,
+  /// Required for motorbikes and cars
+  pub plate_number: Option<String>,
+  pub brand: Option<String>,
+  pub color: Option<String>,
+}
+
+fn server_error(e: DbErr) -> StatusCode {
+  log::error!("Error: {:?}", e);
+  StatusCode::INTERNAL_SERVER_ERROR
+}
+
+#[utoipa::path(
+  get,
+  path = "/household/vehicles",
+  description = "Lấy danh sách các xe đăng ký gửi của phòng mà người dùng đang ở, bao gồm cả các xe chờ duyệt, bị từ chối hoặc đã hủy.",
+  tag = tags::HOUSEHOLD,
+  responses(
+    (status = OK, description = "Vehicles", body = Vec<vehicles::Model>),
+    (status = NOT_FOUND, description = "User has no room"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn get_vehicles(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+) -> Result<Json<Vec<vehicles::Model>>, StatusCode> {
+  let claims = state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+  let membership = find_membership(&state.db, claims.custom.id)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+  let vehicles = Vehicles::find()
+    .filter(vehicles::Column::RoomNumber.eq(membership.room_number))
+    .order_by(vehicles::Column::CreatedAt, Order::Desc)
+    .all(&state.db)
+    .await
+    .map_err(server_error)?;
+
+  Ok(Json(vehicles))
+}
+
+#[utoipa::path(
+  post,
+  path = "/household/vehicles",
+  description = "Đăng ký gửi một xe cho phòng mà người dùng đang ở. Xe máy và ô tô cần có biển số, mỗi biển số chỉ được đăng ký một lần.
+  Xe cần được quản lý duyệt, sau đó phòng sẽ được tính phí gửi xe theo loại xe.",
+  tag = tags::HOUSEHOLD,
+  request_body = VehicleRequest,
+  responses(
+    (status = CREATED, description = "Vehicle registered", body = vehicles::Model),
+    (status = BAD_REQUEST, description = "Missing plate number"),
+    (status = NOT_FOUND, description = "User has no room"),
+    (status = CONFLICT, description = "Plate number already registered"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn register_vehicle(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+  Json(request): Json<VehicleRequest>,
+) -> Result<(StatusCode, Json<vehicles::Model>), StatusCode> {
+  let claims = state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+  let membership = find_membership(&state.db, claims.custom.id)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+  let plate_number = request
+    .plate_number
+    .as_deref()
+    .map(normalize_plate)
+    .filter(|plate| !plate.is_empty());
+  match (&request.vehicle_type, &plate_number) {
+    (VehicleType::Motorbike | VehicleType::Car, None) => return Err(StatusCode::BAD_REQUEST),
+    (_, Some(plate_number)) => {
+      let registered = Vehicles::find()
+        .filter(vehicles::Column::PlateNumber.eq(plate_number))
+        .filter(vehicles::Column::Status.is_in([VehicleStatus::Pending, VehicleStatus::Approved]))
+        .one(&state.db)
+        .await
+        .map_err(server_error)?;
+      if registered.is_some() {
+        log::info!("Plate number already registered: {}", plate_number);
+        return Err(StatusCode::CONFLICT);
+      }
+    }
+    _ => {}
+  }
+
+  let vehicle = vehicles::ActiveModel {
+    room_number: Set(membership.room_number),
+    registered_by: Set(Some(claims.custom.id)),
+    vehicle_type: Set(request.vehicle_type),
+    plate_number: Set(plate_number),
+    brand: Set(optional_text(request.brand)),
+    color: Set(optional_text(request.color)),
+    status: Set(VehicleStatus::Pending),
+    ..Default::default()
+  }
+  .insert(&state.db)
+  .await
+  .map_err(server_error)?;
+  log::info!(
+    "Vehicle {} registered for room {}",
+    vehicle.id,
+    vehicle.room_number
+  );
+
+  Ok((StatusCode::CREATED, Json(vehicle)))
+}
+
+#[utoipa::path(
+  delete,
+  path = "/household/vehicles/{id}",
+  description = "Hủy đăng ký gửi một xe của phòng. Xe chờ duyệt bị xóa, xe đã được duyệt được chuyển sang trạng thái đã hủy
+  và không còn được tính phí gửi xe ở các kỳ sau.",
+  tag = tags::HOUSEHOLD,
+  responses(
+    (status = NO_CONTENT, description = "Vehicle removed"),
+    (status = NOT_FOUND, description = "Vehicle not found"),
+    (status = CONFLICT, description = "Vehicle was rejected or already removed"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn remove_vehicle(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+  Path(id): Path<i32>,
+) -> Result<StatusCode, StatusCode> {
+  let claims = state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+  let membership = find_membership(&state.db, claims.custom.id)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+  let vehicle = Vehicles::find_by_id(id)
+    .filter(vehicles::Column::RoomNumber.eq(membership.room_number))
+    .one(&state.db)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+  match vehicle.status {
+    VehicleStatus::Pending => {
+      Vehicles::delete_by_id(vehicle.id)
+        .exec(&state.db)
+        .await
+        .map_err(server_error)?;
+    }
+    VehicleStatus::Approved => {
+      deregister_vehicle(&state, vehicle)
+        .await
+        .map_err(server_error)?;
+    }
+    VehicleStatus::Rejected | VehicleStatus::Removed => return Err(StatusCode::CONFLICT),
+  }
+
+  Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,1120 @@
+//! Double-entry ledger behind all money movements.
+//!
+//! Every charge, payment, adjustment and refund is an entry made of postings that debit and credit
+//! accounts by the same total. Assets and expenses grow with debits, liabilities and income grow
+//! with credits. Accounts are identified by a code:
+//!
+//! - `bank`, `cash`: where the money is
+//! - `receivable:<room>`: what a room owes
+//! - `credit:<room>`: what the building owes a room, e.g. an overpayment to refund
+//! - `income:fees`, `income:utilities`, `income:parking`, `income:contributions`,
+//!   `income:penalties`: what the building earned, by fee category
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use axum_extra::extract::Query;
+use sea_orm::{FromQueryResult, Order, QueryOrder, QuerySelect, TransactionTrait};
+
+use crate::{
+  entities::{fees_room_assignment, ledger_accounts, ledger_entries, ledger_postings},
+  manager::reports::{load_balances, ReportFormat, ReportParams},
+  prelude::*,
+};
+
+pub const BANK: &str = "bank";
+pub const CASH: &str = "cash";
+pub const FEE_INCOME: &str = "income:fees";
+pub const UTILITIES_INCOME: &str = "income:utilities";
+pub const PARKING_INCOME: &str = "income:parking";
+pub const CONTRIBUTION_INCOME: &str = "income:contributions";
+pub const PENALTY_INCOME: &str = "income:penalties";
+
+pub fn receivable(room_number: i32) -> String {
+  format!("receivable:{}", room_number)
+}
+
+/// The income account that fees of a category are booked into.
+pub fn income_account(category: &FeeCategory) -> &'static str {
+  match category {
+    FeeCategory::Service => FEE_INCOME,
+    FeeCategory::Utilities => UTILITIES_INCOME,
+    FeeCategory::Parking => PARKING_INCOME,
+    FeeCategory::Contribution => CONTRIBUTION_INCOME,
+    FeeCategory::Penalty => PENALTY_INCOME,
+  }
+}
+
+/// Name, type and room of the account with the given code, if the code is valid.
+fn describe_account(code: &str) -> Option<(String, LedgerAccountType, Option<i32>)> {
+  match code {
+    BANK => {
+      return Some((
+        "Tiền gửi ngân hàng".to_string(),
+        LedgerAccountType::Asset,
+        None,
+      ))
+    }
+    CASH => return Some(("Tiền mặt".to_string(), LedgerAccountType::Asset, None)),
+    FEE_INCOME => return Some(("Doanh thu phí".to_string(), LedgerAccountType::Income, None)),
+    UTILITIES_INCOME => {
+      return Some((
+        "Doanh thu điện nước".to_string(),
+        LedgerAccountType::Income,
+        None,
+      ))
+    }
+    PARKING_INCOME => {
+      return Some((
+        "Doanh thu gửi xe".to_string(),
+        LedgerAccountType::Income,
+        None,
+      ))
+    }
+    CONTRIBUTION_INCOME => {
+      return Some((
+        "Các khoản đóng góp".to_string(),
+        LedgerAccountType::Income,
+        None,
+      ))
+    }
+    PENALTY_INCOME => return Some(("Thu tiền phạt".to_string(), LedgerAccountType::Income, None)),
+    _ => {}
+  }
+
+  let (prefix, room_number) = code.split_once(':')?;
+  let room_number = room_number.parse::<i32>().ok()?;
+  match prefix {
+    "receivable" => Some((
+      format!("Phải thu phòng {}", room_number),
+      LedgerAccountType::Asset,
+      Some(room_number),
+    )),
+    "credit" => Some((
+      format!("Tiền trả trước phòng {}", room_number),
+      LedgerAccountType::Liability,
+      Some(room_number),
+    )),
+    _ => None,
+  }
+}
+
+/// Get an account by code, creating it on first use.
+pub(crate) async fn ensure_account<C: ConnectionTrait>(
+  db: &C,
+  code: &str,
+) -> Result<ledger_accounts::Model, DbErr> {
+  let account = LedgerAccounts::find()
+    .filter(ledger_accounts::Column::Code.eq(code))
+    .one(db)
+    .await?;
+  if let Some(account) = account {
+    return Ok(account);
+  }
+
+  let Some((name, account_type, room_number)) = describe_account(code) else {
+    return Err(DbErr::Custom(format!("unknown ledger account: {}", code)));
+  };
+  let account = ledger_accounts::ActiveModel {
+    code: Set(code.to_string()),
+    name: Set(name),
+    account_type: Set(account_type),
+    room_number: Set(room_number),
+    created_at: Set(chrono::Utc::now().naive_utc()),
+    ..Default::default()
+  };
+
+  account.insert(db).await
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PostingLine {
+  /// Account code, e.g. `receivable:101`
+  pub account: String,
+  #[serde(default)]
+  pub debit: i64,
+  #[serde(default)]
+  pub credit: i64,
+}
+
+impl PostingLine {
+  pub fn debit(account: &str, amount: i64) -> Self {
+    Self {
+      account: account.to_string(),
+      debit: amount,
+      credit: 0,
+    }
+  }
+
+  pub fn credit(account: &str, amount: i64) -> Self {
+    Self {
+      account: account.to_string(),
+      debit: 0,
+      credit: amount,
+    }
+  }
+}
+
+pub(crate) struct NewEntry {
+  pub kind: LedgerEntryKind,
+  pub description: String,
+  pub assignment_id: Option<i32>,
+  pub transaction_id: Option<i32>,
+  pub created_by: Option<i32>,
+  pub lines: Vec<PostingLine>,
+}
+
+/// Check that the lines make a valid entry, returns why not.
+pub(crate) fn validate_lines(lines: &[PostingLine]) -> Result<(), String> {
+  if lines.len() < 2 {
+    return Err("an entry needs at least two lines".to_string());
+  }
+  for line in lines {
+    if line.debit < 0 || line.credit < 0 {
+      return Err(format!("negative amount on {}", line.account));
+    }
+    if (line.debit == 0) == (line.credit == 0) {
+      return Err(format!(
+        "{} must have either a debit or a credit",
+        line.account
+      ));
+    }
+  }
+  let total = |amount: fn(&PostingLine) -> i64| {
+    lines
+      .iter()
+      .try_fold(0i64, |total, line| total.checked_add(amount(line)))
+      .ok_or_else(|| "amounts are too large".to_string())
+  };
+  let debit = total(|line| line.debit)?;
+  let credit = total(|line| line.credit)?;
+  if debit != credit {
+    return Err(format!(
+      "entry is not balanced: debit {} != credit {}",
+      debit, credit
+    ));
+  }
+
+  Ok(())
+}
+
+/// Post a balanced entry, all its postings are written or none. Inside a transaction, the entry is
+/// written with the rest of it.
+pub(crate) async fn post_entry<C: ConnectionTrait + TransactionTrait>(
+  db: &C,
+  entry: NewEntry,
+) -> Result<ledger_entries::Model, DbErr> {
+  validate_lines(&entry.lines).map_err(DbErr::Custom)?;
+
+  let txn = db.begin().await?;
+  let new_entry = ledger_entries::ActiveModel {
+    kind: Set(entry.kind),
+    description: Set(entry.description),
+    assignment_id: Set(entry.assignment_id),
+    transaction_id: Set(entry.transaction_id),
+    created_by: Set(entry.created_by),
+    created_at: Set(chrono::Utc::now().naive_utc()),
+    ..Default::default()
+  };
+  let new_entry = new_entry.insert(&txn).await?;
+
+  for line in entry.lines {
+    let account = ensure_account(&txn, &line.account).await?;
+    let posting = ledger_postings::ActiveModel {
+      entry_id: Set(new_entry.id),
+      account_id: Set(account.id),
+      debit: Set(line.debit),
+      credit: Set(line.credit),
+      ..Default::default()
+    };
+    posting.insert(&txn).await?;
+  }
+  txn.commit().await?;
+
+  Ok(new_entry)
+}
+
+/// Bill a fee assignment to its room: the room owes the amount, the building earns it in the
+/// income account of the fee category.
+pub(crate) async fn post_charge<C: ConnectionTrait + TransactionTrait>(
+  db: &C,
+  assignment: &fees_room_assignment::Model,
+  category: &FeeCategory,
+  created_by: Option<i32>,
+) -> Result<ledger_entries::Model, DbErr> {
+  post_entry(
+    db,
+    NewEntry {
+      kind: LedgerEntryKind::Charge,
+      description: format!(
+        "Phí {} - phòng {}",
+        assignment.fee_name, assignment.room_number
+      ),
+      assignment_id: Some(assignment.assignment_id),
+      transaction_id: None,
+      created_by,
+      lines: vec![
+        PostingLine::debit(&receivable(assignment.room_number), assignment.amount),
+        PostingLine::credit(income_account(category), assignment.amount),
+      ],
+    },
+  )
+  .await
+}
+
+/// Bill an assignment the difference after its amount was changed from `old_amount`, e.g. when a
+/// room billed per vehicle registers another one.
+pub(crate) async fn post_amount_change<C: ConnectionTrait + TransactionTrait>(
+  db: &C,
+  assignment: &fees_room_assignment::Model,
+  category: &FeeCategory,
+  old_amount: i64,
+  created_by: Option<i32>,
+) -> Result<ledger_entries::Model, DbErr> {
+  let difference = assignment.amount - old_amount;
+  let receivable = receivable(assignment.room_number);
+  let lines = if difference > 0 {
+    vec![
+      PostingLine::debit(&receivable, difference),
+      PostingLine::credit(income_account(category), difference),
+    ]
+  } else {
+    vec![
+      PostingLine::debit(income_account(category), -difference),
+      PostingLine::credit(&receivable, -difference),
+    ]
+  };
+
+  post_entry(
+    db,
+    NewEntry {
+      kind: LedgerEntryKind::Adjustment,
+      description: format!(
+        "Điều chỉnh phí {} - phòng {}",
+        assignment.fee_name, assignment.room_number
+      ),
+      assignment_id: Some(assignment.assignment_id),
+      transaction_id: None,
+      created_by,
+      lines,
+    },
+  )
+  .await
+}
+
+/// Record a payment of a room into `account`, usually [`BANK`].
+pub(crate) async fn post_payment<C: ConnectionTrait + TransactionTrait>(
+  db: &C,
+  transaction_id: i32,
+  assignment_id: i32,
+  room_number: i32,
+  amount: i64,
+  account: &str,
+) -> Result<ledger_entries::Model, DbErr> {
+  post_entry(
+    db,
+    NewEntry {
+      kind: LedgerEntryKind::Payment,
+      description: format!(
+        "Thanh toán FLATAPP{} - phòng {}",
+        assignment_id, room_number
+      ),
+      assignment_id: Some(assignment_id),
+      transaction_id: Some(transaction_id),
+      created_by: None,
+      lines: vec![
+        PostingLine::debit(account, amount),
+        PostingLine::credit(&receivable(room_number), amount),
+      ],
+    },
+  )
+  .await
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AccountBalance {
+  pub id: i32,
+  pub code: String,
+  pub name: String,
+  #[schema(value_type = String, examples("asset", "liability", "income", "expense"))]
+  pub account_type: LedgerAccountType,
+  pub room_number: Option<i32>,
+  pub debit: i64,
+  pub credit: i64,
+  /// Debit minus credit for assets and expenses, credit minus debit otherwise
+  pub balance: i64,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct AccountTotals {
+  account_id: i32,
+  debit: i64,
+  credit: i64,
+}
+
+/// Balances of all accounts, counting the entries made before `until`.
+async fn account_balances(
+  db: &DatabaseConnection,
+  until: Option<chrono::NaiveDateTime>,
+) -> Result<Vec<AccountBalance>, DbErr> {
+  let mut query = LedgerPostings::find()
+    .select_only()
+    .column(ledger_postings::Column::AccountId)
+    .column_as(
+      Expr::cust("CAST(COALESCE(SUM(ledger_postings.debit), 0) AS BIGINT)"),
+      "debit",
+    )
+    .column_as(
+      Expr::cust("CAST(COALESCE(SUM(ledger_postings.credit), 0) AS BIGINT)"),
+      "credit",
+    )
+    .join(
+      sea_orm::JoinType::InnerJoin,
+      ledger_postings::Relation::LedgerEntries.def(),
+    )
+    .group_by(ledger_postings::Column::AccountId);
+  if let Some(until) = until {
+    query = query.filter(ledger_entries::Column::CreatedAt.lt(until));
+  }
+  let totals = query
+    .into_model::<AccountTotals>()
+    .all(db)
+    .await?
+    .into_iter()
+    .map(|totals| (totals.account_id, totals))
+    .collect::<HashMap<_, _>>();
+
+  let accounts = LedgerAccounts::find()
+    .order_by(ledger_accounts::Column::Code, Order::Asc)
+    .all(db)
+    .await?;
+
+  Ok(
+    accounts
+      .into_iter()
+      .map(|account| {
+        let (debit, credit) = totals
+          .get(&account.id)
+          .map(|totals| (totals.debit, totals.credit))
+          .unwrap_or_default();
+        let balance = match account.account_type {
+          LedgerAccountType::Asset | LedgerAccountType::Expense => debit - credit,
+          LedgerAccountType::Liability | LedgerAccountType::Income => credit - debit,
+        };
+        AccountBalance {
+          id: account.id,
+          code: account.code,
+          name: account.name,
+          account_type: account.account_type,
+          room_number: account.room_number,
+          debit,
+          credit,
+          balance,
+        }
+      })
+      .collect(),
+  )
+}
+
+#[utoipa::path(
+  get,
+  path = "/ledger/accounts",
+  description = "Lấy danh sách các tài khoản kế toán và số dư hiện tại, yêu cầu request có role là Manager.",
+  tag = tags::MANAGER,
+  responses(
+    (status = OK, description = "Accounts retrieved", body = Vec<AccountBalance>),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn get_accounts(
+  State(state): State<AppState>,
+) -> Result<Json<Vec<AccountBalance>>, StatusCode> {
+  let accounts = account_balances(&state.db, None).await.map_err(|e| {
+    log::error!("Error: {:?}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+  })?;
+
+  Ok(Json(accounts))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TrialBalance {
+  /// Last day counted, all entries if not given
+  pub as_of: Option<chrono::NaiveDate>,
+  pub accounts: Vec<AccountBalance>,
+  pub total_debit: i64,
+  pub total_credit: i64,
+  pub balanced: bool,
+}
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct TrialBalanceParams {
+  as_of: Option<chrono::NaiveDate>,
+}
+
+#[utoipa::path(
+  get,
+  path = "/ledger/trial-balance",
+  description = "Bảng cân đối số phát sinh: tổng nợ và tổng có của từng tài khoản tính đến ngày `as_of`, yêu cầu request có role là Manager.
+  Tổng nợ phải bằng tổng có.",
+  tag = tags::MANAGER,
+  params(TrialBalanceParams),
+  responses(
+    (status = OK, description = "Trial balance", body = TrialBalance),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn get_trial_balance(
+  State(state): State<AppState>,
+  Query(TrialBalanceParams { as_of }): Query<TrialBalanceParams>,
+) -> Result<Json<TrialBalance>, StatusCode> {
+  let until = as_of
+    .and_then(|as_of| as_of.succ_opt())
+    .and_then(|until| until.and_hms_opt(0, 0, 0));
+  let accounts = account_balances(&state.db, until).await.map_err(|e| {
+    log::error!("Error: {:?}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+  })?;
+
+  let total_debit = accounts.iter().map(|account| account.debit).sum::<i64>();
+  let total_credit = accounts.iter().map(|account| account.credit).sum::<i64>();
+
+  Ok(Json(TrialBalance {
+    as_of,
+    accounts,
+    total_debit,
+    total_credit,
+    balanced: total_debit == total_credit,
+  }))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PostingInfo {
+  pub account: String,
+  pub debit: i64,
+  pub credit: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct EntryInfo {
+  pub id: i32,
+  #[schema(value_type = String, examples("charge", "payment", "adjustment", "refund"))]
+  pub kind: LedgerEntryKind,
+  pub description: String,
+  pub assignment_id: Option<i32>,
+  pub transaction_id: Option<i32>,
+  pub created_by: Option<i32>,
+  pub created_at: chrono::NaiveDateTime,
+  pub postings: Vec<PostingInfo>,
+}
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct EntryFilter {
+  /// Only entries touching this account code
+  account: Option<String>,
+  from: Option<chrono::NaiveDate>,
+  to: Option<chrono::NaiveDate>,
+}
+
+/// Load entries with their postings, newest first.
+async fn load_entries(
+  db: &DatabaseConnection,
+  entry_ids: Option<Vec<i32>>,
+  filter: &EntryFilter,
+) -> Result<Vec<EntryInfo>, DbErr> {
+  let mut query = LedgerEntries::find().order_by(ledger_entries::Column::Id, Order::Desc);
+  if let Some(entry_ids) = entry_ids {
+    query = query.filter(ledger_entries::Column::Id.is_in(entry_ids));
+  }
+  if let Some(from) = filter.from.and_then(|from| from.and_hms_opt(0, 0, 0)) {
+    query = query.filter(ledger_entries::Column::CreatedAt.gte(from));
+  }
+  if let Some(to) = filter
+    .to
+    .and_then(|to| to.succ_opt())
+    .and_then(|to| to.and_hms_opt(0, 0, 0))
+  {
+    query = query.filter(ledger_entries::Column::CreatedAt.lt(to));
+  }
+  let entries = query.all(db).await?;
+
+  let codes = LedgerAccounts::find()
+    .all(db)
+    .await?
+    .into_iter()
+    .map(|account| (account.id, account.code))
+    .collect::<HashMap<_, _>>();
+  let mut postings = HashMap::<i32, Vec<PostingInfo>>::new();
+  let entry_ids = entries.iter().map(|entry| entry.id).collect::<Vec<_>>();
+  for chunk in entry_ids.chunks(1000) {
+    let chunk_postings = LedgerPostings::find()
+      .filter(ledger_postings::Column::EntryId.is_in(chunk.to_vec()))
+      .order_by(ledger_postings::Column::Id, Order::Asc)
+      .all(db)
+      .await?;
+    for posting in chunk_postings {
+      postings
+        .entry(posting.entry_id)
+        .or_default()
+        .push(PostingInfo {
+          account: codes.get(&posting.account_id).cloned().unwrap_or_default(),
+          debit: posting.debit,
+          credit: posting.credit,
+        });
+    }
+  }
+
+  Ok(
+    entries
+      .into_iter()
+      .map(|entry| EntryInfo {
+        postings: postings.remove(&entry.id).unwrap_or_default(),
+        id: entry.id,
+        kind: entry.kind,
+        description: entry.description,
+        assignment_id: entry.assignment_id,
+        transaction_id: entry.transaction_id,
+        created_by: entry.created_by,
+        created_at: entry.created_at,
+      })
+      .collect(),
+  )
+}
+
+#[utoipa::path(
+  get,
+  path = "/ledger/entries",
+  description = "Lấy danh sách các bút toán kèm các dòng nợ / có, có thể lọc theo tài khoản và khoảng thời gian, yêu cầu request có role là Manager.",
+  tag = tags::MANAGER,
+  params(EntryFilter),
+  responses(
+    (status = OK, description = "Entries retrieved", body = Vec<EntryInfo>),
+    (status = NOT_FOUND, description = "Account not found"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn get_entries(
+  State(state): State<AppState>,
+  Query(filter): Query<EntryFilter>,
+) -> Result<Json<Vec<EntryInfo>>, StatusCode> {
+  let server_error = |e: DbErr| {
+    log::error!("Error: {:?}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+  };
+
+  let entry_ids = match &filter.account {
+    Some(code) => {
+      let account = LedgerAccounts::find()
+        .filter(ledger_accounts::Column::Code.eq(code))
+        .one(&state.db)
+        .await
+        .map_err(server_error)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+      let entry_ids = LedgerPostings::find()
+        .filter(ledger_postings::Column::AccountId.eq(account.id))
+        .all(&state.db)
+        .await
+        .map_err(server_error)?
+        .into_iter()
+        .map(|posting| posting.entry_id)
+        .collect::<HashSet<_>>();
+      Some(entry_ids.into_iter().collect())
+    }
+    None => None,
+  };
+
+  let entries = load_entries(&state.db, entry_ids, &filter)
+    .await
+    .map_err(server_error)?;
+
+  Ok(Json(entries))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ManualEntryInfo {
+  /// `adjustment` or `refund`, charges and payments are posted by the system
+  #[schema(value_type = String, examples("adjustment", "refund"))]
+  pub kind: LedgerEntryKind,
+  pub description: String,
+  pub lines: Vec<PostingLine>,
+}
+
+#[utoipa::path(
+  post,
+  path = "/ledger/entries",
+  description = "Ghi một bút toán điều chỉnh hoặc hoàn tiền, yêu cầu request có role là Manager.
+  Tổng nợ phải bằng tổng có, tài khoản được ghi theo mã, ví dụ `receivable:101`, `credit:101`, `bank`, `cash`, `income:fees`, `income:penalties`.",
+  tag = tags::MANAGER,
+  responses(
+    (status = CREATED, description = "Entry posted", body = EntryInfo),
+    (status = BAD_REQUEST, description = "Invalid entry", body = String),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error", body = String),
+    (status = UNAUTHORIZED, description = "Unauthorized", body = String),
+    (status = FORBIDDEN, description = "Forbidden", body = String),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn add_entry(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+  Json(entry_info): Json<ManualEntryInfo>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+  let claims = state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+    .map_err(|_| (StatusCode::UNAUTHORIZED, "invalid token".to_string()))?;
+
+  if !matches!(
+    entry_info.kind,
+    LedgerEntryKind::Adjustment | LedgerEntryKind::Refund
+  ) {
+    return Err((
+      StatusCode::BAD_REQUEST,
+      "only adjustments and refunds can be posted manually".to_string(),
+    ));
+  }
+  validate_lines(&entry_info.lines).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+  if let Some(line) = entry_info
+    .lines
+    .iter()
+    .find(|line| describe_account(&line.account).is_none())
+  {
+    return Err((
+      StatusCode::BAD_REQUEST,
+      format!("unknown account: {}", line.account),
+    ));
+  }
+
+  let entry = post_entry(
+    &state.db,
+    NewEntry {
+      kind: entry_info.kind,
+      description: entry_info.description,
+      assignment_id: None,
+      transaction_id: None,
+      created_by: Some(claims.custom.id),
+      lines: entry_info.lines,
+    },
+  )
+  .await
+  .map_err(|e| {
+    log::error!("Error: {:?}", e);
+    (
+      StatusCode::INTERNAL_SERVER_ERROR,
+      "server error".to_string(),
+    )
+  })?;
+
+  let entry = load_entries(
+    &state.db,
+    Some(vec![entry.id]),
+    &EntryFilter {
+      account: None,
+      from: None,
+      to: None,
+    },
+  )
+  .await
+  .map_err(|e| {
+    log::error!("Error: {:?}", e);
+    (
+      StatusCode::INTERNAL_SERVER_ERROR,
+      "server error".to_string(),
+    )
+  })?
+  .pop();
+
+  Ok((StatusCode::CREATED, Json(entry)))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ReceivableMismatch {
+  pub room_number: i32,
+  /// Balance of the room's receivable account
+  pub ledger_balance: i64,
+  /// Unpaid amount according to the fee assignments
+  pub outstanding: i64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct IntegrityReport {
+  pub ok: bool,
+  pub unbalanced_entries: Vec<i32>,
+  /// Fee assignments that were never charged in the ledger
+  pub assignments_without_charge: Vec<i32>,
+  /// Payments that were never recorded in the ledger
+  pub transactions_without_payment: Vec<i32>,
+  pub receivable_mismatches: Vec<ReceivableMismatch>,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct EntryTotals {
+  entry_id: i32,
+  debit: i64,
+  credit: i64,
+}
+
+async fn check_integrity(db: &DatabaseConnection) -> Result<IntegrityReport, DbErr> {
+  // every entry must be balanced
+  let unbalanced_entries = LedgerPostings::find()
+    .select_only()
+    .column(ledger_postings::Column::EntryId)
+    .column_as(
+      Expr::cust("CAST(SUM(ledger_postings.debit) AS BIGINT)"),
+      "debit",
+    )
+    .column_as(
+      Expr::cust("CAST(SUM(ledger_postings.credit) AS BIGINT)"),
+      "credit",
+    )
+    .group_by(ledger_postings::Column::EntryId)
+    .into_model::<EntryTotals>()
+    .all(db)
+    .await?
+    .into_iter()
+    .filter(|totals| totals.debit != totals.credit)
+    .map(|totals| totals.entry_id)
+    .collect::<Vec<_>>();
+
+  // every assignment must be charged and every payment recorded
+  let entries = LedgerEntries::find()
+    .filter(ledger_entries::Column::Kind.is_in([LedgerEntryKind::Charge, LedgerEntryKind::Payment]))
+    .all(db)
+    .await?;
+  let charged = entries
+    .iter()
+    .filter(|entry| entry.kind == LedgerEntryKind::Charge)
+    .filter_map(|entry| entry.assignment_id)
+    .collect::<HashSet<_>>();
+  let recorded = entries
+    .iter()
+    .filter(|entry| entry.kind == LedgerEntryKind::Payment)
+    .filter_map(|entry| entry.transaction_id)
+    .collect::<HashSet<_>>();
+  let assignments_without_charge = FeesRoomAssignment::find()
+    .order_by(fees_room_assignment::Column::AssignmentId, Order::Asc)
+    .all(db)
+    .await?
+    .into_iter()
+    .map(|assignment| assignment.assignment_id)
+    .filter(|assignment_id| !charged.contains(assignment_id))
+    .collect::<Vec<_>>();
+  let mut transactions_without_payment = Transactions::find()
+    .all(db)
+    .await?
+    .into_iter()
+    .map(|transaction| transaction.id)
+    .filter(|transaction_id| !recorded.contains(transaction_id))
+    .collect::<Vec<_>>();
+  transactions_without_payment.sort_unstable();
+
+  // what the ledger says a room owes must match its unpaid fees
+  let params = ReportParams {
+    from: None,
+    to: None,
+    format: ReportFormat::Json,
+  };
+  let mut outstanding = HashMap::<i32, i64>::new();
+  for balance in load_balances(db, &params).await? {
+    *outstanding
+      .entry(balance.assignment.room_number)
+      .or_default() += balance.outstanding();
+  }
+  let mut receivables = account_balances(db, None)
+    .await?
+    .into_iter()
+    .filter(|account| account.code.starts_with("receivable:"))
+    .filter_map(|account| Some((account.room_number?, account.balance)))
+    .collect::<HashMap<_, _>>();
+  let mut rooms = outstanding
+    .keys()
+    .chain(receivables.keys())
+    .copied()
+    .collect::<Vec<_>>();
+  rooms.sort_unstable();
+  rooms.dedup();
+  let mut receivable_mismatches = Vec::new();
+  for room_number in rooms {
+    let ledger_balance = receivables.remove(&room_number).unwrap_or_default();
+    let outstanding = outstanding.get(&room_number).copied().unwrap_or_default();
+    if ledger_balance != outstanding {
+      receivable_mismatches.push(ReceivableMismatch {
+        room_number,
+        ledger_balance,
+        outstanding,
+      });
+    }
+  }
+
+  Ok(IntegrityReport {
+    ok: unbalanced_entries.is_empty()
+      && assignments_without_charge.is_empty()
+      && transactions_without_payment.is_empty()
+      && receivable_mismatches.is_empty(),
+    unbalanced_entries,
+    assignments_without_charge,
+    transactions_without_payment,
+    receivable_mismatches,
+  })
+}
+
+#[utoipa::path(
+  get,
+  path = "/ledger/integrity",
+  description = "Kiểm tra tính toàn vẹn của sổ cái, yêu cầu request có role là Manager: các bút toán phải cân, mọi khoản phí đã gán phải được ghi nhận phải thu,
+  mọi giao dịch phải được ghi nhận thanh toán, và số dư phải thu của mỗi phòng phải khớp với số tiền chưa thanh toán.",
+  tag = tags::MANAGER,
+  responses(
+    (status = OK, description = "Integrity report", body = IntegrityReport),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn get_integrity(
+  State(state): State<AppState>,
+) -> Result<Json<IntegrityReport>, StatusCode> {
+  let report = check_integrity(&state.db).await.map_err(|e| {
+    log::error!("Error: {:?}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+  })?;
+
+  Ok(Json(report))
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct BackfillReport {
+  pub charges: usize,
+  pub payments: usize,
+}
+
+#[utoipa::path(
+  post,
+  path = "/ledger/backfill",
+  description = "Ghi vào sổ cái các khoản phí và giao dịch có từ trước khi có sổ cái, yêu cầu request có role là Manager.
+  Trả về số bút toán đã ghi.",
+  tag = tags::MANAGER,
+  responses(
+    (status = OK, description = "Ledger backfilled", body = BackfillReport),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn backfill_ledger(
+  State(state): State<AppState>,
+) -> Result<Json<BackfillReport>, StatusCode> {
+  let server_error = |e: DbErr| {
+    log::error!("Error: {:?}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+  };
+
+  let integrity = check_integrity(&state.db).await.map_err(server_error)?;
+  let mut report = BackfillReport::default();
+
+  for chunk in integrity.assignments_without_charge.chunks(1000) {
+    let assignments = FeesRoomAssignment::find()
+      .filter(fees_room_assignment::Column::AssignmentId.is_in(chunk.to_vec()))
+      .find_also_related(Fees)
+      .all(&state.db)
+      .await
+      .map_err(server_error)?;
+    for (assignment, fee) in assignments {
+      let category = fee.map(|fee| fee.category).unwrap_or_default();
+      post_charge(&state.db, &assignment, &category, None)
+        .await
+        .map_err(server_error)?;
+      report.charges += 1;
+    }
+  }
+
+  for chunk in integrity.transactions_without_payment.chunks(1000) {
+    let transactions = Transactions::find()
+      .filter(crate::entities::transactions::Column::Id.is_in(chunk.to_vec()))
+      .find_also_related(FeesRoomAssignment)
+      .all(&state.db)
+      .await
+      .map_err(server_error)?;
+    for (transaction, assignment) in transactions {
+      let Some(assignment) = assignment else {
+        continue;
+      };
+      post_payment(
+        &state.db,
+        transaction.id,
+        assignment.assignment_id,
+        assignment.room_number,
+        transaction.amount,
+        BANK,
+      )
+      .await
+      .map_err(server_error)?;
+      report.payments += 1;
+    }
+  }
+
+  log::info!(
+    "Ledger backfilled: {} charges, {} payments",
+    report.charges,
+    report.payments
+  );
+
+  Ok(Json(report))
+}
+
+/// Cancel what a fee assignment is still charged, with an opposite adjustment. The charges and
+/// adjustments of the assignment are netted first, so a charge that was already cancelled, e.g.
+/// when the assignment was moved to another room, is not cancelled twice.
+pub(crate) async fn reverse_charges<C: ConnectionTrait + TransactionTrait>(
+  db: &C,
+  assignment_id: i32,
+  created_by: Option<i32>,
+) -> Result<(), DbErr> {
+  let entries = LedgerEntries::find()
+    .filter(ledger_entries::Column::AssignmentId.eq(assignment_id))
+    .filter(
+      ledger_entries::Column::Kind.is_in([LedgerEntryKind::Charge, LedgerEntryKind::Adjustment]),
+    )
+    .order_by(ledger_entries::Column::Id, Order::Asc)
+    .all(db)
+    .await?;
+  let Some(charge) = entries
+    .iter()
+    .rev()
+    .find(|entry| entry.kind == LedgerEntryKind::Charge)
+  else {
+    return Ok(());
+  };
+
+  // debit minus credit of every account the assignment was charged on
+  let mut net = BTreeMap::<String, i64>::new();
+  let postings = LedgerPostings::find()
+    .filter(
+      ledger_postings::Column::EntryId
+        .is_in(entries.iter().map(|entry| entry.id).collect::<Vec<_>>()),
+    )
+    .find_also_related(LedgerAccounts)
+    .all(db)
+    .await?;
+  for (posting, account) in postings {
+    if let Some(account) = account {
+      *net.entry(account.code).or_default() += posting.debit - posting.credit;
+    }
+  }
+  let lines = net
+    .into_iter()
+    .filter(|(_, amount)| *amount != 0)
+    .map(|(account, amount)| {
+      if amount > 0 {
+        PostingLine::credit(&account, amount)
+      } else {
+        PostingLine::debit(&account, -amount)
+      }
+    })
+    .collect::<Vec<_>>();
+  if lines.is_empty() {
+    return Ok(());
+  }
+
+  post_entry(
+    db,
+    NewEntry {
+      kind: LedgerEntryKind::Adjustment,
+      description: format!("Hủy: {}", charge.description),
+      assignment_id: Some(assignment_id),
+      transaction_id: None,
+      created_by,
+      lines,
+    },
+  )
+  .await?;
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn balanced_entry_is_valid() {
+    let lines = vec![
+      PostingLine::debit(BANK, 150_000),
+      PostingLine::credit(&receivable(101), 100_000),
+      PostingLine::credit("credit:101", 50_000),
+    ];
+    assert_eq!(validate_lines(&lines), Ok(()));
+  }
+
+  #[test]
+  fn entry_needs_two_lines() {
+    assert!(validate_lines(&[]).is_err());
+    assert!(validate_lines(&[PostingLine::debit(BANK, 100)]).is_err());
+  }
+
+  #[test]
+  fn unbalanced_entry_is_invalid() {
+    let lines = vec![
+      PostingLine::debit(BANK, 100),
+      PostingLine::credit(FEE_INCOME, 90),
+    ];
+    assert!(validate_lines(&lines).is_err());
+  }
+
+  #[test]
+  fn line_needs_either_a_debit_or_a_credit() {
+    let both = PostingLine {
+      account: BANK.to_string(),
+      debit: 100,
+      credit: 100,
+    };
+    assert!(validate_lines(&[both, PostingLine::credit(FEE_INCOME, 0)]).is_err());
+    let neither = PostingLine {
+      account: BANK.to_string(),
+      debit: 0,
+      credit: 0,
+    };
+    assert!(validate_lines(&[neither.clone(), neither]).is_err());
+  }
+
+  #[test]
+  fn overflowing_totals_are_invalid() {
+    let lines = vec![
+      PostingLine::debit(BANK, i64::MAX),
+      PostingLine::debit(CASH, 2),
+      PostingLine::credit(FEE_INCOME, 1),
+    ];
+    assert!(validate_lines(&lines).is_err());
+  }
+
+  #[test]
+  fn negative_amounts_are_invalid() {
+    let lines = vec![
+      PostingLine::debit(BANK, -100),
+      PostingLine::credit(FEE_INCOME, -100),
+    ];
+    assert!(validate_lines(&lines).is_err());
+  }
+}
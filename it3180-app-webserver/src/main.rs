@@ -1,11 +1,16 @@
 mod admin;
 mod authenticate;
+mod documents;
 mod entities;
 mod family;
 mod household;
+mod ledger;
 mod manager;
 mod middleware;
+mod notify;
+mod payment;
 pub mod prelude;
+mod reminders;
 mod router;
 pub mod types;
 mod user;
@@ -13,6 +18,10 @@ mod webhook;
 
 use crate::prelude::*;
 
+use documents::BuildingInfo;
+use notify::{email::EmailChannel, sms::SmsChannel, webhook::WebhookChannel, Notifier};
+use payment::{bank_statement::BankStatementGateway, sepay::SepayGateway, GatewayRegistry};
+use reminders::ReminderConfig;
 use router::create_router;
 use sea_orm::{sqlx::PgPool, SqlxPostgresConnector};
 use shuttle_runtime::SecretStore;
@@ -20,7 +29,10 @@ use shuttle_runtime::SecretStore;
 #[derive(Debug, Clone)]
 pub(crate) struct AppState {
   pub(crate) db: DatabaseConnection,
-  payment_api_key: String,
+  payment_gateways: GatewayRegistry,
+  building: BuildingInfo,
+  notifier: Notifier,
+  reminders: ReminderConfig,
   jwt_access_secret: HS256Key,
   jwt_refresh_secret: HS256Key,
 }
@@ -30,11 +42,84 @@ async fn main(
   #[shuttle_runtime::Secrets] secrets: SecretStore,
   #[shuttle_shared_db::Postgres(local_uri = "{secrets.DATABASE_URL}")] pool: PgPool,
 ) -> shuttle_axum::ShuttleAxum {
+  let mut payment_gateways = GatewayRegistry::new().with(
+    "sepay",
+    SepayGateway::new(
+      secrets
+        .get("PAYMENT_API_KEY")
+        .expect("PAYMENT_API_KEY not found"),
+    ),
+  );
+  if let Some(api_key) = secrets
+    .get("BANK_WEBHOOK_API_KEY")
+    .filter(|api_key| !api_key.is_empty())
+  {
+    payment_gateways = payment_gateways.with("bank", BankStatementGateway::new("bank", api_key));
+  }
+
+  let building = BuildingInfo {
+    name: secrets
+      .get("BUILDING_NAME")
+      .filter(|name| !name.is_empty())
+      .unwrap_or_else(|| "Chung cư".to_string()),
+    address: secrets
+      .get("BUILDING_ADDRESS")
+      .filter(|address| !address.is_empty()),
+    phone: secrets
+      .get("BUILDING_PHONE")
+      .filter(|phone| !phone.is_empty()),
+  };
+
+  let mut notifier = Notifier::new();
+  if let Some(url) = secrets
+    .get("NOTIFICATION_WEBHOOK_URL")
+    .filter(|url| !url.is_empty())
+  {
+    notifier = notifier.with(WebhookChannel::new(url));
+  }
+  if let Some(target) = secrets
+    .get("NOTIFICATION_EMAIL")
+    .filter(|target| !target.is_empty())
+  {
+    let from = secrets.get("EMAIL_FROM").expect("EMAIL_FROM not found");
+    notifier = notifier
+      .with(EmailChannel::new(&target, &from).expect("Failed to configure NOTIFICATION_EMAIL"));
+  }
+  if let Some(target) = secrets
+    .get("NOTIFICATION_SMS")
+    .filter(|target| !target.is_empty())
+  {
+    notifier = notifier.with(SmsChannel::new(
+      &target,
+      secrets.get("SMS_API_KEY").filter(|key| !key.is_empty()),
+      secrets
+        .get("SMS_SENDER")
+        .filter(|sender| !sender.is_empty()),
+    ));
+  }
+
+  let reminders = match secrets.get("REMINDER_STAGES") {
+    Some(stages) => ReminderConfig::parse(
+      &stages,
+      secrets
+        .get("REMINDER_INTERVAL_MINUTES")
+        .map(|minutes| {
+          minutes
+            .parse()
+            .expect("Failed to parse REMINDER_INTERVAL_MINUTES")
+        })
+        .unwrap_or(60),
+    )
+    .expect("Failed to parse REMINDER_STAGES"),
+    None => ReminderConfig::default(),
+  };
+
   let state = AppState {
     db: SqlxPostgresConnector::from_sqlx_postgres_pool(pool),
-    payment_api_key: secrets
-      .get("PAYMENT_API_KEY")
-      .expect("PAYMENT_API_KEY not found"),
+    payment_gateways,
+    building,
+    notifier,
+    reminders,
     jwt_access_secret: HS256Key::from_bytes(
       hex::decode(
         secrets
@@ -54,6 +139,9 @@ async fn main(
       .as_slice(),
     ),
   };
+  reminders::spawn_scheduler(state.clone());
+  manager::declarations::spawn_expiry_alerts(state.clone());
+  notify::delivery::spawn_delivery_worker(state.clone());
   let router = create_router(state);
 
   Ok(router.into())
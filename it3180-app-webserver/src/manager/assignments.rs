@@ -0,0 +1,498 @@
+//! Changing which rooms a fee is assigned to.
+//!
+//! An assignment that has been paid is part of the payment history and is never removed or moved.
+//! Removing an unpaid assignment reverses its charge in the ledger, moving it bills the new room.
+
+use std::collections::{BTreeSet, HashMap};
+
+use sea_orm::{IntoActiveModel, TransactionTrait};
+
+use crate::{
+  entities::{fees, fees_room_assignment, payment_reminders, rooms, transactions, users},
+  ledger,
+  notify::templates::Notice,
+  prelude::*,
+};
+
+/// Assign a fee to a room, bill it the amount and notify the tenant. The amount is the one of the
+/// fee, unless the room is billed for a number of units, e.g. per vehicle: the amount of the fee is
+/// then the price of a unit, kept on the assignment. Returns `None` if the room already has the
+/// fee.
+pub(crate) async fn create_assignment(
+  state: &AppState,
+  fee: &fees::Model,
+  room_number: i32,
+  tenant: Option<&users::Model>,
+  manager_id: i32,
+  units: Option<i64>,
+) -> Result<Option<fees_room_assignment::Model>, DbErr> {
+  let existing = FeesRoomAssignment::find()
+    .filter(fees_room_assignment::Column::RoomNumber.eq(room_number))
+    .filter(fees_room_assignment::Column::FeeId.eq(fee.id))
+    .one(&state.db)
+    .await?;
+  if existing.is_some() {
+    return Ok(None);
+  }
+
+  let amount = units.map_or(fee.amount, |units| fee.amount * units);
+  // the assignment and its charge are written together
+  let txn = state.db.begin().await?;
+  let assignment = fees_room_assignment::ActiveModel {
+    fee_id: Set(fee.id),
+    room_number: Set(room_number),
+    due_date: Set(fee.due_date),
+    fee_name: Set(fee.name.clone()),
+    amount: Set(amount),
+    unit_price: Set(units.map(|_| fee.amount)),
+    ..Default::default()
+  }
+  .insert(&txn)
+  .await?;
+  ledger::post_charge(&txn, &assignment, &fee.category, Some(manager_id)).await?;
+  txn.commit().await?;
+  log::info!("Fee assigned: {:?}", assignment);
+
+  if let Some(tenant) = tenant {
+    state
+      .notifier
+      .notify_event(
+        &state.db,
+        manager_id,
+        tenant,
+        &Notice::new(NotificationEvent::FeeAssigned)
+          .text("room", room_number)
+          .text("fee_name", &fee.name)
+          .amount("amount", amount)
+          .date("due_date", fee.due_date.date()),
+      )
+      .await?;
+  }
+
+  Ok(Some(assignment))
+}
+
+/// Assignments of the given ids that have a payment.
+pub(crate) async fn paid_assignments(
+  db: &DatabaseConnection,
+  assignments: &[fees_room_assignment::Model],
+) -> Result<BTreeSet<i32>, DbErr> {
+  let mut paid = assignments
+    .iter()
+    .filter(|assignment| assignment.is_paid)
+    .map(|assignment| assignment.assignment_id)
+    .collect::<BTreeSet<_>>();
+
+  let assignment_ids = assignments
+    .iter()
+    .map(|assignment| assignment.assignment_id)
+    .collect::<Vec<_>>();
+  for chunk in assignment_ids.chunks(1000) {
+    let transactions = Transactions::find()
+      .filter(transactions::Column::AssignmentId.is_in(chunk.to_vec()))
+      .all(db)
+      .await?;
+    paid.extend(
+      transactions
+        .into_iter()
+        .map(|transaction| transaction.assignment_id),
+    );
+  }
+
+  Ok(paid)
+}
+
+/// Remove an unpaid assignment and reverse what it was charged.
+pub(crate) async fn remove_assignment(
+  db: &DatabaseConnection,
+  assignment: &fees_room_assignment::Model,
+  manager_id: i32,
+) -> Result<(), DbErr> {
+  let txn = db.begin().await?;
+  ledger::reverse_charges(&txn, assignment.assignment_id, Some(manager_id)).await?;
+  FeesRoomAssignment::delete_by_id(assignment.assignment_id)
+    .exec(&txn)
+    .await?;
+  txn.commit().await?;
+  log::info!("Fee unassigned: {:?}", assignment);
+  Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct UnassignFeeRequest {
+  pub room_numbers: Vec<i32>,
+  /// Remove the unpaid rooms and keep the paid ones, instead of refusing the whole request
+  #[serde(default)]
+  pub skip_paid: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct UnassignFeeReport {
+  pub removed: Vec<i32>,
+  /// Rooms that already paid, which keep the fee
+  pub paid: Vec<i32>,
+  /// Rooms that did not have the fee
+  pub not_assigned: Vec<i32>,
+}
+
+#[utoipa::path(
+  post,
+  path = "/fees/{fee_id}/unassign",
+  description = "Bỏ gán một khoản phí khỏi một hoặc nhiều phòng, yêu cầu request có role là Manager.
+  Nếu có phòng đã thanh toán, yêu cầu bị từ chối với status CONFLICT, trừ khi `skip_paid` là true, khi đó các phòng đã thanh toán được giữ nguyên.
+  Trả về danh sách các phòng đã bỏ gán.",
+  tag = tags::MANAGER,
+  request_body = UnassignFeeRequest,
+  responses(
+    (status = OK, description = "Fee unassigned", body = UnassignFeeReport),
+    (status = CONFLICT, description = "Some rooms already paid", body = UnassignFeeReport),
+    (status = NOT_FOUND, description = "Fee not found"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn unassign_fee(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+  Path(fee_id): Path<i32>,
+  Json(request): Json<UnassignFeeRequest>,
+) -> Result<(StatusCode, Json<UnassignFeeReport>), StatusCode> {
+  let claims = state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+  let server_error = |e: DbErr| {
+    log::error!("Error: {:?}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+  };
+
+  Fees::find_by_id(fee_id)
+    .one(&state.db)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+  let room_numbers = request.room_numbers.into_iter().collect::<BTreeSet<_>>();
+  let assignments = FeesRoomAssignment::find()
+    .filter(fees_room_assignment::Column::FeeId.eq(fee_id))
+    .filter(fees_room_assignment::Column::RoomNumber.is_in(room_numbers.clone()))
+    .all(&state.db)
+    .await
+    .map_err(server_error)?;
+  let paid = paid_assignments(&state.db, &assignments)
+    .await
+    .map_err(server_error)?;
+
+  let mut report = UnassignFeeReport {
+    not_assigned: room_numbers
+      .iter()
+      .filter(|room| !assignments.iter().any(|a| a.room_number == **room))
+      .copied()
+      .collect(),
+    ..Default::default()
+  };
+  let (paid, unpaid): (Vec<_>, Vec<_>) = assignments
+    .into_iter()
+    .partition(|assignment| paid.contains(&assignment.assignment_id));
+  report.paid = paid
+    .iter()
+    .map(|assignment| assignment.room_number)
+    .collect();
+
+  if !report.paid.is_empty() && !request.skip_paid {
+    return Ok((StatusCode::CONFLICT, Json(report)));
+  }
+
+  for assignment in unpaid {
+    remove_assignment(&state.db, &assignment, claims.custom.id)
+      .await
+      .map_err(server_error)?;
+    report.removed.push(assignment.room_number);
+  }
+
+  Ok((StatusCode::OK, Json(report)))
+}
+
+/// Bill an unpaid assignment to another room instead, without notifying anyone. The caller checks
+/// that the room does not have the fee yet.
+pub(crate) async fn move_assignment_to<C: ConnectionTrait + TransactionTrait>(
+  db: &C,
+  assignment: fees_room_assignment::Model,
+  room_number: i32,
+  manager_id: i32,
+) -> Result<fees_room_assignment::Model, DbErr> {
+  let assignment_id = assignment.assignment_id;
+
+  // the old room no longer owes the fee, the new one does
+  let txn = db.begin().await?;
+  ledger::reverse_charges(&txn, assignment_id, Some(manager_id)).await?;
+  PaymentReminders::delete_many()
+    .filter(payment_reminders::Column::AssignmentId.eq(assignment_id))
+    .exec(&txn)
+    .await?;
+  let mut moved = assignment.into_active_model();
+  moved.room_number = Set(room_number);
+  let moved = moved.update(&txn).await?;
+  let category = Fees::find_by_id(moved.fee_id)
+    .one(&txn)
+    .await?
+    .map(|fee| fee.category)
+    .unwrap_or_default();
+  ledger::post_charge(&txn, &moved, &category, Some(manager_id)).await?;
+  txn.commit().await?;
+  log::info!("Fee assignment moved: {:?}", moved);
+
+  Ok(moved)
+}
+
+/// Tell the tenant of a room that it was billed an assignment.
+pub(crate) async fn notify_assigned(
+  state: &AppState,
+  assignment: &fees_room_assignment::Model,
+  tenant: &users::Model,
+  manager_id: i32,
+) -> Result<(), DbErr> {
+  state
+    .notifier
+    .notify_event(
+      &state.db,
+      manager_id,
+      tenant,
+      &Notice::new(NotificationEvent::FeeAssigned)
+        .text("room", assignment.room_number)
+        .text("fee_name", &assignment.fee_name)
+        .amount("amount", assignment.amount)
+        .date("due_date", assignment.due_date.date()),
+    )
+    .await?;
+
+  Ok(())
+}
+
+/// Bill an unpaid assignment to another room instead and notify its tenant. The caller checks
+/// that the room does not have the fee yet.
+pub(crate) async fn transfer_assignment(
+  state: &AppState,
+  assignment: fees_room_assignment::Model,
+  room_number: i32,
+  tenant: Option<&users::Model>,
+  manager_id: i32,
+) -> Result<fees_room_assignment::Model, DbErr> {
+  let moved = move_assignment_to(&state.db, assignment, room_number, manager_id).await?;
+  if let Some(tenant) = tenant {
+    notify_assigned(state, &moved, tenant, manager_id).await?;
+  }
+
+  Ok(moved)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MoveAssignmentRequest {
+  pub room_number: i32,
+}
+
+#[utoipa::path(
+  post,
+  path = "/assignments/{assignment_id}/move",
+  description = "Chuyển một khoản phí đã gán sang phòng khác, yêu cầu request có role là Manager.
+  Không thể chuyển khoản phí đã thanh toán, hoặc sang phòng đã có khoản phí này. Trả về khoản phí sau khi chuyển.",
+  tag = tags::MANAGER,
+  request_body = MoveAssignmentRequest,
+  responses(
+    (status = OK, description = "Assignment moved", body = fees_room_assignment::Model),
+    (status = NOT_FOUND, description = "Assignment or room not found"),
+    (status = CONFLICT, description = "Assignment already paid, or the room already has the fee"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn move_assignment(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+  Path(assignment_id): Path<i32>,
+  Json(MoveAssignmentRequest { room_number }): Json<MoveAssignmentRequest>,
+) -> Result<Json<fees_room_assignment::Model>, StatusCode> {
+  let claims = state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+  let manager_id = claims.custom.id;
+  let server_error = |e: DbErr| {
+    log::error!("Error: {:?}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+  };
+
+  let assignment = FeesRoomAssignment::find_by_id(assignment_id)
+    .one(&state.db)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+  if assignment.room_number == room_number {
+    return Ok(Json(assignment));
+  }
+  let paid = paid_assignments(&state.db, std::slice::from_ref(&assignment))
+    .await
+    .map_err(server_error)?;
+  if !paid.is_empty() {
+    return Err(StatusCode::CONFLICT);
+  }
+
+  let (_, tenant) = Rooms::find_by_id(room_number)
+    .find_also_related(Users)
+    .one(&state.db)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+  let already_assigned = FeesRoomAssignment::find()
+    .filter(fees_room_assignment::Column::FeeId.eq(assignment.fee_id))
+    .filter(fees_room_assignment::Column::RoomNumber.eq(room_number))
+    .one(&state.db)
+    .await
+    .map_err(server_error)?;
+  if already_assigned.is_some() {
+    return Err(StatusCode::CONFLICT);
+  }
+
+  let moved = transfer_assignment(&state, assignment, room_number, tenant.as_ref(), manager_id)
+    .await
+    .map_err(server_error)?;
+
+  Ok(Json(moved))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ReplaceFeeRoomsRequest {
+  /// The rooms that should have the fee after the call
+  pub room_numbers: Vec<i32>,
+  /// Keep the paid rooms that are not in the list, instead of refusing the whole request
+  #[serde(default)]
+  pub skip_paid: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct FeeRoomsDiff {
+  pub added: Vec<i32>,
+  pub removed: Vec<i32>,
+  pub unchanged: Vec<i32>,
+  /// Paid rooms that are not in the list but keep the fee
+  pub paid: Vec<i32>,
+}
+
+#[utoipa::path(
+  put,
+  path = "/fees/{fee_id}/rooms",
+  description = "Thay thế danh sách các phòng được gán một khoản phí, yêu cầu request có role là Manager.
+  Các phòng mới được gán và nhận thông báo, các phòng không còn trong danh sách bị bỏ gán.
+  Nếu cần bỏ gán phòng đã thanh toán, yêu cầu bị từ chối với status CONFLICT, trừ khi `skip_paid` là true. Trả về các thay đổi.",
+  tag = tags::MANAGER,
+  request_body = ReplaceFeeRoomsRequest,
+  responses(
+    (status = OK, description = "Rooms replaced", body = FeeRoomsDiff),
+    (status = CONFLICT, description = "Some removed rooms already paid, or the fee is archived", body = FeeRoomsDiff),
+    (status = NOT_FOUND, description = "Fee or room not found"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn replace_fee_rooms(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+  Path(fee_id): Path<i32>,
+  Json(request): Json<ReplaceFeeRoomsRequest>,
+) -> Result<(StatusCode, Json<FeeRoomsDiff>), StatusCode> {
+  let claims = state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+  let manager_id = claims.custom.id;
+  let server_error = |e: DbErr| {
+    log::error!("Error: {:?}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+  };
+
+  let fee = Fees::find_by_id(fee_id)
+    .one(&state.db)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+  let wanted = request.room_numbers.into_iter().collect::<BTreeSet<_>>();
+  let rooms = Rooms::find()
+    .filter(rooms::Column::RoomNumber.is_in(wanted.clone()))
+    .find_also_related(Users)
+    .all(&state.db)
+    .await
+    .map_err(server_error)?
+    .into_iter()
+    .map(|(room, tenant)| (room.room_number, tenant))
+    .collect::<HashMap<_, _>>();
+  if rooms.len() != wanted.len() {
+    return Err(StatusCode::NOT_FOUND);
+  }
+
+  let assignments = FeesRoomAssignment::find()
+    .filter(fees_room_assignment::Column::FeeId.eq(fee_id))
+    .all(&state.db)
+    .await
+    .map_err(server_error)?;
+  let paid = paid_assignments(&state.db, &assignments)
+    .await
+    .map_err(server_error)?;
+
+  let mut diff = FeeRoomsDiff {
+    added: wanted
+      .iter()
+      .filter(|room| !assignments.iter().any(|a| a.room_number == **room))
+      .copied()
+      .collect(),
+    ..Default::default()
+  };
+  let mut to_remove = vec![];
+  for assignment in assignments {
+    if wanted.contains(&assignment.room_number) {
+      diff.unchanged.push(assignment.room_number);
+    } else if paid.contains(&assignment.assignment_id) {
+      diff.paid.push(assignment.room_number);
+    } else {
+      to_remove.push(assignment);
+    }
+  }
+  diff.removed = to_remove.iter().map(|a| a.room_number).collect();
+
+  if !diff.paid.is_empty() && !request.skip_paid {
+    return Ok((StatusCode::CONFLICT, Json(diff)));
+  }
+  // an archived fee can lose rooms but not get new ones
+  if !diff.added.is_empty() && fee.archived_at.is_some() {
+    return Ok((StatusCode::CONFLICT, Json(diff)));
+  }
+
+  for assignment in &to_remove {
+    remove_assignment(&state.db, assignment, manager_id)
+      .await
+      .map_err(server_error)?;
+  }
+  for room_number in &diff.added {
+    let tenant = rooms.get(room_number).and_then(|tenant| tenant.as_ref());
+    create_assignment(&state, &fee, *room_number, tenant, manager_id, None)
+      .await
+      .map_err(server_error)?;
+  }
+
+  diff.unchanged.sort_unstable();
+  diff.paid.sort_unstable();
+  diff.removed.sort_unstable();
+  Ok((StatusCode::OK, Json(diff)))
+}
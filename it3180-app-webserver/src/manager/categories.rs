@@ -0,0 +1,158 @@
+//! Fee categories and free-form tags.
+
+use std::collections::{BTreeSet, HashMap};
+
+use sea_orm::{ConnectionTrait, Order, QueryOrder, QuerySelect};
+
+use crate::{entities::fee_tags, prelude::*};
+
+/// Name of a category in reports.
+pub fn category_name(category: &FeeCategory) -> &'static str {
+  match category {
+    FeeCategory::Service => "Phí dịch vụ",
+    FeeCategory::Utilities => "Điện nước",
+    FeeCategory::Parking => "Gửi xe",
+    FeeCategory::Contribution => "Đóng góp",
+    FeeCategory::Penalty => "Tiền phạt",
+  }
+}
+
+/// Trim and lowercase the tags, without empty or repeated ones.
+pub(crate) fn normalize_tags(tags: &[String]) -> BTreeSet<String> {
+  tags
+    .iter()
+    .map(|tag| tag.trim().to_lowercase())
+    .filter(|tag| !tag.is_empty())
+    .collect()
+}
+
+/// Replace the tags of a fee.
+pub(crate) async fn set_fee_tags<C: ConnectionTrait>(
+  db: &C,
+  fee_id: i32,
+  tags: &[String],
+) -> Result<(), DbErr> {
+  FeeTags::delete_many()
+    .filter(fee_tags::Column::FeeId.eq(fee_id))
+    .exec(db)
+    .await?;
+
+  let tags = normalize_tags(tags);
+  if tags.is_empty() {
+    return Ok(());
+  }
+  FeeTags::insert_many(tags.into_iter().map(|tag| fee_tags::ActiveModel {
+    fee_id: Set(fee_id),
+    tag: Set(tag),
+  }))
+  .exec(db)
+  .await?;
+  Ok(())
+}
+
+/// Tags of the given fees, sorted.
+pub(crate) async fn load_fee_tags(
+  db: &DatabaseConnection,
+  fee_ids: &[i32],
+) -> Result<HashMap<i32, Vec<String>>, DbErr> {
+  let mut tags = HashMap::<i32, Vec<String>>::new();
+  for chunk in fee_ids.chunks(1000) {
+    let rows = FeeTags::find()
+      .filter(fee_tags::Column::FeeId.is_in(chunk.to_vec()))
+      .order_by(fee_tags::Column::Tag, Order::Asc)
+      .all(db)
+      .await?;
+    for row in rows {
+      tags.entry(row.fee_id).or_default().push(row.tag);
+    }
+  }
+  Ok(tags)
+}
+
+/// Ids of the fees that have a tag.
+pub(crate) async fn fee_ids_with_tag(
+  db: &DatabaseConnection,
+  tag: &str,
+) -> Result<Vec<i32>, DbErr> {
+  FeeTags::find()
+    .select_only()
+    .column(fee_tags::Column::FeeId)
+    .filter(fee_tags::Column::Tag.eq(tag.trim().to_lowercase()))
+    .into_tuple::<i32>()
+    .all(db)
+    .await
+}
+
+#[utoipa::path(
+  get,
+  path = "/fee-tags",
+  description = "Lấy danh sách các nhãn đang được dùng cho các khoản phí, yêu cầu request có role là Manager.",
+  tag = tags::MANAGER,
+  responses(
+    (status = OK, description = "Tags in use", body = Vec<String>),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn get_fee_tags(State(state): State<AppState>) -> Result<Json<Vec<String>>, StatusCode> {
+  let tags = FeeTags::find()
+    .select_only()
+    .column(fee_tags::Column::Tag)
+    .distinct()
+    .order_by(fee_tags::Column::Tag, Order::Asc)
+    .into_tuple::<String>()
+    .all(&state.db)
+    .await
+    .map_err(|e| {
+      log::error!("Error: {:?}", e);
+      StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+  Ok(Json(tags))
+}
+
+/// Give a fee the tags of another, e.g. the next fee of a recurrence chain.
+pub(crate) async fn copy_fee_tags<C: ConnectionTrait>(
+  db: &C,
+  from_fee_id: i32,
+  to_fee_id: i32,
+) -> Result<(), DbErr> {
+  let tags = FeeTags::find()
+    .filter(fee_tags::Column::FeeId.eq(from_fee_id))
+    .all(db)
+    .await?
+    .into_iter()
+    .map(|row| row.tag)
+    .collect::<Vec<_>>();
+  set_fee_tags(db, to_fee_id, &tags).await
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn tags(tags: &[&str]) -> Vec<String> {
+    tags.iter().map(|tag| String::from(*tag)).collect()
+  }
+
+  #[test]
+  fn tags_are_trimmed_and_lowercased() {
+    assert_eq!(
+      normalize_tags(&tags(&[" Tết ", "QUÝ 1"])),
+      BTreeSet::from(["quý 1".to_string(), "tết".to_string()])
+    );
+  }
+
+  #[test]
+  fn empty_and_repeated_tags_are_dropped() {
+    assert_eq!(
+      normalize_tags(&tags(&["", "  ", "thang 3", "Thang 3 ", "thang 3"])),
+      BTreeSet::from(["thang 3".to_string()])
+    );
+    assert!(normalize_tags(&[]).is_empty());
+  }
+}
@@ -0,0 +1,504 @@
+//! The resident census: the family members living in the rooms of the building, and the
+//! demographic statistics that the ward office asks for every quarter.
+//!
+//! Only the family members of the accounts that live in a room are counted.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use axum::response::Response;
+use axum_extra::extract::Query;
+use sea_orm::{Order, QueryOrder};
+
+use super::{
+  registry::floor_of,
+  reports::{export, gender_name, Cell, ReportFormat, ReportRow},
+};
+use crate::{
+  entities::{family, room_members, rooms, users},
+  prelude::*,
+};
+
+/// Lower bounds of the age groups reported to the ward office.
+const AGE_GROUPS: [(u32, &str); 4] = [(0, "0-5"), (6, "6-17"), (18, "18-59"), (60, "60+")];
+/// Ages above this are rejected in the filters.
+const MAX_AGE: u32 = 150;
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct CensusParams {
+  /// Part of the name of the resident
+  pub name: Option<String>,
+  pub room_number: Option<i32>,
+  pub floor: Option<i32>,
+  /// Minimum age in years, inclusive, at most 150
+  pub min_age: Option<u32>,
+  /// Maximum age in years, inclusive, at most 150
+  pub max_age: Option<u32>,
+  #[serde(default)]
+  #[param(inline)]
+  pub format: ReportFormat,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ResidentInfo {
+  pub family_id: i32,
+  pub name: String,
+  pub birthday: chrono::NaiveDate,
+  pub age: u32,
+  pub gender: Option<Gender>,
+  pub relationship: Option<String>,
+  pub national_id: Option<String>,
+  pub occupation: Option<String>,
+  pub phone: Option<String>,
+  pub room_number: i32,
+  pub floor: i32,
+  /// The account the family member was declared by
+  pub account_id: i32,
+  pub account_name: String,
+}
+
+impl ReportRow for ResidentInfo {
+  fn headers() -> &'static [&'static str] {
+    &[
+      "Họ và tên",
+      "Ngày sinh",
+      "Tuổi",
+      "Giới tính",
+      "Số CCCD/CMND",
+      "Quan hệ với chủ hộ",
+      "Nghề nghiệp",
+      "Số điện thoại",
+      "Phòng",
+      "Tầng",
+      "Tài khoản",
+    ]
+  }
+
+  fn cells(&self) -> Vec<Cell> {
+    let text = |value: &Option<String>| Cell::Text(value.clone().unwrap_or_default());
+    vec![
+      Cell::Text(self.name.clone()),
+      Cell::Text(self.birthday.format("%d/%m/%Y").to_string()),
+      Cell::Number(self.age as i64),
+      Cell::Text(
+        self
+          .gender
+          .as_ref()
+          .map(gender_name)
+          .unwrap_or_default()
+          .to_string(),
+      ),
+      text(&self.national_id),
+      text(&self.relationship),
+      text(&self.occupation),
+      text(&self.phone),
+      Cell::Text(self.room_number.to_string()),
+      Cell::Number(self.floor as i64),
+      Cell::Text(self.account_name.clone()),
+    ]
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct GroupCount {
+  pub group: String,
+  pub residents: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FloorCount {
+  pub floor: i32,
+  /// Rooms with someone living in them
+  pub households: u64,
+  pub residents: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CensusStatistics {
+  pub residents: u64,
+  pub households: u64,
+  pub by_age_group: Vec<GroupCount>,
+  /// Residents without a gender are counted as "unknown"
+  pub by_gender: Vec<GroupCount>,
+  pub by_floor: Vec<FloorCount>,
+}
+
+/// A line of the exported statistics.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CensusStatisticsRow {
+  pub category: String,
+  pub group: String,
+  pub households: Option<u64>,
+  pub residents: u64,
+}
+
+impl ReportRow for CensusStatisticsRow {
+  fn headers() -> &'static [&'static str] {
+    &["Chỉ tiêu", "Nhóm", "Số hộ", "Số nhân khẩu"]
+  }
+
+  fn cells(&self) -> Vec<Cell> {
+    vec![
+      Cell::Text(self.category.clone()),
+      Cell::Text(self.group.clone()),
+      Cell::Text(
+        self
+          .households
+          .map(|households| households.to_string())
+          .unwrap_or_default(),
+      ),
+      Cell::Number(self.residents as i64),
+    ]
+  }
+}
+
+impl CensusStatistics {
+  fn rows(&self) -> Vec<CensusStatisticsRow> {
+    let mut rows = vec![CensusStatisticsRow {
+      category: "Tổng".to_string(),
+      group: String::new(),
+      households: Some(self.households),
+      residents: self.residents,
+    }];
+    rows.extend(self.by_age_group.iter().map(|count| CensusStatisticsRow {
+      category: "Độ tuổi".to_string(),
+      group: count.group.clone(),
+      households: None,
+      residents: count.residents,
+    }));
+    rows.extend(self.by_gender.iter().map(|count| CensusStatisticsRow {
+      category: "Giới tính".to_string(),
+      group: count.group.clone(),
+      households: None,
+      residents: count.residents,
+    }));
+    rows.extend(self.by_floor.iter().map(|count| CensusStatisticsRow {
+      category: "Tầng".to_string(),
+      group: count.floor.to_string(),
+      households: Some(count.households),
+      residents: count.residents,
+    }));
+    rows
+  }
+}
+
+fn server_error(e: DbErr) -> StatusCode {
+  log::error!("Error: {:?}", e);
+  StatusCode::INTERNAL_SERVER_ERROR
+}
+
+/// Age in whole years on a day.
+fn age_on(birthday: chrono::NaiveDate, day: chrono::NaiveDate) -> u32 {
+  day.years_since(birthday).unwrap_or(0)
+}
+
+/// The latest birthday of someone at least `age` years old on a day.
+fn born_by(day: chrono::NaiveDate, age: u32) -> chrono::NaiveDate {
+  day
+    .checked_sub_months(chrono::Months::new(age.saturating_mul(12)))
+    .unwrap_or(chrono::NaiveDate::MIN)
+}
+
+/// Reject age filters that are out of range or empty.
+fn check_ages(params: &CensusParams) -> Result<(), StatusCode> {
+  let too_old = [params.min_age, params.max_age]
+    .into_iter()
+    .flatten()
+    .any(|age| age > MAX_AGE);
+  let empty =
+    matches!((params.min_age, params.max_age), (Some(min_age), Some(max_age)) if min_age > max_age);
+  if too_old || empty {
+    return Err(StatusCode::BAD_REQUEST);
+  }
+  Ok(())
+}
+
+/// The residents matching the filters, by room and name. Also returns the rooms with someone
+/// living in them, with their floor.
+async fn load_residents(
+  db: &DatabaseConnection,
+  params: &CensusParams,
+) -> Result<(Vec<ResidentInfo>, HashMap<i32, i32>), DbErr> {
+  let today = chrono::Utc::now().naive_utc().date();
+
+  let mut memberships = RoomMembers::find()
+    .filter(room_members::Column::JoinedAt.is_not_null())
+    .find_also_related(Rooms);
+  if let Some(room_number) = params.room_number {
+    memberships = memberships.filter(room_members::Column::RoomNumber.eq(room_number));
+  }
+  let mut rooms = HashMap::new();
+  let mut homes = HashMap::new();
+  for (membership, room) in memberships.all(db).await? {
+    let floor = room
+      .and_then(|room: rooms::Model| room.floor)
+      .unwrap_or_else(|| floor_of(membership.room_number));
+    if params.floor.is_some_and(|wanted| wanted != floor) {
+      continue;
+    }
+    rooms.insert(membership.room_number, floor);
+    homes.insert(membership.user_id, (membership.room_number, floor));
+  }
+
+  let mut query = family::Entity::find()
+    .find_also_related(Users)
+    .filter(family::Column::AccountId.is_in(homes.keys().copied().collect::<Vec<_>>()))
+    .order_by(family::Column::AccountId, Order::Asc)
+    .order_by(family::Column::Id, Order::Asc);
+  if let Some(name) = params
+    .name
+    .as_deref()
+    .map(str::trim)
+    .filter(|name| !name.is_empty())
+  {
+    query = query.filter(family::Column::Name.contains(name));
+  }
+  if let Some(min_age) = params.min_age {
+    query = query.filter(family::Column::Birthday.lte(born_by(today, min_age)));
+  }
+  if let Some(max_age) = params.max_age {
+    query = query.filter(family::Column::Birthday.gt(born_by(today, max_age.saturating_add(1))));
+  }
+
+  let mut residents = query
+    .all(db)
+    .await?
+    .into_iter()
+    .filter_map(|(member, account): (family::Model, Option<users::Model>)| {
+      let (room_number, floor) = *homes.get(&member.account_id)?;
+      Some(ResidentInfo {
+        family_id: member.id,
+        age: age_on(member.birthday, today),
+        name: member.name,
+        birthday: member.birthday,
+        gender: member.gender,
+        relationship: member.relationship,
+        national_id: member.national_id,
+        occupation: member.occupation,
+        phone: member.phone,
+        room_number,
+        floor,
+        account_id: member.account_id,
+        account_name: account.map(|account| account.name).unwrap_or_default(),
+      })
+    })
+    .collect::<Vec<_>>();
+  residents.sort_by_key(|resident| resident.room_number);
+
+  Ok((residents, rooms))
+}
+
+#[utoipa::path(
+  get,
+  path = "/census/residents",
+  description = "Tìm kiếm và liệt kê các nhân khẩu (thành viên gia đình) đang sống trong tòa nhà, yêu cầu request có role là Manager.
+  Có thể lọc theo tên `name`, số phòng `room_number`, tầng `floor` và độ tuổi `min_age` - `max_age` (tối đa 150), và xuất ra CSV hoặc XLSX.",
+  tag = tags::MANAGER,
+  params(
+    CensusParams
+  ),
+  responses(
+    (status = OK, description = "Residents", body = Vec<ResidentInfo>),
+    (status = BAD_REQUEST, description = "Invalid age range"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn get_census_residents(
+  State(state): State<AppState>,
+  Query(params): Query<CensusParams>,
+) -> Result<Response, StatusCode> {
+  check_ages(&params)?;
+  let (residents, _) = load_residents(&state.db, &params)
+    .await
+    .map_err(server_error)?;
+
+  export("nhan-khau", &residents, params.format)
+}
+
+#[utoipa::path(
+  get,
+  path = "/census/statistics",
+  description = "Thống kê nhân khẩu của tòa nhà theo nhóm tuổi, giới tính, và số hộ, số nhân khẩu theo tầng, yêu cầu request có role là Manager.
+  Dùng các bộ lọc như khi liệt kê nhân khẩu. Khi xuất ra CSV hoặc XLSX, mỗi dòng là một chỉ tiêu.",
+  tag = tags::MANAGER,
+  params(
+    CensusParams
+  ),
+  responses(
+    (status = OK, description = "Census statistics", body = CensusStatistics),
+    (status = BAD_REQUEST, description = "Invalid age range"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn get_census_statistics(
+  State(state): State<AppState>,
+  Query(params): Query<CensusParams>,
+) -> Result<Response, StatusCode> {
+  check_ages(&params)?;
+  let (residents, rooms) = load_residents(&state.db, &params)
+    .await
+    .map_err(server_error)?;
+
+  let mut by_age_group = AGE_GROUPS
+    .iter()
+    .map(|&(_, group)| GroupCount {
+      group: String::from(group),
+      residents: 0,
+    })
+    .collect::<Vec<_>>();
+  let mut by_gender = BTreeMap::new();
+  let mut by_floor = BTreeMap::new();
+  for (room_number, floor) in &rooms {
+    by_floor
+      .entry(*floor)
+      .or_insert_with(|| (HashSet::new(), 0))
+      .0
+      .insert(*room_number);
+  }
+  for resident in &residents {
+    let group = AGE_GROUPS
+      .iter()
+      .rposition(|(from, _)| resident.age >= *from)
+      .unwrap_or(0);
+    by_age_group[group].residents += 1;
+    let gender = resident
+      .gender
+      .as_ref()
+      .map(gender_name)
+      .unwrap_or("Không rõ");
+    *by_gender.entry(gender).or_insert(0) += 1;
+    by_floor
+      .entry(resident.floor)
+      .or_insert_with(|| (HashSet::new(), 0))
+      .1 += 1;
+  }
+
+  let statistics = CensusStatistics {
+    residents: residents.len() as u64,
+    households: rooms.len() as u64,
+    by_age_group,
+    by_gender: by_gender
+      .into_iter()
+      .map(|(group, residents)| GroupCount {
+        group: String::from(group),
+        residents,
+      })
+      .collect(),
+    by_floor: by_floor
+      .into_iter()
+      .map(|(floor, (households, residents))| FloorCount {
+        floor,
+        households: households.len() as u64,
+        residents,
+      })
+      .collect(),
+  };
+
+  match params.format {
+    ReportFormat::Json => Ok(Json(statistics).into_response()),
+    format => export("thong-ke-nhan-khau", &statistics.rows(), format),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn date(y: i32, m: u32, d: u32) -> chrono::NaiveDate {
+    chrono::NaiveDate::from_ymd_opt(y, m, d).unwrap()
+  }
+
+  fn ages(min_age: Option<u32>, max_age: Option<u32>) -> CensusParams {
+    CensusParams {
+      name: None,
+      room_number: None,
+      floor: None,
+      min_age,
+      max_age,
+      format: ReportFormat::Json,
+    }
+  }
+
+  #[test]
+  fn age_changes_on_the_birthday() {
+    let birthday = date(1990, 6, 15);
+    assert_eq!(age_on(birthday, date(2024, 6, 14)), 33);
+    assert_eq!(age_on(birthday, date(2024, 6, 15)), 34);
+    assert_eq!(age_on(birthday, birthday), 0);
+    assert_eq!(age_on(birthday, date(1990, 6, 14)), 0);
+  }
+
+  #[test]
+  fn leap_day_birthday() {
+    let birthday = date(2000, 2, 29);
+    assert_eq!(age_on(birthday, date(2023, 2, 28)), 22);
+    assert_eq!(age_on(birthday, date(2023, 3, 1)), 23);
+    assert_eq!(age_on(birthday, date(2024, 2, 29)), 24);
+  }
+
+  #[test]
+  fn born_by_matches_age_on() {
+    let birthdays = [
+      date(2000, 2, 28),
+      date(2000, 2, 29),
+      date(2000, 3, 1),
+      date(2001, 2, 28),
+      date(2001, 3, 1),
+    ];
+    for day in [
+      date(2024, 2, 28),
+      date(2024, 2, 29),
+      date(2024, 3, 1),
+      date(2025, 2, 28),
+      date(2025, 3, 1),
+    ] {
+      for age in [0, 1, 23, 24, 25] {
+        for birthday in birthdays {
+          assert_eq!(
+            birthday <= born_by(day, age),
+            age_on(birthday, day) >= age,
+            "born {birthday}, {age} years old on {day}"
+          );
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn born_by_the_oldest_age() {
+    assert_eq!(born_by(date(2024, 2, 29), MAX_AGE), date(1874, 2, 28));
+    assert_eq!(born_by(date(2024, 1, 1), u32::MAX), chrono::NaiveDate::MIN);
+  }
+
+  #[test]
+  fn ages_up_to_the_maximum_are_accepted() {
+    assert!(check_ages(&ages(None, None)).is_ok());
+    assert!(check_ages(&ages(Some(18), Some(18))).is_ok());
+    assert!(check_ages(&ages(Some(0), Some(MAX_AGE))).is_ok());
+  }
+
+  #[test]
+  fn invalid_ages_are_rejected() {
+    assert_eq!(
+      check_ages(&ages(Some(MAX_AGE + 1), None)),
+      Err(StatusCode::BAD_REQUEST)
+    );
+    assert_eq!(
+      check_ages(&ages(None, Some(MAX_AGE + 1))),
+      Err(StatusCode::BAD_REQUEST)
+    );
+    assert_eq!(
+      check_ages(&ages(Some(30), Some(20))),
+      Err(StatusCode::BAD_REQUEST)
+    );
+  }
+}
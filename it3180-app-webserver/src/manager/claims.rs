@@ -0,0 +1,346 @@
+//! Room claims made by tenants when they register.
+//!
+//! A tenant does not get a room by declaring it. The claim waits for a manager, who approves it,
+//! which activates the account and moves the tenant in, or rejects it with a reason.
+
+use axum_extra::extract::Query;
+use sea_orm::{Order, QueryOrder, TransactionTrait};
+
+use super::{occupancy::room_accepts, targets};
+use crate::{
+  entities::{room_claims, rooms, users},
+  household::members::{add_resident, find_membership},
+  notify::templates::Notice,
+  prelude::*,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RoomClaimInfo {
+  pub id: i32,
+  pub user_id: i32,
+  pub name: String,
+  pub username: String,
+  pub email: String,
+  pub phone: String,
+  pub account_status: UserStatus,
+  pub room_number: i32,
+  pub status: RoomClaimStatus,
+  /// Why the claim was rejected
+  pub reason: Option<String>,
+  pub reviewed_by: Option<i32>,
+  pub reviewed_at: Option<DateTime>,
+  pub created_at: DateTime,
+  /// The current householder of the room
+  pub householder_id: Option<i32>,
+  /// The claim is pending but the room already has a householder
+  pub conflict: bool,
+}
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct RoomClaimParams {
+  #[param(inline)]
+  pub status: Option<RoomClaimStatus>,
+  pub room_number: Option<i32>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct ApproveClaimInfo {
+  /// Defaults to householder. Other roles join the household of the room.
+  pub role: Option<RoomMemberRole>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RejectClaimInfo {
+  pub reason: String,
+}
+
+fn server_error(e: DbErr) -> StatusCode {
+  log::error!("Error: {:?}", e);
+  StatusCode::INTERNAL_SERVER_ERROR
+}
+
+async fn load_claims(
+  db: &DatabaseConnection,
+  query: Select<RoomClaims>,
+) -> Result<Vec<RoomClaimInfo>, DbErr> {
+  let claims = query
+    .find_also_related(Users)
+    .order_by(room_claims::Column::CreatedAt, Order::Asc)
+    .order_by(room_claims::Column::Id, Order::Asc)
+    .all(db)
+    .await?;
+
+  let room_numbers = claims
+    .iter()
+    .map(|(claim, _)| claim.room_number)
+    .collect::<Vec<_>>();
+  let rooms = Rooms::find()
+    .filter(rooms::Column::RoomNumber.is_in(room_numbers))
+    .all(db)
+    .await?;
+
+  Ok(
+    claims
+      .into_iter()
+      .filter_map(|(claim, user)| {
+        let user = user?;
+        let householder_id = rooms
+          .iter()
+          .find(|room| room.room_number == claim.room_number)
+          .and_then(|room| room.tenant_id);
+        Some(RoomClaimInfo {
+          id: claim.id,
+          user_id: claim.user_id,
+          name: user.name,
+          username: user.username,
+          email: user.email,
+          phone: user.phone,
+          account_status: user.status,
+          room_number: claim.room_number,
+          conflict: claim.status == RoomClaimStatus::Pending && householder_id.is_some(),
+          status: claim.status,
+          reason: claim.reason,
+          reviewed_by: claim.reviewed_by,
+          reviewed_at: claim.reviewed_at,
+          created_at: claim.created_at,
+          householder_id,
+        })
+      })
+      .collect(),
+  )
+}
+
+async fn load_claim(db: &DatabaseConnection, claim_id: i32) -> Result<RoomClaimInfo, StatusCode> {
+  load_claims(db, RoomClaims::find_by_id(claim_id))
+    .await
+    .map_err(server_error)?
+    .pop()
+    .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// A pending claim with its account.
+async fn find_pending_claim(
+  db: &DatabaseConnection,
+  claim_id: i32,
+) -> Result<(room_claims::Model, users::Model), StatusCode> {
+  let (claim, user) = RoomClaims::find_by_id(claim_id)
+    .find_also_related(Users)
+    .one(db)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+  if claim.status != RoomClaimStatus::Pending {
+    return Err(StatusCode::CONFLICT);
+  }
+  Ok((claim, user.ok_or(StatusCode::NOT_FOUND)?))
+}
+
+#[utoipa::path(
+  get,
+  path = "/room-claims",
+  description = "Lấy danh sách các yêu cầu nhận phòng của người thuê khi đăng ký tài khoản, yêu cầu request có role là Manager.
+  Có thể lọc theo trạng thái `status` và số phòng `room_number`. Yêu cầu đang chờ có `conflict` là true nếu phòng đã có chủ hộ.",
+  tag = tags::MANAGER,
+  params(
+    RoomClaimParams
+  ),
+  responses(
+    (status = OK, description = "Room claims", body = Vec<RoomClaimInfo>),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn get_room_claims(
+  State(state): State<AppState>,
+  Query(RoomClaimParams {
+    status,
+    room_number,
+  }): Query<RoomClaimParams>,
+) -> Result<Json<Vec<RoomClaimInfo>>, StatusCode> {
+  let mut query = RoomClaims::find();
+  if let Some(status) = status {
+    query = query.filter(room_claims::Column::Status.eq(status));
+  }
+  if let Some(room_number) = room_number {
+    query = query.filter(room_claims::Column::RoomNumber.eq(room_number));
+  }
+
+  let claims = load_claims(&state.db, query).await.map_err(server_error)?;
+  Ok(Json(claims))
+}
+
+#[utoipa::path(
+  post,
+  path = "/room-claims/{claim_id}/approve",
+  description = "Chấp nhận yêu cầu nhận phòng, yêu cầu request có role là Manager. Tài khoản được kích hoạt và chuyển đến ở phòng,
+  mặc định với vai trò chủ hộ. Nếu phòng đã có chủ hộ, yêu cầu bị từ chối với status CONFLICT, khi đó có thể chấp nhận với vai trò khác hoặc từ chối yêu cầu.",
+  tag = tags::MANAGER,
+  request_body = ApproveClaimInfo,
+  responses(
+    (status = OK, description = "Claim approved", body = RoomClaimInfo),
+    (status = NOT_FOUND, description = "Claim not found"),
+    (status = CONFLICT, description = "Claim already reviewed, room is under renovation or already has a householder, or the account lives in another room"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn approve_room_claim(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+  Path(claim_id): Path<i32>,
+  Json(approve_info): Json<ApproveClaimInfo>,
+) -> Result<Json<RoomClaimInfo>, StatusCode> {
+  let claims = state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+  let role = approve_info.role.unwrap_or(RoomMemberRole::Householder);
+  let now = chrono::Utc::now().naive_utc();
+
+  let (claim, user) = find_pending_claim(&state.db, claim_id).await?;
+  let room = Rooms::find_by_id(claim.room_number)
+    .one(&state.db)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+  if !room_accepts(&room, &role) {
+    log::info!(
+      "Room claim {} conflicts with the household of room {}",
+      claim.id,
+      room.room_number
+    );
+    return Err(StatusCode::CONFLICT);
+  }
+  if find_membership(&state.db, user.id)
+    .await
+    .map_err(server_error)?
+    .is_some()
+  {
+    return Err(StatusCode::CONFLICT);
+  }
+
+  let txn = state.db.begin().await.map_err(server_error)?;
+  add_resident(&txn, room, user.id, role.clone(), now)
+    .await
+    .map_err(server_error)?;
+  let user = users::ActiveModel {
+    status: Set(UserStatus::Active),
+    ..user.into()
+  }
+  .update(&txn)
+  .await
+  .map_err(server_error)?;
+  room_claims::ActiveModel {
+    status: Set(RoomClaimStatus::Approved),
+    reviewed_by: Set(Some(claims.custom.id)),
+    reviewed_at: Set(Some(now)),
+    ..claim.into()
+  }
+  .update(&txn)
+  .await
+  .map_err(server_error)?;
+  txn.commit().await.map_err(server_error)?;
+  log::info!(
+    "Room claim {} approved by {}: user {} moved in as {:?}",
+    claim_id,
+    claims.custom.id,
+    user.id,
+    role
+  );
+
+  // the room may now match the targets of fee assignment rules
+  if let Err(e) = targets::sync_rules(&state).await {
+    log::error!("Failed to sync fee assignment rules: {:?}", e);
+  }
+
+  let claim = load_claim(&state.db, claim_id).await?;
+  if let Err(e) = state
+    .notifier
+    .notify_event(
+      &state.db,
+      claims.custom.id,
+      &user,
+      &Notice::new(NotificationEvent::RoomClaimApproved).text("room", claim.room_number),
+    )
+    .await
+  {
+    log::error!("Error: {:?}", e);
+  }
+
+  Ok(Json(claim))
+}
+
+#[utoipa::path(
+  post,
+  path = "/room-claims/{claim_id}/reject",
+  description = "Từ chối yêu cầu nhận phòng với lý do `reason`, yêu cầu request có role là Manager. Người thuê được thông báo lý do từ chối.
+  Trạng thái kích hoạt của tài khoản không thay đổi.",
+  tag = tags::MANAGER,
+  request_body = RejectClaimInfo,
+  responses(
+    (status = OK, description = "Claim rejected", body = RoomClaimInfo),
+    (status = BAD_REQUEST, description = "Missing reason"),
+    (status = NOT_FOUND, description = "Claim not found"),
+    (status = CONFLICT, description = "Claim already reviewed"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn reject_room_claim(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+  Path(claim_id): Path<i32>,
+  Json(reject_info): Json<RejectClaimInfo>,
+) -> Result<Json<RoomClaimInfo>, StatusCode> {
+  let claims = state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+  let reason = reject_info.reason.trim().to_string();
+  if reason.is_empty() {
+    return Err(StatusCode::BAD_REQUEST);
+  }
+
+  let (claim, user) = find_pending_claim(&state.db, claim_id).await?;
+  room_claims::ActiveModel {
+    status: Set(RoomClaimStatus::Rejected),
+    reason: Set(Some(reason.clone())),
+    reviewed_by: Set(Some(claims.custom.id)),
+    reviewed_at: Set(Some(chrono::Utc::now().naive_utc())),
+    ..claim.into()
+  }
+  .update(&state.db)
+  .await
+  .map_err(server_error)?;
+  log::info!("Room claim {} rejected by {}", claim_id, claims.custom.id);
+
+  let claim = load_claim(&state.db, claim_id).await?;
+  if let Err(e) = state
+    .notifier
+    .notify_event(
+      &state.db,
+      claims.custom.id,
+      &user,
+      &Notice::new(NotificationEvent::RoomClaimRejected)
+        .text("room", claim.room_number)
+        .text("reason", reason),
+    )
+    .await
+  {
+    log::error!("Error: {:?}", e);
+  }
+
+  Ok(Json(claim))
+}
@@ -0,0 +1,544 @@
+//! Review of the tạm trú / tạm vắng declarations, the register requested by the local police, and
+//! alerts for declarations about to expire.
+
+use axum::response::Response;
+use axum_extra::extract::Query;
+use sea_orm::{Order, QueryOrder};
+
+use super::reports::{export, gender_name, Cell, ReportFormat, ReportRow};
+use crate::{
+  entities::residence_declarations,
+  notify::{default_sender, templates::Notice},
+  prelude::*,
+};
+
+/// Approved declarations ending within this many days get an expiry alert.
+const EXPIRY_ALERT_DAYS: i64 = 3;
+/// Longest `expiring_within` accepted, about ten years
+const MAX_EXPIRING_WITHIN_DAYS: i64 = 3650;
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct DeclarationParams {
+  #[param(inline)]
+  pub status: Option<DeclarationStatus>,
+  #[param(inline)]
+  pub kind: Option<DeclarationKind>,
+  pub room_number: Option<i32>,
+  /// Only the approved declarations ending within this many days from today, at most 3650
+  pub expiring_within: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct DeclarationRegisterParams {
+  #[param(inline)]
+  pub kind: Option<DeclarationKind>,
+  /// Start of the period, inclusive
+  pub from: Option<chrono::NaiveDate>,
+  /// End of the period, inclusive
+  pub to: Option<chrono::NaiveDate>,
+  #[serde(default)]
+  #[param(inline)]
+  pub format: ReportFormat,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RejectDeclarationInfo {
+  pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct ExpiryAlertReport {
+  pub sent: usize,
+  pub failed: usize,
+}
+
+/// A line of the tạm trú / tạm vắng register.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DeclarationRegisterRow {
+  pub index: usize,
+  #[serde(flatten)]
+  pub declaration: residence_declarations::Model,
+}
+
+fn kind_name(kind: &DeclarationKind) -> &'static str {
+  match kind {
+    DeclarationKind::TemporaryResidence => "Tạm trú",
+    DeclarationKind::TemporaryAbsence => "Tạm vắng",
+  }
+}
+
+impl ReportRow for DeclarationRegisterRow {
+  fn headers() -> &'static [&'static str] {
+    &[
+      "STT",
+      "Họ và tên",
+      "Ngày sinh",
+      "Giới tính",
+      "Số CCCD/CMND",
+      "Quan hệ với chủ hộ",
+      "Phòng",
+      "Loại",
+      "Từ ngày",
+      "Đến ngày",
+      "Nơi thường trú / Nơi đến",
+      "Lý do",
+    ]
+  }
+
+  fn cells(&self) -> Vec<Cell> {
+    let declaration = &self.declaration;
+    let text = |value: &Option<String>| Cell::Text(value.clone().unwrap_or_default());
+    vec![
+      Cell::Number(self.index as i64),
+      Cell::Text(declaration.full_name.clone()),
+      Cell::Text(
+        declaration
+          .birthday
+          .map(|birthday| birthday.format("%d/%m/%Y").to_string())
+          .unwrap_or_default(),
+      ),
+      Cell::Text(
+        declaration
+          .gender
+          .as_ref()
+          .map(gender_name)
+          .unwrap_or_default()
+          .to_string(),
+      ),
+      text(&declaration.national_id),
+      text(&declaration.relationship),
+      Cell::Text(declaration.room_number.to_string()),
+      Cell::Text(kind_name(&declaration.kind).to_string()),
+      Cell::Text(declaration.start_date.format("%d/%m/%Y").to_string()),
+      Cell::Text(declaration.end_date.format("%d/%m/%Y").to_string()),
+      text(&declaration.address),
+      text(&declaration.reason),
+    ]
+  }
+}
+
+fn server_error(e: DbErr) -> StatusCode {
+  log::error!("Error: {:?}", e);
+  StatusCode::INTERNAL_SERVER_ERROR
+}
+
+fn today() -> chrono::NaiveDate {
+  chrono::Utc::now().naive_utc().date()
+}
+
+/// A notification about a declaration, with its kind and the name of the declared person.
+fn declaration_notice(
+  event: NotificationEvent,
+  declaration: &residence_declarations::Model,
+) -> Notice {
+  let kind_in_english = match declaration.kind {
+    DeclarationKind::TemporaryResidence => "temporary residence",
+    DeclarationKind::TemporaryAbsence => "temporary absence",
+  };
+  Notice::new(event)
+    .label(
+      "kind",
+      kind_name(&declaration.kind).to_lowercase(),
+      kind_in_english,
+    )
+    .text("full_name", &declaration.full_name)
+}
+
+/// Let the resident who made a declaration know about it.
+async fn notify_declarer(
+  state: &AppState,
+  from_user: i32,
+  declaration: &residence_declarations::Model,
+  notice: &Notice,
+) -> Result<(), DbErr> {
+  let Some(declarer) = Users::find_by_id(declaration.declared_by)
+    .one(&state.db)
+    .await?
+  else {
+    return Ok(());
+  };
+  state
+    .notifier
+    .notify_event(&state.db, from_user, &declarer, notice)
+    .await?;
+  Ok(())
+}
+
+/// Set the review of a pending declaration.
+async fn review_declaration(
+  state: &AppState,
+  reviewer: i32,
+  declaration_id: i32,
+  status: DeclarationStatus,
+  review_note: Option<String>,
+) -> Result<residence_declarations::Model, StatusCode> {
+  let declaration = ResidenceDeclarations::find_by_id(declaration_id)
+    .one(&state.db)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+  if declaration.status != DeclarationStatus::Pending {
+    return Err(StatusCode::CONFLICT);
+  }
+
+  let declaration = residence_declarations::ActiveModel {
+    status: Set(status),
+    review_note: Set(review_note),
+    reviewed_by: Set(Some(reviewer)),
+    reviewed_at: Set(Some(chrono::Utc::now().naive_utc())),
+    ..declaration.into()
+  }
+  .update(&state.db)
+  .await
+  .map_err(server_error)?;
+  log::info!(
+    "Residence declaration {} reviewed by {}: {:?}",
+    declaration.id,
+    reviewer,
+    declaration.status
+  );
+  Ok(declaration)
+}
+
+#[utoipa::path(
+  get,
+  path = "/declarations",
+  description = "Lấy danh sách các khai báo tạm trú, tạm vắng, yêu cầu request có role là Manager. Có thể lọc theo trạng thái `status`,
+  loại `kind`, số phòng `room_number`, và các khai báo đã duyệt sắp hết hạn trong `expiring_within` ngày.",
+  tag = tags::MANAGER,
+  params(
+    DeclarationParams
+  ),
+  responses(
+    (status = OK, description = "Declarations", body = Vec<residence_declarations::Model>),
+    (status = BAD_REQUEST, description = "Invalid expiring_within"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn get_declarations(
+  State(state): State<AppState>,
+  Query(params): Query<DeclarationParams>,
+) -> Result<Json<Vec<residence_declarations::Model>>, StatusCode> {
+  let mut query = ResidenceDeclarations::find()
+    .order_by(residence_declarations::Column::StartDate, Order::Desc)
+    .order_by(residence_declarations::Column::Id, Order::Desc);
+  if let Some(status) = params.status {
+    query = query.filter(residence_declarations::Column::Status.eq(status));
+  }
+  if let Some(kind) = params.kind {
+    query = query.filter(residence_declarations::Column::Kind.eq(kind));
+  }
+  if let Some(room_number) = params.room_number {
+    query = query.filter(residence_declarations::Column::RoomNumber.eq(room_number));
+  }
+  if let Some(days) = params.expiring_within {
+    if !(0..=MAX_EXPIRING_WITHIN_DAYS).contains(&days) {
+      return Err(StatusCode::BAD_REQUEST);
+    }
+    let until = today()
+      .checked_add_signed(chrono::Duration::days(days))
+      .ok_or(StatusCode::BAD_REQUEST)?;
+    query = query
+      .filter(residence_declarations::Column::Status.eq(DeclarationStatus::Approved))
+      .filter(residence_declarations::Column::EndDate.gte(today()))
+      .filter(residence_declarations::Column::EndDate.lte(until));
+  }
+
+  let declarations = query.all(&state.db).await.map_err(server_error)?;
+  Ok(Json(declarations))
+}
+
+#[utoipa::path(
+  post,
+  path = "/declarations/{declaration_id}/approve",
+  description = "Duyệt một khai báo tạm trú, tạm vắng, yêu cầu request có role là Manager. Khai báo được đưa vào sổ tạm trú, tạm vắng
+  và người khai báo được thông báo.",
+  tag = tags::MANAGER,
+  responses(
+    (status = OK, description = "Declaration approved", body = residence_declarations::Model),
+    (status = NOT_FOUND, description = "Declaration not found"),
+    (status = CONFLICT, description = "Declaration already reviewed"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn approve_declaration(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+  Path(declaration_id): Path<i32>,
+) -> Result<Json<residence_declarations::Model>, StatusCode> {
+  let claims = state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+  let declaration = review_declaration(
+    &state,
+    claims.custom.id,
+    declaration_id,
+    DeclarationStatus::Approved,
+    None,
+  )
+  .await?;
+
+  if let Err(e) = notify_declarer(
+    &state,
+    claims.custom.id,
+    &declaration,
+    &declaration_notice(NotificationEvent::DeclarationApproved, &declaration)
+      .date("start_date", declaration.start_date)
+      .date("end_date", declaration.end_date),
+  )
+  .await
+  {
+    log::error!("Error: {:?}", e);
+  }
+
+  Ok(Json(declaration))
+}
+
+#[utoipa::path(
+  post,
+  path = "/declarations/{declaration_id}/reject",
+  description = "Từ chối một khai báo tạm trú, tạm vắng với lý do `reason`, yêu cầu request có role là Manager. Người khai báo được thông báo lý do.",
+  tag = tags::MANAGER,
+  request_body = RejectDeclarationInfo,
+  responses(
+    (status = OK, description = "Declaration rejected", body = residence_declarations::Model),
+    (status = BAD_REQUEST, description = "Missing reason"),
+    (status = NOT_FOUND, description = "Declaration not found"),
+    (status = CONFLICT, description = "Declaration already reviewed"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn reject_declaration(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+  Path(declaration_id): Path<i32>,
+  Json(reject_info): Json<RejectDeclarationInfo>,
+) -> Result<Json<residence_declarations::Model>, StatusCode> {
+  let claims = state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+  let reason = reject_info.reason.trim().to_string();
+  if reason.is_empty() {
+    return Err(StatusCode::BAD_REQUEST);
+  }
+
+  let declaration = review_declaration(
+    &state,
+    claims.custom.id,
+    declaration_id,
+    DeclarationStatus::Rejected,
+    Some(reason.clone()),
+  )
+  .await?;
+
+  if let Err(e) = notify_declarer(
+    &state,
+    claims.custom.id,
+    &declaration,
+    &declaration_notice(NotificationEvent::DeclarationRejected, &declaration)
+      .text("reason", reason),
+  )
+  .await
+  {
+    log::error!("Error: {:?}", e);
+  }
+
+  Ok(Json(declaration))
+}
+
+#[utoipa::path(
+  get,
+  path = "/declarations/register",
+  description = "Xuất sổ tạm trú, tạm vắng theo mẫu cơ quan công an yêu cầu, yêu cầu request có role là Manager. Gồm các khai báo đã duyệt
+  có hiệu lực trong khoảng `from` - `to`, có thể lọc theo loại `kind` và xuất ra CSV hoặc XLSX.",
+  tag = tags::MANAGER,
+  params(
+    DeclarationRegisterParams
+  ),
+  responses(
+    (status = OK, description = "Register generated", body = Vec<DeclarationRegisterRow>),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn get_declaration_register(
+  State(state): State<AppState>,
+  Query(params): Query<DeclarationRegisterParams>,
+) -> Result<Response, StatusCode> {
+  let mut query = ResidenceDeclarations::find()
+    .filter(residence_declarations::Column::Status.eq(DeclarationStatus::Approved))
+    .order_by(residence_declarations::Column::StartDate, Order::Asc)
+    .order_by(residence_declarations::Column::Id, Order::Asc);
+  if let Some(kind) = params.kind {
+    query = query.filter(residence_declarations::Column::Kind.eq(kind));
+  }
+  if let Some(from) = params.from {
+    query = query.filter(residence_declarations::Column::EndDate.gte(from));
+  }
+  if let Some(to) = params.to {
+    query = query.filter(residence_declarations::Column::StartDate.lte(to));
+  }
+
+  let rows = query
+    .all(&state.db)
+    .await
+    .map_err(server_error)?
+    .into_iter()
+    .enumerate()
+    .map(|(i, declaration)| DeclarationRegisterRow {
+      index: i + 1,
+      declaration,
+    })
+    .collect::<Vec<_>>();
+
+  export("so-tam-tru-tam-vang", &rows, params.format)
+}
+
+/// Alert the residents whose approved declarations end soon. Every declaration is alerted once.
+pub(crate) async fn send_expiry_alerts(
+  state: &AppState,
+  sender: Option<i32>,
+) -> Result<ExpiryAlertReport, DbErr> {
+  let db = &state.db;
+  let mut report = ExpiryAlertReport::default();
+
+  let sender = match sender {
+    Some(sender) => sender,
+    None => match default_sender(db).await? {
+      Some(manager) => manager,
+      None => {
+        log::warn!("No active manager to send declaration expiry alerts from");
+        return Ok(report);
+      }
+    },
+  };
+
+  let declarations = ResidenceDeclarations::find()
+    .filter(residence_declarations::Column::Status.eq(DeclarationStatus::Approved))
+    .filter(residence_declarations::Column::ExpiryNotifiedAt.is_null())
+    .filter(residence_declarations::Column::EndDate.gte(today()))
+    .filter(
+      residence_declarations::Column::EndDate
+        .lte(today() + chrono::Duration::days(EXPIRY_ALERT_DAYS)),
+    )
+    .all(db)
+    .await?;
+
+  for declaration in declarations {
+    // claim the alert before sending, so that concurrent runs never send it twice
+    let claimed = ResidenceDeclarations::update_many()
+      .col_expr(
+        residence_declarations::Column::ExpiryNotifiedAt,
+        Expr::value(chrono::Utc::now().naive_utc()),
+      )
+      .filter(residence_declarations::Column::Id.eq(declaration.id))
+      .filter(residence_declarations::Column::ExpiryNotifiedAt.is_null())
+      .exec(db)
+      .await?;
+    if claimed.rows_affected == 0 {
+      continue;
+    }
+
+    let notice = declaration_notice(NotificationEvent::DeclarationExpiring, &declaration)
+      .text("room", declaration.room_number)
+      .date("end_date", declaration.end_date);
+    match notify_declarer(state, sender, &declaration, &notice).await {
+      Ok(()) => report.sent += 1,
+      Err(e) => {
+        log::error!(
+          "Failed to send expiry alert for declaration {}: {:?}",
+          declaration.id,
+          e
+        );
+        report.failed += 1;
+        // release the alert so that the next run tries again
+        ResidenceDeclarations::update_many()
+          .col_expr(
+            residence_declarations::Column::ExpiryNotifiedAt,
+            Expr::value(Option::<DateTime>::None),
+          )
+          .filter(residence_declarations::Column::Id.eq(declaration.id))
+          .exec(db)
+          .await?;
+      }
+    }
+  }
+
+  Ok(report)
+}
+
+/// Run [`send_expiry_alerts`] in the background every day.
+pub(crate) fn spawn_expiry_alerts(state: AppState) {
+  tokio::spawn(async move {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(24 * 60 * 60));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    loop {
+      interval.tick().await;
+      match send_expiry_alerts(&state, None).await {
+        Ok(report) if report.sent > 0 || report.failed > 0 => {
+          log::info!(
+            "Declaration expiry alerts: {} sent, {} failed",
+            report.sent,
+            report.failed
+          );
+        }
+        Ok(_) => {}
+        Err(e) => log::error!("Failed to send declaration expiry alerts: {:?}", e),
+      }
+    }
+  });
+}
+
+#[utoipa::path(
+  post,
+  path = "/declarations/alerts/run",
+  description = "Gửi ngay thông báo cho các khai báo tạm trú, tạm vắng đã duyệt sắp hết hạn, yêu cầu request có role là Manager.
+  Mỗi khai báo chỉ được nhắc một lần. Trả về số thông báo đã gửi.",
+  tag = tags::MANAGER,
+  responses(
+    (status = OK, description = "Alerts sent", body = ExpiryAlertReport),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn run_expiry_alerts(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+) -> Result<Json<ExpiryAlertReport>, StatusCode> {
+  let claims = state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+  let report = send_expiry_alerts(&state, Some(claims.custom.id))
+    .await
+    .map_err(server_error)?;
+
+  Ok(Json(report))
+}
@@ -0,0 +1,296 @@
+//! Saved groups of rooms, used as targets when assigning fees.
+
+use std::collections::{BTreeSet, HashMap};
+
+use sea_orm::{Order, PaginatorTrait, QueryOrder, TransactionTrait};
+
+use super::targets;
+use crate::{
+  entities::{room_group_members, room_groups, rooms},
+  prelude::*,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RoomGroupInfo {
+  pub id: i32,
+  pub name: String,
+  pub description: Option<String>,
+  pub room_numbers: Vec<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RoomGroupRequest {
+  pub name: String,
+  pub description: Option<String>,
+  pub room_numbers: Vec<i32>,
+}
+
+fn server_error(e: DbErr) -> StatusCode {
+  log::error!("Error: {:?}", e);
+  StatusCode::INTERNAL_SERVER_ERROR
+}
+
+async fn load_group(
+  db: &DatabaseConnection,
+  group: room_groups::Model,
+) -> Result<RoomGroupInfo, DbErr> {
+  let room_numbers = RoomGroupMembers::find()
+    .filter(room_group_members::Column::GroupId.eq(group.id))
+    .order_by(room_group_members::Column::RoomNumber, Order::Asc)
+    .all(db)
+    .await?
+    .into_iter()
+    .map(|member| member.room_number)
+    .collect();
+
+  Ok(RoomGroupInfo {
+    id: group.id,
+    name: group.name,
+    description: group.description,
+    room_numbers,
+  })
+}
+
+/// Check the request and return its distinct rooms.
+async fn validate_request(
+  db: &DatabaseConnection,
+  request: &RoomGroupRequest,
+  group_id: Option<i32>,
+) -> Result<BTreeSet<i32>, StatusCode> {
+  if request.name.trim().is_empty() {
+    return Err(StatusCode::BAD_REQUEST);
+  }
+
+  let mut same_name = RoomGroups::find().filter(room_groups::Column::Name.eq(request.name.trim()));
+  if let Some(group_id) = group_id {
+    same_name = same_name.filter(room_groups::Column::Id.ne(group_id));
+  }
+  if same_name.one(db).await.map_err(server_error)?.is_some() {
+    return Err(StatusCode::CONFLICT);
+  }
+
+  let room_numbers = request
+    .room_numbers
+    .iter()
+    .copied()
+    .collect::<BTreeSet<_>>();
+  let found = Rooms::find()
+    .filter(rooms::Column::RoomNumber.is_in(room_numbers.clone()))
+    .count(db)
+    .await
+    .map_err(server_error)?;
+  if found as usize != room_numbers.len() {
+    return Err(StatusCode::NOT_FOUND);
+  }
+
+  Ok(room_numbers)
+}
+
+async fn replace_members<C: ConnectionTrait>(
+  db: &C,
+  group_id: i32,
+  room_numbers: &BTreeSet<i32>,
+) -> Result<(), DbErr> {
+  RoomGroupMembers::delete_many()
+    .filter(room_group_members::Column::GroupId.eq(group_id))
+    .exec(db)
+    .await?;
+  if room_numbers.is_empty() {
+    return Ok(());
+  }
+
+  RoomGroupMembers::insert_many(room_numbers.iter().map(|room_number| {
+    room_group_members::ActiveModel {
+      group_id: Set(group_id),
+      room_number: Set(*room_number),
+    }
+  }))
+  .exec(db)
+  .await?;
+  Ok(())
+}
+
+/// Assign the fees of the rules that target groups to their new rooms.
+async fn sync_after_change(state: &AppState) {
+  if let Err(e) = targets::sync_rules(state).await {
+    log::error!("Failed to sync fee assignment rules: {:?}", e);
+  }
+}
+
+#[utoipa::path(
+  get,
+  path = "/room-groups",
+  description = "Lấy danh sách các nhóm phòng đã lưu, yêu cầu request có role là Manager.",
+  tag = tags::MANAGER,
+  responses(
+    (status = OK, description = "Room groups", body = Vec<RoomGroupInfo>),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn get_room_groups(
+  State(state): State<AppState>,
+) -> Result<Json<Vec<RoomGroupInfo>>, StatusCode> {
+  let groups = RoomGroups::find()
+    .order_by(room_groups::Column::Name, Order::Asc)
+    .all(&state.db)
+    .await
+    .map_err(server_error)?;
+  let members = RoomGroupMembers::find()
+    .order_by(room_group_members::Column::RoomNumber, Order::Asc)
+    .all(&state.db)
+    .await
+    .map_err(server_error)?;
+
+  let mut rooms_by_group = HashMap::<i32, Vec<i32>>::new();
+  for member in members {
+    rooms_by_group
+      .entry(member.group_id)
+      .or_default()
+      .push(member.room_number);
+  }
+
+  Ok(Json(
+    groups
+      .into_iter()
+      .map(|group| RoomGroupInfo {
+        room_numbers: rooms_by_group.remove(&group.id).unwrap_or_default(),
+        id: group.id,
+        name: group.name,
+        description: group.description,
+      })
+      .collect(),
+  ))
+}
+
+#[utoipa::path(
+  post,
+  path = "/room-groups",
+  description = "Tạo một nhóm phòng mới, yêu cầu request có role là Manager. Tên nhóm không được trùng với nhóm khác.",
+  tag = tags::MANAGER,
+  request_body = RoomGroupRequest,
+  responses(
+    (status = CREATED, description = "Room group created", body = RoomGroupInfo),
+    (status = BAD_REQUEST, description = "Empty name"),
+    (status = NOT_FOUND, description = "Room not found"),
+    (status = CONFLICT, description = "A group with this name already exists"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn add_room_group(
+  State(state): State<AppState>,
+  Json(request): Json<RoomGroupRequest>,
+) -> Result<(StatusCode, Json<RoomGroupInfo>), StatusCode> {
+  let room_numbers = validate_request(&state.db, &request, None).await?;
+
+  let txn = state.db.begin().await.map_err(server_error)?;
+  let group = room_groups::ActiveModel {
+    name: Set(request.name.trim().to_string()),
+    description: Set(request.description),
+    created_at: Set(chrono::Utc::now().naive_utc()),
+    ..Default::default()
+  }
+  .insert(&txn)
+  .await
+  .map_err(server_error)?;
+  replace_members(&txn, group.id, &room_numbers)
+    .await
+    .map_err(server_error)?;
+  txn.commit().await.map_err(server_error)?;
+  log::info!("Room group created: {:?}", group);
+
+  let group = load_group(&state.db, group).await.map_err(server_error)?;
+  Ok((StatusCode::CREATED, Json(group)))
+}
+
+#[utoipa::path(
+  put,
+  path = "/room-groups/{id}",
+  description = "Chỉnh sửa tên và danh sách phòng của một nhóm phòng, yêu cầu request có role là Manager.
+  Các khoản phí được gán theo nhóm và đang được đồng bộ sẽ được gán cho các phòng mới của nhóm.",
+  tag = tags::MANAGER,
+  request_body = RoomGroupRequest,
+  responses(
+    (status = OK, description = "Room group updated", body = RoomGroupInfo),
+    (status = BAD_REQUEST, description = "Empty name"),
+    (status = NOT_FOUND, description = "Group or room not found"),
+    (status = CONFLICT, description = "A group with this name already exists"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn edit_room_group(
+  State(state): State<AppState>,
+  Path(id): Path<i32>,
+  Json(request): Json<RoomGroupRequest>,
+) -> Result<Json<RoomGroupInfo>, StatusCode> {
+  let group = RoomGroups::find_by_id(id)
+    .one(&state.db)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+  let room_numbers = validate_request(&state.db, &request, Some(id)).await?;
+
+  let txn = state.db.begin().await.map_err(server_error)?;
+  let group = room_groups::ActiveModel {
+    name: Set(request.name.trim().to_string()),
+    description: Set(request.description),
+    ..group.into()
+  }
+  .update(&txn)
+  .await
+  .map_err(server_error)?;
+  replace_members(&txn, id, &room_numbers)
+    .await
+    .map_err(server_error)?;
+  txn.commit().await.map_err(server_error)?;
+  log::info!("Room group updated: {:?}", group);
+
+  sync_after_change(&state).await;
+
+  let group = load_group(&state.db, group).await.map_err(server_error)?;
+  Ok(Json(group))
+}
+
+#[utoipa::path(
+  delete,
+  path = "/room-groups/{id}",
+  description = "Xóa một nhóm phòng, yêu cầu request có role là Manager. Các khoản phí đã gán theo nhóm được giữ nguyên.",
+  tag = tags::MANAGER,
+  responses(
+    (status = NO_CONTENT, description = "Room group removed"),
+    (status = NOT_FOUND, description = "Group not found"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn remove_room_group(State(state): State<AppState>, Path(id): Path<i32>) -> StatusCode {
+  match RoomGroups::delete_by_id(id).exec(&state.db).await {
+    Ok(res) if res.rows_affected == 0 => StatusCode::NOT_FOUND,
+    Ok(_) => {
+      log::info!("Room group removed: {}", id);
+      StatusCode::NO_CONTENT
+    }
+    Err(e) => {
+      log::error!("Error: {:?}", e);
+      StatusCode::INTERNAL_SERVER_ERROR
+    }
+  }
+}
@@ -0,0 +1,156 @@
+//! Versioned history of fee edits.
+//!
+//! Every change of a fee stores its full state as a new version, so that the history shows who
+//! changed what and when. What a room was billed is snapshotted on its assignment separately.
+
+use sea_orm::{ConnectionTrait, Order, QueryOrder, QuerySelect};
+
+use crate::{
+  entities::{fee_versions, fees},
+  prelude::*,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FeeVersionInfo {
+  pub version: i32,
+  pub name: String,
+  pub amount: i64,
+  pub is_required: bool,
+  pub due_date: DateTime,
+  pub recurrence_type: Option<RecurrenceType>,
+  pub category: FeeCategory,
+  pub changed_by: Option<i32>,
+  pub changed_by_name: Option<String>,
+  pub changed_at: DateTime,
+  /// Fields that differ from the previous version, empty for the first one
+  pub changes: Vec<String>,
+}
+
+/// Whether an edit changed anything the history keeps track of.
+pub(crate) fn is_changed(old: &fees::Model, new: &fees::Model) -> bool {
+  old.name != new.name
+    || old.amount != new.amount
+    || old.is_required != new.is_required
+    || old.due_date != new.due_date
+    || old.recurrence_type != new.recurrence_type
+    || old.category != new.category
+}
+
+/// Store the current state of a fee as its next version.
+pub(crate) async fn record_fee_version<C: ConnectionTrait>(
+  db: &C,
+  fee: &fees::Model,
+  changed_by: Option<i32>,
+) -> Result<fee_versions::Model, DbErr> {
+  let latest = FeeVersions::find()
+    .select_only()
+    .column_as(fee_versions::Column::Version.max(), "version")
+    .filter(fee_versions::Column::FeeId.eq(fee.id))
+    .into_tuple::<Option<i32>>()
+    .one(db)
+    .await?
+    .flatten()
+    .unwrap_or(0);
+
+  fee_versions::ActiveModel {
+    fee_id: Set(fee.id),
+    version: Set(latest + 1),
+    name: Set(fee.name.clone()),
+    amount: Set(fee.amount),
+    is_required: Set(fee.is_required),
+    due_date: Set(fee.due_date),
+    recurrence_type: Set(fee.recurrence_type.clone()),
+    category: Set(fee.category.clone()),
+    changed_by: Set(changed_by),
+    changed_at: Set(chrono::Utc::now().naive_utc()),
+    ..Default::default()
+  }
+  .insert(db)
+  .await
+}
+
+fn changed_fields(previous: &fee_versions::Model, version: &fee_versions::Model) -> Vec<String> {
+  let mut changes = vec![];
+  if previous.name != version.name {
+    changes.push("name".to_string());
+  }
+  if previous.amount != version.amount {
+    changes.push("amount".to_string());
+  }
+  if previous.is_required != version.is_required {
+    changes.push("is_required".to_string());
+  }
+  if previous.due_date != version.due_date {
+    changes.push("due_date".to_string());
+  }
+  if previous.recurrence_type != version.recurrence_type {
+    changes.push("recurrence_type".to_string());
+  }
+  if previous.category != version.category {
+    changes.push("category".to_string());
+  }
+  changes
+}
+
+#[utoipa::path(
+  get,
+  path = "/fees/{id}/history",
+  description = "Lấy lịch sử chỉnh sửa của một khoản phí, yêu cầu request có role là Manager.
+  Mỗi phiên bản gồm thông tin khoản phí sau khi chỉnh sửa, người chỉnh sửa và các trường đã thay đổi.",
+  tag = tags::MANAGER,
+  responses(
+    (status = OK, description = "Fee history", body = Vec<FeeVersionInfo>),
+    (status = NOT_FOUND, description = "Fee not found"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn get_fee_history(
+  State(state): State<AppState>,
+  Path(id): Path<i32>,
+) -> Result<Json<Vec<FeeVersionInfo>>, StatusCode> {
+  match Fees::find_by_id(id).one(&state.db).await {
+    Ok(Some(_)) => {}
+    Ok(None) => return Err(StatusCode::NOT_FOUND),
+    Err(e) => {
+      log::error!("Error: {:?}", e);
+      return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+  }
+
+  let versions = FeeVersions::find()
+    .filter(fee_versions::Column::FeeId.eq(id))
+    .order_by(fee_versions::Column::Version, Order::Asc)
+    .find_also_related(Users)
+    .all(&state.db)
+    .await
+    .map_err(|e| {
+      log::error!("Error: {:?}", e);
+      StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+  let mut history = Vec::with_capacity(versions.len());
+  let mut previous: Option<&fee_versions::Model> = None;
+  for (version, user) in &versions {
+    history.push(FeeVersionInfo {
+      version: version.version,
+      name: version.name.clone(),
+      amount: version.amount,
+      is_required: version.is_required,
+      due_date: version.due_date,
+      recurrence_type: version.recurrence_type.clone(),
+      category: version.category.clone(),
+      changed_by: version.changed_by,
+      changed_by_name: user.as_ref().map(|user| user.name.clone()),
+      changed_at: version.changed_at,
+      changes: previous.map_or(vec![], |previous| changed_fields(previous, version)),
+    });
+    previous = Some(version);
+  }
+
+  Ok(Json(history))
+}
@@ -0,0 +1,390 @@
+//! Import of bank statement exports, used to backfill payments the webhook missed.
+
+use std::{collections::HashMap, io::Cursor};
+
+use axum::extract::Multipart;
+use calamine::{open_workbook_auto_from_rs, Data, Reader};
+
+use crate::{
+  entities::transaction_logs,
+  notify::live::publish_payment,
+  payment::{parse_transfer_date, settle_transfer, SettlementOutcome, TransferRecord},
+  prelude::*,
+};
+
+/// Which column of the statement holds which value, by header name (case insensitive).
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+pub struct StatementColumnMapping {
+  pub transaction_date: String,
+  pub description: String,
+  pub reference_code: String,
+  /// Signed amount column. Leave empty if the statement has separate credit / debit columns.
+  pub amount: Option<String>,
+  pub credit: Option<String>,
+  pub debit: Option<String>,
+  pub account_number: Option<String>,
+  pub balance: Option<String>,
+  /// `chrono` format of the date column, tried before the common formats
+  pub date_format: Option<String>,
+  /// Number of lines before the header row, e.g. the bank's letterhead
+  pub skip_rows: usize,
+}
+
+impl Default for StatementColumnMapping {
+  fn default() -> Self {
+    Self {
+      transaction_date: "transaction_date".to_string(),
+      description: "description".to_string(),
+      reference_code: "reference_code".to_string(),
+      amount: Some("amount".to_string()),
+      credit: None,
+      debit: None,
+      account_number: None,
+      balance: None,
+      date_format: None,
+      skip_rows: 0,
+    }
+  }
+}
+
+/// The multipart form accepted by the import endpoint.
+#[derive(ToSchema)]
+#[allow(unused)]
+pub struct ImportStatementForm {
+  /// The statement, as a `.csv` or `.xlsx` file
+  #[schema(value_type = String, format = Binary)]
+  file: Vec<u8>,
+  /// Column mapping as JSON, defaults to [`StatementColumnMapping::default`]
+  mapping: Option<StatementColumnMapping>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ImportRowResult {
+  /// Line number in the file, starting at 1
+  pub row: usize,
+  pub reference_code: Option<String>,
+  pub amount: Option<i64>,
+  pub outcome: Option<SettlementOutcome>,
+  pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct ImportReport {
+  pub total: usize,
+  pub settled: usize,
+  pub duplicates: usize,
+  pub failed: usize,
+  pub rows: Vec<ImportRowResult>,
+}
+
+#[utoipa::path(
+  post,
+  path = "/payments/import",
+  description = "Nhập sao kê ngân hàng (CSV hoặc XLSX) để bổ sung các giao dịch bị thiếu khi webhook gặp sự cố, yêu cầu request có role là Manager.
+  Giao dịch trùng mã tham chiếu sẽ bị bỏ qua. Mỗi dòng được ghi riêng, lỗi ở một dòng không ảnh hưởng đến các dòng đã ghi. Trả về kết quả xử lý của từng dòng.",
+  tag = tags::MANAGER,
+  request_body(content = ImportStatementForm, content_type = "multipart/form-data"),
+  responses(
+    (status = OK, description = "Statement imported", body = ImportReport),
+    (status = BAD_REQUEST, description = "Invalid file or mapping", body = String),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error", body = String),
+    (status = UNAUTHORIZED, description = "Unauthorized", body = String),
+    (status = FORBIDDEN, description = "Forbidden", body = String),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn import_bank_statement(
+  State(state): State<AppState>,
+  mut multipart: Multipart,
+) -> Result<Json<ImportReport>, (StatusCode, String)> {
+  let bad_request = |msg: &str| (StatusCode::BAD_REQUEST, msg.to_string());
+
+  let mut file = None;
+  let mut mapping = StatementColumnMapping::default();
+  while let Some(field) = multipart
+    .next_field()
+    .await
+    .map_err(|_| bad_request("invalid form"))?
+  {
+    match field.name() {
+      Some("file") => {
+        let file_name = field.file_name().unwrap_or_default().to_lowercase();
+        let bytes = field
+          .bytes()
+          .await
+          .map_err(|_| bad_request("invalid file"))?;
+        file = Some((file_name, bytes));
+      }
+      Some("mapping") => {
+        let text = field
+          .text()
+          .await
+          .map_err(|_| bad_request("invalid mapping"))?;
+        mapping = serde_json::from_str(&text).map_err(|e| bad_request(&e.to_string()))?;
+      }
+      _ => {}
+    }
+  }
+
+  let Some((file_name, bytes)) = file else {
+    return Err(bad_request("missing file"));
+  };
+  let rows = if file_name.ends_with(".xlsx") || file_name.ends_with(".xls") {
+    read_xlsx(&bytes)
+  } else {
+    read_csv(&bytes)
+  }
+  .map_err(|e| bad_request(&e.to_string()))?;
+
+  let rows = rows.into_iter().skip(mapping.skip_rows).collect::<Vec<_>>();
+  let Some((header, rows)) = rows.split_first() else {
+    return Err(bad_request("empty file"));
+  };
+  let header = header
+    .iter()
+    .enumerate()
+    .map(|(i, name)| (name.trim().to_lowercase(), i))
+    .collect::<HashMap<_, _>>();
+
+  let mut report = ImportReport::default();
+  for (i, row) in rows.iter().enumerate() {
+    // + 1 for the header, + 1 to count from 1
+    let line = mapping.skip_rows + i + 2;
+    if row.iter().all(|cell| cell.trim().is_empty()) {
+      continue;
+    }
+    report.total += 1;
+
+    let mut result = ImportRowResult {
+      row: line,
+      reference_code: None,
+      amount: None,
+      outcome: None,
+      error: None,
+    };
+
+    let record = match row_to_record(&mapping, &header, row) {
+      Ok(record) => record,
+      Err(e) => {
+        result.error = Some(e.to_string());
+        report.failed += 1;
+        report.rows.push(result);
+        continue;
+      }
+    };
+    result.reference_code = record.reference_code.clone();
+    result.amount = Some(record.transfer_amount);
+
+    // each row is settled in its own transaction, so a failed row is reported and the rows before
+    // it stay imported
+    let outcome = match import_record(&state.db, record).await {
+      Ok(outcome) => outcome,
+      Err(e) => {
+        log::error!("Error: {:?}", e);
+        result.error = Some("server error".to_string());
+        report.failed += 1;
+        report.rows.push(result);
+        continue;
+      }
+    };
+
+    match outcome {
+      SettlementOutcome::Settled { assignment_id } => {
+        report.settled += 1;
+        if let Err(e) = publish_payment(&state, assignment_id).await {
+          log::error!("Failed to publish payment {}: {:?}", assignment_id, e);
+        }
+      }
+      SettlementOutcome::Duplicate => report.duplicates += 1,
+      SettlementOutcome::Outgoing => {}
+      _ => report.failed += 1,
+    }
+    result.outcome = Some(outcome);
+    report.rows.push(result);
+  }
+
+  log::info!(
+    "Statement imported: {} rows, {} settled, {} duplicates, {} failed",
+    report.total,
+    report.settled,
+    report.duplicates,
+    report.failed
+  );
+
+  Ok(Json(report))
+}
+
+/// Settle a transfer of the statement, unless it already came in through the webhook.
+async fn import_record(
+  db: &DatabaseConnection,
+  record: TransferRecord,
+) -> Result<SettlementOutcome, DbErr> {
+  let existing = TransactionLogs::find()
+    .filter(transaction_logs::Column::ReferenceCode.eq(record.reference_code.clone()))
+    .one(db)
+    .await?;
+  match existing {
+    Some(_) => Ok(SettlementOutcome::Duplicate),
+    None => settle_transfer(db, record).await,
+  }
+}
+
+fn row_to_record(
+  mapping: &StatementColumnMapping,
+  header: &HashMap<String, usize>,
+  row: &[String],
+) -> anyhow::Result<TransferRecord> {
+  let cell = |column: &str| -> anyhow::Result<&str> {
+    let index = header
+      .get(&column.trim().to_lowercase())
+      .ok_or_else(|| anyhow::anyhow!("column not found: {}", column))?;
+    Ok(row.get(*index).map(|cell| cell.trim()).unwrap_or_default())
+  };
+  let optional_cell = |column: &Option<String>| -> anyhow::Result<Option<&str>> {
+    match column {
+      Some(column) => cell(column).map(|value| Some(value).filter(|value| !value.is_empty())),
+      None => Ok(None),
+    }
+  };
+
+  let date = cell(&mapping.transaction_date)?;
+  let transaction_date = mapping
+    .date_format
+    .as_ref()
+    .and_then(|format| chrono::NaiveDateTime::parse_from_str(date, format).ok())
+    .or_else(|| parse_transfer_date(date))
+    .ok_or_else(|| anyhow::anyhow!("invalid date: {}", date))?;
+
+  let transfer_amount = match optional_cell(&mapping.amount)? {
+    Some(amount) => parse_amount(amount)?,
+    None => {
+      let credit = optional_cell(&mapping.credit)?
+        .map(parse_amount)
+        .transpose()?
+        .unwrap_or_default();
+      let debit = optional_cell(&mapping.debit)?
+        .map(parse_amount)
+        .transpose()?
+        .unwrap_or_default();
+      credit.abs() - debit.abs()
+    }
+  };
+
+  let reference_code = cell(&mapping.reference_code)?;
+  if reference_code.is_empty() {
+    anyhow::bail!("missing reference code");
+  }
+
+  Ok(TransferRecord {
+    provider: "statement".to_string(),
+    external_id: Some(reference_code.to_string()),
+    gateway: "statement".to_string(),
+    transaction_date,
+    account_number: optional_cell(&mapping.account_number)?
+      .unwrap_or_default()
+      .to_string(),
+    sub_account: None,
+    transfer_amount,
+    accumulated: optional_cell(&mapping.balance)?
+      .map(parse_amount)
+      .transpose()?
+      .unwrap_or_default(),
+    code: None,
+    content: cell(&mapping.description)?.to_string(),
+    reference_code: Some(reference_code.to_string()),
+    description: None,
+  })
+}
+
+/// Parse an amount in VND, e.g. `1,000,000`, `1.000.000`, `-200000` or `200000.00`.
+fn parse_amount(amount: &str) -> anyhow::Result<i64> {
+  let amount = amount.trim();
+  let negative = amount.starts_with('-') || (amount.starts_with('(') && amount.ends_with(')'));
+  // drop a trailing decimal part such as ".00" or ",00"
+  let amount = match amount.rfind(['.', ',']) {
+    Some(i) if amount.len() - i == 3 => &amount[..i],
+    _ => amount,
+  };
+  let digits = amount
+    .chars()
+    .filter(|c| c.is_ascii_digit())
+    .collect::<String>();
+  let value = digits
+    .parse::<i64>()
+    .map_err(|_| anyhow::anyhow!("invalid amount: {}", amount))?;
+  Ok(if negative { -value } else { value })
+}
+
+pub(super) fn read_csv(bytes: &[u8]) -> anyhow::Result<Vec<Vec<String>>> {
+  // some banks export with a BOM
+  let bytes = bytes.strip_prefix(b"\xef\xbb\xbf").unwrap_or(bytes);
+  let mut reader = csv::ReaderBuilder::new()
+    .has_headers(false)
+    .flexible(true)
+    .from_reader(bytes);
+  let mut rows = vec![];
+  for record in reader.records() {
+    rows.push(record?.iter().map(|cell| cell.to_string()).collect());
+  }
+  Ok(rows)
+}
+
+pub(super) fn read_xlsx(bytes: &[u8]) -> anyhow::Result<Vec<Vec<String>>> {
+  let mut workbook = open_workbook_auto_from_rs(Cursor::new(bytes))?;
+  let range = workbook
+    .worksheet_range_at(0)
+    .ok_or_else(|| anyhow::anyhow!("the workbook has no sheet"))??;
+  Ok(
+    range
+      .rows()
+      .map(|row| {
+        row
+          .iter()
+          .map(|cell| match cell {
+            Data::DateTime(date) => date
+              .as_datetime()
+              .map(|date| date.format("%Y-%m-%d %H:%M:%S").to_string())
+              .unwrap_or_default(),
+            Data::Float(value) if value.fract() == 0.0 => format!("{}", *value as i64),
+            Data::Empty => String::new(),
+            cell => cell.to_string(),
+          })
+          .collect()
+      })
+      .collect(),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn amount_with_thousands_separators() {
+    assert_eq!(parse_amount("1,000,000").unwrap(), 1_000_000);
+    assert_eq!(parse_amount("1.000.000").unwrap(), 1_000_000);
+    assert_eq!(parse_amount(" 200000 ").unwrap(), 200_000);
+  }
+
+  #[test]
+  fn amount_decimal_part_is_dropped() {
+    assert_eq!(parse_amount("200000.00").unwrap(), 200_000);
+    assert_eq!(parse_amount("1.000.000,00").unwrap(), 1_000_000);
+    assert_eq!(parse_amount("1,000,000.00").unwrap(), 1_000_000);
+  }
+
+  #[test]
+  fn negative_amount() {
+    assert_eq!(parse_amount("-200000").unwrap(), -200_000);
+    assert_eq!(parse_amount("(200,000)").unwrap(), -200_000);
+  }
+
+  #[test]
+  fn invalid_amount() {
+    assert!(parse_amount("").is_err());
+    assert!(parse_amount("abc").is_err());
+  }
+}
@@ -0,0 +1,232 @@
+//! Invitation links that managers hand out to new tenants, e.g. when a contract is signed.
+//!
+//! The link carries a signed token bound to a room and a role. Registering with it creates an
+//! active account that lives in the room right away, without waiting for a room claim review.
+//! Every invitation is stored so it registers one account only and can be revoked.
+
+use sea_orm::{Order, QueryOrder};
+
+use super::occupancy::room_accepts;
+use crate::{entities::room_invitations, prelude::*};
+
+/// Subject of the invitation tokens, so they are never mistaken for access tokens.
+pub(crate) const INVITATION_SUBJECT: &str = "room_invitation";
+
+const DEFAULT_EXPIRY_HOURS: u64 = 72;
+const MAX_EXPIRY_HOURS: u64 = 24 * 30;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct CreateInvitationInfo {
+  /// Defaults to householder
+  pub role: Option<RoomMemberRole>,
+  /// Only this email can register with the invitation
+  pub email: Option<String>,
+  /// Defaults to 72 hours, at most 30 days
+  pub expires_in_hours: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct IssuedInvitation {
+  #[serde(flatten)]
+  pub invitation: room_invitations::Model,
+  /// Signed token to register with, only shown once
+  pub token: String,
+}
+
+fn server_error(e: DbErr) -> StatusCode {
+  log::error!("Error: {:?}", e);
+  StatusCode::INTERNAL_SERVER_ERROR
+}
+
+/// Verify an invitation token and find its invitation, if it can still be used.
+pub(crate) async fn find_usable_invitation(
+  state: &AppState,
+  token: &str,
+) -> Result<Option<room_invitations::Model>, DbErr> {
+  let options = VerificationOptions {
+    required_subject: Some(INVITATION_SUBJECT.to_string()),
+    ..Default::default()
+  };
+  let Ok(claims) = state
+    .jwt_access_secret
+    .verify_token::<RoomInvitationClaims>(token, Some(options))
+  else {
+    return Ok(None);
+  };
+
+  let invitation = RoomInvitations::find_by_id(claims.custom.invitation_id)
+    .filter(room_invitations::Column::RoomNumber.eq(claims.custom.room_number))
+    .filter(room_invitations::Column::UsedAt.is_null())
+    .filter(room_invitations::Column::RevokedAt.is_null())
+    .filter(room_invitations::Column::ExpiresAt.gt(chrono::Utc::now().naive_utc()))
+    .one(&state.db)
+    .await?;
+  Ok(invitation)
+}
+
+#[utoipa::path(
+  post,
+  path = "/rooms/{room_number}/invitations",
+  description = "Tạo liên kết mời người thuê mới vào một phòng với một vai trò, yêu cầu request có role là Manager. Trả về token đã ký,
+  có hạn sử dụng `expires_in_hours` (mặc định 72 giờ). Người được mời đăng ký bằng token và được kích hoạt, chuyển vào phòng ngay lập tức.
+  Mặc định vai trò là chủ hộ, khi đó phòng phải chưa có chủ hộ.",
+  tag = tags::MANAGER,
+  request_body = CreateInvitationInfo,
+  responses(
+    (status = CREATED, description = "Invitation created", body = IssuedInvitation),
+    (status = BAD_REQUEST, description = "Invalid expiry"),
+    (status = NOT_FOUND, description = "Room not found"),
+    (status = CONFLICT, description = "Room is under renovation, or already has a householder"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn create_invitation(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+  Path(room_number): Path<i32>,
+  Json(invitation_info): Json<CreateInvitationInfo>,
+) -> Result<(StatusCode, Json<IssuedInvitation>), StatusCode> {
+  let claims = state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+  let role = invitation_info.role.unwrap_or(RoomMemberRole::Householder);
+  let expires_in_hours = invitation_info
+    .expires_in_hours
+    .unwrap_or(DEFAULT_EXPIRY_HOURS);
+  if expires_in_hours == 0 || expires_in_hours > MAX_EXPIRY_HOURS {
+    return Err(StatusCode::BAD_REQUEST);
+  }
+  let email = invitation_info
+    .email
+    .map(|email| email.trim().to_string())
+    .filter(|email| !email.is_empty());
+
+  let room = Rooms::find_by_id(room_number)
+    .one(&state.db)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+  if !room_accepts(&room, &role) {
+    return Err(StatusCode::CONFLICT);
+  }
+
+  let invitation = room_invitations::ActiveModel {
+    room_number: Set(room_number),
+    role: Set(role.clone()),
+    email: Set(email),
+    created_by: Set(Some(claims.custom.id)),
+    expires_at: Set(
+      chrono::Utc::now().naive_utc() + chrono::Duration::hours(expires_in_hours as i64),
+    ),
+    ..Default::default()
+  }
+  .insert(&state.db)
+  .await
+  .map_err(server_error)?;
+
+  let token_claims = Claims::with_custom_claims(
+    RoomInvitationClaims {
+      invitation_id: invitation.id,
+      room_number,
+      role,
+    },
+    Duration::from_hours(expires_in_hours),
+  )
+  .with_subject(INVITATION_SUBJECT);
+  let token = state
+    .jwt_access_secret
+    .authenticate(token_claims)
+    .map_err(|e| {
+      log::error!("Error creating invitation token: {:?}", e);
+      StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+  log::info!(
+    "Room invitation {} created by {} for room {}",
+    invitation.id,
+    claims.custom.id,
+    room_number
+  );
+
+  Ok((
+    StatusCode::CREATED,
+    Json(IssuedInvitation { invitation, token }),
+  ))
+}
+
+#[utoipa::path(
+  get,
+  path = "/rooms/{room_number}/invitations",
+  description = "Lấy danh sách các liên kết mời vào một phòng, bao gồm cả các liên kết đã được sử dụng, đã thu hồi hoặc hết hạn,
+  yêu cầu request có role là Manager.",
+  tag = tags::MANAGER,
+  responses(
+    (status = OK, description = "Room invitations", body = Vec<room_invitations::Model>),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn get_invitations(
+  State(state): State<AppState>,
+  Path(room_number): Path<i32>,
+) -> Result<Json<Vec<room_invitations::Model>>, StatusCode> {
+  let invitations = RoomInvitations::find()
+    .filter(room_invitations::Column::RoomNumber.eq(room_number))
+    .order_by(room_invitations::Column::CreatedAt, Order::Desc)
+    .all(&state.db)
+    .await
+    .map_err(server_error)?;
+  Ok(Json(invitations))
+}
+
+#[utoipa::path(
+  delete,
+  path = "/invitations/{invitation_id}",
+  description = "Thu hồi một liên kết mời chưa được sử dụng, yêu cầu request có role là Manager.",
+  tag = tags::MANAGER,
+  responses(
+    (status = NO_CONTENT, description = "Invitation revoked"),
+    (status = NOT_FOUND, description = "Invitation not found"),
+    (status = CONFLICT, description = "Invitation already used"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn revoke_invitation(
+  State(state): State<AppState>,
+  Path(invitation_id): Path<i32>,
+) -> Result<StatusCode, StatusCode> {
+  let invitation = RoomInvitations::find_by_id(invitation_id)
+    .one(&state.db)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+  if invitation.used_at.is_some() {
+    return Err(StatusCode::CONFLICT);
+  }
+  if invitation.revoked_at.is_none() {
+    room_invitations::ActiveModel {
+      revoked_at: Set(Some(chrono::Utc::now().naive_utc())),
+      ..invitation.into()
+    }
+    .update(&state.db)
+    .await
+    .map_err(server_error)?;
+    log::info!("Room invitation {} revoked", invitation_id);
+  }
+
+  Ok(StatusCode::NO_CONTENT)
+}
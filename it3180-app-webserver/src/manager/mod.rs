@@ -1,11 +1,32 @@
-use crate::{entities::*, household::FeesRoomInfo, prelude::*};
+pub mod assignments;
+pub mod categories;
+pub mod census;
+pub mod claims;
+pub mod declarations;
+pub mod groups;
+pub mod history;
+pub mod import;
+pub mod invitations;
+pub mod occupancy;
+pub mod registry;
+pub mod reports;
+pub mod residents;
+pub mod targets;
+pub mod vehicles;
+
+use crate::{
+  entities::*,
+  household::{is_overdue, FeesRoomInfo},
+  ledger,
+  prelude::*,
+};
 
 pub mod types {
   use crate::Fees;
   use sea_orm::{DerivePartialModel, FromQueryResult};
   use utoipa::ToSchema;
 
-  use crate::entities::sea_orm_active_enums::RecurrenceType;
+  use crate::entities::sea_orm_active_enums::{FeeCategory, RecurrenceType};
 
   #[derive(
     Debug,
@@ -23,6 +44,24 @@ pub mod types {
     pub name: String,
     pub amount: i64,
     pub due_date: chrono::NaiveDateTime,
+    pub archived_at: Option<chrono::NaiveDateTime>,
+    pub category: FeeCategory,
+  }
+
+  #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ToSchema)]
+  pub struct FeeListItem {
+    #[serde(flatten)]
+    pub info: FeesInfo,
+    pub tags: Vec<String>,
+  }
+
+  #[derive(Debug, serde::Deserialize, utoipa::IntoParams)]
+  pub struct GetFeesParams {
+    /// Also return the archived fees
+    pub include_archived: Option<bool>,
+    #[param(inline)]
+    pub category: Option<FeeCategory>,
+    pub tag: Option<String>,
   }
 
   #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ToSchema)]
@@ -32,19 +71,28 @@ pub mod types {
     pub is_required: bool,
     pub due_date: chrono::NaiveDateTime,
     pub recurrence_type: Option<RecurrenceType>,
+    /// Service if not given, unchanged when editing
+    pub category: Option<FeeCategory>,
+    /// Unchanged when editing if not given
+    pub tags: Option<Vec<String>>,
   }
 }
 
-use sea_orm::{sea_query::OnConflict, Order, QueryOrder};
+use axum_extra::extract::Query;
+use sea_orm::{Order, PaginatorTrait, QueryOrder, TransactionTrait};
 use types::*;
 
 #[utoipa::path(
   get,
   path = "/fees",
-  description = "Lấy danh sách tất cả các khoản phí, yêu cầu request có role là Manager. Trả về danh sách các khoản phí.",
+  description = "Lấy danh sách tất cả các khoản phí, yêu cầu request có role là Manager. Trả về danh sách các khoản phí.
+  Các khoản phí đã lưu trữ chỉ được trả về khi `include_archived` là true. Có thể lọc theo danh mục `category` và nhãn `tag`.",
   tag = tags::MANAGER,
+  params(
+    GetFeesParams
+  ),
   responses(
-    (status = OK, description = "Fees retrieved", body = Vec<FeesInfo>),
+    (status = OK, description = "Fees retrieved", body = Vec<FeeListItem>),
     (status = INTERNAL_SERVER_ERROR, description = "Server error", body = String),
     (status = UNAUTHORIZED, description = "Unauthorized", body = String),
     (status = FORBIDDEN, description = "Forbidden", body = String),
@@ -55,23 +103,51 @@ use types::*;
 )]
 pub async fn get_fees(
   State(state): State<AppState>,
-) -> Result<impl IntoResponse, impl IntoResponse> {
-  let fees = match Fees::find()
-    .order_by(fees::Column::DueDate, Order::Asc)
+  Query(GetFeesParams {
+    include_archived,
+    category,
+    tag,
+  }): Query<GetFeesParams>,
+) -> Result<impl IntoResponse, (StatusCode, HeaderMap, String)> {
+  let server_error = |e: DbErr| {
+    log::error!("Error: {:?}", e);
+    (
+      StatusCode::INTERNAL_SERVER_ERROR,
+      HeaderMap::new(),
+      "".to_string(),
+    )
+  };
+
+  let mut query = Fees::find().order_by(fees::Column::DueDate, Order::Asc);
+  if !include_archived.unwrap_or(false) {
+    query = query.filter(fees::Column::ArchivedAt.is_null());
+  }
+  if let Some(category) = category {
+    query = query.filter(fees::Column::Category.eq(category));
+  }
+  if let Some(tag) = tag {
+    let fee_ids = categories::fee_ids_with_tag(&state.db, &tag)
+      .await
+      .map_err(server_error)?;
+    query = query.filter(fees::Column::Id.is_in(fee_ids));
+  }
+
+  let fees = query
     .into_partial_model::<FeesInfo>()
     .all(&state.db)
     .await
-  {
-    Ok(fees) => fees,
-    Err(e) => {
-      log::error!("Error: {:?}", e);
-      return Err((
-        StatusCode::INTERNAL_SERVER_ERROR,
-        HeaderMap::new(),
-        "".to_string(),
-      ));
-    }
-  };
+    .map_err(server_error)?;
+  let fee_ids = fees.iter().map(|fee| fee.id).collect::<Vec<_>>();
+  let mut tags = categories::load_fee_tags(&state.db, &fee_ids)
+    .await
+    .map_err(server_error)?;
+  let fees = fees
+    .into_iter()
+    .map(|info| FeeListItem {
+      tags: tags.remove(&info.id).unwrap_or_default(),
+      info,
+    })
+    .collect::<Vec<_>>();
 
   Ok((
     StatusCode::OK,
@@ -97,10 +173,21 @@ pub async fn get_fees(
 )]
 pub async fn add_fee(
   State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
   Json(fee_info): Json<AddFeeInfo>,
 ) -> StatusCode {
   log::debug!("Adding fee: {:?}", fee_info);
 
+  let claims = match state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+  {
+    Ok(claims) => claims,
+    Err(_) => {
+      return StatusCode::UNAUTHORIZED;
+    }
+  };
+
   let new_fee = fees::ActiveModel {
     amount: Set(fee_info.amount),
     name: Set(fee_info.name),
@@ -108,12 +195,13 @@ pub async fn add_fee(
     is_required: Set(fee_info.is_required),
     is_recurring: Set(fee_info.recurrence_type.is_some()),
     recurrence_type: Set(fee_info.recurrence_type.clone()),
+    category: Set(fee_info.category.clone().unwrap_or_default()),
     ..Default::default()
   };
-  let res = match Fees::insert(new_fee).exec(&state.db).await {
-    Ok(res) => {
-      log::info!("Fee added: {:?}", res);
-      res
+  let new_fee = match new_fee.insert(&state.db).await {
+    Ok(new_fee) => {
+      log::info!("Fee added: {:?}", new_fee);
+      new_fee
     }
     Err(e) => {
       log::error!("Error: {:?}", e);
@@ -121,11 +209,22 @@ pub async fn add_fee(
     }
   };
 
+  if let Err(e) = history::record_fee_version(&state.db, &new_fee, Some(claims.custom.id)).await {
+    log::error!("Error: {:?}", e);
+    return StatusCode::INTERNAL_SERVER_ERROR;
+  }
+  if let Some(tags) = &fee_info.tags {
+    if let Err(e) = categories::set_fee_tags(&state.db, new_fee.id, tags).await {
+      log::error!("Error: {:?}", e);
+      return StatusCode::INTERNAL_SERVER_ERROR;
+    }
+  }
+
   // make recurrence entry
   if fee_info.recurrence_type.is_some() {
     let recurrence_entry = fee_recurrence::ActiveModel {
-      fee_id: Set(res.last_insert_id),
-      previous_fee_id: Set(res.last_insert_id),
+      fee_id: Set(new_fee.id),
+      previous_fee_id: Set(new_fee.id),
       due_date: Set(fee_info.due_date),
       ..Default::default()
     };
@@ -150,11 +249,13 @@ pub async fn add_fee(
 #[utoipa::path(
   delete,
   path = "/fees/{id}",
-  description = "Xóa một khoản phí, yêu cầu request có role là Manager. Kiểm tra khoản thu có tồn tại không, và trả về status NO_CONTENT nếu thành công",
+  description = "Xóa một khoản phí, yêu cầu request có role là Manager. Kiểm tra khoản thu có tồn tại không, và trả về status NO_CONTENT nếu thành công.
+  Không thể xóa khoản phí đã có phòng thanh toán, khi đó hãy lưu trữ khoản phí.",
   tag = tags::MANAGER,
   responses(
     (status = NO_CONTENT, description = "Fee removed"),
     (status = NOT_FOUND, description = "Fee not found"),
+    (status = CONFLICT, description = "Fee has payments, archive it instead"),
     (status = INTERNAL_SERVER_ERROR, description = "Server error"),
     (status = UNAUTHORIZED, description = "Unauthorized"),
     (status = FORBIDDEN, description = "Forbidden"),
@@ -163,13 +264,81 @@ pub async fn add_fee(
     ("Authorization" = [])
   )
 )]
-pub async fn remove_fee(State(state): State<AppState>, Path(id): Path<i32>) -> StatusCode {
-  let res = Fees::delete_by_id(id).exec(&state.db).await;
+pub async fn remove_fee(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+  Path(id): Path<i32>,
+) -> StatusCode {
+  let claims = match state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+  {
+    Ok(claims) => claims,
+    Err(_) => {
+      return StatusCode::UNAUTHORIZED;
+    }
+  };
+
+  let assignments = match FeesRoomAssignment::find()
+    .filter(fees_room_assignment::Column::FeeId.eq(id))
+    .all(&state.db)
+    .await
+  {
+    Ok(assignments) => assignments,
+    Err(e) => {
+      log::error!("Error: {:?}", e);
+      return StatusCode::INTERNAL_SERVER_ERROR;
+    }
+  };
+
+  // payment history must never be deleted with the fee
+  let assignment_ids = assignments
+    .iter()
+    .map(|assignment| assignment.assignment_id)
+    .collect::<Vec<_>>();
+  let payments = match Transactions::find()
+    .filter(transactions::Column::AssignmentId.is_in(assignment_ids))
+    .count(&state.db)
+    .await
+  {
+    Ok(payments) => payments,
+    Err(e) => {
+      log::error!("Error: {:?}", e);
+      return StatusCode::INTERNAL_SERVER_ERROR;
+    }
+  };
+  if payments > 0 || assignments.iter().any(|assignment| assignment.is_paid) {
+    log::info!("Fee {} has payments, refusing to delete it", id);
+    return StatusCode::CONFLICT;
+  }
+
+  // the unpaid assignments go away with the fee, cancel what they were charged
+  let txn = match state.db.begin().await {
+    Ok(txn) => txn,
+    Err(e) => {
+      log::error!("Error: {:?}", e);
+      return StatusCode::INTERNAL_SERVER_ERROR;
+    }
+  };
+  for assignment in &assignments {
+    if let Err(e) =
+      ledger::reverse_charges(&txn, assignment.assignment_id, Some(claims.custom.id)).await
+    {
+      log::error!("Error: {:?}", e);
+      return StatusCode::INTERNAL_SERVER_ERROR;
+    }
+  }
+
+  let res = Fees::delete_by_id(id).exec(&txn).await;
 
   let Ok(res) = res else {
     log::error!("Error: {:?}", res);
     return StatusCode::INTERNAL_SERVER_ERROR;
   };
+  if let Err(e) = txn.commit().await {
+    log::error!("Error: {:?}", e);
+    return StatusCode::INTERNAL_SERVER_ERROR;
+  }
 
   match res.rows_affected {
     0 => {
@@ -183,6 +352,83 @@ pub async fn remove_fee(State(state): State<AppState>, Path(id): Path<i32>) -> S
   }
 }
 
+/// Set or clear the archive date of a fee.
+async fn set_fee_archived(state: &AppState, id: i32, archived: bool) -> StatusCode {
+  let fee = match Fees::find_by_id(id).one(&state.db).await {
+    Ok(Some(fee)) => fee,
+    Ok(None) => {
+      return StatusCode::NOT_FOUND;
+    }
+    Err(e) => {
+      log::error!("Error: {:?}", e);
+      return StatusCode::INTERNAL_SERVER_ERROR;
+    }
+  };
+
+  // keep the original archive date when archiving twice
+  let archived_at = match (archived, fee.archived_at) {
+    (true, Some(archived_at)) => Some(archived_at),
+    (true, None) => Some(chrono::Utc::now().naive_utc()),
+    (false, _) => None,
+  };
+  let fee = fees::ActiveModel {
+    archived_at: Set(archived_at),
+    ..fee.into()
+  };
+
+  match Fees::update(fee).exec(&state.db).await {
+    Ok(res) => {
+      log::info!("Fee archived: {} {:?}", archived, res.id);
+      StatusCode::NO_CONTENT
+    }
+    Err(e) => {
+      log::error!("Error: {:?}", e);
+      StatusCode::INTERNAL_SERVER_ERROR
+    }
+  }
+}
+
+#[utoipa::path(
+  post,
+  path = "/fees/{id}/archive",
+  description = "Lưu trữ một khoản phí, yêu cầu request có role là Manager. Khoản phí bị ẩn khỏi danh sách và không thể gán thêm cho phòng,
+  nhưng lịch sử gán và thanh toán vẫn được giữ nguyên. Trả về status NO_CONTENT nếu thành công",
+  tag = tags::MANAGER,
+  responses(
+    (status = NO_CONTENT, description = "Fee archived"),
+    (status = NOT_FOUND, description = "Fee not found"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn archive_fee(State(state): State<AppState>, Path(id): Path<i32>) -> StatusCode {
+  set_fee_archived(&state, id, true).await
+}
+
+#[utoipa::path(
+  post,
+  path = "/fees/{id}/restore",
+  description = "Khôi phục một khoản phí đã lưu trữ, yêu cầu request có role là Manager. Trả về status NO_CONTENT nếu thành công",
+  tag = tags::MANAGER,
+  responses(
+    (status = NO_CONTENT, description = "Fee restored"),
+    (status = NOT_FOUND, description = "Fee not found"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn restore_fee(State(state): State<AppState>, Path(id): Path<i32>) -> StatusCode {
+  set_fee_archived(&state, id, false).await
+}
+
 // #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ToSchema)]
 // pub struct DetailedFeeInfo {}
 
@@ -195,6 +441,9 @@ pub struct DetailedFeeInfo {
   pub created_at: chrono::NaiveDateTime,
   pub due_date: chrono::NaiveDateTime,
   pub recurrence_type: Option<RecurrenceType>,
+  pub archived_at: Option<chrono::NaiveDateTime>,
+  pub category: FeeCategory,
+  pub tags: Vec<String>,
   pub fee_assignments: Vec<FeesRoomInfo>,
 }
 
@@ -256,17 +505,27 @@ pub async fn get_one_fee(
       assignment_id: fr.0.assignment_id,
       room_number: fr.0.room_number,
       fee_id: fr.0.fee_id,
-      fee_name: fee.name.clone(),
+      fee_name: fr.0.fee_name,
+      fee_category: fee.category.clone(),
       fee_amount: match fr.1 {
         Some(transaction) => Some(transaction.amount),
-        None => Some(fee.amount),
+        None => Some(fr.0.amount),
       },
       due_date: fr.0.due_date,
       payment_date: fr.0.payment_date,
       is_paid: fr.0.is_paid,
+      overdue: is_overdue(fr.0.due_date, fr.0.is_paid),
     })
     .collect::<Vec<_>>();
 
+  let tags = match categories::load_fee_tags(&state.db, &[fee.id]).await {
+    Ok(mut tags) => tags.remove(&fee.id).unwrap_or_default(),
+    Err(e) => {
+      log::error!("Error: {:?}", e);
+      return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+  };
+
   let fee = DetailedFeeInfo {
     id: fee.id,
     name: fee.name,
@@ -275,6 +534,9 @@ pub async fn get_one_fee(
     created_at: fee.created_at,
     due_date: fee.due_date,
     recurrence_type: fee.recurrence_type,
+    archived_at: fee.archived_at,
+    category: fee.category,
+    tags,
     fee_assignments: fee_rooms,
   };
 
@@ -285,11 +547,13 @@ pub async fn get_one_fee(
   put,
   path = "/fees/{id}",
   description = "Chỉnh sửa thông tin một khoản phí, yêu cầu request có role là Manager. Kiểm tra khoản thu có tồn tại không, 
-  trả về status NO_CONTENT nếu thành công",
+  trả về status NO_CONTENT nếu thành công. Các phòng đã được gán giữ nguyên số tiền tại thời điểm gán, mỗi lần chỉnh sửa được lưu vào lịch sử.
+  Không thể chỉnh sửa khoản phí đã lưu trữ, hay đổi loại phí khi khoản phí đã được gán cho phòng.",
   tag = tags::MANAGER,
   responses(
     (status = NO_CONTENT, description = "Fee updated"),
     (status = NOT_FOUND, description = "Fee not found"),
+    (status = CONFLICT, description = "Fee is archived, or its category cannot change because it is assigned"),
     (status = INTERNAL_SERVER_ERROR, description = "Server error"),
     (status = UNAUTHORIZED, description = "Unauthorized"),
     (status = FORBIDDEN, description = "Forbidden"),
@@ -300,9 +564,20 @@ pub async fn get_one_fee(
 )]
 pub async fn edit_fee_info(
   State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
   Path(id): Path<i32>,
   Json(fee_info): Json<AddFeeInfo>,
 ) -> StatusCode {
+  let claims = match state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+  {
+    Ok(claims) => claims,
+    Err(_) => {
+      return StatusCode::UNAUTHORIZED;
+    }
+  };
+
   let fee = match Fees::find_by_id(id).one(&state.db).await {
     Ok(fee) => fee,
     Err(e) => {
@@ -318,24 +593,65 @@ pub async fn edit_fee_info(
     }
   };
 
-  let fee = fees::ActiveModel {
+  if fee.archived_at.is_some() {
+    return StatusCode::CONFLICT;
+  }
+
+  // the charges of the assignments are posted under the category of the fee
+  if fee_info
+    .category
+    .as_ref()
+    .is_some_and(|category| *category != fee.category)
+  {
+    match FeesRoomAssignment::find()
+      .filter(fees_room_assignment::Column::FeeId.eq(id))
+      .count(&state.db)
+      .await
+    {
+      Ok(0) => {}
+      Ok(_) => return StatusCode::CONFLICT,
+      Err(e) => {
+        log::error!("Error: {:?}", e);
+        return StatusCode::INTERNAL_SERVER_ERROR;
+      }
+    }
+  }
+
+  let fee_model = fees::ActiveModel {
     amount: Set(fee_info.amount),
     name: Set(fee_info.name),
     due_date: Set(fee_info.due_date),
     is_required: Set(fee_info.is_required),
     is_recurring: Set(fee_info.recurrence_type.is_some()),
     recurrence_type: Set(fee_info.recurrence_type.clone()),
-    ..fee.into()
+    category: Set(fee_info.category.clone().unwrap_or(fee.category.clone())),
+    ..fee.clone().into()
   };
 
-  match Fees::update(fee).exec(&state.db).await {
+  let updated = match Fees::update(fee_model).exec(&state.db).await {
     Ok(res) => {
       log::info!("Fee updated: {:?}", res);
+      res
     }
     Err(e) => {
       log::error!("Error: {:?}", e);
       return StatusCode::INTERNAL_SERVER_ERROR;
     }
+  };
+
+  if let Some(tags) = &fee_info.tags {
+    if let Err(e) = categories::set_fee_tags(&state.db, id, tags).await {
+      log::error!("Error: {:?}", e);
+      return StatusCode::INTERNAL_SERVER_ERROR;
+    }
+  }
+
+  // assignments keep what they were billed, the edit only shows up in the history
+  if history::is_changed(&fee, &updated) {
+    if let Err(e) = history::record_fee_version(&state.db, &updated, Some(claims.custom.id)).await {
+      log::error!("Error: {:?}", e);
+      return StatusCode::INTERNAL_SERVER_ERROR;
+    }
   }
 
   let recurrence = match FeeRecurrence::find()
@@ -463,6 +779,7 @@ pub async fn get_rooms(
   responses(
     (status = OK, description = "Fee assigned"),
     (status = NOT_FOUND, description = "Fee not found or room not found"),
+    (status = CONFLICT, description = "Fee is archived"),
     (status = INTERNAL_SERVER_ERROR, description = "Server error"),
     (status = UNAUTHORIZED, description = "Unauthorized"),
     (status = FORBIDDEN, description = "Forbidden"),
@@ -510,6 +827,9 @@ pub async fn assign_fee(
   if fee.is_none() {
     return StatusCode::NOT_FOUND;
   }
+  if fee.as_ref().unwrap().archived_at.is_some() {
+    return StatusCode::CONFLICT;
+  }
 
   // check if all rooms exist
   let rooms = match Rooms::find()
@@ -529,66 +849,20 @@ pub async fn assign_fee(
   }
 
   // assign fee to rooms
+  let fee = fee.unwrap();
   for room_info in rooms {
-    // check if fee is already assigned to room
-    let fee_room = FeesRoomAssignment::find()
-      .filter(fees_room_assignment::Column::RoomNumber.eq(room_info.0.room_number))
-      .filter(fees_room_assignment::Column::FeeId.eq(fee_id))
-      .one(&state.db)
-      .await;
-
-    if let Ok(Some(_)) = fee_room {
-      continue;
-    }
-
-    let fee_room = fees_room_assignment::ActiveModel {
-      fee_id: Set(fee_id),
-      room_number: Set(room_info.0.room_number),
-      due_date: Set(fee.as_ref().unwrap().due_date),
-      ..Default::default()
-    };
-
-    match FeesRoomAssignment::insert(fee_room)
-      .on_conflict(
-        OnConflict::columns([
-          fees_room_assignment::Column::RoomNumber,
-          fees_room_assignment::Column::FeeId,
-        ])
-        .do_nothing()
-        // .update_columns([fees_room_assignment::Column::FeeId])
-        .to_owned(),
-      )
-      .exec(&state.db)
-      .await
+    if let Err(e) = assignments::create_assignment(
+      &state,
+      &fee,
+      room_info.0.room_number,
+      room_info.1.as_ref(),
+      manager_id,
+      None,
+    )
+    .await
     {
-      Ok(res) => {
-        log::info!("Fee assigned: {:?}", res);
-
-        // send notification
-        let user = room_info.1.unwrap();
-
-        let notification = notifications::ActiveModel {
-          title: Set(format!("Thông báo về phí {}", fee.as_ref().unwrap().name)),
-          message: Set(
-            format!("Phòng {} có khoản phí {} với số tiền cần thanh toán là {} VND. Vui lòng thanh toán trước ngày {}", room_info.0.room_number, fee.as_ref().unwrap().name, fee.as_ref().unwrap().amount, fee.as_ref().unwrap().due_date.format("%d/%m/%Y")),
-          ),
-          from_user: Set(manager_id),
-          to_user: Set(user.id),
-          ..Default::default()
-        };
-
-        match Notifications::insert(notification).exec(&state.db).await {
-          Ok(_) => {}
-          Err(e) => {
-            log::error!("Error: {:?}", e);
-            return StatusCode::INTERNAL_SERVER_ERROR;
-          }
-        }
-      }
-      Err(e) => {
-        log::error!("Error: {:?}", e);
-        return StatusCode::INTERNAL_SERVER_ERROR;
-      }
+      log::error!("Error: {:?}", e);
+      return StatusCode::INTERNAL_SERVER_ERROR;
     }
   }
 
@@ -631,19 +905,20 @@ pub async fn get_rooms_detailed(
       StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
+  // vacant rooms are listed in the room registry
   let response = rooms
     .into_iter()
-    .map(|room| {
+    .filter_map(|room| {
       let room_info = room.0;
-      let user_info = room.1.unwrap();
+      let user_info = room.1?;
 
-      DetailedRoomInfo {
+      Some(DetailedRoomInfo {
         room_number: room_info.room_number,
         tenant_id: user_info.id,
         tenant_name: user_info.name,
         tenant_email: user_info.email,
         tenant_phone: user_info.phone,
-      }
+      })
     })
     .collect::<Vec<_>>();
 
@@ -701,15 +976,17 @@ pub async fn send_notification(
     };
 
     for user in users {
-      let notification = notifications::ActiveModel {
-        title: Set(notification_info.title.clone()),
-        message: Set(notification_info.message.clone()),
-        from_user: Set(manager_id),
-        to_user: Set(user.id),
-        ..Default::default()
-      };
-
-      if let Err(e) = Notifications::insert(notification).exec(&state.db).await {
+      if let Err(e) = state
+        .notifier
+        .notify(
+          &state.db,
+          manager_id,
+          &user,
+          &notification_info.title,
+          &notification_info.message,
+        )
+        .await
+      {
         log::error!("Error: {:?}", e);
         return StatusCode::INTERNAL_SERVER_ERROR;
       }
@@ -740,16 +1017,20 @@ pub async fn send_notification(
 
     let user = user.unwrap();
 
-    let notification = notifications::ActiveModel {
-      title: Set(notification_info.title.clone()),
-      message: Set(notification_info.message.clone()),
-      from_user: Set(manager_id),
-      to_user: Set(user.id),
-      ..Default::default()
-    };
-
-    match Notifications::insert(notification).exec(&state.db).await {
-      Ok(_) => return StatusCode::OK,
+    match state
+      .notifier
+      .notify(
+        &state.db,
+        manager_id,
+        &user,
+        &notification_info.title,
+        &notification_info.message,
+      )
+      .await
+    {
+      Ok(_) => {
+        return StatusCode::OK;
+      }
       Err(e) => {
         log::error!("Error: {:?}", e);
         return StatusCode::INTERNAL_SERVER_ERROR;
@@ -0,0 +1,452 @@
+//! Households moving in and out of rooms, and the history of who lived where.
+//!
+//! Fees stay with the room they were billed to. Before a household moves out its unpaid fees
+//! must be paid, transferred to another room, or explicitly left on the room.
+
+use sea_orm::{Order, QueryOrder, TransactionTrait};
+
+use super::{
+  assignments::{move_assignment_to, notify_assigned, paid_assignments},
+  targets,
+};
+use crate::{
+  entities::{fees_room_assignment, room_members, room_occupancies, rooms},
+  household::members::{
+    add_resident, find_membership, load_members, remove_resident, RoomMemberInfo,
+  },
+  prelude::*,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct OccupancyInfo {
+  pub id: i32,
+  pub room_number: i32,
+  pub user_id: i32,
+  pub name: String,
+  pub role: RoomMemberRole,
+  pub moved_in_at: DateTime,
+  /// Empty while the user still lives in the room
+  pub moved_out_at: Option<DateTime>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MoveInRequest {
+  pub user_id: i32,
+  /// Defaults to householder
+  pub role: Option<RoomMemberRole>,
+  /// Defaults to now
+  pub moved_in_at: Option<DateTime>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct MoveOutRequest {
+  /// The member moving out. Defaults to the householder, who moves out with the whole household.
+  pub user_id: Option<i32>,
+  /// Defaults to now
+  pub moved_out_at: Option<DateTime>,
+  /// Bill the unpaid fees of the room to this room instead, e.g. the household's new room
+  pub transfer_to_room: Option<i32>,
+  /// Leave the unpaid fees on the room
+  #[serde(default)]
+  pub keep_outstanding: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct MoveOutReport {
+  /// The accounts that moved out
+  pub moved_out: Vec<i32>,
+  /// Unpaid fees of the room, which block the move-out unless transferred or kept
+  pub outstanding: Vec<fees_room_assignment::Model>,
+  /// Fees transferred to another room
+  pub transferred: Vec<i32>,
+  /// Fees that could not be transferred because the other room already has them
+  pub conflicts: Vec<i32>,
+}
+
+fn server_error(e: DbErr) -> StatusCode {
+  log::error!("Error: {:?}", e);
+  StatusCode::INTERNAL_SERVER_ERROR
+}
+
+/// Whether the household of a room can take a new member with this role: a householder takes a
+/// vacant room, the others join an existing household.
+pub(crate) fn room_accepts(room: &rooms::Model, role: &RoomMemberRole) -> bool {
+  room.status != RoomStatus::UnderRenovation
+    && (*role == RoomMemberRole::Householder) != room.tenant_id.is_some()
+}
+
+async fn load_occupancies(
+  db: &DatabaseConnection,
+  query: Select<RoomOccupancies>,
+) -> Result<Vec<OccupancyInfo>, DbErr> {
+  Ok(
+    query
+      .find_also_related(Users)
+      .order_by(room_occupancies::Column::MovedInAt, Order::Asc)
+      .order_by(room_occupancies::Column::Id, Order::Asc)
+      .all(db)
+      .await?
+      .into_iter()
+      .filter_map(|(occupancy, user)| {
+        Some(OccupancyInfo {
+          id: occupancy.id,
+          room_number: occupancy.room_number,
+          user_id: occupancy.user_id,
+          name: user?.name,
+          role: occupancy.role,
+          moved_in_at: occupancy.moved_in_at,
+          moved_out_at: occupancy.moved_out_at,
+        })
+      })
+      .collect(),
+  )
+}
+
+#[utoipa::path(
+  post,
+  path = "/rooms/{room_number}/move-in",
+  description = "Ghi nhận một tài khoản chuyển đến ở một phòng từ một ngày, yêu cầu request có role là Manager.
+  Mặc định tài khoản trở thành chủ hộ, khi đó phòng phải chưa có chủ hộ. Các khoản phí đang được đồng bộ theo quy tắc sẽ được gán cho phòng.
+  Trả về danh sách thành viên của phòng.",
+  tag = tags::MANAGER,
+  request_body = MoveInRequest,
+  responses(
+    (status = OK, description = "Moved in", body = Vec<RoomMemberInfo>),
+    (status = BAD_REQUEST, description = "Account is not a tenant, or the move-in date is in the future"),
+    (status = NOT_FOUND, description = "Room or account not found"),
+    (status = CONFLICT, description = "Room is under renovation or already has a householder, or the account lives in another room"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn move_in(
+  State(state): State<AppState>,
+  Path(room_number): Path<i32>,
+  Json(request): Json<MoveInRequest>,
+) -> Result<Json<Vec<RoomMemberInfo>>, StatusCode> {
+  let role = request.role.unwrap_or(RoomMemberRole::Householder);
+  let now = chrono::Utc::now().naive_utc();
+  let moved_in_at = request.moved_in_at.unwrap_or(now);
+  if moved_in_at > now {
+    return Err(StatusCode::BAD_REQUEST);
+  }
+
+  // the checks and the move-in are done together
+  let txn = state.db.begin().await.map_err(server_error)?;
+  let room = Rooms::find_by_id(room_number)
+    .one(&txn)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+  let user = Users::find_by_id(request.user_id)
+    .one(&txn)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+  if user.role != UserRole::Tenant {
+    return Err(StatusCode::BAD_REQUEST);
+  }
+  if !room_accepts(&room, &role) {
+    return Err(StatusCode::CONFLICT);
+  }
+  if find_membership(&txn, user.id)
+    .await
+    .map_err(server_error)?
+    .is_some()
+  {
+    return Err(StatusCode::CONFLICT);
+  }
+
+  add_resident(&txn, room, user.id, role.clone(), moved_in_at)
+    .await
+    .map_err(server_error)?;
+  txn.commit().await.map_err(server_error)?;
+  log::info!(
+    "Moved in: room {}, user {}, {:?}, at {}",
+    room_number,
+    user.id,
+    role,
+    moved_in_at
+  );
+
+  // the room may now match the targets of fee assignment rules
+  if role == RoomMemberRole::Householder {
+    if let Err(e) = targets::sync_rules(&state).await {
+      log::error!("Failed to sync fee assignment rules: {:?}", e);
+    }
+  }
+
+  let members = load_members(&state.db, room_number, true)
+    .await
+    .map_err(server_error)?;
+  Ok(Json(members))
+}
+
+#[utoipa::path(
+  post,
+  path = "/rooms/{room_number}/move-out",
+  description = "Ghi nhận một thành viên hoặc cả hộ chuyển đi khỏi phòng, yêu cầu request có role là Manager. Người chuyển đi mất quyền truy cập vào phòng.
+  Khi chủ hộ chuyển đi, cả hộ chuyển đi theo và phòng trở thành phòng trống. Nếu phòng còn khoản phí chưa thanh toán, yêu cầu bị từ chối với status CONFLICT,
+  trừ khi các khoản phí được chuyển sang phòng `transfer_to_room` hoặc được giữ lại cho phòng với `keep_outstanding`.",
+  tag = tags::MANAGER,
+  request_body = MoveOutRequest,
+  responses(
+    (status = OK, description = "Moved out", body = MoveOutReport),
+    (status = BAD_REQUEST, description = "Cannot transfer to the same room, or the move-out date is before the move-in"),
+    (status = NOT_FOUND, description = "Room, member or transfer room not found"),
+    (status = CONFLICT, description = "Unpaid fees left, or the transfer room already has some of them", body = MoveOutReport),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn move_out(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+  Path(room_number): Path<i32>,
+  Json(request): Json<MoveOutRequest>,
+) -> Result<(StatusCode, Json<MoveOutReport>), StatusCode> {
+  let claims = state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+  let manager_id = claims.custom.id;
+  let moved_out_at = request
+    .moved_out_at
+    .unwrap_or_else(|| chrono::Utc::now().naive_utc());
+
+  let room = Rooms::find_by_id(room_number)
+    .one(&state.db)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+  let members = RoomMembers::find()
+    .filter(room_members::Column::RoomNumber.eq(room_number))
+    .all(&state.db)
+    .await
+    .map_err(server_error)?;
+  let user_id = request
+    .user_id
+    .or(room.tenant_id)
+    .ok_or(StatusCode::NOT_FOUND)?;
+  let member = members
+    .iter()
+    .find(|member| member.user_id == user_id && member.joined_at.is_some())
+    .ok_or(StatusCode::NOT_FOUND)?;
+  // the householder moves out with the whole household, after all of it moved in
+  let moved_in_at = if member.role == RoomMemberRole::Householder {
+    members.iter().filter_map(|member| member.joined_at).max()
+  } else {
+    member.joined_at
+  };
+  if moved_in_at.is_some_and(|moved_in_at| moved_out_at < moved_in_at) {
+    return Err(StatusCode::BAD_REQUEST);
+  }
+  let mut report = MoveOutReport::default();
+
+  // a member leaving the household settles nothing
+  if member.role != RoomMemberRole::Householder {
+    let txn = state.db.begin().await.map_err(server_error)?;
+    remove_resident(&txn, member, moved_out_at)
+      .await
+      .map_err(server_error)?;
+    txn.commit().await.map_err(server_error)?;
+    report.moved_out.push(user_id);
+    return Ok((StatusCode::OK, Json(report)));
+  }
+
+  let assignments = FeesRoomAssignment::find()
+    .filter(fees_room_assignment::Column::RoomNumber.eq(room_number))
+    .filter(fees_room_assignment::Column::IsPaid.eq(false))
+    .order_by(fees_room_assignment::Column::DueDate, Order::Asc)
+    .all(&state.db)
+    .await
+    .map_err(server_error)?;
+  let paid = paid_assignments(&state.db, &assignments)
+    .await
+    .map_err(server_error)?;
+  let outstanding = assignments
+    .into_iter()
+    .filter(|assignment| !paid.contains(&assignment.assignment_id))
+    .collect::<Vec<_>>();
+
+  // the fees are transferred and the household leaves together, or nothing changes
+  let txn = state.db.begin().await.map_err(server_error)?;
+  let mut transferred = vec![];
+  let mut new_tenant = None;
+  if !outstanding.is_empty() {
+    match request.transfer_to_room {
+      Some(target) => {
+        if target == room_number {
+          return Err(StatusCode::BAD_REQUEST);
+        }
+        let (_, tenant) = Rooms::find_by_id(target)
+          .find_also_related(Users)
+          .one(&txn)
+          .await
+          .map_err(server_error)?
+          .ok_or(StatusCode::NOT_FOUND)?;
+
+        // the other room may already have some of the fees
+        let fee_ids = outstanding
+          .iter()
+          .map(|assignment| assignment.fee_id)
+          .collect::<Vec<_>>();
+        let target_fees = FeesRoomAssignment::find()
+          .filter(fees_room_assignment::Column::RoomNumber.eq(target))
+          .filter(fees_room_assignment::Column::FeeId.is_in(fee_ids))
+          .all(&txn)
+          .await
+          .map_err(server_error)?
+          .into_iter()
+          .map(|assignment| assignment.fee_id)
+          .collect::<Vec<_>>();
+        report.conflicts = outstanding
+          .iter()
+          .filter(|assignment| target_fees.contains(&assignment.fee_id))
+          .map(|assignment| assignment.assignment_id)
+          .collect();
+        if !report.conflicts.is_empty() {
+          report.outstanding = outstanding;
+          return Ok((StatusCode::CONFLICT, Json(report)));
+        }
+
+        for assignment in outstanding {
+          let moved = move_assignment_to(&txn, assignment, target, manager_id)
+            .await
+            .map_err(server_error)?;
+          report.transferred.push(moved.assignment_id);
+          transferred.push(moved);
+        }
+        new_tenant = tenant;
+      }
+      None if request.keep_outstanding => report.outstanding = outstanding,
+      None => {
+        report.outstanding = outstanding;
+        return Ok((StatusCode::CONFLICT, Json(report)));
+      }
+    }
+  }
+
+  // the whole household leaves, the householder last
+  let mut members = members;
+  members.sort_by_key(|member| member.role == RoomMemberRole::Householder);
+  for member in &members {
+    remove_resident(&txn, member, moved_out_at)
+      .await
+      .map_err(server_error)?;
+    if member.joined_at.is_some() {
+      report.moved_out.push(member.user_id);
+    }
+  }
+  rooms::ActiveModel {
+    tenant_id: Set(None),
+    status: Set(RoomStatus::Vacant),
+    ..room.into()
+  }
+  .update(&txn)
+  .await
+  .map_err(server_error)?;
+  txn.commit().await.map_err(server_error)?;
+  log::info!(
+    "Moved out: room {}, users {:?}, at {}",
+    room_number,
+    report.moved_out,
+    moved_out_at
+  );
+
+  if let Some(tenant) = new_tenant {
+    for assignment in &transferred {
+      if let Err(e) = notify_assigned(&state, assignment, &tenant, manager_id).await {
+        log::error!("Failed to notify the transferred fees: {:?}", e);
+      }
+    }
+  }
+
+  Ok((StatusCode::OK, Json(report)))
+}
+
+#[utoipa::path(
+  get,
+  path = "/rooms/{room_number}/occupancy",
+  description = "Lấy lịch sử những người đã và đang ở một phòng, với vai trò và thời gian chuyển đến, chuyển đi, yêu cầu request có role là Manager.",
+  tag = tags::MANAGER,
+  responses(
+    (status = OK, description = "Occupancy timeline", body = Vec<OccupancyInfo>),
+    (status = NOT_FOUND, description = "Room not found"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn get_room_occupancy(
+  State(state): State<AppState>,
+  Path(room_number): Path<i32>,
+) -> Result<Json<Vec<OccupancyInfo>>, StatusCode> {
+  Rooms::find_by_id(room_number)
+    .one(&state.db)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+  let timeline = load_occupancies(
+    &state.db,
+    RoomOccupancies::find().filter(room_occupancies::Column::RoomNumber.eq(room_number)),
+  )
+  .await
+  .map_err(server_error)?;
+  Ok(Json(timeline))
+}
+
+#[utoipa::path(
+  get,
+  path = "/assignments/{assignment_id}/occupants",
+  description = "Lấy danh sách những người ở phòng vào ngày đến hạn của một khoản phí đã gán, yêu cầu request có role là Manager.
+  Dùng để biết ai chịu trách nhiệm với một khoản phí cũ.",
+  tag = tags::MANAGER,
+  responses(
+    (status = OK, description = "Occupants at the due date", body = Vec<OccupancyInfo>),
+    (status = NOT_FOUND, description = "Assignment not found"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn get_assignment_occupants(
+  State(state): State<AppState>,
+  Path(assignment_id): Path<i32>,
+) -> Result<Json<Vec<OccupancyInfo>>, StatusCode> {
+  let assignment = FeesRoomAssignment::find_by_id(assignment_id)
+    .one(&state.db)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+  let occupants = load_occupancies(
+    &state.db,
+    RoomOccupancies::find()
+      .filter(room_occupancies::Column::RoomNumber.eq(assignment.room_number))
+      .filter(room_occupancies::Column::MovedInAt.lte(assignment.due_date))
+      .filter(
+        Condition::any()
+          .add(room_occupancies::Column::MovedOutAt.is_null())
+          .add(room_occupancies::Column::MovedOutAt.gt(assignment.due_date)),
+      ),
+  )
+  .await
+  .map_err(server_error)?;
+  Ok(Json(occupants))
+}
@@ -0,0 +1,644 @@
+//! The room registry: managers set up the rooms of a building, with their floor, area, type and
+//! occupancy status, before anyone registers.
+//!
+//! A room with a tenant is occupied. Tenants can only claim a room that is already in the registry,
+//! and it is occupied once a manager approves the claim.
+
+use std::collections::HashMap;
+
+use axum::extract::Multipart;
+use axum_extra::extract::Query;
+use sea_orm::{Order, PaginatorTrait, QueryOrder, TransactionTrait};
+
+use super::{
+  import::{read_csv, read_xlsx},
+  targets,
+};
+use crate::{
+  entities::{documents, fees_room_assignment, room_occupancies, rooms, users},
+  prelude::*,
+};
+
+/// Rooms are numbered by floor: room 512 is on floor 5.
+pub(crate) const ROOMS_PER_FLOOR: i32 = 100;
+
+/// The floor of a room, from its number.
+pub(crate) fn floor_of(room_number: i32) -> i32 {
+  room_number / ROOMS_PER_FLOOR
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RoomInfo {
+  pub room_number: i32,
+  pub building: Option<String>,
+  pub floor: Option<i32>,
+  /// Area in square meters
+  pub area: Option<f64>,
+  pub room_type: Option<String>,
+  pub status: RoomStatus,
+  pub notes: Option<String>,
+  pub tenant_id: Option<i32>,
+  pub tenant_name: Option<String>,
+}
+
+impl RoomInfo {
+  fn new(room: rooms::Model, tenant: Option<users::Model>) -> Self {
+    Self {
+      room_number: room.room_number,
+      building: room.building,
+      floor: room.floor,
+      area: room.area,
+      room_type: room.room_type,
+      status: room.status,
+      notes: room.notes,
+      tenant_id: room.tenant_id,
+      tenant_name: tenant.map(|tenant| tenant.name),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct RoomDetails {
+  pub building: Option<String>,
+  /// Defaults to the floor in the room number
+  pub floor: Option<i32>,
+  pub area: Option<f64>,
+  pub room_type: Option<String>,
+  /// Defaults to vacant for a new room
+  pub status: Option<RoomStatus>,
+  pub notes: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AddRoomInfo {
+  pub room_number: i32,
+  #[serde(flatten)]
+  pub details: RoomDetails,
+}
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct RoomRegistryParams {
+  pub building: Option<String>,
+  pub floor: Option<i32>,
+  #[param(inline)]
+  pub status: Option<RoomStatus>,
+}
+
+fn server_error(e: DbErr) -> StatusCode {
+  log::error!("Error: {:?}", e);
+  StatusCode::INTERNAL_SERVER_ERROR
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+  value
+    .map(|value| value.trim().to_string())
+    .filter(|value| !value.is_empty())
+}
+
+/// Check the details and write them on the room. A room with a tenant cannot be vacant.
+fn apply_details(room: &mut rooms::ActiveModel, details: RoomDetails) -> Result<(), String> {
+  if details
+    .area
+    .is_some_and(|area| !area.is_finite() || area <= 0.0)
+  {
+    return Err("area must be positive".to_string());
+  }
+  if details.status == Some(RoomStatus::Vacant) && room.tenant_id.as_ref().is_some() {
+    return Err("a room with a tenant cannot be vacant".to_string());
+  }
+
+  if let Some(building) = non_empty(details.building) {
+    room.building = Set(Some(building));
+  }
+  if let Some(floor) = details.floor {
+    room.floor = Set(Some(floor));
+  }
+  if let Some(area) = details.area {
+    room.area = Set(Some(area));
+  }
+  if let Some(room_type) = non_empty(details.room_type) {
+    room.room_type = Set(Some(room_type));
+  }
+  if let Some(status) = details.status {
+    room.status = Set(status);
+  }
+  if let Some(notes) = details.notes {
+    room.notes = Set(non_empty(Some(notes)));
+  }
+  Ok(())
+}
+
+fn new_room(room_number: i32) -> rooms::ActiveModel {
+  rooms::ActiveModel {
+    room_number: Set(room_number),
+    tenant_id: Set(None),
+    building: Set(None),
+    floor: Set(Some(floor_of(room_number))),
+    area: Set(None),
+    room_type: Set(None),
+    status: Set(RoomStatus::Vacant),
+    notes: Set(None),
+  }
+}
+
+#[utoipa::path(
+  get,
+  path = "/rooms/registry",
+  description = "Lấy danh sách các phòng cùng với tòa nhà, tầng, diện tích, loại phòng, trạng thái và người thuê, yêu cầu request có role là Manager.
+  Có thể lọc theo tòa nhà, tầng và trạng thái.",
+  tag = tags::MANAGER,
+  params(RoomRegistryParams),
+  responses(
+    (status = OK, description = "Rooms", body = Vec<RoomInfo>),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn get_room_registry(
+  State(state): State<AppState>,
+  Query(params): Query<RoomRegistryParams>,
+) -> Result<Json<Vec<RoomInfo>>, StatusCode> {
+  let mut query = Rooms::find()
+    .find_also_related(Users)
+    .order_by(rooms::Column::Building, Order::Asc)
+    .order_by(rooms::Column::RoomNumber, Order::Asc);
+  if let Some(building) = params.building {
+    query = query.filter(rooms::Column::Building.eq(building));
+  }
+  if let Some(floor) = params.floor {
+    query = query.filter(rooms::Column::Floor.eq(floor));
+  }
+  if let Some(status) = params.status {
+    query = query.filter(rooms::Column::Status.eq(status));
+  }
+
+  let rooms = query.all(&state.db).await.map_err(server_error)?;
+  Ok(Json(
+    rooms
+      .into_iter()
+      .map(|(room, tenant)| RoomInfo::new(room, tenant))
+      .collect(),
+  ))
+}
+
+#[utoipa::path(
+  get,
+  path = "/rooms/{room_number}",
+  description = "Lấy thông tin của một phòng, yêu cầu request có role là Manager.",
+  tag = tags::MANAGER,
+  responses(
+    (status = OK, description = "Room", body = RoomInfo),
+    (status = NOT_FOUND, description = "Room not found"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn get_room(
+  State(state): State<AppState>,
+  Path(room_number): Path<i32>,
+) -> Result<Json<RoomInfo>, StatusCode> {
+  let (room, tenant) = Rooms::find_by_id(room_number)
+    .find_also_related(Users)
+    .one(&state.db)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+  Ok(Json(RoomInfo::new(room, tenant)))
+}
+
+#[utoipa::path(
+  post,
+  path = "/rooms",
+  description = "Thêm một phòng mới vào danh sách phòng, yêu cầu request có role là Manager. Phòng mới chưa có người thuê,
+  tầng mặc định được lấy từ số phòng (phòng 512 ở tầng 5).",
+  tag = tags::MANAGER,
+  request_body = AddRoomInfo,
+  responses(
+    (status = CREATED, description = "Room created", body = RoomInfo),
+    (status = BAD_REQUEST, description = "Invalid room details", body = String),
+    (status = CONFLICT, description = "Room already exists", body = String),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error", body = String),
+    (status = UNAUTHORIZED, description = "Unauthorized", body = String),
+    (status = FORBIDDEN, description = "Forbidden", body = String),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn add_room(
+  State(state): State<AppState>,
+  Json(room_info): Json<AddRoomInfo>,
+) -> Result<(StatusCode, Json<RoomInfo>), (StatusCode, String)> {
+  let server_error = |e: DbErr| {
+    log::error!("Error: {:?}", e);
+    (
+      StatusCode::INTERNAL_SERVER_ERROR,
+      "server error".to_string(),
+    )
+  };
+
+  if room_info.room_number <= 0 {
+    return Err((StatusCode::BAD_REQUEST, "invalid room number".to_string()));
+  }
+  let existing = Rooms::find_by_id(room_info.room_number)
+    .one(&state.db)
+    .await
+    .map_err(server_error)?;
+  if existing.is_some() {
+    return Err((StatusCode::CONFLICT, "room already exists".to_string()));
+  }
+
+  let mut room = new_room(room_info.room_number);
+  apply_details(&mut room, room_info.details).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+  let room = room.insert(&state.db).await.map_err(server_error)?;
+  log::info!("Room created: {:?}", room);
+
+  Ok((StatusCode::CREATED, Json(RoomInfo::new(room, None))))
+}
+
+#[utoipa::path(
+  put,
+  path = "/rooms/{room_number}",
+  description = "Chỉnh sửa thông tin của một phòng, yêu cầu request có role là Manager. Các trường không được gửi lên được giữ nguyên.
+  Phòng đang có người thuê không thể chuyển sang trạng thái trống.",
+  tag = tags::MANAGER,
+  request_body = RoomDetails,
+  responses(
+    (status = OK, description = "Room updated", body = RoomInfo),
+    (status = BAD_REQUEST, description = "Invalid room details", body = String),
+    (status = NOT_FOUND, description = "Room not found", body = String),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error", body = String),
+    (status = UNAUTHORIZED, description = "Unauthorized", body = String),
+    (status = FORBIDDEN, description = "Forbidden", body = String),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn edit_room(
+  State(state): State<AppState>,
+  Path(room_number): Path<i32>,
+  Json(details): Json<RoomDetails>,
+) -> Result<Json<RoomInfo>, (StatusCode, String)> {
+  let server_error = |e: DbErr| {
+    log::error!("Error: {:?}", e);
+    (
+      StatusCode::INTERNAL_SERVER_ERROR,
+      "server error".to_string(),
+    )
+  };
+
+  let (room, tenant) = Rooms::find_by_id(room_number)
+    .find_also_related(Users)
+    .one(&state.db)
+    .await
+    .map_err(server_error)?
+    .ok_or((StatusCode::NOT_FOUND, "room not found".to_string()))?;
+
+  let mut room: rooms::ActiveModel = room.into();
+  apply_details(&mut room, details).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+  let room = room.update(&state.db).await.map_err(server_error)?;
+  log::info!("Room updated: {:?}", room);
+
+  // the room may now match the targets of fee assignment rules, e.g. another building
+  if let Err(e) = targets::sync_rules(&state).await {
+    log::error!("Failed to sync fee assignment rules: {:?}", e);
+  }
+
+  Ok(Json(RoomInfo::new(room, tenant)))
+}
+
+#[utoipa::path(
+  delete,
+  path = "/rooms/{room_number}",
+  description = "Xóa một phòng khỏi danh sách phòng, yêu cầu request có role là Manager.
+  Không thể xóa phòng đang có người thuê, đã từng có người ở, hoặc đã được gán phí hay xuất hóa đơn.",
+  tag = tags::MANAGER,
+  responses(
+    (status = NO_CONTENT, description = "Room removed"),
+    (status = NOT_FOUND, description = "Room not found"),
+    (status = CONFLICT, description = "Room has a tenant, occupancy history, fees or documents"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn remove_room(
+  State(state): State<AppState>,
+  Path(room_number): Path<i32>,
+) -> Result<StatusCode, StatusCode> {
+  let room = Rooms::find_by_id(room_number)
+    .one(&state.db)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+  if room.tenant_id.is_some() {
+    return Err(StatusCode::CONFLICT);
+  }
+
+  let assignments = FeesRoomAssignment::find()
+    .filter(fees_room_assignment::Column::RoomNumber.eq(room_number))
+    .count(&state.db)
+    .await
+    .map_err(server_error)?;
+  let documents = Documents::find()
+    .filter(documents::Column::RoomNumber.eq(room_number))
+    .count(&state.db)
+    .await
+    .map_err(server_error)?;
+  let occupancies = RoomOccupancies::find()
+    .filter(room_occupancies::Column::RoomNumber.eq(room_number))
+    .count(&state.db)
+    .await
+    .map_err(server_error)?;
+  if assignments > 0 || documents > 0 || occupancies > 0 {
+    return Err(StatusCode::CONFLICT);
+  }
+
+  Rooms::delete_by_id(room_number)
+    .exec(&state.db)
+    .await
+    .map_err(server_error)?;
+  log::info!("Room removed: {}", room_number);
+
+  Ok(StatusCode::NO_CONTENT)
+}
+
+/// The multipart form accepted by the room import endpoint.
+#[derive(ToSchema)]
+#[allow(unused)]
+pub struct ImportRoomsForm {
+  /// The rooms, as a `.csv` or `.xlsx` file with the columns `room_number`, `building`, `floor`,
+  /// `area`, `room_type`, `status` and `notes`. Only `room_number` is required.
+  #[schema(value_type = String, format = Binary)]
+  file: Vec<u8>,
+  /// Building of the rows that leave it empty
+  building: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RoomImportOutcome {
+  Created,
+  Updated,
+  Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RoomImportRowResult {
+  /// Line number in the file, starting at 1
+  pub row: usize,
+  pub room_number: Option<i32>,
+  pub outcome: RoomImportOutcome,
+  pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct RoomImportReport {
+  pub total: usize,
+  pub created: usize,
+  pub updated: usize,
+  pub failed: usize,
+  pub rows: Vec<RoomImportRowResult>,
+}
+
+/// Read a row of the room file. Empty cells are left out.
+fn row_to_room(header: &HashMap<String, usize>, row: &[String]) -> anyhow::Result<AddRoomInfo> {
+  let cell = |column: &str| {
+    header
+      .get(column)
+      .and_then(|index| row.get(*index))
+      .map(|cell| cell.trim())
+      .filter(|cell| !cell.is_empty())
+  };
+
+  let room_number = cell("room_number").ok_or_else(|| anyhow::anyhow!("missing room number"))?;
+  let room_number = room_number
+    .parse::<i32>()
+    .ok()
+    .filter(|room_number| *room_number > 0)
+    .ok_or_else(|| anyhow::anyhow!("invalid room number: {}", room_number))?;
+
+  let floor = cell("floor")
+    .map(|floor| {
+      floor
+        .parse::<i32>()
+        .map_err(|_| anyhow::anyhow!("invalid floor: {}", floor))
+    })
+    .transpose()?;
+  let area = cell("area")
+    .map(|area| {
+      area
+        .replace(',', ".")
+        .parse::<f64>()
+        .map_err(|_| anyhow::anyhow!("invalid area: {}", area))
+    })
+    .transpose()?;
+  let status = cell("status")
+    .map(|status| {
+      serde_json::from_value::<RoomStatus>(serde_json::Value::String(status.to_lowercase()))
+        .map_err(|_| anyhow::anyhow!("invalid status: {}", status))
+    })
+    .transpose()?;
+
+  Ok(AddRoomInfo {
+    room_number,
+    details: RoomDetails {
+      building: cell("building").map(str::to_string),
+      floor,
+      area,
+      room_type: cell("room_type").map(str::to_string),
+      status,
+      notes: cell("notes").map(str::to_string),
+    },
+  })
+}
+
+#[utoipa::path(
+  post,
+  path = "/rooms/import",
+  description = "Nhập danh sách phòng từ file CSV hoặc XLSX, yêu cầu request có role là Manager. Phòng chưa có sẽ được tạo mới,
+  phòng đã có được cập nhật theo các ô không trống. Các dòng được ghi cùng lúc, nếu có lỗi máy chủ thì không dòng nào được ghi. Trả về kết quả xử lý của từng dòng.",
+  tag = tags::MANAGER,
+  request_body(content = ImportRoomsForm, content_type = "multipart/form-data"),
+  responses(
+    (status = OK, description = "Rooms imported", body = RoomImportReport),
+    (status = BAD_REQUEST, description = "Invalid file", body = String),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error", body = String),
+    (status = UNAUTHORIZED, description = "Unauthorized", body = String),
+    (status = FORBIDDEN, description = "Forbidden", body = String),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn import_rooms(
+  State(state): State<AppState>,
+  mut multipart: Multipart,
+) -> Result<Json<RoomImportReport>, (StatusCode, String)> {
+  let bad_request = |msg: &str| (StatusCode::BAD_REQUEST, msg.to_string());
+  let server_error = |e: DbErr| {
+    log::error!("Error: {:?}", e);
+    (
+      StatusCode::INTERNAL_SERVER_ERROR,
+      "server error".to_string(),
+    )
+  };
+
+  let mut file = None;
+  let mut default_building = None;
+  while let Some(field) = multipart
+    .next_field()
+    .await
+    .map_err(|_| bad_request("invalid form"))?
+  {
+    match field.name() {
+      Some("file") => {
+        let file_name = field.file_name().unwrap_or_default().to_lowercase();
+        let bytes = field
+          .bytes()
+          .await
+          .map_err(|_| bad_request("invalid file"))?;
+        file = Some((file_name, bytes));
+      }
+      Some("building") => {
+        let text = field
+          .text()
+          .await
+          .map_err(|_| bad_request("invalid building"))?;
+        default_building = non_empty(Some(text));
+      }
+      _ => {}
+    }
+  }
+
+  let Some((file_name, bytes)) = file else {
+    return Err(bad_request("missing file"));
+  };
+  let rows = if file_name.ends_with(".xlsx") || file_name.ends_with(".xls") {
+    read_xlsx(&bytes)
+  } else {
+    read_csv(&bytes)
+  }
+  .map_err(|e| bad_request(&e.to_string()))?;
+
+  let Some((header, rows)) = rows.split_first() else {
+    return Err(bad_request("empty file"));
+  };
+  let header = header
+    .iter()
+    .enumerate()
+    .map(|(i, name)| (name.trim().to_lowercase(), i))
+    .collect::<HashMap<_, _>>();
+  if !header.contains_key("room_number") {
+    return Err(bad_request("column not found: room_number"));
+  }
+
+  // the rows are written together, a server error leaves no row half imported
+  let txn = state.db.begin().await.map_err(server_error)?;
+  let mut report = RoomImportReport::default();
+  for (i, row) in rows.iter().enumerate() {
+    if row.iter().all(|cell| cell.trim().is_empty()) {
+      continue;
+    }
+    report.total += 1;
+
+    let mut result = RoomImportRowResult {
+      // + 1 for the header, + 1 to count from 1
+      row: i + 2,
+      room_number: None,
+      outcome: RoomImportOutcome::Failed,
+      error: None,
+    };
+
+    let mut room_info = match row_to_room(&header, row) {
+      Ok(room_info) => room_info,
+      Err(e) => {
+        result.error = Some(e.to_string());
+        report.failed += 1;
+        report.rows.push(result);
+        continue;
+      }
+    };
+    result.room_number = Some(room_info.room_number);
+    if room_info.details.building.is_none() {
+      room_info.details.building = default_building.clone();
+    }
+
+    let existing = Rooms::find_by_id(room_info.room_number)
+      .one(&txn)
+      .await
+      .map_err(server_error)?;
+    let (mut room, outcome) = match existing {
+      Some(room) => (room.into(), RoomImportOutcome::Updated),
+      None => (new_room(room_info.room_number), RoomImportOutcome::Created),
+    };
+    if let Err(e) = apply_details(&mut room, room_info.details) {
+      result.error = Some(e);
+      report.failed += 1;
+      report.rows.push(result);
+      continue;
+    }
+
+    match outcome {
+      RoomImportOutcome::Created => {
+        room.insert(&txn).await.map_err(server_error)?;
+        report.created += 1;
+      }
+      _ => {
+        room.update(&txn).await.map_err(server_error)?;
+        report.updated += 1;
+      }
+    }
+    result.outcome = outcome;
+    report.rows.push(result);
+  }
+  txn.commit().await.map_err(server_error)?;
+
+  log::info!(
+    "Rooms imported: {} rows, {} created, {} updated, {} failed",
+    report.total,
+    report.created,
+    report.updated,
+    report.failed
+  );
+
+  if report.created + report.updated > 0 {
+    if let Err(e) = targets::sync_rules(&state).await {
+      log::error!("Failed to sync fee assignment rules: {:?}", e);
+    }
+  }
+
+  Ok(Json(report))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn floor_is_the_hundreds_of_the_room_number() {
+    assert_eq!(floor_of(512), 5);
+    assert_eq!(floor_of(500), 5);
+    assert_eq!(floor_of(599), 5);
+    assert_eq!(floor_of(1203), 12);
+  }
+
+  #[test]
+  fn ground_floor_rooms() {
+    assert_eq!(floor_of(7), 0);
+    assert_eq!(floor_of(99), 0);
+  }
+}
@@ -0,0 +1,904 @@
+//! Financial reports for the managers, as JSON or exported to CSV / XLSX.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use axum::response::Response;
+use axum_extra::extract::Query;
+use rust_xlsxwriter::{Format, Workbook};
+
+use super::categories::category_name;
+use crate::{
+  entities::{fees, fees_room_assignment, transactions},
+  prelude::*,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportFormat {
+  #[default]
+  Json,
+  Csv,
+  Xlsx,
+}
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct ReportParams {
+  /// Start of the period, inclusive
+  pub from: Option<chrono::NaiveDate>,
+  /// End of the period, inclusive
+  pub to: Option<chrono::NaiveDate>,
+  #[serde(default)]
+  #[param(inline)]
+  pub format: ReportFormat,
+}
+
+impl ReportParams {
+  fn range(&self) -> (Option<chrono::NaiveDateTime>, Option<chrono::NaiveDateTime>) {
+    (
+      self.from.and_then(|from| from.and_hms_opt(0, 0, 0)),
+      self
+        .to
+        .and_then(|to| to.succ_opt())
+        .and_then(|to| to.and_hms_opt(0, 0, 0)),
+    )
+  }
+}
+
+/// A cell of an exported report.
+pub(crate) enum Cell {
+  Text(String),
+  Number(i64),
+  Percent(f64),
+}
+
+/// A report row that can be exported to CSV and XLSX.
+pub(crate) trait ReportRow: Serialize {
+  fn headers() -> &'static [&'static str];
+  fn cells(&self) -> Vec<Cell>;
+}
+
+/// Vietnamese name of a gender, as written in the exported registers.
+pub(crate) fn gender_name(gender: &Gender) -> &'static str {
+  match gender {
+    Gender::Male => "Nam",
+    Gender::Female => "Nữ",
+    Gender::Other => "Khác",
+  }
+}
+
+/// Respond with the rows in the requested format.
+pub(crate) fn export<T: ReportRow>(
+  name: &str,
+  rows: &[T],
+  format: ReportFormat,
+) -> Result<Response, StatusCode> {
+  let (content_type, extension, content) = match format {
+    ReportFormat::Json => return Ok(Json(rows).into_response()),
+    ReportFormat::Csv => ("text/csv; charset=utf-8", "csv", to_csv(rows)),
+    ReportFormat::Xlsx => (
+      "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+      "xlsx",
+      to_xlsx(name, rows),
+    ),
+  };
+  let content = content.map_err(|e| {
+    log::error!("Failed to export report: {:?}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+  })?;
+
+  Ok(
+    (
+      StatusCode::OK,
+      [
+        (header::CONTENT_TYPE, content_type.to_string()),
+        (
+          header::CONTENT_DISPOSITION,
+          format!("attachment; filename=\"{}.{}\"", name, extension),
+        ),
+      ],
+      content,
+    )
+      .into_response(),
+  )
+}
+
+fn to_csv<T: ReportRow>(rows: &[T]) -> anyhow::Result<Vec<u8>> {
+  // BOM so that Excel reads the Vietnamese headers as UTF-8
+  let mut writer = csv::Writer::from_writer(b"\xef\xbb\xbf".to_vec());
+  writer.write_record(T::headers())?;
+  for row in rows {
+    writer.write_record(row.cells().into_iter().map(|cell| match cell {
+      Cell::Text(text) => text,
+      Cell::Number(number) => number.to_string(),
+      Cell::Percent(rate) => format!("{:.2}", rate * 100.0),
+    }))?;
+  }
+  Ok(writer.into_inner()?)
+}
+
+fn to_xlsx<T: ReportRow>(name: &str, rows: &[T]) -> anyhow::Result<Vec<u8>> {
+  let mut workbook = Workbook::new();
+  let sheet = workbook.add_worksheet();
+  sheet.set_name(name)?;
+
+  let bold = Format::new().set_bold();
+  let money = Format::new().set_num_format("#,##0");
+  let percent = Format::new().set_num_format("0.00%");
+
+  for (col, header) in T::headers().iter().enumerate() {
+    sheet.write_string_with_format(0, col as u16, *header, &bold)?;
+  }
+  for (i, row) in rows.iter().enumerate() {
+    let row_index = i as u32 + 1;
+    for (col, cell) in row.cells().into_iter().enumerate() {
+      let col = col as u16;
+      match cell {
+        Cell::Text(text) => sheet.write_string(row_index, col, text)?,
+        Cell::Number(number) => {
+          sheet.write_number_with_format(row_index, col, number as f64, &money)?
+        }
+        Cell::Percent(rate) => sheet.write_number_with_format(row_index, col, rate, &percent)?,
+      };
+    }
+  }
+  sheet.autofit();
+
+  Ok(workbook.save_to_buffer()?)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FeeReportRow {
+  pub fee_id: i32,
+  pub fee_name: String,
+  pub category: FeeCategory,
+  pub assigned_rooms: u64,
+  pub paid_rooms: u64,
+  pub expected: i64,
+  pub collected: i64,
+  pub outstanding: i64,
+  /// Collected over expected, between 0 and 1
+  pub collection_rate: f64,
+}
+
+impl ReportRow for FeeReportRow {
+  fn headers() -> &'static [&'static str] {
+    &[
+      "Mã phí",
+      "Khoản phí",
+      "Danh mục",
+      "Số phòng được gán",
+      "Số phòng đã nộp",
+      "Phải thu",
+      "Đã thu",
+      "Còn nợ",
+      "Tỷ lệ thu",
+    ]
+  }
+
+  fn cells(&self) -> Vec<Cell> {
+    vec![
+      Cell::Number(self.fee_id as i64),
+      Cell::Text(self.fee_name.clone()),
+      Cell::Text(category_name(&self.category).to_string()),
+      Cell::Number(self.assigned_rooms as i64),
+      Cell::Number(self.paid_rooms as i64),
+      Cell::Number(self.expected),
+      Cell::Number(self.collected),
+      Cell::Number(self.outstanding),
+      Cell::Percent(self.collection_rate),
+    ]
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CategoryReportRow {
+  pub category: FeeCategory,
+  pub fees: u64,
+  pub assignments: u64,
+  pub expected: i64,
+  pub collected: i64,
+  pub outstanding: i64,
+  pub collection_rate: f64,
+}
+
+impl ReportRow for CategoryReportRow {
+  fn headers() -> &'static [&'static str] {
+    &[
+      "Danh mục",
+      "Số khoản phí",
+      "Số lượt gán",
+      "Phải thu",
+      "Đã thu",
+      "Còn nợ",
+      "Tỷ lệ thu",
+    ]
+  }
+
+  fn cells(&self) -> Vec<Cell> {
+    vec![
+      Cell::Text(category_name(&self.category).to_string()),
+      Cell::Number(self.fees as i64),
+      Cell::Number(self.assignments as i64),
+      Cell::Number(self.expected),
+      Cell::Number(self.collected),
+      Cell::Number(self.outstanding),
+      Cell::Percent(self.collection_rate),
+    ]
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RoomReportRow {
+  pub room_number: i32,
+  pub assigned_fees: u64,
+  pub paid_fees: u64,
+  pub expected: i64,
+  pub collected: i64,
+  pub outstanding: i64,
+}
+
+impl ReportRow for RoomReportRow {
+  fn headers() -> &'static [&'static str] {
+    &[
+      "Phòng",
+      "Số khoản phí",
+      "Số khoản đã nộp",
+      "Phải thu",
+      "Đã thu",
+      "Còn nợ",
+    ]
+  }
+
+  fn cells(&self) -> Vec<Cell> {
+    vec![
+      Cell::Number(self.room_number as i64),
+      Cell::Number(self.assigned_fees as i64),
+      Cell::Number(self.paid_fees as i64),
+      Cell::Number(self.expected),
+      Cell::Number(self.collected),
+      Cell::Number(self.outstanding),
+    ]
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MonthReportRow {
+  /// Month of the due date, `YYYY-MM`
+  pub month: String,
+  pub expected: i64,
+  pub collected: i64,
+  pub outstanding: i64,
+  pub collection_rate: f64,
+}
+
+impl ReportRow for MonthReportRow {
+  fn headers() -> &'static [&'static str] {
+    &["Tháng", "Phải thu", "Đã thu", "Còn nợ", "Tỷ lệ thu"]
+  }
+
+  fn cells(&self) -> Vec<Cell> {
+    vec![
+      Cell::Text(self.month.clone()),
+      Cell::Number(self.expected),
+      Cell::Number(self.collected),
+      Cell::Number(self.outstanding),
+      Cell::Percent(self.collection_rate),
+    ]
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CashFlowRow {
+  /// Month of the payment, `YYYY-MM`
+  pub month: String,
+  pub payments: u64,
+  pub amount: i64,
+  /// Total collected since the start of the period
+  pub cumulative: i64,
+}
+
+impl ReportRow for CashFlowRow {
+  fn headers() -> &'static [&'static str] {
+    &["Tháng", "Số giao dịch", "Số tiền thu", "Lũy kế"]
+  }
+
+  fn cells(&self) -> Vec<Cell> {
+    vec![
+      Cell::Text(self.month.clone()),
+      Cell::Number(self.payments as i64),
+      Cell::Number(self.amount),
+      Cell::Number(self.cumulative),
+    ]
+  }
+}
+
+/// A fee assignment with what was billed and what was paid for it.
+pub(crate) struct AssignmentBalance {
+  pub assignment: fees_room_assignment::Model,
+  pub fee: fees::Model,
+  pub expected: i64,
+  pub collected: i64,
+}
+
+impl AssignmentBalance {
+  pub fn outstanding(&self) -> i64 {
+    if self.assignment.is_paid {
+      0
+    } else {
+      (self.expected - self.collected).max(0)
+    }
+  }
+}
+
+/// Load the fee assignments due within the period of the report, with their payments.
+pub(crate) async fn load_balances(
+  db: &DatabaseConnection,
+  params: &ReportParams,
+) -> Result<Vec<AssignmentBalance>, DbErr> {
+  let (from, to) = params.range();
+  let mut query = FeesRoomAssignment::find().find_also_related(Fees);
+  if let Some(from) = from {
+    query = query.filter(fees_room_assignment::Column::DueDate.gte(from));
+  }
+  if let Some(to) = to {
+    query = query.filter(fees_room_assignment::Column::DueDate.lt(to));
+  }
+  let assignments = query.all(db).await?;
+
+  let mut paid = HashMap::new();
+  let assignment_ids = assignments
+    .iter()
+    .map(|(assignment, _)| assignment.assignment_id)
+    .collect::<Vec<_>>();
+  for chunk in assignment_ids.chunks(1000) {
+    let transactions = Transactions::find()
+      .filter(transactions::Column::AssignmentId.is_in(chunk.to_vec()))
+      .all(db)
+      .await?;
+    for transaction in transactions {
+      *paid.entry(transaction.assignment_id).or_insert(0) += transaction.amount;
+    }
+  }
+
+  Ok(
+    assignments
+      .into_iter()
+      .filter_map(|(assignment, fee)| {
+        let fee = fee?;
+        Some(AssignmentBalance {
+          expected: assignment.amount,
+          collected: paid.get(&assignment.assignment_id).copied().unwrap_or(0),
+          assignment,
+          fee,
+        })
+      })
+      .collect(),
+  )
+}
+
+fn collection_rate(collected: i64, expected: i64) -> f64 {
+  if expected == 0 {
+    return 0.0;
+  }
+  collected as f64 / expected as f64
+}
+
+#[utoipa::path(
+  get,
+  path = "/reports/fees",
+  description = "Báo cáo số tiền đã thu và còn nợ theo từng khoản phí, kèm tỷ lệ thu, yêu cầu request có role là Manager.
+  Lọc theo hạn nộp trong khoảng `from` - `to`, có thể xuất ra CSV hoặc XLSX.",
+  tag = tags::MANAGER,
+  params(ReportParams),
+  responses(
+    (status = OK, description = "Report generated", body = Vec<FeeReportRow>),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn report_by_fee(
+  State(state): State<AppState>,
+  Query(params): Query<ReportParams>,
+) -> Result<Response, StatusCode> {
+  let balances = load_balances(&state.db, &params).await.map_err(|e| {
+    log::error!("Error: {:?}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+  })?;
+
+  let mut rows = BTreeMap::<i32, FeeReportRow>::new();
+  for balance in &balances {
+    let row = rows.entry(balance.fee.id).or_insert_with(|| FeeReportRow {
+      fee_id: balance.fee.id,
+      fee_name: balance.fee.name.clone(),
+      category: balance.fee.category.clone(),
+      assigned_rooms: 0,
+      paid_rooms: 0,
+      expected: 0,
+      collected: 0,
+      outstanding: 0,
+      collection_rate: 0.0,
+    });
+    row.assigned_rooms += 1;
+    row.paid_rooms += balance.assignment.is_paid as u64;
+    row.expected += balance.expected;
+    row.collected += balance.collected;
+    row.outstanding += balance.outstanding();
+  }
+  let rows = rows
+    .into_values()
+    .map(|row| FeeReportRow {
+      collection_rate: collection_rate(row.collected, row.expected),
+      ..row
+    })
+    .collect::<Vec<_>>();
+
+  export("bao-cao-khoan-phi", &rows, params.format)
+}
+
+#[utoipa::path(
+  get,
+  path = "/reports/categories",
+  description = "Báo cáo số tiền đã thu và còn nợ theo từng danh mục phí (dịch vụ, điện nước, gửi xe, đóng góp, tiền phạt), yêu cầu request có role là Manager.
+  Lọc theo hạn nộp trong khoảng `from` - `to`, có thể xuất ra CSV hoặc XLSX.",
+  tag = tags::MANAGER,
+  params(ReportParams),
+  responses(
+    (status = OK, description = "Report generated", body = Vec<CategoryReportRow>),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn report_by_category(
+  State(state): State<AppState>,
+  Query(params): Query<ReportParams>,
+) -> Result<Response, StatusCode> {
+  let balances = load_balances(&state.db, &params).await.map_err(|e| {
+    log::error!("Error: {:?}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+  })?;
+
+  let mut rows = HashMap::<FeeCategory, (CategoryReportRow, HashSet<i32>)>::new();
+  for balance in &balances {
+    let (row, fee_ids) = rows.entry(balance.fee.category.clone()).or_insert_with(|| {
+      (
+        CategoryReportRow {
+          category: balance.fee.category.clone(),
+          fees: 0,
+          assignments: 0,
+          expected: 0,
+          collected: 0,
+          outstanding: 0,
+          collection_rate: 0.0,
+        },
+        HashSet::new(),
+      )
+    });
+    fee_ids.insert(balance.fee.id);
+    row.assignments += 1;
+    row.expected += balance.expected;
+    row.collected += balance.collected;
+    row.outstanding += balance.outstanding();
+  }
+  let mut rows = rows
+    .into_values()
+    .map(|(row, fee_ids)| CategoryReportRow {
+      fees: fee_ids.len() as u64,
+      collection_rate: collection_rate(row.collected, row.expected),
+      ..row
+    })
+    .collect::<Vec<_>>();
+  rows.sort_by_key(|row| category_name(&row.category));
+
+  export("bao-cao-danh-muc", &rows, params.format)
+}
+
+#[utoipa::path(
+  get,
+  path = "/reports/rooms",
+  description = "Báo cáo số tiền đã thu và còn nợ theo từng phòng, yêu cầu request có role là Manager.
+  Lọc theo hạn nộp trong khoảng `from` - `to`, có thể xuất ra CSV hoặc XLSX.",
+  tag = tags::MANAGER,
+  params(ReportParams),
+  responses(
+    (status = OK, description = "Report generated", body = Vec<RoomReportRow>),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn report_by_room(
+  State(state): State<AppState>,
+  Query(params): Query<ReportParams>,
+) -> Result<Response, StatusCode> {
+  let balances = load_balances(&state.db, &params).await.map_err(|e| {
+    log::error!("Error: {:?}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+  })?;
+
+  let mut rows = BTreeMap::<i32, RoomReportRow>::new();
+  for balance in &balances {
+    let room_number = balance.assignment.room_number;
+    let row = rows.entry(room_number).or_insert_with(|| RoomReportRow {
+      room_number,
+      assigned_fees: 0,
+      paid_fees: 0,
+      expected: 0,
+      collected: 0,
+      outstanding: 0,
+    });
+    row.assigned_fees += 1;
+    row.paid_fees += balance.assignment.is_paid as u64;
+    row.expected += balance.expected;
+    row.collected += balance.collected;
+    row.outstanding += balance.outstanding();
+  }
+  let rows = rows.into_values().collect::<Vec<_>>();
+
+  export("bao-cao-phong", &rows, params.format)
+}
+
+#[utoipa::path(
+  get,
+  path = "/reports/monthly",
+  description = "Báo cáo số tiền phải thu, đã thu và còn nợ theo tháng của hạn nộp, yêu cầu request có role là Manager.
+  Lọc theo hạn nộp trong khoảng `from` - `to`, có thể xuất ra CSV hoặc XLSX.",
+  tag = tags::MANAGER,
+  params(ReportParams),
+  responses(
+    (status = OK, description = "Report generated", body = Vec<MonthReportRow>),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn report_by_month(
+  State(state): State<AppState>,
+  Query(params): Query<ReportParams>,
+) -> Result<Response, StatusCode> {
+  let balances = load_balances(&state.db, &params).await.map_err(|e| {
+    log::error!("Error: {:?}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+  })?;
+
+  let mut rows = BTreeMap::<String, MonthReportRow>::new();
+  for balance in &balances {
+    let month = balance.assignment.due_date.format("%Y-%m").to_string();
+    let row = rows.entry(month.clone()).or_insert_with(|| MonthReportRow {
+      month,
+      expected: 0,
+      collected: 0,
+      outstanding: 0,
+      collection_rate: 0.0,
+    });
+    row.expected += balance.expected;
+    row.collected += balance.collected;
+    row.outstanding += balance.outstanding();
+  }
+  let rows = rows
+    .into_values()
+    .map(|row| MonthReportRow {
+      collection_rate: collection_rate(row.collected, row.expected),
+      ..row
+    })
+    .collect::<Vec<_>>();
+
+  export("bao-cao-theo-thang", &rows, params.format)
+}
+
+#[utoipa::path(
+  get,
+  path = "/reports/cash-flow",
+  description = "Báo cáo dòng tiền thu theo tháng từ các giao dịch đã thanh toán, yêu cầu request có role là Manager.
+  Lọc theo ngày thanh toán trong khoảng `from` - `to`, có thể xuất ra CSV hoặc XLSX.",
+  tag = tags::MANAGER,
+  params(ReportParams),
+  responses(
+    (status = OK, description = "Report generated", body = Vec<CashFlowRow>),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn report_cash_flow(
+  State(state): State<AppState>,
+  Query(params): Query<ReportParams>,
+) -> Result<Response, StatusCode> {
+  let (from, to) = params.range();
+  let mut query = Transactions::find();
+  if let Some(from) = from {
+    query = query.filter(transactions::Column::CreatedAt.gte(from));
+  }
+  if let Some(to) = to {
+    query = query.filter(transactions::Column::CreatedAt.lt(to));
+  }
+  let transactions = query.all(&state.db).await.map_err(|e| {
+    log::error!("Error: {:?}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+  })?;
+
+  let mut months = BTreeMap::<String, (u64, i64)>::new();
+  for transaction in transactions {
+    let month = months
+      .entry(transaction.created_at.format("%Y-%m").to_string())
+      .or_default();
+    month.0 += 1;
+    month.1 += transaction.amount;
+  }
+
+  let mut cumulative = 0;
+  let rows = months
+    .into_iter()
+    .map(|(month, (payments, amount))| {
+      cumulative += amount;
+      CashFlowRow {
+        month,
+        payments,
+        amount,
+        cumulative,
+      }
+    })
+    .collect::<Vec<_>>();
+
+  export("bao-cao-dong-tien", &rows, params.format)
+}
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct AgingParams {
+  /// Date the debt is aged at, defaults to today
+  pub as_of: Option<chrono::NaiveDate>,
+  #[serde(default)]
+  #[param(inline)]
+  pub format: ReportFormat,
+}
+
+/// Unpaid amounts split by how many days they are past the due date.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct AgingBuckets {
+  pub days_0_30: i64,
+  pub days_31_60: i64,
+  pub days_61_90: i64,
+  pub days_over_90: i64,
+  pub total: i64,
+  /// Number of overdue fee assignments
+  pub overdue_fees: u64,
+  /// Days past due of the oldest unpaid fee
+  pub max_days_overdue: i64,
+}
+
+impl AgingBuckets {
+  fn add(&mut self, days_overdue: i64, amount: i64) {
+    match days_overdue {
+      0..=30 => self.days_0_30 += amount,
+      31..=60 => self.days_31_60 += amount,
+      61..=90 => self.days_61_90 += amount,
+      _ => self.days_over_90 += amount,
+    }
+    self.total += amount;
+    self.overdue_fees += 1;
+    self.max_days_overdue = self.max_days_overdue.max(days_overdue);
+  }
+
+  fn cells(&self) -> Vec<Cell> {
+    vec![
+      Cell::Number(self.days_0_30),
+      Cell::Number(self.days_31_60),
+      Cell::Number(self.days_61_90),
+      Cell::Number(self.days_over_90),
+      Cell::Number(self.total),
+      Cell::Number(self.overdue_fees as i64),
+      Cell::Number(self.max_days_overdue),
+    ]
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RoomAgingRow {
+  pub room_number: i32,
+  pub tenant_name: Option<String>,
+  #[serde(flatten)]
+  pub buckets: AgingBuckets,
+}
+
+impl ReportRow for RoomAgingRow {
+  fn headers() -> &'static [&'static str] {
+    &[
+      "Phòng",
+      "Chủ hộ",
+      "0-30 ngày",
+      "31-60 ngày",
+      "61-90 ngày",
+      "Trên 90 ngày",
+      "Tổng nợ quá hạn",
+      "Số khoản quá hạn",
+      "Số ngày quá hạn lâu nhất",
+    ]
+  }
+
+  fn cells(&self) -> Vec<Cell> {
+    let mut cells = vec![
+      Cell::Number(self.room_number as i64),
+      Cell::Text(self.tenant_name.clone().unwrap_or_default()),
+    ];
+    cells.extend(self.buckets.cells());
+    cells
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FeeAgingRow {
+  pub fee_id: i32,
+  pub fee_name: String,
+  #[serde(flatten)]
+  pub buckets: AgingBuckets,
+}
+
+impl ReportRow for FeeAgingRow {
+  fn headers() -> &'static [&'static str] {
+    &[
+      "Mã phí",
+      "Khoản phí",
+      "0-30 ngày",
+      "31-60 ngày",
+      "61-90 ngày",
+      "Trên 90 ngày",
+      "Tổng nợ quá hạn",
+      "Số khoản quá hạn",
+      "Số ngày quá hạn lâu nhất",
+    ]
+  }
+
+  fn cells(&self) -> Vec<Cell> {
+    let mut cells = vec![
+      Cell::Number(self.fee_id as i64),
+      Cell::Text(self.fee_name.clone()),
+    ];
+    cells.extend(self.buckets.cells());
+    cells
+  }
+}
+
+/// Load the unpaid fee assignments past their due date, with the number of days overdue.
+async fn load_overdue(
+  db: &DatabaseConnection,
+  as_of: Option<chrono::NaiveDate>,
+) -> Result<Vec<(i64, AssignmentBalance)>, DbErr> {
+  let as_of = as_of
+    .and_then(|as_of| as_of.and_hms_opt(23, 59, 59))
+    .unwrap_or_else(|| chrono::Utc::now().naive_utc());
+
+  let params = ReportParams {
+    from: None,
+    to: Some(as_of.date()),
+    format: ReportFormat::Json,
+  };
+  let balances = load_balances(db, &params).await?;
+
+  Ok(
+    balances
+      .into_iter()
+      .filter(|balance| {
+        !balance.assignment.is_paid
+          && balance.assignment.due_date < as_of
+          && balance.outstanding() > 0
+      })
+      .map(|balance| ((as_of - balance.assignment.due_date).num_days(), balance))
+      .collect(),
+  )
+}
+
+#[utoipa::path(
+  get,
+  path = "/reports/aging/rooms",
+  description = "Báo cáo tuổi nợ theo từng phòng: số tiền chưa thanh toán được chia theo số ngày quá hạn (0-30, 31-60, 61-90, trên 90 ngày),
+  yêu cầu request có role là Manager. Các phòng nợ lâu nhất được xếp trước, có thể xuất ra CSV hoặc XLSX.",
+  tag = tags::MANAGER,
+  params(AgingParams),
+  responses(
+    (status = OK, description = "Report generated", body = Vec<RoomAgingRow>),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn report_aging_by_room(
+  State(state): State<AppState>,
+  Query(params): Query<AgingParams>,
+) -> Result<Response, StatusCode> {
+  let overdue = load_overdue(&state.db, params.as_of).await.map_err(|e| {
+    log::error!("Error: {:?}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+  })?;
+
+  let tenants = Rooms::find()
+    .find_also_related(Users)
+    .all(&state.db)
+    .await
+    .map_err(|e| {
+      log::error!("Error: {:?}", e);
+      StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .into_iter()
+    .map(|(room, tenant)| (room.room_number, tenant.map(|tenant| tenant.name)))
+    .collect::<HashMap<_, _>>();
+
+  let mut rows = BTreeMap::<i32, RoomAgingRow>::new();
+  for (days_overdue, balance) in overdue {
+    let room_number = balance.assignment.room_number;
+    rows
+      .entry(room_number)
+      .or_insert_with(|| RoomAgingRow {
+        room_number,
+        tenant_name: tenants.get(&room_number).cloned().flatten(),
+        buckets: AgingBuckets::default(),
+      })
+      .buckets
+      .add(days_overdue, balance.outstanding());
+  }
+  let mut rows = rows.into_values().collect::<Vec<_>>();
+  rows.sort_by_key(|row| std::cmp::Reverse((row.buckets.max_days_overdue, row.buckets.total)));
+
+  export("bao-cao-tuoi-no-phong", &rows, params.format)
+}
+
+#[utoipa::path(
+  get,
+  path = "/reports/aging/fees",
+  description = "Báo cáo tuổi nợ theo từng khoản phí: số tiền chưa thanh toán được chia theo số ngày quá hạn (0-30, 31-60, 61-90, trên 90 ngày),
+  yêu cầu request có role là Manager. Có thể xuất ra CSV hoặc XLSX.",
+  tag = tags::MANAGER,
+  params(AgingParams),
+  responses(
+    (status = OK, description = "Report generated", body = Vec<FeeAgingRow>),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn report_aging_by_fee(
+  State(state): State<AppState>,
+  Query(params): Query<AgingParams>,
+) -> Result<Response, StatusCode> {
+  let overdue = load_overdue(&state.db, params.as_of).await.map_err(|e| {
+    log::error!("Error: {:?}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+  })?;
+
+  let mut rows = BTreeMap::<i32, FeeAgingRow>::new();
+  for (days_overdue, balance) in overdue {
+    rows
+      .entry(balance.fee.id)
+      .or_insert_with(|| FeeAgingRow {
+        fee_id: balance.fee.id,
+        fee_name: balance.fee.name.clone(),
+        buckets: AgingBuckets::default(),
+      })
+      .buckets
+      .add(days_overdue, balance.outstanding());
+  }
+  let rows = rows.into_values().collect::<Vec<_>>();
+
+  export("bao-cao-tuoi-no-khoan-phi", &rows, params.format)
+}
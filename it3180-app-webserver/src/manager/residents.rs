@@ -0,0 +1,185 @@
+//! Managing the residents of a room on behalf of its householder.
+
+use sea_orm::TransactionTrait;
+
+use crate::{
+  entities::room_members,
+  household::members::{
+    add_resident, find_membership, load_members, remove_resident, RoomMemberInfo,
+  },
+  prelude::*,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AddMemberInfo {
+  pub user_id: i32,
+  /// Making someone the householder also makes them the tenant of the room
+  pub role: RoomMemberRole,
+}
+
+fn server_error(e: DbErr) -> StatusCode {
+  log::error!("Error: {:?}", e);
+  StatusCode::INTERNAL_SERVER_ERROR
+}
+
+#[utoipa::path(
+  get,
+  path = "/rooms/{room_number}/members",
+  description = "Lấy danh sách các thành viên của một phòng, bao gồm cả các lời mời chưa được chấp nhận, yêu cầu request có role là Manager.",
+  tag = tags::MANAGER,
+  responses(
+    (status = OK, description = "Room members", body = Vec<RoomMemberInfo>),
+    (status = NOT_FOUND, description = "Room not found"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn get_members(
+  State(state): State<AppState>,
+  Path(room_number): Path<i32>,
+) -> Result<Json<Vec<RoomMemberInfo>>, StatusCode> {
+  Rooms::find_by_id(room_number)
+    .one(&state.db)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+  let members = load_members(&state.db, room_number, true)
+    .await
+    .map_err(server_error)?;
+  Ok(Json(members))
+}
+
+#[utoipa::path(
+  post,
+  path = "/rooms/{room_number}/members",
+  description = "Thêm một tài khoản vào phòng hoặc đổi vai trò của một thành viên, yêu cầu request có role là Manager.
+  Thành viên được thêm không cần chấp nhận lời mời. Nếu vai trò là chủ hộ, chủ hộ cũ trở thành người ở cùng.",
+  tag = tags::MANAGER,
+  request_body = AddMemberInfo,
+  responses(
+    (status = OK, description = "Room members", body = Vec<RoomMemberInfo>),
+    (status = BAD_REQUEST, description = "Account is not a tenant"),
+    (status = NOT_FOUND, description = "Room or account not found"),
+    (status = CONFLICT, description = "Account lives in another room, or is the householder"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn add_member(
+  State(state): State<AppState>,
+  Path(room_number): Path<i32>,
+  Json(member_info): Json<AddMemberInfo>,
+) -> Result<Json<Vec<RoomMemberInfo>>, StatusCode> {
+  let room = Rooms::find_by_id(room_number)
+    .one(&state.db)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+  let user = Users::find_by_id(member_info.user_id)
+    .one(&state.db)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+  if user.role != UserRole::Tenant {
+    return Err(StatusCode::BAD_REQUEST);
+  }
+
+  let txn = state.db.begin().await.map_err(server_error)?;
+  let membership = find_membership(&txn, user.id).await.map_err(server_error)?;
+  if membership
+    .as_ref()
+    .is_some_and(|membership| membership.room_number != room_number)
+  {
+    return Err(StatusCode::CONFLICT);
+  }
+
+  // the householder is replaced, not demoted
+  let existing = RoomMembers::find()
+    .filter(room_members::Column::RoomNumber.eq(room_number))
+    .filter(room_members::Column::UserId.eq(user.id))
+    .one(&txn)
+    .await
+    .map_err(server_error)?;
+  if existing.is_some_and(|member| {
+    member.role == RoomMemberRole::Householder && member_info.role != RoomMemberRole::Householder
+  }) {
+    return Err(StatusCode::CONFLICT);
+  }
+
+  add_resident(
+    &txn,
+    room,
+    user.id,
+    member_info.role.clone(),
+    chrono::Utc::now().naive_utc(),
+  )
+  .await
+  .map_err(server_error)?;
+  txn.commit().await.map_err(server_error)?;
+  log::info!(
+    "Room member added: room {}, user {}, {:?}",
+    room_number,
+    user.id,
+    member_info.role
+  );
+
+  let members = load_members(&state.db, room_number, true)
+    .await
+    .map_err(server_error)?;
+  Ok(Json(members))
+}
+
+#[utoipa::path(
+  delete,
+  path = "/rooms/{room_number}/members/{user_id}",
+  description = "Xóa một thành viên khỏi phòng hoặc hủy lời mời, yêu cầu request có role là Manager.
+  Không thể xóa chủ hộ, cần chuyển vai trò chủ hộ cho thành viên khác trước.",
+  tag = tags::MANAGER,
+  params(
+    ("room_number" = i32, Path, description = "Room number"),
+    ("user_id" = i32, Path, description = "Member account id"),
+  ),
+  responses(
+    (status = NO_CONTENT, description = "Member removed"),
+    (status = NOT_FOUND, description = "Member not found"),
+    (status = CONFLICT, description = "Member is the householder"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn remove_member(
+  State(state): State<AppState>,
+  Path((room_number, user_id)): Path<(i32, i32)>,
+) -> Result<StatusCode, StatusCode> {
+  let member = RoomMembers::find()
+    .filter(room_members::Column::RoomNumber.eq(room_number))
+    .filter(room_members::Column::UserId.eq(user_id))
+    .one(&state.db)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+  if member.role == RoomMemberRole::Householder {
+    return Err(StatusCode::CONFLICT);
+  }
+
+  let txn = state.db.begin().await.map_err(server_error)?;
+  remove_resident(&txn, &member, chrono::Utc::now().naive_utc())
+    .await
+    .map_err(server_error)?;
+  txn.commit().await.map_err(server_error)?;
+
+  Ok(StatusCode::NO_CONTENT)
+}
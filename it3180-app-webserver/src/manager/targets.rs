@@ -0,0 +1,499 @@
+//! Assigning fees to rooms chosen by a rule instead of a list of room numbers.
+//!
+//! A target is resolved to rooms when the fee is assigned. If the assignment is kept in sync, the
+//! target is stored as a rule and resolved again whenever rooms change, and the fee is assigned
+//! to the rooms that newly match. Rooms that stop matching keep what they were billed; remove the
+//! rule before unassigning rooms it still targets.
+//!
+//! Vacant rooms are not billed by rules: except for a list of rooms, a target only matches rooms
+//! with a tenant.
+//!
+//! A vehicle target bills a room once per approved vehicle of the type: the amount of the fee is
+//! the price per vehicle, kept on the assignment when the room is assigned. While unpaid, the
+//! assignments of a vehicle rule follow the vehicles of the room: they are billed again at that
+//! price when one is approved or removed, and a room left without vehicles of the type is
+//! unassigned.
+
+use std::collections::{BTreeSet, HashMap};
+
+use sea_orm::{Order, QueryOrder, TransactionTrait};
+
+use super::assignments::{create_assignment, paid_assignments, remove_assignment};
+use crate::{
+  entities::{
+    fee_assignment_rules, fees, fees_room_assignment, room_group_members, rooms, users, vehicles,
+  },
+  ledger,
+  notify::default_sender,
+  prelude::*,
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AssignmentTarget {
+  /// The listed rooms
+  Rooms { room_numbers: Vec<i32> },
+  /// Every room with a tenant, vacant rooms are left out
+  All,
+  /// Rooms whose tenant account is active
+  Occupied,
+  /// Rooms on a floor, of every building unless one is given
+  Floor {
+    floor: i32,
+    building: Option<String>,
+  },
+  /// Rooms of a building
+  Building { building: String },
+  /// A saved room group
+  Group { group_id: i32 },
+  /// Rooms with approved vehicles of a type, billed per vehicle
+  Vehicles { vehicle_type: VehicleType },
+}
+
+/// The number of approved vehicles of a type, by room.
+pub(crate) async fn count_vehicles(
+  db: &DatabaseConnection,
+  vehicle_type: &VehicleType,
+) -> Result<HashMap<i32, i64>, DbErr> {
+  let mut counts = HashMap::new();
+  for vehicle in Vehicles::find()
+    .filter(vehicles::Column::VehicleType.eq(vehicle_type.clone()))
+    .filter(vehicles::Column::Status.eq(VehicleStatus::Approved))
+    .all(db)
+    .await?
+  {
+    *counts.entry(vehicle.room_number).or_insert(0) += 1;
+  }
+  Ok(counts)
+}
+
+/// The number of units each room of a target is billed for, if the target bills per unit.
+async fn billed_units(
+  db: &DatabaseConnection,
+  target: &AssignmentTarget,
+) -> Result<HashMap<i32, i64>, DbErr> {
+  match target {
+    AssignmentTarget::Vehicles { vehicle_type } => count_vehicles(db, vehicle_type).await,
+    _ => Ok(HashMap::new()),
+  }
+}
+
+/// Bill the unpaid assignments of a per-vehicle fee for the vehicles their room has now, at the
+/// price per vehicle they were assigned with, and unassign the rooms that have none left. Returns
+/// the rooms whose bill changed.
+async fn rebill_vehicles(
+  db: &DatabaseConnection,
+  fee: &fees::Model,
+  units: &HashMap<i32, i64>,
+  manager_id: i32,
+) -> Result<Vec<i32>, DbErr> {
+  let assignments = FeesRoomAssignment::find()
+    .filter(fees_room_assignment::Column::FeeId.eq(fee.id))
+    .filter(fees_room_assignment::Column::IsPaid.eq(false))
+    .all(db)
+    .await?;
+  let paid = paid_assignments(db, &assignments).await?;
+
+  let mut rebilled = vec![];
+  for assignment in assignments {
+    // assigned without a price per vehicle, e.g. by a list of rooms
+    let Some(unit_price) = assignment.unit_price else {
+      continue;
+    };
+    let amount = unit_price
+      * units
+        .get(&assignment.room_number)
+        .copied()
+        .unwrap_or_default();
+    if paid.contains(&assignment.assignment_id) || amount == assignment.amount {
+      continue;
+    }
+    let room_number = assignment.room_number;
+    if amount == 0 {
+      remove_assignment(db, &assignment, manager_id).await?;
+    } else {
+      let old_amount = assignment.amount;
+      let txn = db.begin().await?;
+      let assignment = fees_room_assignment::ActiveModel {
+        amount: Set(amount),
+        ..assignment.into()
+      }
+      .update(&txn)
+      .await?;
+      ledger::post_amount_change(
+        &txn,
+        &assignment,
+        &fee.category,
+        old_amount,
+        Some(manager_id),
+      )
+      .await?;
+      txn.commit().await?;
+      log::info!(
+        "Fee assignment billed again: {} -> {}, {:?}",
+        old_amount,
+        amount,
+        assignment
+      );
+    }
+    rebilled.push(room_number);
+  }
+
+  Ok(rebilled)
+}
+
+/// Find the rooms of a target, with their tenant.
+pub(crate) async fn resolve_target(
+  db: &DatabaseConnection,
+  target: &AssignmentTarget,
+) -> Result<Vec<(rooms::Model, Option<users::Model>)>, DbErr> {
+  let query = Rooms::find()
+    .find_also_related(Users)
+    .order_by(rooms::Column::RoomNumber, Order::Asc);
+  let query = match target {
+    AssignmentTarget::Rooms { room_numbers } => {
+      query.filter(rooms::Column::RoomNumber.is_in(room_numbers.clone()))
+    }
+    AssignmentTarget::All => query.filter(rooms::Column::TenantId.is_not_null()),
+    AssignmentTarget::Occupied => query.filter(users::Column::Status.eq(UserStatus::Active)),
+    AssignmentTarget::Floor { floor, building } => {
+      let query = query
+        .filter(rooms::Column::TenantId.is_not_null())
+        .filter(rooms::Column::Floor.eq(*floor));
+      match building {
+        Some(building) => query.filter(rooms::Column::Building.eq(building.clone())),
+        None => query,
+      }
+    }
+    AssignmentTarget::Building { building } => query
+      .filter(rooms::Column::TenantId.is_not_null())
+      .filter(rooms::Column::Building.eq(building.clone())),
+    AssignmentTarget::Group { group_id } => {
+      let room_numbers = RoomGroupMembers::find()
+        .filter(room_group_members::Column::GroupId.eq(*group_id))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|member| member.room_number)
+        .collect::<Vec<_>>();
+      query
+        .filter(rooms::Column::TenantId.is_not_null())
+        .filter(rooms::Column::RoomNumber.is_in(room_numbers))
+    }
+    AssignmentTarget::Vehicles { vehicle_type } => {
+      let room_numbers = count_vehicles(db, vehicle_type)
+        .await?
+        .into_keys()
+        .collect::<Vec<_>>();
+      query
+        .filter(rooms::Column::TenantId.is_not_null())
+        .filter(rooms::Column::RoomNumber.is_in(room_numbers))
+    }
+  };
+
+  query.all(db).await
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TargetAssignRequest {
+  pub target: AssignmentTarget,
+  /// Also assign the fee to the rooms that match the target later
+  #[serde(default)]
+  pub keep_in_sync: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct TargetAssignReport {
+  pub assigned: Vec<i32>,
+  pub already_assigned: Vec<i32>,
+  /// The stored rule, if the assignment is kept in sync
+  pub rule_id: Option<i32>,
+}
+
+#[utoipa::path(
+  post,
+  path = "/fees/{fee_id}/assign-target",
+  description = "Gán một khoản phí cho các phòng theo quy tắc, yêu cầu request có role là Manager: danh sách phòng, tất cả các phòng có người thuê,
+  các phòng đang có người ở, các phòng của một tầng hoặc một tòa nhà, một nhóm phòng đã lưu, hoặc các phòng có xe đã được duyệt của một loại (tính phí theo số xe). Trừ danh sách phòng, các phòng trống không được gán. Nếu `keep_in_sync` là true, quy tắc được lưu lại
+  và khoản phí sẽ được gán cho các phòng thỏa mãn quy tắc sau này. Trả về danh sách các phòng được gán.",
+  tag = tags::MANAGER,
+  request_body = TargetAssignRequest,
+  responses(
+    (status = OK, description = "Fee assigned", body = TargetAssignReport),
+    (status = NOT_FOUND, description = "Fee, room or group not found"),
+    (status = CONFLICT, description = "Fee is archived"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn assign_fee_to_target(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+  Path(fee_id): Path<i32>,
+  Json(request): Json<TargetAssignRequest>,
+) -> Result<Json<TargetAssignReport>, StatusCode> {
+  let claims = state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+  let manager_id = claims.custom.id;
+  let server_error = |e: DbErr| {
+    log::error!("Error: {:?}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+  };
+
+  let fee = Fees::find_by_id(fee_id)
+    .one(&state.db)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+  if fee.archived_at.is_some() {
+    return Err(StatusCode::CONFLICT);
+  }
+
+  let rooms = resolve_target(&state.db, &request.target)
+    .await
+    .map_err(server_error)?;
+  match &request.target {
+    AssignmentTarget::Rooms { room_numbers } => {
+      let room_numbers = room_numbers.iter().collect::<BTreeSet<_>>();
+      if rooms.len() != room_numbers.len() {
+        return Err(StatusCode::NOT_FOUND);
+      }
+    }
+    AssignmentTarget::Group { group_id } => {
+      RoomGroups::find_by_id(*group_id)
+        .one(&state.db)
+        .await
+        .map_err(server_error)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    }
+    _ => {}
+  }
+
+  let units = billed_units(&state.db, &request.target)
+    .await
+    .map_err(server_error)?;
+
+  let mut report = TargetAssignReport::default();
+  for (room, tenant) in &rooms {
+    match create_assignment(
+      &state,
+      &fee,
+      room.room_number,
+      tenant.as_ref(),
+      manager_id,
+      units.get(&room.room_number).copied(),
+    )
+    .await
+    .map_err(server_error)?
+    {
+      Some(_) => report.assigned.push(room.room_number),
+      None => report.already_assigned.push(room.room_number),
+    }
+  }
+
+  if request.keep_in_sync {
+    let target = serde_json::to_value(&request.target).map_err(|e| {
+      log::error!("Error: {:?}", e);
+      StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let rule = fee_assignment_rules::ActiveModel {
+      fee_id: Set(fee_id),
+      target: Set(target),
+      created_by: Set(Some(manager_id)),
+      created_at: Set(chrono::Utc::now().naive_utc()),
+      ..Default::default()
+    }
+    .insert(&state.db)
+    .await
+    .map_err(server_error)?;
+    log::info!("Fee assignment rule added: {:?}", rule);
+    report.rule_id = Some(rule.id);
+  }
+
+  Ok(Json(report))
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct RuleSyncReport {
+  pub rules: usize,
+  /// New assignments, as `(fee_id, room_number)`
+  pub assigned: Vec<(i32, i32)>,
+  /// Unpaid assignments billed again for the vehicles of their room, or unassigned when it has
+  /// none left, as `(fee_id, room_number)`
+  pub rebilled: Vec<(i32, i32)>,
+}
+
+/// Assign the fees of the stored rules to the rooms that newly match them, and bill the rooms of
+/// vehicle rules for the vehicles they have now.
+pub(crate) async fn sync_rules(state: &AppState) -> Result<RuleSyncReport, DbErr> {
+  let db = &state.db;
+  let mut report = RuleSyncReport::default();
+
+  let rules = FeeAssignmentRules::find()
+    .find_also_related(Fees)
+    .filter(fees::Column::ArchivedAt.is_null())
+    .all(db)
+    .await?;
+  if rules.is_empty() {
+    return Ok(report);
+  }
+  let fallback_sender = default_sender(db).await?;
+
+  for (rule, fee) in rules {
+    let Some(fee) = fee else {
+      continue;
+    };
+    let target = match serde_json::from_value::<AssignmentTarget>(rule.target.clone()) {
+      Ok(target) => target,
+      Err(e) => {
+        log::error!("Invalid target in fee assignment rule {}: {:?}", rule.id, e);
+        continue;
+      }
+    };
+    let Some(manager_id) = rule.created_by.or(fallback_sender) else {
+      log::warn!("No active manager to assign fees from");
+      continue;
+    };
+
+    report.rules += 1;
+    let units = billed_units(db, &target).await?;
+    for (room, tenant) in resolve_target(db, &target).await? {
+      if create_assignment(
+        state,
+        &fee,
+        room.room_number,
+        tenant.as_ref(),
+        manager_id,
+        units.get(&room.room_number).copied(),
+      )
+      .await?
+      .is_some()
+      {
+        report.assigned.push((fee.id, room.room_number));
+      }
+    }
+
+    if matches!(target, AssignmentTarget::Vehicles { .. }) {
+      for room_number in rebill_vehicles(db, &fee, &units, manager_id).await? {
+        report.rebilled.push((fee.id, room_number));
+      }
+    }
+  }
+
+  Ok(report)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FeeRuleInfo {
+  pub id: i32,
+  pub fee_id: i32,
+  pub target: AssignmentTarget,
+  pub created_by: Option<i32>,
+  pub created_at: DateTime,
+}
+
+#[utoipa::path(
+  get,
+  path = "/fees/{fee_id}/rules",
+  description = "Lấy danh sách các quy tắc gán phí đang được đồng bộ của một khoản phí, yêu cầu request có role là Manager.",
+  tag = tags::MANAGER,
+  responses(
+    (status = OK, description = "Fee assignment rules", body = Vec<FeeRuleInfo>),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn get_fee_rules(
+  State(state): State<AppState>,
+  Path(fee_id): Path<i32>,
+) -> Result<Json<Vec<FeeRuleInfo>>, StatusCode> {
+  let rules = FeeAssignmentRules::find()
+    .filter(fee_assignment_rules::Column::FeeId.eq(fee_id))
+    .order_by(fee_assignment_rules::Column::Id, Order::Asc)
+    .all(&state.db)
+    .await
+    .map_err(|e| {
+      log::error!("Error: {:?}", e);
+      StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+  Ok(Json(
+    rules
+      .into_iter()
+      .filter_map(|rule| {
+        Some(FeeRuleInfo {
+          target: serde_json::from_value(rule.target).ok()?,
+          id: rule.id,
+          fee_id: rule.fee_id,
+          created_by: rule.created_by,
+          created_at: rule.created_at,
+        })
+      })
+      .collect(),
+  ))
+}
+
+#[utoipa::path(
+  delete,
+  path = "/fee-rules/{id}",
+  description = "Ngừng đồng bộ một quy tắc gán phí, yêu cầu request có role là Manager. Các phòng đã được gán giữ nguyên khoản phí.",
+  tag = tags::MANAGER,
+  responses(
+    (status = NO_CONTENT, description = "Rule removed"),
+    (status = NOT_FOUND, description = "Rule not found"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn remove_fee_rule(State(state): State<AppState>, Path(id): Path<i32>) -> StatusCode {
+  match FeeAssignmentRules::delete_by_id(id).exec(&state.db).await {
+    Ok(res) if res.rows_affected == 0 => StatusCode::NOT_FOUND,
+    Ok(_) => {
+      log::info!("Fee assignment rule removed: {}", id);
+      StatusCode::NO_CONTENT
+    }
+    Err(e) => {
+      log::error!("Error: {:?}", e);
+      StatusCode::INTERNAL_SERVER_ERROR
+    }
+  }
+}
+
+#[utoipa::path(
+  post,
+  path = "/fee-rules/sync",
+  description = "Đồng bộ ngay các quy tắc gán phí, yêu cầu request có role là Manager. Khoản phí được gán cho các phòng mới thỏa mãn quy tắc.",
+  tag = tags::MANAGER,
+  responses(
+    (status = OK, description = "Rules synced", body = RuleSyncReport),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn run_rule_sync(
+  State(state): State<AppState>,
+) -> Result<Json<RuleSyncReport>, StatusCode> {
+  let report = sync_rules(&state).await.map_err(|e| {
+    log::error!("Error: {:?}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+  })?;
+  Ok(Json(report))
+}
@@ -0,0 +1,435 @@
+//! Vehicles parked in the building. Residents register the vehicles of their room, and a manager
+//! approves them and gives them a parking slot.
+//!
+//! Approved vehicles are billed by the parking fees assigned to a vehicle target, see
+//! [`super::targets`].
+
+use axum_extra::extract::Query;
+use sea_orm::{Order, QueryOrder};
+
+use super::targets;
+use crate::{entities::vehicles, notify::templates::Notice, prelude::*};
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct VehicleParams {
+  #[param(inline)]
+  pub status: Option<VehicleStatus>,
+  #[param(inline)]
+  pub vehicle_type: Option<VehicleType>,
+  pub room_number: Option<i32>,
+  /// Part of the plate number, separators are ignored
+  pub plate: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct ParkingSlotInfo {
+  pub parking_slot: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RejectVehicleInfo {
+  pub reason: String,
+}
+
+/// Plate numbers are stored in capitals without separators: "29a-123.45" is "29A12345".
+pub(crate) fn normalize_plate(plate: &str) -> String {
+  plate
+    .chars()
+    .filter(|c| c.is_ascii_alphanumeric())
+    .map(|c| c.to_ascii_uppercase())
+    .collect()
+}
+
+fn optional_slot(parking_slot: Option<String>) -> Option<String> {
+  parking_slot
+    .map(|slot| slot.trim().to_uppercase())
+    .filter(|slot| !slot.is_empty())
+}
+
+pub(crate) fn vehicle_type_name(vehicle_type: &VehicleType) -> &'static str {
+  match vehicle_type {
+    VehicleType::Bicycle => "Xe đạp",
+    VehicleType::ElectricBicycle => "Xe đạp điện",
+    VehicleType::Motorbike => "Xe máy",
+    VehicleType::Car => "Ô tô",
+  }
+}
+
+/// A notification about a vehicle, which is called e.g. "xe máy 29A12345" in it.
+fn vehicle_notice(event: NotificationEvent, vehicle: &vehicles::Model) -> Notice {
+  let name_in_english = match vehicle.vehicle_type {
+    VehicleType::Bicycle => "bicycle",
+    VehicleType::ElectricBicycle => "electric bicycle",
+    VehicleType::Motorbike => "motorbike",
+    VehicleType::Car => "car",
+  };
+  let names = [
+    vehicle_type_name(&vehicle.vehicle_type).to_lowercase(),
+    name_in_english.to_string(),
+  ];
+  let [vi, en] = names.map(|name| match &vehicle.plate_number {
+    Some(plate_number) => format!("{} {}", name, plate_number),
+    None => name,
+  });
+  Notice::new(event)
+    .label("vehicle", vi, en)
+    .text("room", vehicle.room_number)
+}
+
+fn server_error(e: DbErr) -> StatusCode {
+  log::error!("Error: {:?}", e);
+  StatusCode::INTERNAL_SERVER_ERROR
+}
+
+/// Check that a parking slot is not used by another approved vehicle.
+async fn check_parking_slot(
+  db: &DatabaseConnection,
+  parking_slot: &Option<String>,
+  vehicle_id: i32,
+) -> Result<(), StatusCode> {
+  let Some(parking_slot) = parking_slot else {
+    return Ok(());
+  };
+  let taken = Vehicles::find()
+    .filter(vehicles::Column::ParkingSlot.eq(parking_slot))
+    .filter(vehicles::Column::Status.eq(VehicleStatus::Approved))
+    .filter(vehicles::Column::Id.ne(vehicle_id))
+    .one(db)
+    .await
+    .map_err(server_error)?;
+  if taken.is_some() {
+    log::info!("Parking slot already taken: {}", parking_slot);
+    return Err(StatusCode::CONFLICT);
+  }
+  Ok(())
+}
+
+/// Let the resident who registered a vehicle know about it.
+async fn notify_registrant(
+  state: &AppState,
+  from_user: i32,
+  vehicle: &vehicles::Model,
+  notice: &Notice,
+) -> Result<(), DbErr> {
+  let Some(user_id) = vehicle.registered_by else {
+    return Ok(());
+  };
+  let Some(user) = Users::find_by_id(user_id).one(&state.db).await? else {
+    return Ok(());
+  };
+  state
+    .notifier
+    .notify_event(&state.db, from_user, &user, notice)
+    .await?;
+  Ok(())
+}
+
+async fn find_vehicle(
+  db: &DatabaseConnection,
+  vehicle_id: i32,
+) -> Result<vehicles::Model, StatusCode> {
+  Vehicles::find_by_id(vehicle_id)
+    .one(db)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)
+}
+
+#[utoipa::path(
+  get,
+  path = "/vehicles",
+  description = "Lấy danh sách các xe đăng ký gửi trong tòa nhà, yêu cầu request có role là Manager. Có thể lọc theo trạng thái `status`,
+  loại xe `vehicle_type`, số phòng `room_number`, và tìm theo biển số `plate` (không phân biệt dấu gạch, dấu chấm).",
+  tag = tags::MANAGER,
+  params(
+    VehicleParams
+  ),
+  responses(
+    (status = OK, description = "Vehicles", body = Vec<vehicles::Model>),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn get_vehicles(
+  State(state): State<AppState>,
+  Query(params): Query<VehicleParams>,
+) -> Result<Json<Vec<vehicles::Model>>, StatusCode> {
+  let mut query = Vehicles::find()
+    .order_by(vehicles::Column::RoomNumber, Order::Asc)
+    .order_by(vehicles::Column::Id, Order::Asc);
+  if let Some(status) = params.status {
+    query = query.filter(vehicles::Column::Status.eq(status));
+  }
+  if let Some(vehicle_type) = params.vehicle_type {
+    query = query.filter(vehicles::Column::VehicleType.eq(vehicle_type));
+  }
+  if let Some(room_number) = params.room_number {
+    query = query.filter(vehicles::Column::RoomNumber.eq(room_number));
+  }
+  if let Some(plate) = params.plate.as_deref().map(normalize_plate) {
+    if !plate.is_empty() {
+      query = query.filter(vehicles::Column::PlateNumber.contains(plate));
+    }
+  }
+
+  let vehicles = query.all(&state.db).await.map_err(server_error)?;
+  Ok(Json(vehicles))
+}
+
+#[utoipa::path(
+  post,
+  path = "/vehicles/{vehicle_id}/approve",
+  description = "Duyệt một xe đăng ký gửi, có thể kèm vị trí đỗ `parking_slot`, yêu cầu request có role là Manager.
+  Các khoản phí gửi xe được gán theo loại xe sẽ được gán cho phòng, và người đăng ký được thông báo.",
+  tag = tags::MANAGER,
+  request_body = ParkingSlotInfo,
+  responses(
+    (status = OK, description = "Vehicle approved", body = vehicles::Model),
+    (status = NOT_FOUND, description = "Vehicle not found"),
+    (status = CONFLICT, description = "Vehicle already reviewed, or parking slot taken"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn approve_vehicle(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+  Path(vehicle_id): Path<i32>,
+  Json(slot_info): Json<ParkingSlotInfo>,
+) -> Result<Json<vehicles::Model>, StatusCode> {
+  let claims = state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+  let parking_slot = optional_slot(slot_info.parking_slot);
+
+  let vehicle = find_vehicle(&state.db, vehicle_id).await?;
+  if vehicle.status != VehicleStatus::Pending {
+    return Err(StatusCode::CONFLICT);
+  }
+  check_parking_slot(&state.db, &parking_slot, vehicle_id).await?;
+
+  let vehicle = vehicles::ActiveModel {
+    status: Set(VehicleStatus::Approved),
+    parking_slot: Set(parking_slot),
+    reviewed_by: Set(Some(claims.custom.id)),
+    reviewed_at: Set(Some(chrono::Utc::now().naive_utc())),
+    ..vehicle.into()
+  }
+  .update(&state.db)
+  .await
+  .map_err(server_error)?;
+  log::info!(
+    "Vehicle {} of room {} approved by {}",
+    vehicle.id,
+    vehicle.room_number,
+    claims.custom.id
+  );
+
+  // the room may now be billed, or billed more, by the parking fees of its vehicle type
+  if let Err(e) = targets::sync_rules(&state).await {
+    log::error!("Failed to sync fee assignment rules: {:?}", e);
+  }
+
+  let notice = vehicle_notice(NotificationEvent::VehicleApproved, &vehicle);
+  let notice = match &vehicle.parking_slot {
+    Some(slot) => notice.text("parking_slot", slot),
+    None => notice.label("parking_slot", "chưa xếp", "not assigned yet"),
+  };
+  if let Err(e) = notify_registrant(&state, claims.custom.id, &vehicle, &notice).await {
+    log::error!("Error: {:?}", e);
+  }
+
+  Ok(Json(vehicle))
+}
+
+#[utoipa::path(
+  post,
+  path = "/vehicles/{vehicle_id}/reject",
+  description = "Từ chối một xe đăng ký gửi với lý do `reason`, yêu cầu request có role là Manager. Người đăng ký được thông báo lý do.",
+  tag = tags::MANAGER,
+  request_body = RejectVehicleInfo,
+  responses(
+    (status = OK, description = "Vehicle rejected", body = vehicles::Model),
+    (status = BAD_REQUEST, description = "Missing reason"),
+    (status = NOT_FOUND, description = "Vehicle not found"),
+    (status = CONFLICT, description = "Vehicle already reviewed"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn reject_vehicle(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+  Path(vehicle_id): Path<i32>,
+  Json(reject_info): Json<RejectVehicleInfo>,
+) -> Result<Json<vehicles::Model>, StatusCode> {
+  let claims = state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+  let reason = reject_info.reason.trim().to_string();
+  if reason.is_empty() {
+    return Err(StatusCode::BAD_REQUEST);
+  }
+
+  let vehicle = find_vehicle(&state.db, vehicle_id).await?;
+  if vehicle.status != VehicleStatus::Pending {
+    return Err(StatusCode::CONFLICT);
+  }
+  let vehicle = vehicles::ActiveModel {
+    status: Set(VehicleStatus::Rejected),
+    review_note: Set(Some(reason.clone())),
+    reviewed_by: Set(Some(claims.custom.id)),
+    reviewed_at: Set(Some(chrono::Utc::now().naive_utc())),
+    ..vehicle.into()
+  }
+  .update(&state.db)
+  .await
+  .map_err(server_error)?;
+  log::info!("Vehicle {} rejected by {}", vehicle.id, claims.custom.id);
+
+  let notice = vehicle_notice(NotificationEvent::VehicleRejected, &vehicle).text("reason", reason);
+  if let Err(e) = notify_registrant(&state, claims.custom.id, &vehicle, &notice).await {
+    log::error!("Error: {:?}", e);
+  }
+
+  Ok(Json(vehicle))
+}
+
+#[utoipa::path(
+  put,
+  path = "/vehicles/{vehicle_id}/parking-slot",
+  description = "Đổi vị trí đỗ của một xe đã được duyệt, yêu cầu request có role là Manager. Để trống `parking_slot` để bỏ vị trí đỗ.",
+  tag = tags::MANAGER,
+  request_body = ParkingSlotInfo,
+  responses(
+    (status = OK, description = "Parking slot changed", body = vehicles::Model),
+    (status = NOT_FOUND, description = "Vehicle not found"),
+    (status = CONFLICT, description = "Vehicle not approved, or parking slot taken"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn update_parking_slot(
+  State(state): State<AppState>,
+  Path(vehicle_id): Path<i32>,
+  Json(slot_info): Json<ParkingSlotInfo>,
+) -> Result<Json<vehicles::Model>, StatusCode> {
+  let parking_slot = optional_slot(slot_info.parking_slot);
+
+  let vehicle = find_vehicle(&state.db, vehicle_id).await?;
+  if vehicle.status != VehicleStatus::Approved {
+    return Err(StatusCode::CONFLICT);
+  }
+  check_parking_slot(&state.db, &parking_slot, vehicle_id).await?;
+
+  let vehicle = vehicles::ActiveModel {
+    parking_slot: Set(parking_slot),
+    ..vehicle.into()
+  }
+  .update(&state.db)
+  .await
+  .map_err(server_error)?;
+
+  Ok(Json(vehicle))
+}
+
+#[utoipa::path(
+  delete,
+  path = "/vehicles/{vehicle_id}",
+  description = "Hủy đăng ký gửi của một xe, yêu cầu request có role là Manager. Xe được giữ lại trong lịch sử với trạng thái đã hủy,
+  các khoản phí gửi xe chưa thanh toán của phòng được tính lại theo số xe còn lại, và được hủy gán nếu phòng không còn xe loại đó.",
+  tag = tags::MANAGER,
+  responses(
+    (status = OK, description = "Vehicle removed", body = vehicles::Model),
+    (status = NOT_FOUND, description = "Vehicle not found"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn remove_vehicle(
+  State(state): State<AppState>,
+  Path(vehicle_id): Path<i32>,
+) -> Result<Json<vehicles::Model>, StatusCode> {
+  let vehicle = find_vehicle(&state.db, vehicle_id).await?;
+  let vehicle = deregister_vehicle(&state, vehicle)
+    .await
+    .map_err(server_error)?;
+  Ok(Json(vehicle))
+}
+
+/// Stop parking a vehicle, keeping it for the history, and bill its room for one vehicle less.
+pub(crate) async fn deregister_vehicle(
+  state: &AppState,
+  vehicle: vehicles::Model,
+) -> Result<vehicles::Model, DbErr> {
+  if vehicle.status == VehicleStatus::Removed {
+    return Ok(vehicle);
+  }
+  let vehicle = vehicles::ActiveModel {
+    status: Set(VehicleStatus::Removed),
+    removed_at: Set(Some(chrono::Utc::now().naive_utc())),
+    ..vehicle.into()
+  }
+  .update(&state.db)
+  .await?;
+  log::info!(
+    "Vehicle {} of room {} removed",
+    vehicle.id,
+    vehicle.room_number
+  );
+
+  if let Err(e) = targets::sync_rules(state).await {
+    log::error!("Failed to sync fee assignment rules: {:?}", e);
+  }
+
+  Ok(vehicle)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn plate_separators_are_removed() {
+    assert_eq!(normalize_plate("29A-123.45"), "29A12345");
+    assert_eq!(normalize_plate(" 30 E1 - 234 56 "), "30E123456");
+  }
+
+  #[test]
+  fn plate_is_uppercased() {
+    assert_eq!(normalize_plate("29a-123.45"), "29A12345");
+  }
+
+  #[test]
+  fn plate_without_letters_or_digits_is_empty() {
+    assert_eq!(normalize_plate(""), "");
+    assert_eq!(normalize_plate(" -./ "), "");
+  }
+
+  #[test]
+  fn non_ascii_characters_are_dropped() {
+    assert_eq!(normalize_plate("29Đ-123.45"), "2912345");
+  }
+}
@@ -0,0 +1,271 @@
+//! Delivery of the queued notifications to the external channels.
+//!
+//! The worker sends the due deliveries whenever notifications are queued, and at least every
+//! minute for the retries. A failed delivery is retried with an increasing delay, and given up
+//! after [`MAX_ATTEMPTS`].
+
+use std::time::Duration;
+
+use axum_extra::extract::Query;
+use sea_orm::{sea_query::Expr, Order, QueryOrder, QuerySelect};
+
+use crate::{
+  entities::{notification_deliveries, notifications},
+  prelude::*,
+  user::NEXT_CURSOR_HEADER,
+};
+
+/// Attempts before a delivery is marked as failed
+pub const MAX_ATTEMPTS: i32 = 5;
+/// Delay before the first retry, doubled after each failed attempt
+const RETRY_DELAY_MINUTES: i64 = 1;
+/// Deliveries sent in one pass
+const BATCH_SIZE: u64 = 50;
+/// How long a delivery is reserved by the worker sending it
+const CLAIM_MINUTES: i64 = 5;
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+const DEFAULT_DELIVERY_PAGE: u64 = 50;
+const MAX_DELIVERY_PAGE: u64 = 200;
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct DeliveryParams {
+  /// The `x-next-cursor` header of the previous page, deliveries older than it are returned
+  pub cursor: Option<i32>,
+  /// Defaults to 50, at most 200
+  pub limit: Option<u64>,
+  #[param(inline)]
+  pub status: Option<DeliveryStatus>,
+  /// Name of the channel, e.g. `email` or `sms`
+  pub channel: Option<String>,
+  /// Recipient of the notification
+  pub user_id: Option<i32>,
+}
+
+fn server_error(e: DbErr) -> StatusCode {
+  log::error!("Error: {:?}", e);
+  StatusCode::INTERNAL_SERVER_ERROR
+}
+
+/// Send the queued deliveries in the background.
+pub(crate) fn spawn_delivery_worker(state: AppState) {
+  tokio::spawn(async move {
+    loop {
+      match send_due_deliveries(&state).await {
+        Ok(0) => {}
+        Ok(sent) => log::info!("Notification deliveries: {} sent", sent),
+        Err(e) => log::error!("Failed to send notification deliveries: {:?}", e),
+      }
+      // woken up early when notifications are queued
+      let _ = tokio::time::timeout(POLL_INTERVAL, state.notifier.woken()).await;
+    }
+  });
+}
+
+/// Send the deliveries that are due, and return how many were sent.
+async fn send_due_deliveries(state: &AppState) -> Result<usize, DbErr> {
+  let now = chrono::Utc::now().naive_utc();
+  let due = NotificationDeliveries::find()
+    .filter(notification_deliveries::Column::Status.eq(DeliveryStatus::Queued))
+    .filter(notification_deliveries::Column::NextAttemptAt.lte(now))
+    .order_by(notification_deliveries::Column::NextAttemptAt, Order::Asc)
+    .limit(BATCH_SIZE)
+    .all(&state.db)
+    .await?;
+
+  let mut sent = 0;
+  for delivery in due {
+    // reserve the delivery, in case another instance of the server picked it too
+    let claimed = NotificationDeliveries::update_many()
+      .col_expr(
+        notification_deliveries::Column::NextAttemptAt,
+        Expr::value(now + chrono::Duration::minutes(CLAIM_MINUTES)),
+      )
+      .filter(notification_deliveries::Column::Id.eq(delivery.id))
+      .filter(notification_deliveries::Column::Status.eq(DeliveryStatus::Queued))
+      .filter(notification_deliveries::Column::NextAttemptAt.eq(delivery.next_attempt_at))
+      .exec(&state.db)
+      .await?;
+    if claimed.rows_affected == 0 {
+      continue;
+    }
+
+    if send_delivery(state, delivery).await? {
+      sent += 1;
+    }
+  }
+
+  Ok(sent)
+}
+
+/// Make one attempt at a delivery and record the outcome. Returns whether it was sent.
+async fn send_delivery(
+  state: &AppState,
+  delivery: notification_deliveries::Model,
+) -> Result<bool, DbErr> {
+  let attempts = delivery.attempts + 1;
+  let result = match state.notifier.channel(&delivery.channel) {
+    Some(channel) => {
+      let notification = Notifications::find_by_id(delivery.notification_id)
+        .one(&state.db)
+        .await?;
+      let recipient = match &notification {
+        Some(notification) => {
+          Users::find_by_id(notification.to_user)
+            .one(&state.db)
+            .await?
+        }
+        None => None,
+      };
+      match (notification, recipient) {
+        (Some(notification), Some(recipient)) => {
+          channel
+            .send(&recipient, &notification.title, &notification.message)
+            .await
+        }
+        _ => Err(anyhow::anyhow!("Notification or recipient not found")),
+      }
+    }
+    None => Err(anyhow::anyhow!(
+      "Channel {} is not configured",
+      delivery.channel
+    )),
+  };
+
+  let now = chrono::Utc::now().naive_utc();
+  let mut update: notification_deliveries::ActiveModel = delivery.clone().into();
+  update.attempts = Set(attempts);
+  let sent = match result {
+    Ok(()) => {
+      update.status = Set(DeliveryStatus::Sent);
+      update.sent_at = Set(Some(now));
+      update.last_error = Set(None);
+      true
+    }
+    Err(e) => {
+      log::error!(
+        "Failed to send notification {} through {} (attempt {}): {:?}",
+        delivery.notification_id,
+        delivery.channel,
+        attempts,
+        e
+      );
+      update.last_error = Set(Some(format!("{:#}", e)));
+      if attempts >= MAX_ATTEMPTS || state.notifier.channel(&delivery.channel).is_none() {
+        update.status = Set(DeliveryStatus::Failed);
+      } else {
+        update.next_attempt_at =
+          Set(now + chrono::Duration::minutes(RETRY_DELAY_MINUTES << (attempts - 1)));
+      }
+      false
+    }
+  };
+  update.update(&state.db).await?;
+
+  Ok(sent)
+}
+
+#[utoipa::path(
+  get,
+  path = "/notification-deliveries",
+  description = "Lấy nhật ký gửi thông báo qua các kênh ngoài ứng dụng (email, SMS, ...), mới nhất trước, yêu cầu request có role là Manager.
+  Trả về tối đa `limit` bản ghi. Nếu còn bản ghi cũ hơn, header `x-next-cursor` chứa giá trị `cursor` để lấy trang tiếp theo. Có thể lọc theo trạng thái `status` (chờ gửi, đã gửi, thất bại), kênh `channel` và người nhận `user_id`.",
+  tag = tags::MANAGER,
+  params(
+    DeliveryParams
+  ),
+  responses(
+    (status = OK, description = "Deliveries", body = Vec<notification_deliveries::Model>,
+      headers(("x-next-cursor" = i32, description = "Cursor of the next page, if there is one"))),
+    (status = BAD_REQUEST, description = "Invalid limit"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn get_deliveries(
+  State(state): State<AppState>,
+  Query(params): Query<DeliveryParams>,
+) -> Result<impl IntoResponse, StatusCode> {
+  let limit = params.limit.unwrap_or(DEFAULT_DELIVERY_PAGE);
+  if limit == 0 || limit > MAX_DELIVERY_PAGE {
+    return Err(StatusCode::BAD_REQUEST);
+  }
+
+  let mut query = NotificationDeliveries::find();
+  if let Some(cursor) = params.cursor {
+    query = query.filter(notification_deliveries::Column::Id.lt(cursor));
+  }
+  if let Some(status) = params.status {
+    query = query.filter(notification_deliveries::Column::Status.eq(status));
+  }
+  if let Some(channel) = params.channel {
+    query = query.filter(notification_deliveries::Column::Channel.eq(channel));
+  }
+  if let Some(user_id) = params.user_id {
+    query = query
+      .inner_join(Notifications)
+      .filter(notifications::Column::ToUser.eq(user_id));
+  }
+
+  // one more than the page, to know whether there is a next page
+  let mut deliveries = query
+    .order_by(notification_deliveries::Column::Id, Order::Desc)
+    .limit(limit + 1)
+    .all(&state.db)
+    .await
+    .map_err(server_error)?;
+
+  let mut headers = HeaderMap::new();
+  if deliveries.len() as u64 > limit {
+    deliveries.truncate(limit as usize);
+    if let Some(last) = deliveries.last() {
+      headers.insert(NEXT_CURSOR_HEADER, last.id.into());
+    }
+  }
+
+  Ok((headers, Json(deliveries)))
+}
+
+#[utoipa::path(
+  post,
+  path = "/notification-deliveries/{id}/retry",
+  description = "Gửi lại một thông báo đã gửi thất bại qua kênh ngoài ứng dụng, yêu cầu request có role là Manager.
+  Số lần thử được đặt lại và thông báo được gửi ngay.",
+  tag = tags::MANAGER,
+  responses(
+    (status = OK, description = "Delivery queued", body = notification_deliveries::Model),
+    (status = NOT_FOUND, description = "Delivery not found"),
+    (status = CONFLICT, description = "Delivery was not failed"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn retry_delivery(
+  State(state): State<AppState>,
+  Path(id): Path<i32>,
+) -> Result<Json<notification_deliveries::Model>, StatusCode> {
+  let delivery = NotificationDeliveries::find_by_id(id)
+    .one(&state.db)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+  if delivery.status != DeliveryStatus::Failed {
+    return Err(StatusCode::CONFLICT);
+  }
+
+  let mut delivery: notification_deliveries::ActiveModel = delivery.into();
+  delivery.status = Set(DeliveryStatus::Queued);
+  delivery.attempts = Set(0);
+  delivery.next_attempt_at = Set(chrono::Utc::now().naive_utc());
+  let delivery = delivery.update(&state.db).await.map_err(server_error)?;
+  state.notifier.wake();
+
+  Ok(Json(delivery))
+}
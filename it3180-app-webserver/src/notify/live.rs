@@ -0,0 +1,154 @@
+//! Live delivery to the connected clients over server-sent events: new notifications, and
+//! payment confirmations for the rooms of the user, so that the app does not have to poll.
+//!
+//! Events are only pushed to the clients connected when they happen. A client that falls behind
+//! gets a `resync` event and should reload its notifications.
+
+use std::{convert::Infallible, time::Duration};
+
+use axum::response::sse::{Event, KeepAlive, Sse};
+use tokio::sync::broadcast;
+use tokio_stream::{wrappers::BroadcastStream, Stream, StreamExt};
+
+use crate::{
+  entities::{fees_room_assignment, notifications, room_members},
+  prelude::*,
+};
+
+/// Events kept for the clients that are slow to read them.
+const FEED_CAPACITY: usize = 256;
+
+/// An event pushed to a user.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LiveEvent {
+  /// A new notification
+  Notification { notification: notifications::Model },
+  /// A fee of the room of the user was paid
+  Payment {
+    assignment_id: i32,
+    room_number: i32,
+    fee_name: String,
+    amount: i64,
+    paid_at: Option<DateTime>,
+  },
+}
+
+impl LiveEvent {
+  fn name(&self) -> &'static str {
+    match self {
+      LiveEvent::Notification { .. } => "notification",
+      LiveEvent::Payment { .. } => "payment",
+    }
+  }
+}
+
+/// The events of every user, each client only reads its own.
+#[derive(Debug, Clone)]
+pub struct LiveFeed {
+  sender: broadcast::Sender<(i32, LiveEvent)>,
+}
+
+impl Default for LiveFeed {
+  fn default() -> Self {
+    Self {
+      sender: broadcast::channel(FEED_CAPACITY).0,
+    }
+  }
+}
+
+impl LiveFeed {
+  /// Push an event to the clients of a user, if any are connected.
+  pub fn publish(&self, user_id: i32, event: LiveEvent) {
+    // an error only means that nobody is listening
+    let _ = self.sender.send((user_id, event));
+  }
+
+  /// Push a new notification to its recipient.
+  pub fn publish_notification(&self, notification: &notifications::Model) {
+    self.publish(
+      notification.to_user,
+      LiveEvent::Notification {
+        notification: notification.clone(),
+      },
+    );
+  }
+
+  fn subscribe(&self, user_id: i32) -> impl Stream<Item = Result<Event, Infallible>> {
+    BroadcastStream::new(self.sender.subscribe()).filter_map(move |received| match received {
+      Ok((to_user, event)) if to_user == user_id => Some(Ok(
+        Event::default()
+          .event(event.name())
+          .json_data(&event)
+          .unwrap_or_else(|_| Event::default().event("resync")),
+      )),
+      Ok(_) => None,
+      Err(_) => Some(Ok(Event::default().event("resync").data("lagged"))),
+    })
+  }
+}
+
+/// Push a payment confirmation to everyone living in the room of a settled fee assignment.
+pub(crate) async fn publish_payment(state: &AppState, assignment_id: i32) -> Result<(), DbErr> {
+  let Some(assignment) = FeesRoomAssignment::find_by_id(assignment_id)
+    .one(&state.db)
+    .await?
+  else {
+    return Ok(());
+  };
+  let members = RoomMembers::find()
+    .filter(room_members::Column::RoomNumber.eq(assignment.room_number))
+    .filter(room_members::Column::JoinedAt.is_not_null())
+    .all(&state.db)
+    .await?;
+
+  let fees_room_assignment::Model {
+    assignment_id,
+    room_number,
+    fee_name,
+    amount,
+    payment_date,
+    ..
+  } = assignment;
+  for member in members {
+    state.notifier.live().publish(
+      member.user_id,
+      LiveEvent::Payment {
+        assignment_id,
+        room_number,
+        fee_name: fee_name.clone(),
+        amount,
+        paid_at: payment_date,
+      },
+    );
+  }
+  Ok(())
+}
+
+#[utoipa::path(
+  get,
+  path = "/notifications/stream",
+  description = "Nhận thông báo mới và xác nhận thanh toán các khoản phí của phòng ngay khi chúng xảy ra, qua server-sent events,
+  yêu cầu request có token ở header. Sự kiện `notification` chứa thông báo mới, sự kiện `payment` chứa khoản phí vừa được thanh toán.
+  Sự kiện `resync` nghĩa là có sự kiện bị bỏ lỡ, ứng dụng cần tải lại thông báo.",
+  tag = tags::USER,
+  responses(
+    (status = OK, description = "Event stream", body = LiveEvent, content_type = "text/event-stream"),
+    (status = UNAUTHORIZED, description = "Invalid token", body = String),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn notification_stream(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, StatusCode> {
+  let claims = state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+  let events = state.notifier.live().subscribe(claims.custom.id);
+  Ok(Sse::new(events).keep_alive(KeepAlive::new().interval(Duration::from_secs(15))))
+}
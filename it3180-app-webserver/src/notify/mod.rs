@@ -0,0 +1,191 @@
+//! Delivery of notifications to the users.
+//!
+//! Every notification is stored in the `notifications` table, where the app shows it. It is then
+//! pushed to the connected clients of the user, and queued in `notification_deliveries` for each
+//! external channel configured on the server that the user has opted in to, e.g. email, SMS or a
+//! chat bot webhook. The delivery worker sends the queued notifications and retries the failures.
+
+pub mod delivery;
+pub mod email;
+pub mod live;
+pub mod sink;
+pub mod sms;
+pub mod templates;
+pub mod webhook;
+
+use std::sync::Arc;
+
+use sea_orm::{Order, QueryOrder};
+use tokio::sync::Notify;
+
+use crate::{
+  entities::{notification_deliveries, notification_preferences, notifications, users},
+  prelude::*,
+};
+use live::LiveFeed;
+use templates::Notice;
+
+/// A channel that delivers notifications outside of the app.
+#[async_trait::async_trait]
+pub trait NotificationChannel: std::fmt::Debug + Send + Sync {
+  /// Name of the channel, used in the logs
+  fn name(&self) -> &str;
+
+  /// Whether the user wants to receive notifications through this channel
+  fn opted_in(&self, _preferences: &notification_preferences::Model) -> bool {
+    true
+  }
+
+  async fn send(&self, to: &users::Model, title: &str, message: &str) -> anyhow::Result<()>;
+}
+
+/// Stores notifications and queues them for the external channels.
+#[derive(Debug, Clone, Default)]
+pub struct Notifier {
+  channels: Arc<Vec<Arc<dyn NotificationChannel>>>,
+  live: LiveFeed,
+  wake: Arc<Notify>,
+}
+
+impl Notifier {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn with(mut self, channel: impl NotificationChannel + 'static) -> Self {
+    Arc::make_mut(&mut self.channels).push(Arc::new(channel));
+    self
+  }
+
+  /// The live feed of the connected clients.
+  pub fn live(&self) -> &LiveFeed {
+    &self.live
+  }
+
+  /// The external channel with the given name, if it is configured.
+  pub fn channel(&self, name: &str) -> Option<&Arc<dyn NotificationChannel>> {
+    self.channels.iter().find(|channel| channel.name() == name)
+  }
+
+  /// Wake the delivery worker up, e.g. after queueing deliveries.
+  pub fn wake(&self) {
+    self.wake.notify_one();
+  }
+
+  /// Wait until the delivery worker is woken up.
+  pub(crate) async fn woken(&self) {
+    self.wake.notified().await
+  }
+
+  /// Send a notification to a user.
+  ///
+  /// Only failing to store the notification is an error. External channels are best effort,
+  /// failing to queue a delivery is logged.
+  pub async fn notify(
+    &self,
+    db: &DatabaseConnection,
+    from_user: i32,
+    to: &users::Model,
+    title: &str,
+    message: &str,
+  ) -> Result<notifications::Model, DbErr> {
+    let notification = notifications::ActiveModel {
+      title: Set(title.to_string()),
+      message: Set(message.to_string()),
+      from_user: Set(from_user),
+      to_user: Set(to.id),
+      ..Default::default()
+    };
+    let notification = notification.insert(db).await?;
+    self.live.publish_notification(&notification);
+
+    if self.channels.is_empty() {
+      return Ok(notification);
+    }
+    let preferences = match preferences_of(db, to.id).await {
+      Ok(preferences) => preferences,
+      Err(e) => {
+        log::error!("Error: {:?}", e);
+        return Ok(notification);
+      }
+    };
+    let deliveries = self
+      .channels
+      .iter()
+      .filter(|channel| channel.opted_in(&preferences))
+      .map(|channel| notification_deliveries::ActiveModel {
+        notification_id: Set(notification.id),
+        channel: Set(channel.name().to_string()),
+        status: Set(DeliveryStatus::Queued),
+        ..Default::default()
+      })
+      .collect::<Vec<_>>();
+    if !deliveries.is_empty() {
+      match NotificationDeliveries::insert_many(deliveries)
+        .exec(db)
+        .await
+      {
+        Ok(_) => self.wake(),
+        Err(e) => log::error!(
+          "Failed to queue deliveries of notification {}: {:?}",
+          notification.id,
+          e
+        ),
+      }
+    }
+
+    Ok(notification)
+  }
+
+  /// Send an automated notification to a user, rendered in their language.
+  pub async fn notify_event(
+    &self,
+    db: &DatabaseConnection,
+    from_user: i32,
+    to: &users::Model,
+    notice: &Notice,
+  ) -> Result<notifications::Model, DbErr> {
+    // a failed lookup should not cost the user the notification itself
+    let language = match preferences_of(db, to.id).await {
+      Ok(preferences) => preferences.language,
+      Err(e) => {
+        log::error!(
+          "Failed to get the language of user {}, using {}: {:?}",
+          to.id,
+          templates::DEFAULT_LANGUAGE,
+          e
+        );
+        templates::DEFAULT_LANGUAGE.to_string()
+      }
+    };
+    let (title, message) = templates::render(db, notice, &language).await?;
+    self.notify(db, from_user, to, &title, &message).await
+  }
+}
+
+/// The notification preferences of a user, or the defaults if they never set them.
+pub(crate) async fn preferences_of(
+  db: &DatabaseConnection,
+  user_id: i32,
+) -> Result<notification_preferences::Model, DbErr> {
+  let preferences = NotificationPreferences::find_by_id(user_id).one(db).await?;
+  Ok(preferences.unwrap_or(notification_preferences::Model {
+    user_id,
+    email: true,
+    sms: false,
+    webhook: true,
+    language: templates::DEFAULT_LANGUAGE.to_string(),
+    updated_at: chrono::Utc::now().naive_utc(),
+  }))
+}
+
+/// The user that automated notifications are sent from: the first active manager.
+pub(crate) async fn default_sender(db: &DatabaseConnection) -> Result<Option<i32>, DbErr> {
+  let manager = Users::find()
+    .filter(users::Column::Role.eq(UserRole::Manager))
+    .filter(users::Column::Status.eq(UserStatus::Active))
+    .order_by(users::Column::Id, Order::Asc)
+    .one(db)
+    .await?;
+  Ok(manager.map(|manager| manager.id))
+}
@@ -0,0 +1,43 @@
+//! Local outputs for the email and SMS channels, so that notifications can be read during
+//! development without an SMTP server or an SMS gateway.
+
+use std::{fs::OpenOptions, io::Write, path::PathBuf};
+
+#[derive(Debug, Clone)]
+pub enum Sink {
+  /// Print the messages to the standard output
+  Stdout,
+  /// Append the messages to a file
+  File(PathBuf),
+}
+
+impl Sink {
+  /// Parse `stdout` or `file:///path/to/file`.
+  pub fn parse(target: &str) -> Option<Self> {
+    match target {
+      "stdout" => Some(Sink::Stdout),
+      _ => target
+        .strip_prefix("file://")
+        .filter(|path| !path.is_empty())
+        .map(|path| Sink::File(PathBuf::from(path))),
+    }
+  }
+
+  pub async fn write(&self, record: String) -> anyhow::Result<()> {
+    match self {
+      Sink::Stdout => {
+        println!("{}", record);
+        Ok(())
+      }
+      Sink::File(path) => {
+        let path = path.clone();
+        tokio::task::spawn_blocking(move || {
+          let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+          writeln!(file, "{}", record)?;
+          Ok(())
+        })
+        .await?
+      }
+    }
+  }
+}
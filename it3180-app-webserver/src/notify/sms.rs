@@ -0,0 +1,85 @@
+//! Sends notifications by SMS, through an HTTP gateway or a local sink.
+
+use crate::{
+  entities::{notification_preferences, users},
+  prelude::*,
+};
+
+use super::{sink::Sink, NotificationChannel};
+
+#[derive(Debug, Clone)]
+enum Gateway {
+  Http {
+    client: reqwest::Client,
+    url: String,
+    api_key: Option<String>,
+  },
+  Sink(Sink),
+}
+
+#[derive(Debug, Clone)]
+pub struct SmsChannel {
+  gateway: Gateway,
+  sender: Option<String>,
+}
+
+impl SmsChannel {
+  /// `target` is the URL of the gateway, which receives the messages as JSON, or a sink: `stdout`
+  /// or `file:///path/to/file`.
+  pub fn new(target: &str, api_key: Option<String>, sender: Option<String>) -> Self {
+    let gateway = match Sink::parse(target) {
+      Some(sink) => Gateway::Sink(sink),
+      None => Gateway::Http {
+        client: reqwest::Client::builder()
+          .timeout(std::time::Duration::from_secs(10))
+          .build()
+          .expect("failed to build the SMS gateway client"),
+        url: target.to_string(),
+        api_key,
+      },
+    };
+
+    Self { gateway, sender }
+  }
+}
+
+#[async_trait::async_trait]
+impl NotificationChannel for SmsChannel {
+  fn name(&self) -> &str {
+    "sms"
+  }
+
+  fn opted_in(&self, preferences: &notification_preferences::Model) -> bool {
+    preferences.sms
+  }
+
+  async fn send(&self, to: &users::Model, title: &str, message: &str) -> anyhow::Result<()> {
+    if to.phone.is_empty() {
+      anyhow::bail!("User {} has no phone number", to.id);
+    }
+    let text = format!("{}: {}", title, message);
+
+    match &self.gateway {
+      Gateway::Http {
+        client,
+        url,
+        api_key,
+      } => {
+        let mut request = client.post(url).json(&json!({
+          "to": to.phone,
+          "from": self.sender,
+          "text": text,
+        }));
+        if let Some(api_key) = api_key {
+          request = request.bearer_auth(api_key);
+        }
+        request.send().await?.error_for_status()?;
+      }
+      Gateway::Sink(sink) => {
+        sink.write(format!("SMS to {}: {}", to.phone, text)).await?;
+      }
+    }
+
+    Ok(())
+  }
+}
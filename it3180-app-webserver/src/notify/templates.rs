@@ -0,0 +1,585 @@
+//! Templates of the automated notifications, e.g. fee notices and payment reminders.
+//!
+//! Every event has built-in templates in Vietnamese and English. Managers can reword them, or add
+//! templates in other languages, without a redeploy. A notification is rendered in the language of
+//! its recipient, falling back to Vietnamese.
+//!
+//! Templates contain placeholders such as `{room}` or `{amount}`, see [`placeholders`].
+
+use std::sync::LazyLock;
+
+use axum_extra::extract::Query;
+use regex::{Captures, Regex};
+use sea_orm::{sea_query::OnConflict, Iterable, Order, QueryOrder};
+
+use crate::{documents::pdf::format_vnd, entities::notification_templates, prelude::*};
+
+/// Language of the recipients that did not choose one
+pub const DEFAULT_LANGUAGE: &str = "vi";
+/// Languages that have built-in templates
+pub const BUILT_IN_LANGUAGES: [&str; 2] = ["vi", "en"];
+
+/// A value filled into the placeholders of a template.
+#[derive(Debug, Clone)]
+pub enum Value {
+  Text(String),
+  Amount(i64),
+  Date(chrono::NaiveDate),
+  /// A text in Vietnamese and in English, the English one is used for the other languages
+  Label {
+    vi: String,
+    en: String,
+  },
+}
+
+impl Value {
+  fn render(&self, language: &str) -> String {
+    match self {
+      Value::Text(text) => text.clone(),
+      Value::Amount(amount) => format_vnd(*amount),
+      Value::Date(date) => date.format("%d/%m/%Y").to_string(),
+      Value::Label { vi, en } => match language {
+        "vi" => vi.clone(),
+        _ => en.clone(),
+      },
+    }
+  }
+}
+
+/// An automated notification, before it is rendered in the language of its recipient.
+#[derive(Debug, Clone)]
+pub struct Notice {
+  event: NotificationEvent,
+  values: Vec<(&'static str, Value)>,
+}
+
+impl Notice {
+  pub fn new(event: NotificationEvent) -> Self {
+    Self {
+      event,
+      values: Vec::new(),
+    }
+  }
+
+  pub fn text(mut self, name: &'static str, text: impl ToString) -> Self {
+    self.values.push((name, Value::Text(text.to_string())));
+    self
+  }
+
+  pub fn amount(mut self, name: &'static str, amount: i64) -> Self {
+    self.values.push((name, Value::Amount(amount)));
+    self
+  }
+
+  pub fn date(mut self, name: &'static str, date: chrono::NaiveDate) -> Self {
+    self.values.push((name, Value::Date(date)));
+    self
+  }
+
+  pub fn label(mut self, name: &'static str, vi: impl Into<String>, en: impl Into<String>) -> Self {
+    self.values.push((
+      name,
+      Value::Label {
+        vi: vi.into(),
+        en: en.into(),
+      },
+    ));
+    self
+  }
+
+  /// Fill the placeholders of a template. Unknown placeholders are kept as they are.
+  fn fill(&self, template: &str, language: &str) -> String {
+    PLACEHOLDER_PATTERN
+      .replace_all(template, |captures: &Captures| {
+        self
+          .values
+          .iter()
+          .find(|(name, _)| *name == &captures[1])
+          .map(|(_, value)| value.render(language))
+          .unwrap_or_else(|| captures[0].to_string())
+      })
+      .into_owned()
+  }
+}
+
+/// Matches the placeholders of a template, e.g. `{room}`
+static PLACEHOLDER_PATTERN: LazyLock<Regex> =
+  LazyLock::new(|| Regex::new(r"\{([a-z_]+)\}").unwrap());
+
+/// The placeholders that can be used in the templates of an event.
+pub fn placeholders(event: &NotificationEvent) -> &'static [&'static str] {
+  match event {
+    NotificationEvent::FeeAssigned => &["room", "fee_name", "amount", "due_date"],
+    NotificationEvent::PaymentReminderUpcoming
+    | NotificationEvent::PaymentReminderDue
+    | NotificationEvent::PaymentReminderOverdue => &[
+      "room",
+      "fee_name",
+      "amount",
+      "due_date",
+      "days",
+      "reference",
+    ],
+    NotificationEvent::RoomInvitation | NotificationEvent::RoomClaimApproved => &["room"],
+    NotificationEvent::RoomClaimRejected => &["room", "reason"],
+    NotificationEvent::DeclarationApproved => &["kind", "full_name", "start_date", "end_date"],
+    NotificationEvent::DeclarationRejected => &["kind", "full_name", "reason"],
+    NotificationEvent::DeclarationExpiring => &["kind", "full_name", "room", "end_date"],
+    NotificationEvent::VehicleApproved => &["vehicle", "room", "parking_slot"],
+    NotificationEvent::VehicleRejected => &["vehicle", "room", "reason"],
+  }
+}
+
+/// The built-in title and body of an event, in Vietnamese or English.
+pub fn built_in_template(
+  event: &NotificationEvent,
+  language: &str,
+) -> Option<(&'static str, &'static str)> {
+  let template = match (event, language) {
+    (NotificationEvent::FeeAssigned, "vi") => (
+      "Thông báo về phí {fee_name}",
+      "Phòng {room} có khoản phí {fee_name} với số tiền cần thanh toán là {amount}. Vui lòng thanh toán trước ngày {due_date}",
+    ),
+    (NotificationEvent::FeeAssigned, "en") => (
+      "Fee notice: {fee_name}",
+      "Room {room} has a new fee {fee_name} of {amount}. Please pay before {due_date}",
+    ),
+    (NotificationEvent::PaymentReminderUpcoming, "vi") => (
+      "Nhắc nhở thanh toán phí {fee_name}",
+      "Khoản phí {fee_name} của phòng {room} sẽ đến hạn vào ngày {due_date} (còn {days} ngày). Số tiền cần thanh toán là {amount}, nội dung chuyển khoản: {reference}",
+    ),
+    (NotificationEvent::PaymentReminderUpcoming, "en") => (
+      "Payment reminder: {fee_name}",
+      "The fee {fee_name} of room {room} is due on {due_date} ({days} days left). The amount due is {amount}, transfer description: {reference}",
+    ),
+    (NotificationEvent::PaymentReminderDue, "vi") => (
+      "Nhắc nhở thanh toán phí {fee_name}",
+      "Khoản phí {fee_name} của phòng {room} đến hạn thanh toán hôm nay ({due_date}). Số tiền cần thanh toán là {amount}, nội dung chuyển khoản: {reference}",
+    ),
+    (NotificationEvent::PaymentReminderDue, "en") => (
+      "Payment reminder: {fee_name}",
+      "The fee {fee_name} of room {room} is due today ({due_date}). The amount due is {amount}, transfer description: {reference}",
+    ),
+    (NotificationEvent::PaymentReminderOverdue, "vi") => (
+      "Nhắc nhở thanh toán phí {fee_name}",
+      "Khoản phí {fee_name} của phòng {room} đã quá hạn {days} ngày (hạn nộp {due_date}). Số tiền cần thanh toán là {amount}, nội dung chuyển khoản: {reference}",
+    ),
+    (NotificationEvent::PaymentReminderOverdue, "en") => (
+      "Payment reminder: {fee_name}",
+      "The fee {fee_name} of room {room} is {days} days overdue (due on {due_date}). The amount due is {amount}, transfer description: {reference}",
+    ),
+    (NotificationEvent::RoomInvitation, "vi") => (
+      "Lời mời vào phòng {room}",
+      "Bạn được mời trở thành thành viên của phòng {room}. Vui lòng chấp nhận hoặc từ chối lời mời trong mục hộ gia đình.",
+    ),
+    (NotificationEvent::RoomInvitation, "en") => (
+      "Invitation to room {room}",
+      "You are invited to become a member of room {room}. Please accept or decline the invitation in the household section.",
+    ),
+    (NotificationEvent::RoomClaimApproved, "vi") => (
+      "Yêu cầu nhận phòng {room} đã được chấp nhận",
+      "Tài khoản của bạn đã được kích hoạt và bạn đã trở thành thành viên của phòng {room}.",
+    ),
+    (NotificationEvent::RoomClaimApproved, "en") => (
+      "Your claim for room {room} was approved",
+      "Your account is activated and you are now a member of room {room}.",
+    ),
+    (NotificationEvent::RoomClaimRejected, "vi") => (
+      "Yêu cầu nhận phòng {room} bị từ chối",
+      "Lý do: {reason}",
+    ),
+    (NotificationEvent::RoomClaimRejected, "en") => (
+      "Your claim for room {room} was rejected",
+      "Reason: {reason}",
+    ),
+    (NotificationEvent::DeclarationApproved, "vi") => (
+      "Khai báo {kind} đã được duyệt",
+      "Khai báo {kind} của {full_name} từ ngày {start_date} đến ngày {end_date} đã được duyệt.",
+    ),
+    (NotificationEvent::DeclarationApproved, "en") => (
+      "Your {kind} declaration was approved",
+      "The {kind} declaration of {full_name} from {start_date} to {end_date} was approved.",
+    ),
+    (NotificationEvent::DeclarationRejected, "vi") => (
+      "Khai báo {kind} bị từ chối",
+      "Khai báo {kind} của {full_name} bị từ chối. Lý do: {reason}",
+    ),
+    (NotificationEvent::DeclarationRejected, "en") => (
+      "Your {kind} declaration was rejected",
+      "The {kind} declaration of {full_name} was rejected. Reason: {reason}",
+    ),
+    (NotificationEvent::DeclarationExpiring, "vi") => (
+      "Khai báo {kind} sắp hết hạn",
+      "Khai báo {kind} của {full_name} tại phòng {room} sẽ hết hạn vào ngày {end_date}. Vui lòng khai báo lại nếu cần gia hạn.",
+    ),
+    (NotificationEvent::DeclarationExpiring, "en") => (
+      "Your {kind} declaration expires soon",
+      "The {kind} declaration of {full_name} in room {room} expires on {end_date}. Please declare again if it needs to be extended.",
+    ),
+    (NotificationEvent::VehicleApproved, "vi") => (
+      "Đăng ký gửi xe đã được duyệt",
+      "Đăng ký gửi {vehicle} của phòng {room} đã được duyệt. Vị trí đỗ: {parking_slot}.",
+    ),
+    (NotificationEvent::VehicleApproved, "en") => (
+      "Vehicle registration approved",
+      "The registration of the {vehicle} of room {room} for parking was approved. Parking slot: {parking_slot}.",
+    ),
+    (NotificationEvent::VehicleRejected, "vi") => (
+      "Đăng ký gửi xe bị từ chối",
+      "Đăng ký gửi {vehicle} của phòng {room} bị từ chối. Lý do: {reason}",
+    ),
+    (NotificationEvent::VehicleRejected, "en") => (
+      "Vehicle registration rejected",
+      "The registration of the {vehicle} of room {room} for parking was rejected. Reason: {reason}",
+    ),
+    _ => return None,
+  };
+  Some(template)
+}
+
+/// A language code such as `vi`, `en` or `zh-tw`.
+pub fn is_valid_language(language: &str) -> bool {
+  (2..=10).contains(&language.len()) && language.chars().all(|c| c.is_ascii_lowercase() || c == '-')
+}
+
+/// Render a notice in a language: with the template of the managers if there is one, else the
+/// built-in one, else in the default language.
+pub(crate) async fn render(
+  db: &DatabaseConnection,
+  notice: &Notice,
+  language: &str,
+) -> Result<(String, String), DbErr> {
+  let edited = NotificationTemplates::find()
+    .filter(notification_templates::Column::Event.eq(notice.event.clone()))
+    .filter(notification_templates::Column::Language.is_in([language, DEFAULT_LANGUAGE]))
+    .all(db)
+    .await?;
+  let edited_in = |language: &str| {
+    edited
+      .iter()
+      .find(|template| template.language == language)
+      .map(|template| (template.title.as_str(), template.body.as_str()))
+  };
+
+  let (used_language, (title, body)) = edited_in(language)
+    .or_else(|| built_in_template(&notice.event, language))
+    .map(|template| (language, template))
+    .or_else(|| {
+      edited_in(DEFAULT_LANGUAGE)
+        .or_else(|| built_in_template(&notice.event, DEFAULT_LANGUAGE))
+        .map(|template| (DEFAULT_LANGUAGE, template))
+    })
+    .unwrap_or((DEFAULT_LANGUAGE, ("", "")));
+
+  Ok((
+    notice.fill(title, used_language),
+    notice.fill(body, used_language),
+  ))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct NotificationTemplateInfo {
+  pub event: NotificationEvent,
+  pub language: String,
+  pub title: String,
+  pub body: String,
+  /// Placeholders that can be used in the title and body, e.g. `room` for `{room}`
+  pub placeholders: Vec<String>,
+  /// Whether the template was edited by a manager, else it is built in
+  pub customized: bool,
+  pub updated_by: Option<i32>,
+  pub updated_at: Option<DateTime>,
+}
+
+impl NotificationTemplateInfo {
+  fn built_in(event: NotificationEvent, language: &str) -> Option<Self> {
+    let (title, body) = built_in_template(&event, language)?;
+    Some(Self {
+      placeholders: placeholder_names(&event),
+      event,
+      language: language.to_string(),
+      title: title.to_string(),
+      body: body.to_string(),
+      customized: false,
+      updated_by: None,
+      updated_at: None,
+    })
+  }
+
+  fn edited(template: notification_templates::Model) -> Self {
+    Self {
+      placeholders: placeholder_names(&template.event),
+      event: template.event,
+      language: template.language,
+      title: template.title,
+      body: template.body,
+      customized: true,
+      updated_by: template.updated_by,
+      updated_at: Some(template.updated_at),
+    }
+  }
+}
+
+fn placeholder_names(event: &NotificationEvent) -> Vec<String> {
+  placeholders(event)
+    .iter()
+    .map(|name| String::from(*name))
+    .collect()
+}
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct TemplateParams {
+  pub language: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TemplateRequest {
+  pub title: String,
+  pub body: String,
+}
+
+fn server_error(e: DbErr) -> StatusCode {
+  log::error!("Error: {:?}", e);
+  StatusCode::INTERNAL_SERVER_ERROR
+}
+
+#[utoipa::path(
+  get,
+  path = "/notification-templates",
+  description = "Lấy mẫu thông báo tự động của từng sự kiện (gán phí, nhắc nợ, duyệt khai báo, ...) theo từng ngôn ngữ, yêu cầu request có role là Manager.
+  Bao gồm các mẫu có sẵn bằng tiếng Việt và tiếng Anh, và các mẫu do quản lý sửa. Có thể lọc theo ngôn ngữ `language`.",
+  tag = tags::MANAGER,
+  params(
+    TemplateParams
+  ),
+  responses(
+    (status = OK, description = "Templates", body = Vec<NotificationTemplateInfo>),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn get_templates(
+  State(state): State<AppState>,
+  Query(params): Query<TemplateParams>,
+) -> Result<Json<Vec<NotificationTemplateInfo>>, StatusCode> {
+  let mut query = NotificationTemplates::find()
+    .order_by(notification_templates::Column::Language, Order::Asc)
+    .order_by(notification_templates::Column::Event, Order::Asc);
+  if let Some(language) = &params.language {
+    query = query.filter(notification_templates::Column::Language.eq(language));
+  }
+  let edited = query.all(&state.db).await.map_err(server_error)?;
+
+  let mut templates = Vec::new();
+  for language in BUILT_IN_LANGUAGES {
+    if params
+      .language
+      .as_deref()
+      .is_some_and(|wanted| wanted != language)
+    {
+      continue;
+    }
+    for event in NotificationEvent::iter() {
+      let customized = edited
+        .iter()
+        .any(|template| template.event == event && template.language == language);
+      if !customized {
+        templates.extend(NotificationTemplateInfo::built_in(event, language));
+      }
+    }
+  }
+  templates.extend(edited.into_iter().map(NotificationTemplateInfo::edited));
+  templates.sort_by(|a, b| {
+    (a.language.as_str(), a.event.to_value()).cmp(&(b.language.as_str(), b.event.to_value()))
+  });
+
+  Ok(Json(templates))
+}
+
+#[utoipa::path(
+  put,
+  path = "/notification-templates/{event}/{language}",
+  description = "Sửa mẫu thông báo tự động của một sự kiện trong một ngôn ngữ, hoặc thêm mẫu cho ngôn ngữ mới, yêu cầu request có role là Manager.
+  Tiêu đề `title` và nội dung `body` có thể chứa các biến như `{room}`, `{amount}`, danh sách biến của từng sự kiện có trong `placeholders`.
+  Mã ngôn ngữ gồm chữ thường và dấu gạch, ví dụ `vi`, `en`, `zh-tw`.",
+  tag = tags::MANAGER,
+  request_body = TemplateRequest,
+  responses(
+    (status = OK, description = "Template saved", body = NotificationTemplateInfo),
+    (status = BAD_REQUEST, description = "Empty template, invalid language or unknown placeholder", body = String),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn update_template(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+  Path((event, language)): Path<(NotificationEvent, String)>,
+  Json(request): Json<TemplateRequest>,
+) -> Result<Json<NotificationTemplateInfo>, (StatusCode, String)> {
+  let claims = state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+    .map_err(|_| (StatusCode::UNAUTHORIZED, String::new()))?;
+
+  let title = request.title.trim().to_string();
+  let body = request.body.trim().to_string();
+  if title.is_empty() || body.is_empty() {
+    return Err((StatusCode::BAD_REQUEST, "Empty title or body".to_string()));
+  }
+  if !is_valid_language(&language) {
+    return Err((
+      StatusCode::BAD_REQUEST,
+      format!("Invalid language: {}", language),
+    ));
+  }
+  let known = placeholders(&event);
+  if let Some(unknown) = PLACEHOLDER_PATTERN
+    .captures_iter(&title)
+    .chain(PLACEHOLDER_PATTERN.captures_iter(&body))
+    .map(|captures| captures[1].to_string())
+    .find(|name| !known.contains(&name.as_str()))
+  {
+    return Err((
+      StatusCode::BAD_REQUEST,
+      format!("Unknown placeholder: {{{}}}", unknown),
+    ));
+  }
+
+  let template = notification_templates::ActiveModel {
+    event: Set(event.clone()),
+    language: Set(language.clone()),
+    title: Set(title),
+    body: Set(body),
+    updated_by: Set(Some(claims.custom.id)),
+    updated_at: Set(chrono::Utc::now().naive_utc()),
+    ..Default::default()
+  };
+  NotificationTemplates::insert(template)
+    .on_conflict(
+      OnConflict::columns([
+        notification_templates::Column::Event,
+        notification_templates::Column::Language,
+      ])
+      .update_columns([
+        notification_templates::Column::Title,
+        notification_templates::Column::Body,
+        notification_templates::Column::UpdatedBy,
+        notification_templates::Column::UpdatedAt,
+      ])
+      .to_owned(),
+    )
+    .exec(&state.db)
+    .await
+    .map_err(|e| (server_error(e), String::new()))?;
+
+  let template = NotificationTemplates::find()
+    .filter(notification_templates::Column::Event.eq(event))
+    .filter(notification_templates::Column::Language.eq(language))
+    .one(&state.db)
+    .await
+    .map_err(|e| (server_error(e), String::new()))?
+    .ok_or((StatusCode::INTERNAL_SERVER_ERROR, String::new()))?;
+  log::info!(
+    "Notification template {:?} ({}) updated by {}",
+    template.event,
+    template.language,
+    claims.custom.id
+  );
+
+  Ok(Json(NotificationTemplateInfo::edited(template)))
+}
+
+#[utoipa::path(
+  delete,
+  path = "/notification-templates/{event}/{language}",
+  description = "Xóa mẫu thông báo do quản lý sửa, yêu cầu request có role là Manager. Sự kiện sẽ dùng lại mẫu có sẵn của ngôn ngữ,
+  hoặc mẫu tiếng Việt nếu ngôn ngữ không có mẫu có sẵn.",
+  tag = tags::MANAGER,
+  responses(
+    (status = NO_CONTENT, description = "Template removed"),
+    (status = NOT_FOUND, description = "Template was not edited"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn reset_template(
+  State(state): State<AppState>,
+  Path((event, language)): Path<(NotificationEvent, String)>,
+) -> Result<StatusCode, StatusCode> {
+  let result = NotificationTemplates::delete_many()
+    .filter(notification_templates::Column::Event.eq(event))
+    .filter(notification_templates::Column::Language.eq(language))
+    .exec(&state.db)
+    .await
+    .map_err(server_error)?;
+  if result.rows_affected == 0 {
+    return Err(StatusCode::NOT_FOUND);
+  }
+
+  Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn notice() -> Notice {
+    Notice::new(NotificationEvent::FeeAssigned)
+      .text("room", 101)
+      .text("fee_name", "Phí dịch vụ")
+      .amount("amount", 1_500_000)
+      .date(
+        "due_date",
+        chrono::NaiveDate::from_ymd_opt(2024, 3, 5).unwrap(),
+      )
+  }
+
+  #[test]
+  fn fill_replaces_placeholders() {
+    assert_eq!(
+      notice().fill("Phòng {room}: {fee_name} {amount}, hạn {due_date}", "vi"),
+      format!(
+        "Phòng 101: Phí dịch vụ {}, hạn 05/03/2024",
+        format_vnd(1_500_000)
+      )
+    );
+  }
+
+  #[test]
+  fn fill_repeats_placeholders() {
+    assert_eq!(notice().fill("{room}-{room}", "vi"), "101-101");
+  }
+
+  #[test]
+  fn fill_keeps_unknown_placeholders() {
+    assert_eq!(
+      notice().fill("{room} {reason} {Room} {}", "vi"),
+      "101 {reason} {Room} {}"
+    );
+  }
+
+  #[test]
+  fn fill_renders_labels_in_language() {
+    let notice = Notice::new(NotificationEvent::DeclarationApproved).label(
+      "kind",
+      "Tạm trú",
+      "Temporary stay",
+    );
+    assert_eq!(notice.fill("{kind}", "vi"), "Tạm trú");
+    assert_eq!(notice.fill("{kind}", "en"), "Temporary stay");
+    assert_eq!(notice.fill("{kind}", "fr"), "Temporary stay");
+  }
+}
@@ -0,0 +1,61 @@
+//! Forwards notifications as JSON to an HTTP endpoint, e.g. a Zalo or Telegram bot bridge.
+
+use crate::{
+  entities::{notification_preferences, users},
+  prelude::*,
+};
+
+use super::NotificationChannel;
+
+/// How long the endpoint has to answer, so that a slow bridge does not hold up the other channels
+const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+#[derive(Debug, Clone)]
+pub struct WebhookChannel {
+  client: reqwest::Client,
+  url: String,
+}
+
+impl WebhookChannel {
+  pub fn new(url: String) -> Self {
+    Self {
+      client: reqwest::Client::builder()
+        .timeout(TIMEOUT)
+        .build()
+        .expect("failed to build the webhook client"),
+      url,
+    }
+  }
+}
+
+#[async_trait::async_trait]
+impl NotificationChannel for WebhookChannel {
+  fn name(&self) -> &str {
+    "webhook"
+  }
+
+  fn opted_in(&self, preferences: &notification_preferences::Model) -> bool {
+    preferences.webhook
+  }
+
+  async fn send(&self, to: &users::Model, title: &str, message: &str) -> anyhow::Result<()> {
+    self
+      .client
+      .post(&self.url)
+      .json(&json!({
+        "to": {
+          "id": to.id,
+          "name": to.name,
+          "email": to.email,
+          "phone": to.phone,
+        },
+        "title": title,
+        "message": message,
+      }))
+      .send()
+      .await?
+      .error_for_status()?;
+
+    Ok(())
+  }
+}
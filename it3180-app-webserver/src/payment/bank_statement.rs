@@ -0,0 +1,90 @@
+use anyhow::Context;
+
+use super::{parse_transfer_date, PaymentGateway, TransferRecord};
+use crate::prelude::*;
+
+/// One line of a bank statement, in a bank-neutral format.
+/// Example in JSON:
+/// {
+///   "transaction_id": "FT24123456789",
+///   "bank": "BIDV",
+///   "transaction_date": "2024-12-01 08:30:00",
+///   "account_number": "0123499999",
+///   "amount": 200000,
+///   "balance": 19077000,
+///   "description": "FLATAPP12 thanh toan phi",
+///   "reference": "FT24123456789"
+/// }
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct BankStatementEntry {
+  /// Used to drop repeated notifications, the reference is used if it is missing. One of them is
+  /// required.
+  pub transaction_id: Option<String>,
+  pub bank: Option<String>,
+  pub transaction_date: String,
+  pub account_number: Option<String>,
+  /// Positive for credit, negative for debit
+  pub amount: i64,
+  pub balance: Option<i64>,
+  pub description: String,
+  pub reference: Option<String>,
+}
+
+impl BankStatementEntry {
+  pub fn into_record(self, provider: &str) -> anyhow::Result<TransferRecord> {
+    let transaction_date = parse_transfer_date(&self.transaction_date)
+      .with_context(|| format!("invalid transaction date: {}", self.transaction_date))?;
+    // without an id, a repeated notification would be settled again
+    let external_id = self
+      .transaction_id
+      .or(self.reference.clone())
+      .context("missing transaction id and reference")?;
+
+    Ok(TransferRecord {
+      provider: provider.to_string(),
+      external_id: Some(external_id),
+      gateway: self.bank.unwrap_or_else(|| provider.to_string()),
+      transaction_date,
+      account_number: self.account_number.unwrap_or_default(),
+      sub_account: None,
+      transfer_amount: self.amount,
+      accumulated: self.balance.unwrap_or_default(),
+      code: None,
+      content: self.description,
+      reference_code: self.reference,
+      description: None,
+    })
+  }
+}
+
+/// A generic gateway for banks that push statement lines as JSON, authenticated with an
+/// `X-Api-Key` header.
+#[derive(Debug, Clone)]
+pub struct BankStatementGateway {
+  provider: String,
+  api_key: String,
+}
+
+impl BankStatementGateway {
+  pub fn new(provider: &str, api_key: String) -> Self {
+    Self {
+      provider: provider.to_string(),
+      api_key,
+    }
+  }
+}
+
+impl PaymentGateway for BankStatementGateway {
+  fn authenticate(&self, headers: &HeaderMap, _body: &[u8]) -> bool {
+    headers
+      .get("X-Api-Key")
+      .and_then(|v| v.to_str().ok())
+      .is_some_and(|api_key| api_key == self.api_key)
+  }
+
+  fn parse(&self, body: &[u8]) -> anyhow::Result<TransferRecord> {
+    let entry: BankStatementEntry = serde_json::from_slice(body)?;
+    entry.into_record(&self.provider)
+  }
+}
@@ -0,0 +1,391 @@
+//! Payment gateway adapters and the settlement logic shared by all of them.
+//!
+//! Every gateway turns its own notification format into a [`TransferRecord`]. The record is
+//! stored in `transaction_logs` and then matched against a fee assignment using the
+//! `FLATAPP<assignment_id>` code in the transfer memo.
+
+pub mod bank_statement;
+pub mod sepay;
+
+use std::{
+  collections::HashMap,
+  sync::{Arc, LazyLock},
+};
+
+use regex::Regex;
+use sea_orm::{DatabaseTransaction, IntoActiveModel, TransactionTrait};
+
+use crate::{
+  entities::{fee_recurrence, fees, fees_room_assignment, transaction_logs, transactions},
+  ledger,
+  manager::{categories, history},
+  prelude::*,
+};
+
+/// A payment gateway that can notify the server about incoming bank transfers.
+pub trait PaymentGateway: std::fmt::Debug + Send + Sync {
+  /// Check that the notification was sent by the gateway.
+  fn authenticate(&self, headers: &HeaderMap, body: &[u8]) -> bool;
+
+  /// Parse the notification body into a transfer record.
+  fn parse(&self, body: &[u8]) -> anyhow::Result<TransferRecord>;
+}
+
+/// A bank transfer, normalised from whatever format the gateway uses.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TransferRecord {
+  /// The gateway that reported the transfer, e.g. `sepay`
+  pub provider: String,
+  /// The id of the transfer on the gateway side, used to drop repeated notifications
+  pub external_id: Option<String>,
+  /// The bank that handled the transfer
+  pub gateway: String,
+  pub transaction_date: chrono::NaiveDateTime,
+  pub account_number: String,
+  pub sub_account: Option<String>,
+  /// Positive for money coming in, negative for money going out
+  pub transfer_amount: i64,
+  pub accumulated: i64,
+  pub code: Option<String>,
+  pub content: String,
+  pub reference_code: Option<String>,
+  pub description: Option<String>,
+}
+
+/// The gateways known to the server, keyed by the `{provider}` part of the webhook URL.
+#[derive(Debug, Clone, Default)]
+pub struct GatewayRegistry(Arc<HashMap<String, Arc<dyn PaymentGateway>>>);
+
+impl GatewayRegistry {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn with(mut self, provider: &str, gateway: impl PaymentGateway + 'static) -> Self {
+    Arc::make_mut(&mut self.0).insert(provider.to_string(), Arc::new(gateway));
+    self
+  }
+
+  pub fn get(&self, provider: &str) -> Option<Arc<dyn PaymentGateway>> {
+    self.0.get(provider).cloned()
+  }
+}
+
+/// What happened to a transfer after it was matched against the fee assignments.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum SettlementOutcome {
+  /// The assignment was marked as paid
+  Settled {
+    assignment_id: i32,
+  },
+  /// The transfer was already logged before
+  Duplicate,
+  /// Money going out of the account, nothing to settle
+  Outgoing,
+  /// The memo has no valid payment code, or the code points to no assignment
+  NoMatch,
+  AlreadyPaid {
+    assignment_id: i32,
+  },
+  AmountMismatch {
+    assignment_id: i32,
+    expected: i64,
+    received: i64,
+  },
+}
+
+/// Log a transfer and settle the fee assignment it pays for.
+pub(crate) async fn settle_transfer(
+  db: &DatabaseConnection,
+  record: TransferRecord,
+) -> Result<SettlementOutcome, DbErr> {
+  // the log, the payment and what it is posted in the ledger are written together or not at all
+  let txn = db.begin().await?;
+  let outcome = settle(&txn, record).await?;
+  txn.commit().await?;
+
+  Ok(outcome)
+}
+
+async fn settle(
+  db: &DatabaseTransaction,
+  record: TransferRecord,
+) -> Result<SettlementOutcome, DbErr> {
+  // a gateway may send the same notification more than once
+  if let Some(external_id) = &record.external_id {
+    let existing = TransactionLogs::find()
+      .filter(transaction_logs::Column::Provider.eq(&record.provider))
+      .filter(transaction_logs::Column::ExternalId.eq(external_id))
+      .one(db)
+      .await?;
+    if existing.is_some() {
+      log::info!(
+        "Transfer {} from {} already logged",
+        external_id,
+        record.provider
+      );
+      return Ok(SettlementOutcome::Duplicate);
+    }
+  }
+
+  // insert transaction log
+  let transaction_log = transaction_logs::ActiveModel {
+    provider: Set(record.provider),
+    external_id: Set(record.external_id),
+    gateway: Set(record.gateway),
+    transaction_date: Set(record.transaction_date),
+    account_number: Set(record.account_number),
+    sub_account: Set(record.sub_account),
+    transfer_amount: Set(record.transfer_amount),
+    accumulated: Set(record.accumulated),
+    code: Set(record.code),
+    content: Set(record.content),
+    reference_code: Set(record.reference_code),
+    description: Set(record.description),
+    ..Default::default()
+  };
+  let transaction_log = transaction_log.insert(db).await?;
+
+  if transaction_log.transfer_amount <= 0 {
+    return Ok(SettlementOutcome::Outgoing);
+  }
+
+  let content = transaction_log.content;
+  let Some(code) = parse_payment_code(&content) else {
+    log::error!("Failed to parse code from content: {:?}", content);
+    return Ok(SettlementOutcome::NoMatch);
+  };
+
+  // get corresponding fee room assignment and fee
+  let fee_room_assignment = FeesRoomAssignment::find_by_id(code)
+    .find_also_related(Fees)
+    .one(db)
+    .await?;
+  let Some((assignment, Some(fee))) = fee_room_assignment else {
+    log::error!("Fee room assignment not found: {:?}", code);
+    return Ok(SettlementOutcome::NoMatch);
+  };
+
+  // check if payment is valid
+  if assignment.is_paid {
+    log::info!("Fee room assignment already paid: {:?}", code);
+    return Ok(SettlementOutcome::AlreadyPaid {
+      assignment_id: code,
+    });
+  }
+  // the room owes what it was billed, even if the fee was edited since
+  if assignment.amount != transaction_log.transfer_amount {
+    log::error!(
+      "Amount mismatch: expected {}, got {}",
+      assignment.amount,
+      transaction_log.transfer_amount
+    );
+    return Ok(SettlementOutcome::AmountMismatch {
+      assignment_id: code,
+      expected: assignment.amount,
+      received: transaction_log.transfer_amount,
+    });
+  }
+
+  let settled = SettlementOutcome::Settled {
+    assignment_id: code,
+  };
+
+  // update assignment
+  let transaction_date = transaction_log.transaction_date;
+  let mut assignment = assignment.into_active_model();
+  assignment.is_paid = Set(true);
+  assignment.payment_date = Set(Some(transaction_date));
+  let assignment = assignment.save(db).await?;
+
+  let new_transaction = transactions::ActiveModel {
+    amount: Set(transaction_log.transfer_amount),
+    created_at: Set(transaction_date),
+    assignment_id: Set(code),
+    ..Default::default()
+  };
+  let new_transaction = new_transaction.insert(db).await?;
+  let room_number = assignment.room_number.clone().unwrap();
+  ledger::post_payment(
+    db,
+    new_transaction.id,
+    code,
+    room_number,
+    new_transaction.amount,
+    ledger::BANK,
+  )
+  .await?;
+
+  // an archived fee does not recur anymore
+  if fee.archived_at.is_some() {
+    return Ok(settled);
+  }
+
+  // check if the fee is in a recurrence chain and assign the next fee to the room
+  let next_recurrence = FeeRecurrence::find()
+    .filter(fee_recurrence::Column::PreviousFeeId.eq(fee.id))
+    .filter(fee_recurrence::Column::FeeId.ne(fee.id))
+    .one(db)
+    .await?;
+  if let Some(next_recurrence) = next_recurrence {
+    if let Some(next_fee) = Fees::find_by_id(next_recurrence.fee_id).one(db).await? {
+      // assign the next fee to the current room
+      let new_fee = fees_room_assignment::ActiveModel {
+        room_number: assignment.room_number.clone(),
+        fee_id: Set(next_recurrence.fee_id),
+        due_date: Set(next_recurrence.due_date),
+        fee_name: Set(next_fee.name.clone()),
+        amount: Set(next_fee.amount),
+        ..Default::default()
+      };
+      let new_assignment = new_fee.insert(db).await?;
+      ledger::post_charge(db, &new_assignment, &next_fee.category, None).await?;
+    }
+    return Ok(settled);
+  }
+
+  // check if the fee is the last in a recurrence chain and create a new fee for the next due date
+  let recurrence_entry = FeeRecurrence::find()
+    .filter(fee_recurrence::Column::FeeId.eq(fee.id))
+    .one(db)
+    .await?;
+  if recurrence_entry.is_some() {
+    // create a new fee for the next due date
+    let new_due_date = fee.due_date
+      + match fee.recurrence_type.as_ref().unwrap() {
+        RecurrenceType::Weekly => chrono::Duration::days(7),
+        RecurrenceType::Monthly => chrono::Duration::days(30),
+        RecurrenceType::Yearly => chrono::Duration::days(365),
+      };
+    let new_fee = fees::ActiveModel {
+      name: Set(fee.name.clone()),
+      amount: Set(fee.amount),
+      is_required: Set(fee.is_required),
+      created_at: Set(chrono::Utc::now().naive_utc()),
+      is_recurring: Set(true),
+      due_date: Set(new_due_date),
+      recurrence_type: Set(fee.recurrence_type.clone()),
+      category: Set(fee.category.clone()),
+      ..Default::default()
+    };
+    let new_fee = new_fee.insert(db).await?;
+    history::record_fee_version(db, &new_fee, None).await?;
+    categories::copy_fee_tags(db, fee.id, new_fee.id).await?;
+    let new_fee_id = new_fee.id;
+    // assign the new fee to the room
+    let new_assignment = fees_room_assignment::ActiveModel {
+      room_number: assignment.room_number.clone(),
+      fee_id: Set(new_fee_id),
+      due_date: Set(new_due_date),
+      fee_name: Set(new_fee.name.clone()),
+      amount: Set(new_fee.amount),
+      ..Default::default()
+    };
+    let new_assignment = new_assignment.insert(db).await?;
+    ledger::post_charge(db, &new_assignment, &new_fee.category, None).await?;
+    // create a new recurrence chain
+    let new_recurrence = fee_recurrence::ActiveModel {
+      fee_id: Set(new_fee_id),
+      previous_fee_id: Set(fee.id),
+      due_date: Set(new_due_date),
+      ..Default::default()
+    };
+    new_recurrence.save(db).await?;
+  }
+
+  Ok(settled)
+}
+
+static PAYMENT_CODE_PATTERN: LazyLock<Regex> =
+  LazyLock::new(|| Regex::new(r"FLATAPP(?<code>[0-9]+)").unwrap());
+
+/// Find the `FLATAPP<assignment_id>` code in a transfer memo.
+pub(crate) fn parse_payment_code(content: &str) -> Option<i32> {
+  PAYMENT_CODE_PATTERN
+    .captures(content)
+    .and_then(|c| c.name("code").map(|m| m.as_str().parse::<i32>().ok()))
+    .flatten()
+}
+
+/// Parse a transfer date in one of the formats banks commonly use.
+pub(crate) fn parse_transfer_date(date: &str) -> Option<chrono::NaiveDateTime> {
+  let date = date.trim();
+  if let Ok(date) = chrono::DateTime::parse_from_rfc3339(date) {
+    return Some(date.naive_utc());
+  }
+  for format in ["%Y-%m-%d %H:%M:%S", "%d/%m/%Y %H:%M:%S", "%d/%m/%Y %H:%M"] {
+    if let Ok(date) = chrono::NaiveDateTime::parse_from_str(date, format) {
+      return Some(date);
+    }
+  }
+  for format in ["%Y-%m-%d", "%d/%m/%Y", "%d-%m-%Y"] {
+    if let Ok(date) = chrono::NaiveDate::parse_from_str(date, format) {
+      return date.and_hms_opt(0, 0, 0);
+    }
+  }
+  None
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn datetime(y: i32, m: u32, d: u32, h: u32, min: u32, s: u32) -> chrono::NaiveDateTime {
+    chrono::NaiveDate::from_ymd_opt(y, m, d)
+      .unwrap()
+      .and_hms_opt(h, min, s)
+      .unwrap()
+  }
+
+  #[test]
+  fn transfer_date_with_time() {
+    assert_eq!(
+      parse_transfer_date("2024-03-05 14:30:15"),
+      Some(datetime(2024, 3, 5, 14, 30, 15))
+    );
+    assert_eq!(
+      parse_transfer_date("05/03/2024 14:30:15"),
+      Some(datetime(2024, 3, 5, 14, 30, 15))
+    );
+    assert_eq!(
+      parse_transfer_date("05/03/2024 14:30"),
+      Some(datetime(2024, 3, 5, 14, 30, 0))
+    );
+  }
+
+  #[test]
+  fn transfer_date_in_rfc3339_is_converted_to_utc() {
+    assert_eq!(
+      parse_transfer_date("2024-03-05T14:30:15+07:00"),
+      Some(datetime(2024, 3, 5, 7, 30, 15))
+    );
+  }
+
+  #[test]
+  fn transfer_date_without_time_is_midnight() {
+    for date in ["2024-03-05", "05/03/2024", "05-03-2024", " 05/03/2024 "] {
+      assert_eq!(
+        parse_transfer_date(date),
+        Some(datetime(2024, 3, 5, 0, 0, 0))
+      );
+    }
+  }
+
+  #[test]
+  fn invalid_transfer_date() {
+    assert_eq!(parse_transfer_date(""), None);
+    assert_eq!(parse_transfer_date("32/01/2024"), None);
+    assert_eq!(parse_transfer_date("yesterday"), None);
+  }
+
+  #[test]
+  fn payment_code_in_memo() {
+    assert_eq!(
+      parse_payment_code("NGUYEN VAN A FLATAPP42 chuyen tien"),
+      Some(42)
+    );
+    assert_eq!(parse_payment_code("FLATAPP"), None);
+    assert_eq!(parse_payment_code("chuyen tien phong 101"), None);
+  }
+}
@@ -0,0 +1,87 @@
+use super::{PaymentGateway, TransferRecord};
+use crate::prelude::*;
+
+/// The payload sent by the SePay webhook
+/// Example in JSON:
+/// {
+///   "id": 3,
+///   "gateway": "Vietcombank",
+///   "transactionDate": "2023-03-25 00:00:01",
+///   "accountNumber": "0123499999",
+///   "code": null,
+///   "content": "TSF3",
+///   "transferType": "in",
+///   "transferAmount": 2277000,
+///   "accumulated": 19077000,
+///   "subAccount": null,
+///   "referenceCode": "MBVCB.3278907687",
+///   "description": ""
+/// }
+#[derive(serde::Serialize, serde::Deserialize, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SepayPayload {
+  pub id: i32,
+  pub gateway: String,
+  pub transaction_date: String,
+  pub account_number: String,
+  pub sub_account: Option<String>,
+  pub transfer_type: Option<String>,
+  pub transfer_amount: i64,
+  pub accumulated: i64,
+  pub code: Option<String>,
+  pub content: String,
+  pub reference_code: Option<String>,
+  pub description: Option<String>,
+}
+
+/// SePay, authenticated with an `Authorization: Apikey <api_key>` header.
+#[derive(Debug, Clone)]
+pub struct SepayGateway {
+  api_key: String,
+}
+
+impl SepayGateway {
+  pub fn new(api_key: String) -> Self {
+    Self { api_key }
+  }
+}
+
+impl PaymentGateway for SepayGateway {
+  fn authenticate(&self, headers: &HeaderMap, _body: &[u8]) -> bool {
+    // header format: "Authorization":"Apikey <api_key>"
+    let auth = headers.get("Authorization").and_then(|v| v.to_str().ok());
+    match auth {
+      Some(auth) if auth.starts_with("Apikey ") => {
+        auth.trim_start_matches("Apikey ") == self.api_key
+      }
+      _ => false,
+    }
+  }
+
+  fn parse(&self, body: &[u8]) -> anyhow::Result<TransferRecord> {
+    let payload: SepayPayload = serde_json::from_slice(body)?;
+
+    let transaction_date =
+      chrono::NaiveDateTime::parse_from_str(&payload.transaction_date, "%Y-%m-%d %H:%M:%S")
+        .unwrap_or(chrono::Utc::now().naive_utc());
+    let transfer_amount = match payload.transfer_type.as_deref() {
+      Some("out") => -payload.transfer_amount,
+      _ => payload.transfer_amount,
+    };
+
+    Ok(TransferRecord {
+      provider: "sepay".to_string(),
+      external_id: Some(payload.id.to_string()),
+      gateway: payload.gateway,
+      transaction_date,
+      account_number: payload.account_number,
+      sub_account: payload.sub_account,
+      transfer_amount,
+      accumulated: payload.accumulated,
+      code: payload.code,
+      content: payload.content,
+      reference_code: payload.reference_code,
+      description: payload.description,
+    })
+  }
+}
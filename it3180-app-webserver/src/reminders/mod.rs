@@ -0,0 +1,353 @@
+//! Reminders for unpaid fees, sent at configured stages around the due date.
+//!
+//! A stage is a number of days relative to the due date, negative before it. Every assignment
+//! gets at most one reminder per stage, recorded in `payment_reminders`. When the server was
+//! down over several stages, only the latest one is sent.
+
+use std::collections::{HashMap, HashSet};
+
+use sea_orm::sea_query::OnConflict;
+
+use crate::{
+  entities::{fees_room_assignment, payment_reminders},
+  notify::{default_sender, templates::Notice},
+  prelude::*,
+};
+
+/// How far from the due date a stage can be, in days
+pub const MAX_STAGE_DAYS: i32 = 3650;
+
+#[derive(Debug, Clone)]
+pub struct ReminderConfig {
+  /// Days relative to the due date, sorted
+  pub stages: Vec<i32>,
+  /// How often the scheduler looks for reminders to send, disabled if zero
+  pub interval: std::time::Duration,
+}
+
+impl Default for ReminderConfig {
+  fn default() -> Self {
+    Self {
+      stages: vec![-7, 0, 3, 10],
+      interval: std::time::Duration::from_secs(60 * 60),
+    }
+  }
+}
+
+impl ReminderConfig {
+  /// Parse the stages from a comma separated list such as `-7,0,3,10`.
+  pub fn parse(stages: &str, interval_minutes: u64) -> anyhow::Result<Self> {
+    let mut stages = stages
+      .split(',')
+      .filter(|stage| !stage.trim().is_empty())
+      .map(|stage| stage.trim().parse::<i32>())
+      .collect::<Result<Vec<_>, _>>()?;
+    if let Some(stage) = stages
+      .iter()
+      .find(|stage| !(-MAX_STAGE_DAYS..=MAX_STAGE_DAYS).contains(*stage))
+    {
+      anyhow::bail!("reminder stage {stage} is more than {MAX_STAGE_DAYS} days from the due date");
+    }
+    stages.sort_unstable();
+    stages.dedup();
+
+    Ok(Self {
+      stages,
+      interval: std::time::Duration::from_secs(interval_minutes * 60),
+    })
+  }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct ReminderRunReport {
+  pub sent: usize,
+  /// Assignments whose current stage was already reminded
+  pub already_sent: usize,
+  pub failed: usize,
+}
+
+/// The stage an assignment is at on `today`: the last one whose date has passed.
+fn current_stage(
+  stages: &[i32],
+  due_date: chrono::NaiveDateTime,
+  today: chrono::NaiveDate,
+) -> Option<i32> {
+  let days_past_due = (today - due_date.date()).num_days();
+  stages
+    .iter()
+    .rev()
+    .find(|stage| i64::from(**stage) <= days_past_due)
+    .copied()
+}
+
+fn reminder_notice(assignment: &fees_room_assignment::Model, stage: i32) -> Notice {
+  let event = match stage {
+    ..0 => NotificationEvent::PaymentReminderUpcoming,
+    0 => NotificationEvent::PaymentReminderDue,
+    _ => NotificationEvent::PaymentReminderOverdue,
+  };
+
+  Notice::new(event)
+    .text("room", assignment.room_number)
+    .text("fee_name", &assignment.fee_name)
+    .amount("amount", assignment.amount)
+    .date("due_date", assignment.due_date.date())
+    .text("days", stage.abs())
+    .text("reference", format!("FLATAPP{}", assignment.assignment_id))
+}
+
+/// Send the reminders that are due. Reminders come from `sender`, or from the first active
+/// manager if not given.
+pub(crate) async fn send_reminders(
+  state: &AppState,
+  sender: Option<i32>,
+) -> Result<ReminderRunReport, DbErr> {
+  let db = &state.db;
+  let stages = &state.reminders.stages;
+  let mut report = ReminderRunReport::default();
+  let Some(first_stage) = stages.first() else {
+    return Ok(report);
+  };
+
+  let sender = match sender {
+    Some(sender) => sender,
+    None => match default_sender(db).await? {
+      Some(manager) => manager,
+      None => {
+        log::warn!("No active manager to send payment reminders from");
+        return Ok(report);
+      }
+    },
+  };
+
+  // unpaid assignments that reached at least the first stage
+  let today = chrono::Utc::now().naive_utc().date();
+  let Some(horizon) = today
+    .checked_add_signed(chrono::Duration::days(1 - i64::from(*first_stage)))
+    .and_then(|horizon| horizon.and_hms_opt(0, 0, 0))
+  else {
+    log::warn!("Reminder stage {} is out of range", first_stage);
+    return Ok(report);
+  };
+  let assignments = FeesRoomAssignment::find()
+    .filter(fees_room_assignment::Column::IsPaid.eq(false))
+    .filter(fees_room_assignment::Column::DueDate.lt(horizon))
+    .all(db)
+    .await?;
+  if assignments.is_empty() {
+    return Ok(report);
+  }
+
+  let tenants = Rooms::find()
+    .find_also_related(Users)
+    .all(db)
+    .await?
+    .into_iter()
+    .filter_map(|(room, tenant)| Some((room.room_number, tenant?)))
+    .collect::<HashMap<_, _>>();
+
+  let mut reminded = HashSet::new();
+  let assignment_ids = assignments
+    .iter()
+    .map(|assignment| assignment.assignment_id)
+    .collect::<Vec<_>>();
+  for chunk in assignment_ids.chunks(1000) {
+    let reminders = PaymentReminders::find()
+      .filter(payment_reminders::Column::AssignmentId.is_in(chunk.to_vec()))
+      .all(db)
+      .await?;
+    reminded.extend(
+      reminders
+        .into_iter()
+        .map(|reminder| (reminder.assignment_id, reminder.offset_days)),
+    );
+  }
+
+  for assignment in assignments {
+    let Some(stage) = current_stage(stages, assignment.due_date, today) else {
+      continue;
+    };
+    if reminded.contains(&(assignment.assignment_id, stage)) {
+      report.already_sent += 1;
+      continue;
+    }
+    let Some(tenant) = tenants.get(&assignment.room_number) else {
+      continue;
+    };
+
+    // claim the stage before sending, so that concurrent runs never send it twice
+    let reminder = payment_reminders::ActiveModel {
+      assignment_id: Set(assignment.assignment_id),
+      offset_days: Set(stage),
+      sent_at: Set(chrono::Utc::now().naive_utc()),
+      ..Default::default()
+    };
+    let claimed = PaymentReminders::insert(reminder)
+      .on_conflict(
+        OnConflict::columns([
+          payment_reminders::Column::AssignmentId,
+          payment_reminders::Column::OffsetDays,
+        ])
+        .do_nothing()
+        .to_owned(),
+      )
+      .exec_without_returning(db)
+      .await?;
+    if claimed == 0 {
+      report.already_sent += 1;
+      continue;
+    }
+
+    let notice = reminder_notice(&assignment, stage);
+    match state
+      .notifier
+      .notify_event(db, sender, tenant, &notice)
+      .await
+    {
+      Ok(_) => report.sent += 1,
+      Err(e) => {
+        log::error!(
+          "Failed to send reminder for assignment {}: {:?}",
+          assignment.assignment_id,
+          e
+        );
+        report.failed += 1;
+        // release the stage so that the next run tries again
+        PaymentReminders::delete_many()
+          .filter(payment_reminders::Column::AssignmentId.eq(assignment.assignment_id))
+          .filter(payment_reminders::Column::OffsetDays.eq(stage))
+          .exec(db)
+          .await?;
+      }
+    }
+  }
+
+  Ok(report)
+}
+
+/// Run [`send_reminders`] in the background at the configured interval.
+pub(crate) fn spawn_scheduler(state: AppState) {
+  if state.reminders.interval.is_zero() || state.reminders.stages.is_empty() {
+    log::info!("Payment reminders are disabled");
+    return;
+  }
+
+  tokio::spawn(async move {
+    let mut interval = tokio::time::interval(state.reminders.interval);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    loop {
+      interval.tick().await;
+      match send_reminders(&state, None).await {
+        Ok(report) if report.sent > 0 || report.failed > 0 => {
+          log::info!(
+            "Payment reminders: {} sent, {} failed",
+            report.sent,
+            report.failed
+          );
+        }
+        Ok(_) => {}
+        Err(e) => log::error!("Failed to send payment reminders: {:?}", e),
+      }
+    }
+  });
+}
+
+#[utoipa::path(
+  post,
+  path = "/reminders/run",
+  description = "Gửi ngay các thông báo nhắc nhở thanh toán đến hạn (trước hạn, đúng hạn và quá hạn theo cấu hình), yêu cầu request có role là Manager.
+  Mỗi khoản phí chỉ được nhắc một lần ở mỗi mốc. Trả về số thông báo đã gửi.",
+  tag = tags::MANAGER,
+  responses(
+    (status = OK, description = "Reminders sent", body = ReminderRunReport),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = FORBIDDEN, description = "Forbidden"),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn run_reminders(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+) -> Result<Json<ReminderRunReport>, StatusCode> {
+  let claims = state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+  let report = send_reminders(&state, Some(claims.custom.id))
+    .await
+    .map_err(|e| {
+      log::error!("Error: {:?}", e);
+      StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+  Ok(Json(report))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn stages_are_sorted_and_deduplicated() {
+    let config = ReminderConfig::parse(" 10, 3,-7 ,0,3,", 30).unwrap();
+    assert_eq!(config.stages, vec![-7, 0, 3, 10]);
+    assert_eq!(config.interval, std::time::Duration::from_secs(30 * 60));
+  }
+
+  #[test]
+  fn no_stages() {
+    assert!(ReminderConfig::parse("", 60).unwrap().stages.is_empty());
+    assert!(ReminderConfig::parse(" , ", 60).unwrap().stages.is_empty());
+  }
+
+  #[test]
+  fn invalid_stage() {
+    assert!(ReminderConfig::parse("-7,soon", 60).is_err());
+    assert!(ReminderConfig::parse("1.5", 60).is_err());
+  }
+
+  #[test]
+  fn stage_too_far_from_due_date() {
+    assert!(ReminderConfig::parse("-3650,3650", 60).is_ok());
+    assert!(ReminderConfig::parse("0,3651", 60).is_err());
+    assert!(ReminderConfig::parse("-3651", 60).is_err());
+    assert!(ReminderConfig::parse("-9223372036854775808", 60).is_err());
+  }
+
+  #[test]
+  fn stage_is_the_last_one_reached() {
+    let stages = [-7, 0, 3, 10];
+    let due_date = chrono::NaiveDate::from_ymd_opt(2024, 3, 10)
+      .unwrap()
+      .and_hms_opt(17, 0, 0)
+      .unwrap();
+    let stage_on = |month, day| {
+      current_stage(
+        &stages,
+        due_date,
+        chrono::NaiveDate::from_ymd_opt(2024, month, day).unwrap(),
+      )
+    };
+
+    assert_eq!(stage_on(3, 2), None);
+    assert_eq!(stage_on(3, 3), Some(-7));
+    assert_eq!(stage_on(3, 9), Some(-7));
+    assert_eq!(stage_on(3, 10), Some(0));
+    assert_eq!(stage_on(3, 12), Some(0));
+    assert_eq!(stage_on(3, 13), Some(3));
+    assert_eq!(stage_on(4, 30), Some(10));
+  }
+
+  #[test]
+  fn no_stage_without_stages() {
+    let due_date = chrono::NaiveDate::from_ymd_opt(2024, 3, 10)
+      .unwrap()
+      .and_hms_opt(0, 0, 0)
+      .unwrap();
+    assert_eq!(current_stage(&[], due_date, due_date.date()), None);
+  }
+}
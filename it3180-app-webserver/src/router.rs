@@ -54,6 +54,9 @@ pub(crate) fn create_router(state: crate::AppState) -> Router {
   let authenticate_router = OpenApiRouter::new()
     .routes(routes!(authenticate::account_login))
     .routes(routes!(authenticate::account_register))
+    .routes(routes!(
+      authenticate::invitation::account_register_invitation
+    ))
     .routes(routes!(authenticate::recover_password::recover_password))
     .routes(routes!(grant_new_access_token))
     .routes(routes!(authenticate::account_logout));
@@ -63,13 +66,47 @@ pub(crate) fn create_router(state: crate::AppState) -> Router {
     .routes(routes!(user::update_password))
     .routes(routes!(user::get_user_role))
     .routes(routes!(user::get_notifications))
+    .routes(routes!(crate::notify::live::notification_stream))
+    .routes(routes!(user::get_unread_count))
+    .routes(routes!(user::mark_notification_read))
+    .routes(routes!(user::mark_all_notifications_read))
+    .routes(routes!(user::delete_notification))
+    .routes(routes!(
+      user::get_notification_preferences,
+      user::update_notification_preferences
+    ))
     .routes(routes!(check_token))
     .routes(routes!(crate::household::get_household_info))
+    .routes(routes!(
+      crate::household::members::get_room_members,
+      crate::household::members::invite_room_member
+    ))
+    .routes(routes!(crate::household::members::remove_room_member))
+    .routes(routes!(crate::household::members::get_invitations))
+    .routes(routes!(crate::household::members::accept_invitation))
+    .routes(routes!(crate::household::members::decline_invitation))
+    .routes(routes!(
+      crate::household::declarations::get_declarations,
+      crate::household::declarations::add_declaration
+    ))
+    .routes(routes!(crate::household::declarations::cancel_declaration))
+    .routes(routes!(
+      crate::household::vehicles::get_vehicles,
+      crate::household::vehicles::register_vehicle
+    ))
+    .routes(routes!(crate::household::vehicles::remove_vehicle))
     // .routes(routes!(crate::household::pay_fee))
     .routes(routes!(
       crate::family::get_family_members,
       crate::family::add_family_member
     ))
+    .routes(routes!(
+      crate::family::update_family_member,
+      crate::family::remove_family_member
+    ))
+    .routes(routes!(crate::documents::get_household_documents))
+    .routes(routes!(crate::documents::download_household_document))
+    .routes(routes!(crate::documents::download_household_receipt))
     .layer(middleware::from_fn_with_state(
       state.clone(),
       crate::middleware::validate_request,
@@ -95,10 +132,98 @@ pub(crate) fn create_router(state: crate::AppState) -> Router {
       crate::manager::get_one_fee,
       crate::manager::edit_fee_info
     ))
+    .routes(routes!(crate::manager::archive_fee))
+    .routes(routes!(crate::manager::history::get_fee_history))
+    .routes(routes!(crate::manager::restore_fee))
     .routes(routes!(crate::manager::assign_fee))
-    .routes(routes!(crate::manager::get_rooms))
+    .routes(routes!(crate::manager::assignments::unassign_fee))
+    .routes(routes!(crate::manager::assignments::replace_fee_rooms))
+    .routes(routes!(crate::manager::assignments::move_assignment))
+    .routes(routes!(crate::manager::categories::get_fee_tags))
+    .routes(routes!(crate::manager::targets::assign_fee_to_target))
+    .routes(routes!(crate::manager::targets::get_fee_rules))
+    .routes(routes!(crate::manager::targets::remove_fee_rule))
+    .routes(routes!(crate::manager::targets::run_rule_sync))
+    .routes(routes!(
+      crate::manager::groups::get_room_groups,
+      crate::manager::groups::add_room_group
+    ))
+    .routes(routes!(
+      crate::manager::groups::edit_room_group,
+      crate::manager::groups::remove_room_group
+    ))
+    .routes(routes!(
+      crate::manager::get_rooms,
+      crate::manager::registry::add_room
+    ))
     .routes(routes!(crate::manager::get_rooms_detailed))
+    .routes(routes!(crate::manager::registry::get_room_registry))
+    .routes(routes!(
+      crate::manager::registry::get_room,
+      crate::manager::registry::edit_room,
+      crate::manager::registry::remove_room
+    ))
+    .routes(routes!(crate::manager::registry::import_rooms))
+    .routes(routes!(
+      crate::manager::residents::get_members,
+      crate::manager::residents::add_member
+    ))
+    .routes(routes!(crate::manager::residents::remove_member))
+    .routes(routes!(crate::manager::occupancy::move_in))
+    .routes(routes!(crate::manager::occupancy::move_out))
+    .routes(routes!(crate::manager::occupancy::get_room_occupancy))
+    .routes(routes!(crate::manager::occupancy::get_assignment_occupants))
+    .routes(routes!(crate::manager::claims::get_room_claims))
+    .routes(routes!(crate::manager::claims::approve_room_claim))
+    .routes(routes!(crate::manager::claims::reject_room_claim))
+    .routes(routes!(
+      crate::manager::invitations::create_invitation,
+      crate::manager::invitations::get_invitations
+    ))
+    .routes(routes!(crate::manager::invitations::revoke_invitation))
+    .routes(routes!(crate::manager::declarations::get_declarations))
+    .routes(routes!(crate::manager::declarations::approve_declaration))
+    .routes(routes!(crate::manager::declarations::reject_declaration))
+    .routes(routes!(
+      crate::manager::declarations::get_declaration_register
+    ))
+    .routes(routes!(crate::manager::declarations::run_expiry_alerts))
+    .routes(routes!(crate::manager::census::get_census_residents))
+    .routes(routes!(crate::manager::census::get_census_statistics))
+    .routes(routes!(crate::manager::vehicles::get_vehicles))
+    .routes(routes!(crate::manager::vehicles::approve_vehicle))
+    .routes(routes!(crate::manager::vehicles::reject_vehicle))
+    .routes(routes!(crate::manager::vehicles::update_parking_slot))
+    .routes(routes!(crate::manager::vehicles::remove_vehicle))
     .routes(routes!(crate::manager::send_notification))
+    .routes(routes!(crate::notify::delivery::get_deliveries))
+    .routes(routes!(crate::notify::delivery::retry_delivery))
+    .routes(routes!(crate::notify::templates::get_templates))
+    .routes(routes!(
+      crate::notify::templates::update_template,
+      crate::notify::templates::reset_template
+    ))
+    .routes(routes!(crate::manager::import::import_bank_statement))
+    .routes(routes!(crate::manager::reports::report_by_fee))
+    .routes(routes!(crate::manager::reports::report_by_category))
+    .routes(routes!(crate::manager::reports::report_by_room))
+    .routes(routes!(crate::manager::reports::report_by_month))
+    .routes(routes!(crate::manager::reports::report_cash_flow))
+    .routes(routes!(crate::manager::reports::report_aging_by_room))
+    .routes(routes!(crate::manager::reports::report_aging_by_fee))
+    .routes(routes!(crate::documents::generate_invoices))
+    .routes(routes!(crate::documents::get_documents))
+    .routes(routes!(crate::documents::download_document))
+    .routes(routes!(crate::documents::download_receipt))
+    .routes(routes!(crate::reminders::run_reminders))
+    .routes(routes!(crate::ledger::get_accounts))
+    .routes(routes!(
+      crate::ledger::get_entries,
+      crate::ledger::add_entry
+    ))
+    .routes(routes!(crate::ledger::get_trial_balance))
+    .routes(routes!(crate::ledger::get_integrity))
+    .routes(routes!(crate::ledger::backfill_ledger))
     .layer(middleware::from_fn_with_state(
       state.clone(),
       crate::middleware::manager_middleware,
@@ -110,7 +235,11 @@ pub(crate) fn create_router(state: crate::AppState) -> Router {
 
   let (auth_router, api) = OpenApiRouter::with_openapi(ApiDoc::openapi())
     .routes(routes!(health_check))
-    .routes(routes!(crate::webhook::webhook_payment_handler))
+    .routes(routes!(
+      crate::webhook::webhook_payment_handler,
+      crate::webhook::legacy_check_payment
+    ))
+    .routes(routes!(crate::webhook::legacy_webhook_payment_handler))
     .routes(routes!(crate::webhook::check_payment))
     .nest("/auth", authenticate_router)
     .nest("/user", user_router)
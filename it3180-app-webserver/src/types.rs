@@ -41,9 +41,22 @@ pub struct RegisterInfo {
   pub password: String,
   #[schema(value_type = String, examples("manager", "tenant"))]
   pub role: UserRole,
+  /// Room claimed by a tenant, given once a manager approves the claim
   pub room_id: Option<i32>,
 }
 
+/// Represents a registration through a room invitation, the room and role come from the token
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct InvitationRegisterInfo {
+  pub token: String,
+  pub name: String,
+  pub username: String,
+  pub email: String,
+  pub phone: String,
+  pub password: String,
+}
+
 /// Represents an access token refresh request
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ToSchema)]
 pub struct RefreshTokenRequest {
@@ -83,3 +96,11 @@ pub(crate) struct RefreshTokenClaims {
   pub role: UserRole,
   pub refresh_token_version: i32,
 }
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct RoomInvitationClaims {
+  pub invitation_id: i32,
+  pub room_number: i32,
+  pub role: RoomMemberRole,
+}
@@ -2,10 +2,11 @@ pub mod types;
 
 use crate::prelude::*;
 
-use crate::entities::{notifications, users};
+use crate::entities::{notification_preferences, notifications, users};
 
-use sea_orm::sea_query::Alias;
-use sea_orm::{FromQueryResult, QuerySelect};
+use axum_extra::extract::Query;
+use sea_orm::sea_query::{Alias, OnConflict};
+use sea_orm::{FromQueryResult, Order, PaginatorTrait, QueryOrder, QuerySelect};
 use tags::USER;
 
 #[utoipa::path(
@@ -343,17 +344,81 @@ pub struct NotificationInfo {
   pub title: String,
   pub message: String,
   pub created_at: chrono::NaiveDateTime,
+  pub from_user: i32,
   pub from: String,
   pub to: String,
+  /// Empty while the notification is unread
+  pub read_at: Option<chrono::NaiveDateTime>,
+}
+
+const DEFAULT_NOTIFICATION_PAGE: u64 = 50;
+const MAX_NOTIFICATION_PAGE: u64 = 200;
+
+/// Header with the cursor of the next page of notifications.
+pub const NEXT_CURSOR_HEADER: &str = "x-next-cursor";
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct NotificationParams {
+  /// The `x-next-cursor` header of the previous page, notifications older than it are returned
+  pub cursor: Option<i32>,
+  /// Defaults to 50, at most 200
+  pub limit: Option<u64>,
+  /// Only the unread notifications
+  #[serde(default)]
+  pub unread: bool,
+  /// Id of the sender
+  pub sender: Option<i32>,
+  /// Sent on or after this day
+  pub from: Option<chrono::NaiveDate>,
+  /// Sent on or before this day
+  pub to: Option<chrono::NaiveDate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct UnreadCount {
+  pub unread: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MarkedRead {
+  pub updated: u64,
+}
+
+/// Channels outside of the app that the user receives notifications through
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct NotificationPreferencesInfo {
+  pub email: bool,
+  pub sms: bool,
+  /// The webhook channel, e.g. a Zalo or Telegram bot, if the server has one
+  #[serde(default = "default_webhook")]
+  pub webhook: bool,
+  /// Language of the automated notifications, e.g. `vi` or `en`
+  #[serde(default = "default_language")]
+  pub language: String,
+}
+
+fn default_webhook() -> bool {
+  true
+}
+
+fn default_language() -> String {
+  crate::notify::templates::DEFAULT_LANGUAGE.to_string()
 }
 
 #[utoipa::path(
   get,
   path = "/notifications",
-  description = "Lấy thông báo của người dùng, yêu cầu request có token ở header. Trả về thông báo dưới dạng JSON.",
+  description = "Lấy thông báo của người dùng, mới nhất trước, yêu cầu request có token ở header. Trả về tối đa `limit` thông báo dưới dạng JSON.
+  Nếu còn thông báo cũ hơn, header `x-next-cursor` chứa giá trị `cursor` để lấy trang tiếp theo. Có thể lọc các thông báo chưa đọc `unread`,
+  theo người gửi `sender` và theo ngày gửi `from` - `to`.",
   tag = USER,
+  params(
+    NotificationParams
+  ),
   responses(
-    (status = OK, description = "User notifications", body = Vec<notifications::Model>),
+    (status = OK, description = "User notifications", body = Vec<NotificationInfo>,
+      headers(("x-next-cursor" = i32, description = "Cursor of the next page, if there is one"))),
+    (status = BAD_REQUEST, description = "Invalid limit", body = String),
     (status = UNAUTHORIZED, description = "Invalid token", body = String),
     (status = INTERNAL_SERVER_ERROR, description = "Server error", body = String),
   ),
@@ -364,7 +429,8 @@ pub struct NotificationInfo {
 pub async fn get_notifications(
   State(state): State<AppState>,
   TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
-) -> Result<Json<Vec<NotificationInfo>>, StatusCode> {
+  Query(params): Query<NotificationParams>,
+) -> Result<impl IntoResponse, StatusCode> {
   let jwt_access_secret = &state.jwt_access_secret;
   let claims = match jwt_access_secret.verify_token::<AccessTokenClaims>(&bearer.token(), None) {
     Ok(claims) => claims,
@@ -374,9 +440,36 @@ pub async fn get_notifications(
   };
 
   let user_id = claims.custom.id;
+  let limit = params.limit.unwrap_or(DEFAULT_NOTIFICATION_PAGE);
+  if limit == 0 || limit > MAX_NOTIFICATION_PAGE {
+    return Err(StatusCode::BAD_REQUEST);
+  }
+
+  let mut query = Notifications::find().filter(notifications::Column::ToUser.eq(user_id));
+  if let Some(cursor) = params.cursor {
+    query = query.filter(notifications::Column::Id.lt(cursor));
+  }
+  if params.unread {
+    query = query.filter(notifications::Column::ReadAt.is_null());
+  }
+  if let Some(sender) = params.sender {
+    query = query.filter(notifications::Column::FromUser.eq(sender));
+  }
+  if let Some(from) = params.from.and_then(|from| from.and_hms_opt(0, 0, 0)) {
+    query = query.filter(notifications::Column::CreatedAt.gte(from));
+  }
+  if let Some(to) = params
+    .to
+    .and_then(|to| to.succ_opt())
+    .and_then(|to| to.and_hms_opt(0, 0, 0))
+  {
+    query = query.filter(notifications::Column::CreatedAt.lt(to));
+  }
 
-  let notifications = match Notifications::find()
-    .filter(notifications::Column::ToUser.eq(user_id))
+  // one more than the page, to know whether there is a next page
+  let mut notifications = match query
+    .order_by(notifications::Column::Id, Order::Desc)
+    .limit(limit + 1)
     .column_as(
       Expr::col((Alias::new("users_from"), users::Column::Name)),
       "from",
@@ -406,5 +499,278 @@ pub async fn get_notifications(
     }
   };
 
-  Ok(Json(notifications))
+  let mut headers = HeaderMap::new();
+  if notifications.len() as u64 > limit {
+    notifications.truncate(limit as usize);
+    if let Some(last) = notifications.last() {
+      headers.insert(NEXT_CURSOR_HEADER, last.id.into());
+    }
+  }
+
+  Ok((headers, Json(notifications)))
+}
+
+#[utoipa::path(
+  get,
+  path = "/notifications/unread-count",
+  description = "Lấy số thông báo chưa đọc của người dùng, yêu cầu request có token ở header.",
+  tag = USER,
+  responses(
+    (status = OK, description = "Unread notifications", body = UnreadCount),
+    (status = UNAUTHORIZED, description = "Invalid token", body = String),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error", body = String),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn get_unread_count(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+) -> Result<Json<UnreadCount>, StatusCode> {
+  let claims = state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+  let unread = Notifications::find()
+    .filter(notifications::Column::ToUser.eq(claims.custom.id))
+    .filter(notifications::Column::ReadAt.is_null())
+    .count(&state.db)
+    .await
+    .map_err(|e| {
+      log::error!("Error: {:?}", e);
+      StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+  Ok(Json(UnreadCount { unread }))
+}
+
+#[utoipa::path(
+  post,
+  path = "/notifications/{id}/read",
+  description = "Đánh dấu một thông báo của người dùng là đã đọc, yêu cầu request có token ở header.",
+  tag = USER,
+  responses(
+    (status = NO_CONTENT, description = "Notification marked as read"),
+    (status = NOT_FOUND, description = "Notification not found", body = String),
+    (status = UNAUTHORIZED, description = "Invalid token", body = String),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error", body = String),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn mark_notification_read(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+  Path(id): Path<i32>,
+) -> Result<StatusCode, StatusCode> {
+  let claims = state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+  let server_error = |e: DbErr| {
+    log::error!("Error: {:?}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+  };
+
+  let notification = Notifications::find_by_id(id)
+    .filter(notifications::Column::ToUser.eq(claims.custom.id))
+    .one(&state.db)
+    .await
+    .map_err(server_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+  if notification.read_at.is_none() {
+    notifications::ActiveModel {
+      read_at: Set(Some(chrono::Utc::now().naive_utc())),
+      ..notification.into()
+    }
+    .update(&state.db)
+    .await
+    .map_err(server_error)?;
+  }
+
+  Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+  post,
+  path = "/notifications/read-all",
+  description = "Đánh dấu tất cả thông báo của người dùng là đã đọc, yêu cầu request có token ở header. Trả về số thông báo được đánh dấu.",
+  tag = USER,
+  responses(
+    (status = OK, description = "Notifications marked as read", body = MarkedRead),
+    (status = UNAUTHORIZED, description = "Invalid token", body = String),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error", body = String),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn mark_all_notifications_read(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+) -> Result<Json<MarkedRead>, StatusCode> {
+  let claims = state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+  let result = Notifications::update_many()
+    .col_expr(
+      notifications::Column::ReadAt,
+      Expr::value(chrono::Utc::now().naive_utc()),
+    )
+    .filter(notifications::Column::ToUser.eq(claims.custom.id))
+    .filter(notifications::Column::ReadAt.is_null())
+    .exec(&state.db)
+    .await
+    .map_err(|e| {
+      log::error!("Error: {:?}", e);
+      StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+  Ok(Json(MarkedRead {
+    updated: result.rows_affected,
+  }))
+}
+
+#[utoipa::path(
+  delete,
+  path = "/notifications/{id}",
+  description = "Xóa một thông báo của người dùng, yêu cầu request có token ở header.",
+  tag = USER,
+  responses(
+    (status = NO_CONTENT, description = "Notification deleted"),
+    (status = NOT_FOUND, description = "Notification not found", body = String),
+    (status = UNAUTHORIZED, description = "Invalid token", body = String),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error", body = String),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn delete_notification(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+  Path(id): Path<i32>,
+) -> Result<StatusCode, StatusCode> {
+  let claims = state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+  let result = Notifications::delete_many()
+    .filter(notifications::Column::Id.eq(id))
+    .filter(notifications::Column::ToUser.eq(claims.custom.id))
+    .exec(&state.db)
+    .await
+    .map_err(|e| {
+      log::error!("Error: {:?}", e);
+      StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+  if result.rows_affected == 0 {
+    return Err(StatusCode::NOT_FOUND);
+  }
+
+  Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+  get,
+  path = "/notification-preferences",
+  description = "Lấy các kênh ngoài ứng dụng mà người dùng nhận thông báo qua (email, SMS, webhook), yêu cầu request có token ở header.
+  Mặc định người dùng nhận thông báo qua email và không nhận qua SMS.",
+  tag = USER,
+  responses(
+    (status = OK, description = "Notification preferences", body = NotificationPreferencesInfo),
+    (status = UNAUTHORIZED, description = "Invalid token", body = String),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error", body = String),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn get_notification_preferences(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+) -> Result<Json<NotificationPreferencesInfo>, StatusCode> {
+  let claims = state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+  let preferences = crate::notify::preferences_of(&state.db, claims.custom.id)
+    .await
+    .map_err(|e| {
+      log::error!("Error: {:?}", e);
+      StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+  Ok(Json(NotificationPreferencesInfo {
+    email: preferences.email,
+    sms: preferences.sms,
+    webhook: preferences.webhook,
+    language: preferences.language,
+  }))
+}
+
+#[utoipa::path(
+  put,
+  path = "/notification-preferences",
+  description = "Chọn các kênh ngoài ứng dụng mà người dùng nhận thông báo qua (email, SMS, webhook), yêu cầu request có token ở header.
+  Thông báo vẫn luôn được hiển thị trong ứng dụng. Các thông báo tự động được gửi theo ngôn ngữ `language` (mặc định là `vi`).",
+  tag = USER,
+  request_body = NotificationPreferencesInfo,
+  responses(
+    (status = OK, description = "Notification preferences updated", body = NotificationPreferencesInfo),
+    (status = BAD_REQUEST, description = "Invalid language", body = String),
+    (status = UNAUTHORIZED, description = "Invalid token", body = String),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error", body = String),
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn update_notification_preferences(
+  State(state): State<AppState>,
+  TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
+  Json(preferences): Json<NotificationPreferencesInfo>,
+) -> Result<Json<NotificationPreferencesInfo>, StatusCode> {
+  let claims = state
+    .jwt_access_secret
+    .verify_token::<AccessTokenClaims>(bearer.token(), None)
+    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+  if !crate::notify::templates::is_valid_language(&preferences.language) {
+    return Err(StatusCode::BAD_REQUEST);
+  }
+
+  NotificationPreferences::insert(notification_preferences::ActiveModel {
+    user_id: Set(claims.custom.id),
+    email: Set(preferences.email),
+    sms: Set(preferences.sms),
+    webhook: Set(preferences.webhook),
+    language: Set(preferences.language.clone()),
+    updated_at: Set(chrono::Utc::now().naive_utc()),
+  })
+  .on_conflict(
+    OnConflict::column(notification_preferences::Column::UserId)
+      .update_columns([
+        notification_preferences::Column::Email,
+        notification_preferences::Column::Sms,
+        notification_preferences::Column::Webhook,
+        notification_preferences::Column::Language,
+        notification_preferences::Column::UpdatedAt,
+      ])
+      .to_owned(),
+  )
+  .exec(&state.db)
+  .await
+  .map_err(|e| {
+    log::error!("Error: {:?}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+  })?;
+
+  Ok(Json(preferences))
 }
@@ -1,288 +1,128 @@
+use axum::body::Bytes;
+
 use crate::{
-  entities::{fee_recurrence, fees, fees_room_assignment, transaction_logs, transactions},
+  notify::live::publish_payment,
+  payment::{sepay::SepayPayload, settle_transfer, SettlementOutcome},
   prelude::*,
 };
 
-use regex::Regex;
-use sea_orm::IntoActiveModel;
-
-/// The payload sent by the webhook
-/// Example in JSON:
-/// {
-///   "id": 3,
-///   "gateway": "Vietcombank",
-///   "transactionDate": "2023-03-25 00:00:01",
-///   "accountNumber": "0123499999",
-///   "code": null,
-///   "content": "TSF3",
-///   "transferType": "in",
-///   "transferAmount": 2277000,
-///   "accumulated": 19077000,
-///   "subAccount": null,
-///   "referenceCode": "MBVCB.3278907687",
-///   "description": ""
-/// }
-#[derive(serde::Serialize, serde::Deserialize, Debug, ToSchema)]
-#[serde(rename_all = "camelCase")]
-pub struct WebhookPayload {
-  pub id: i32,
-  pub gateway: String,
-  pub transaction_date: String,
-  pub account_number: String,
-  pub sub_account: Option<String>,
-  pub transfer_amount: i64,
-  pub accumulated: i64,
-  pub code: Option<String>,
-  pub content: String,
-  pub reference_code: Option<String>,
-  pub description: Option<String>,
-}
-
 #[utoipa::path(
   post,
-  path = "/webhook/payment",
+  path = "/webhook/payment/{provider}",
   summary = "Webhook for payment",
-  description = "Webhook for payment"
+  description = "Webhook nhận thông báo chuyển khoản từ cổng thanh toán. `provider` là tên cổng thanh toán đã được cấu hình, ví dụ `sepay` hoặc `bank`.",
+  tag = tags::WEBHOOK,
+  params(
+    ("provider" = String, Path, description = "Payment gateway name")
+  ),
+  request_body(content = String, description = "Notification body in the gateway's own format", content_type = "application/json"),
+  responses(
+    (status = CREATED, description = "Payment settled"),
+    (status = OK, description = "Notification already handled"),
+    (status = ACCEPTED, description = "Transfer logged, but it pays for no fee"),
+    (status = BAD_REQUEST, description = "Invalid payment"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = NOT_FOUND, description = "Unknown provider"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+  )
 )]
 pub async fn webhook_payment_handler(
   headers: HeaderMap,
   State(state): State<AppState>,
-  Json(payload): Json<WebhookPayload>,
+  Path(provider): Path<String>,
+  body: Bytes,
 ) -> Result<impl IntoResponse, StatusCode> {
-  // header format: "Authorization":"Apikey <api_key>"
-  // parse api key
-  let auth = headers.get("Authorization").and_then(|v| v.to_str().ok());
-  match auth {
-    Some(auth) if auth.starts_with("Apikey ") => {
-      let api_key = auth.trim_start_matches("Apikey ");
-      if api_key != state.payment_api_key {
-        return Err(StatusCode::UNAUTHORIZED);
-      }
-    }
-    _ => return Err(StatusCode::UNAUTHORIZED),
-  }
-
-  // insert transaction log
-  let transaction_date =
-    chrono::NaiveDateTime::parse_from_str(&payload.transaction_date, "%Y-%m-%d %H:%M:%S")
-      .unwrap_or(chrono::Utc::now().naive_utc());
-  let transaction_log = transaction_logs::ActiveModel {
-    id: Set(payload.id),
-    gateway: Set(payload.gateway),
-    transaction_date: Set(transaction_date),
-    account_number: Set(payload.account_number),
-    sub_account: Set(payload.sub_account),
-    transfer_amount: Set(payload.transfer_amount),
-    accumulated: Set(payload.accumulated),
-    code: Set(payload.code),
-    content: Set(payload.content),
-    reference_code: Set(payload.reference_code),
-    description: Set(payload.description),
-    ..Default::default()
-  };
-  let transaction_log = match transaction_log.insert(&state.db).await {
-    Ok(transaction_log) => transaction_log,
-    Err(e) => {
-      log::error!("Failed to insert transaction log: {:?}", e);
-      return Err(StatusCode::INTERNAL_SERVER_ERROR);
-    }
-  };
-
-  let content = transaction_log.content;
-  let re = Regex::new(r"FLATAPP(?<code>[0-9]+)").unwrap();
-  let code = re
-    .captures(&content)
-    .and_then(|c| c.name("code").map(|m| m.as_str().parse::<i32>().ok()))
-    .flatten();
-  let code = match code {
-    Some(code) => code,
-    None => {
-      log::error!("Failed to parse code from content: {:?}", content);
-      return Err(StatusCode::INTERNAL_SERVER_ERROR);
-    }
-  };
-
-  // get corresponding fee room assignment and fee
-  let fee_room_assignment = match FeesRoomAssignment::find_by_id(code)
-    .find_also_related(Fees)
-    .one(&state.db)
-    .await
-  {
-    Ok(fee_room_assignment) => fee_room_assignment,
-    Err(e) => {
-      log::error!("Failed to find fee room assignment: {:?}", e);
-      return Err(StatusCode::INTERNAL_SERVER_ERROR);
-    }
-  };
-  let fee_room_assignment = match fee_room_assignment {
-    Some(fee_room_assignment) => fee_room_assignment,
-    None => {
-      log::error!("Fee room assignment not found: {:?}", code);
-      return Err(StatusCode::INTERNAL_SERVER_ERROR);
-    }
-  };
-  let (assignment, fee) = fee_room_assignment;
-  let fee = match fee {
-    Some(fee) => fee,
+  let gateway = match state.payment_gateways.get(&provider) {
+    Some(gateway) => gateway,
     None => {
-      log::error!("Fee not found: {:?}", code);
-      return Err(StatusCode::INTERNAL_SERVER_ERROR);
+      log::error!("Unknown payment provider: {}", provider);
+      return Err(StatusCode::NOT_FOUND);
     }
   };
 
-  // check if payment is valid
-  if assignment.is_paid {
-    log::info!("Fee room assignment already paid: {:?}", code);
-    return Err(StatusCode::BAD_REQUEST);
-  }
-  if fee.amount != payload.transfer_amount {
-    log::error!(
-      "Amount mismatch: expected {}, got {}",
-      fee.amount,
-      payload.transfer_amount
-    );
-    return Err(StatusCode::BAD_REQUEST);
+  if !gateway.authenticate(&headers, &body) {
+    return Err(StatusCode::UNAUTHORIZED);
   }
 
-  // update assignment
-  let mut assignment = assignment.into_active_model();
-  assignment.is_paid = Set(true);
-  assignment.payment_date = Set(Some(transaction_date));
-  let assignment = match assignment.save(&state.db).await {
-    Ok(assignment) => assignment,
+  let record = match gateway.parse(&body) {
+    Ok(record) => record,
     Err(e) => {
-      log::error!("Failed to update assignment: {:?}", e);
-      return Err(StatusCode::INTERNAL_SERVER_ERROR);
+      log::error!("Failed to parse {} notification: {:?}", provider, e);
+      return Err(StatusCode::BAD_REQUEST);
     }
   };
 
-  let new_transaction = transactions::ActiveModel {
-    amount: Set(payload.transfer_amount),
-    created_at: Set(transaction_date),
-    assignment_id: Set(code),
-    ..Default::default()
-  };
-  new_transaction.save(&state.db).await.map_err(|e| {
-    log::error!("Failed to insert transaction: {:?}", e);
-    StatusCode::INTERNAL_SERVER_ERROR
-  })?;
-
-  // check if the fee is in a recurrence chain and assign the next fee to the room
-  let next_recurrence = match FeeRecurrence::find()
-    .filter(fee_recurrence::Column::PreviousFeeId.eq(fee.id))
-    .filter(fee_recurrence::Column::FeeId.ne(fee.id))
-    .one(&state.db)
-    .await
-  {
-    Ok(entry) => entry,
+  let outcome = match settle_transfer(&state.db, record).await {
+    Ok(outcome) => outcome,
     Err(e) => {
-      log::error!("Failed to find next recurrence: {}", e);
+      log::error!("Failed to settle transfer: {:?}", e);
       return Err(StatusCode::INTERNAL_SERVER_ERROR);
     }
   };
-  if let Some(next_recurrence) = next_recurrence {
-    // assign the next fee to the current room
-    let new_fee = fees_room_assignment::ActiveModel {
-      room_number: assignment.room_number.clone(),
-      fee_id: Set(next_recurrence.fee_id),
-      due_date: Set(next_recurrence.due_date),
-      ..Default::default()
-    };
-    new_fee.save(&state.db).await.map_err(|e| {
-      log::error!("Failed to save new fee assignment: {}", e);
-      StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-    return Ok((
-      StatusCode::CREATED,
+
+  match outcome {
+    SettlementOutcome::Settled { assignment_id } => {
+      // the payment screen of the room waits for this
+      if let Err(e) = publish_payment(&state, assignment_id).await {
+        log::error!("Failed to publish payment {}: {:?}", assignment_id, e);
+      }
+      Ok((
+        StatusCode::CREATED,
+        Json(json!({
+          "success": true
+        })),
+      ))
+    }
+    SettlementOutcome::Duplicate | SettlementOutcome::Outgoing => Ok((
+      StatusCode::OK,
       Json(json!({
         "success": true
       })),
-    ));
-  }
-
-  // check if the fee is the last in a recurrence chain and create a new fee for the next due date
-  let recurrence_entry = match FeeRecurrence::find()
-    .filter(fee_recurrence::Column::FeeId.eq(fee.id))
-    .one(&state.db)
-    .await
-  {
-    Ok(entry) => entry,
-    Err(e) => {
-      log::error!("Failed to find recurrence: {}", e);
-      return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    )),
+    // the transfer is logged for the manager to look at, the gateway must not send it again
+    SettlementOutcome::NoMatch => Ok((
+      StatusCode::ACCEPTED,
+      Json(json!({
+        "success": true
+      })),
+    )),
+    SettlementOutcome::AlreadyPaid { .. } | SettlementOutcome::AmountMismatch { .. } => {
+      Err(StatusCode::BAD_REQUEST)
     }
-  };
-  if recurrence_entry.is_some() {
-    // find the fee that is being paid
-    let old_fee = Fees::find_by_id(fee.id)
-      .one(&state.db)
-      .await
-      .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    let old_fee = match old_fee {
-      Some(fee) => fee,
-      None => {
-        return Err(StatusCode::NOT_FOUND);
-      }
-    };
-    // create a new fee for the next due date
-    let new_due_date = old_fee.due_date
-      + match { old_fee.recurrence_type.as_ref().unwrap() } {
-        RecurrenceType::Weekly => chrono::Duration::days(7),
-        RecurrenceType::Monthly => chrono::Duration::days(30),
-        RecurrenceType::Yearly => chrono::Duration::days(365),
-      };
-    let new_fee = fees::ActiveModel {
-      name: Set(old_fee.name.clone()),
-      amount: Set(old_fee.amount),
-      is_required: Set(old_fee.is_required),
-      created_at: Set(chrono::Utc::now().naive_utc()),
-      is_recurring: Set(true),
-      due_date: Set(new_due_date),
-      recurrence_type: Set(old_fee.recurrence_type.clone()),
-      ..Default::default()
-    };
-    let new_fee = new_fee.save(&state.db).await.map_err(|e| {
-      log::error!("Failed to save new fee: {}", e);
-      StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-    let new_fee_id = new_fee.id.unwrap();
-    // assign the new fee to the room
-    let new_assignment = fees_room_assignment::ActiveModel {
-      room_number: assignment.room_number.clone(),
-      fee_id: Set(new_fee_id),
-      due_date: Set(new_due_date),
-      ..Default::default()
-    };
-    new_assignment.save(&state.db).await.map_err(|e| {
-      log::error!("Failed to save new fee assignment: {}", e);
-      StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-    // create a new recurrence chain
-    let new_recurrence = fee_recurrence::ActiveModel {
-      fee_id: Set(new_fee_id),
-      previous_fee_id: Set(fee.id),
-      due_date: Set(new_due_date),
-      ..Default::default()
-    };
-    new_recurrence.save(&state.db).await.map_err(|e| {
-      log::error!("Failed to save new recurrence: {}", e);
-      StatusCode::INTERNAL_SERVER_ERROR
-    })?;
   }
+}
 
-  Ok((
-    StatusCode::CREATED,
-    Json(json!({
-      "success": true
-    })),
-  ))
+#[utoipa::path(
+  post,
+  path = "/webhook/payment",
+  summary = "Webhook for payment (SePay)",
+  description = "Đường dẫn cũ của webhook thanh toán, tương đương với `/webhook/payment/sepay`.",
+  tag = tags::WEBHOOK,
+  request_body(content = SepayPayload, content_type = "application/json"),
+  responses(
+    (status = CREATED, description = "Payment settled"),
+    (status = BAD_REQUEST, description = "Invalid payment"),
+    (status = UNAUTHORIZED, description = "Unauthorized"),
+    (status = INTERNAL_SERVER_ERROR, description = "Server error"),
+  )
+)]
+pub async fn legacy_webhook_payment_handler(
+  headers: HeaderMap,
+  state: State<AppState>,
+  body: Bytes,
+) -> Result<impl IntoResponse, StatusCode> {
+  webhook_payment_handler(headers, state, Path("sepay".to_string()), body).await
 }
 
 #[utoipa::path(
   get,
-  path = "/webhook/payment/{id}",
+  path = "/payment/{id}",
   summary = "Check payment status",
-  description = "Check payment status"
+  description = "Check payment status",
+  tag = tags::WEBHOOK,
+  security(
+    ("Authorization" = [])
+  )
 )]
 pub async fn check_payment(
   TypedHeader(bearer): TypedHeader<Authorization<Bearer>>,
@@ -317,3 +157,26 @@ pub async fn check_payment(
     StatusCode::NOT_FOUND
   }
 }
+
+/// The old path of [`check_payment`], kept for the clients that still poll it. The segment is the
+/// assignment id, named like the provider of the payment webhook so that both routes can share it.
+#[utoipa::path(
+  get,
+  path = "/webhook/payment/{provider}",
+  summary = "Check payment status (deprecated)",
+  description = "Đường dẫn cũ của `/payment/{id}`, tham số là mã khoản phí của phòng.",
+  tag = tags::WEBHOOK,
+  params(
+    ("provider" = i32, Path, description = "Fee assignment id")
+  ),
+  security(
+    ("Authorization" = [])
+  )
+)]
+pub async fn legacy_check_payment(
+  bearer: TypedHeader<Authorization<Bearer>>,
+  state: State<AppState>,
+  Path(id): Path<i32>,
+) -> StatusCode {
+  check_payment(bearer, state, Path(id)).await
+}
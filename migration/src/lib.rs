@@ -12,6 +12,28 @@ mod m20240101_000007_create_notifications_table;
 mod m20240101_000008_create_fee_recurrence_table;
 mod m20240101_000009_create_family_table;
 mod m20240101_000010_create_transaction_logs_table;
+mod m20240101_000011_add_provider_to_transaction_logs;
+mod m20240101_000012_create_documents_table;
+mod m20240101_000013_create_payment_reminders_table;
+mod m20240101_000014_create_ledger_tables;
+mod m20240101_000015_add_archived_at_to_fees;
+mod m20240101_000016_create_fee_versions_table;
+mod m20240101_000017_create_room_groups_tables;
+mod m20240101_000018_add_category_and_tags_to_fees;
+mod m20240101_000019_add_details_to_rooms;
+mod m20240101_000020_create_room_members_table;
+mod m20240101_000021_create_room_occupancies_table;
+mod m20240101_000022_create_room_claims_table;
+mod m20240101_000023_create_room_invitations_table;
+mod m20240101_000024_add_details_to_family;
+mod m20240101_000025_create_residence_declarations_table;
+mod m20240101_000026_create_vehicles_table;
+mod m20240101_000027_add_read_at_to_notifications;
+mod m20240101_000028_create_notification_delivery_tables;
+mod m20240101_000029_create_notification_templates_table;
+mod m20240101_000030_add_unit_price_to_assignments;
+mod m20240101_000031_add_category_to_fee_versions;
+mod m20240101_000032_add_webhook_to_notification_preferences;
 
 pub struct Migrator;
 
@@ -30,6 +52,28 @@ impl MigratorTrait for Migrator {
       Box::new(m20240101_000008_create_fee_recurrence_table::Migration),
       Box::new(m20240101_000009_create_family_table::Migration),
       Box::new(m20240101_000010_create_transaction_logs_table::Migration),
+      Box::new(m20240101_000011_add_provider_to_transaction_logs::Migration),
+      Box::new(m20240101_000012_create_documents_table::Migration),
+      Box::new(m20240101_000013_create_payment_reminders_table::Migration),
+      Box::new(m20240101_000014_create_ledger_tables::Migration),
+      Box::new(m20240101_000015_add_archived_at_to_fees::Migration),
+      Box::new(m20240101_000016_create_fee_versions_table::Migration),
+      Box::new(m20240101_000017_create_room_groups_tables::Migration),
+      Box::new(m20240101_000018_add_category_and_tags_to_fees::Migration),
+      Box::new(m20240101_000019_add_details_to_rooms::Migration),
+      Box::new(m20240101_000020_create_room_members_table::Migration),
+      Box::new(m20240101_000021_create_room_occupancies_table::Migration),
+      Box::new(m20240101_000022_create_room_claims_table::Migration),
+      Box::new(m20240101_000023_create_room_invitations_table::Migration),
+      Box::new(m20240101_000024_add_details_to_family::Migration),
+      Box::new(m20240101_000025_create_residence_declarations_table::Migration),
+      Box::new(m20240101_000026_create_vehicles_table::Migration),
+      Box::new(m20240101_000027_add_read_at_to_notifications::Migration),
+      Box::new(m20240101_000028_create_notification_delivery_tables::Migration),
+      Box::new(m20240101_000029_create_notification_templates_table::Migration),
+      Box::new(m20240101_000030_add_unit_price_to_assignments::Migration),
+      Box::new(m20240101_000031_add_category_to_fee_versions::Migration),
+      Box::new(m20240101_000032_add_webhook_to_notification_preferences::Migration),
     ]
   }
 }
@@ -0,0 +1,88 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20240101_000010_create_transaction_logs_table::TransactionLogs;
+
+#[derive(DeriveIden)]
+enum TransactionLogsExt {
+  Provider,
+  ExternalId,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+  async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .alter_table(
+        Table::alter()
+          .table(TransactionLogs::Table)
+          .add_column(string(TransactionLogsExt::Provider).default("sepay"))
+          .add_column(string_null(TransactionLogsExt::ExternalId))
+          .to_owned(),
+      )
+      .await?;
+
+    // the id used to come from the gateway, keep it as the external id and let the
+    // database generate ids from now on
+    let db = manager.get_connection();
+    db.execute_unprepared("UPDATE transaction_logs SET external_id = id::text")
+      .await?;
+    db.execute_unprepared(
+      "CREATE SEQUENCE IF NOT EXISTS transaction_logs_id_seq OWNED BY transaction_logs.id",
+    )
+    .await?;
+    db.execute_unprepared(
+      "SELECT setval('transaction_logs_id_seq', COALESCE((SELECT MAX(id) FROM transaction_logs), 0) + 1, false)",
+    )
+    .await?;
+    db.execute_unprepared(
+      "ALTER TABLE transaction_logs ALTER COLUMN id SET DEFAULT nextval('transaction_logs_id_seq')",
+    )
+    .await?;
+
+    manager
+      .create_index(
+        Index::create()
+          .table(TransactionLogs::Table)
+          .name("unique_transaction_logs_provider_external_id")
+          .unique()
+          .col(TransactionLogsExt::Provider)
+          .col(TransactionLogsExt::ExternalId)
+          .to_owned(),
+      )
+      .await?;
+
+    Ok(())
+  }
+
+  async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .drop_index(
+        Index::drop()
+          .name("unique_transaction_logs_provider_external_id")
+          .table(TransactionLogs::Table)
+          .to_owned(),
+      )
+      .await?;
+
+    let db = manager.get_connection();
+    db.execute_unprepared("ALTER TABLE transaction_logs ALTER COLUMN id DROP DEFAULT")
+      .await?;
+    db.execute_unprepared("DROP SEQUENCE IF EXISTS transaction_logs_id_seq")
+      .await?;
+
+    manager
+      .alter_table(
+        Table::alter()
+          .table(TransactionLogs::Table)
+          .drop_column(TransactionLogsExt::Provider)
+          .drop_column(TransactionLogsExt::ExternalId)
+          .to_owned(),
+      )
+      .await?;
+
+    Ok(())
+  }
+}
@@ -0,0 +1,99 @@
+use extension::postgres::Type;
+use sea_orm::{ActiveEnum, DbBackend, DeriveActiveEnum, EnumIter, Schema};
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20240101_000003_create_rooms_table::Rooms;
+use crate::m20240101_000006_create_transactions_table::Transactions;
+
+#[derive(EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "document_kind")]
+pub enum DocumentKind {
+  #[sea_orm(string_value = "invoice")]
+  Invoice,
+  #[sea_orm(string_value = "receipt")]
+  Receipt,
+}
+
+#[derive(DeriveIden)]
+pub enum Documents {
+  Table,
+  Id,
+  Kind,
+  RoomNumber,
+  Period,
+  TransactionId,
+  FileName,
+  Content,
+  CreatedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+  async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    let schema = Schema::new(DbBackend::Postgres);
+    manager
+      .create_type(schema.create_enum_from_active_enum::<DocumentKind>())
+      .await?;
+
+    manager
+      .create_table(
+        Table::create()
+          .table(Documents::Table)
+          .if_not_exists()
+          .col(pk_auto(Documents::Id))
+          .col(
+            ColumnDef::new(Documents::Kind)
+              .custom(DocumentKind::name())
+              .not_null(),
+          )
+          .col(integer(Documents::RoomNumber).not_null())
+          .col(string_null(Documents::Period))
+          .col(integer_null(Documents::TransactionId).unique_key())
+          .col(string(Documents::FileName).not_null())
+          .col(binary(Documents::Content).not_null())
+          .col(
+            timestamp(Documents::CreatedAt)
+              .not_null()
+              .default(Expr::current_timestamp()),
+          )
+          .foreign_key(
+            ForeignKey::create()
+              .name("fk_documents_room_number")
+              .from(Documents::Table, Documents::RoomNumber)
+              .to(Rooms::Table, Rooms::RoomNumber)
+              .on_delete(ForeignKeyAction::Cascade),
+          )
+          .foreign_key(
+            ForeignKey::create()
+              .name("fk_documents_transaction_id")
+              .from(Documents::Table, Documents::TransactionId)
+              .to(Transactions::Table, Transactions::Id)
+              .on_delete(ForeignKeyAction::SetNull),
+          )
+          .to_owned(),
+      )
+      .await?;
+
+    Ok(())
+  }
+
+  async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .drop_table(Table::drop().table(Documents::Table).if_exists().to_owned())
+      .await?;
+
+    manager
+      .drop_type(
+        Type::drop()
+          .name(DocumentKind::name())
+          .if_exists()
+          .to_owned(),
+      )
+      .await?;
+
+    Ok(())
+  }
+}
@@ -0,0 +1,75 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20240101_000005_create_fees_room_table::FeesRoomAssignment;
+
+#[derive(DeriveIden)]
+pub enum PaymentReminders {
+  Table,
+  Id,
+  AssignmentId,
+  OffsetDays,
+  SentAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+  async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .create_table(
+        Table::create()
+          .table(PaymentReminders::Table)
+          .if_not_exists()
+          .col(pk_auto(PaymentReminders::Id))
+          .col(integer(PaymentReminders::AssignmentId).not_null())
+          .col(integer(PaymentReminders::OffsetDays).not_null())
+          .col(
+            timestamp(PaymentReminders::SentAt)
+              .not_null()
+              .default(Expr::current_timestamp()),
+          )
+          .foreign_key(
+            ForeignKey::create()
+              .name("fk_payment_reminders_assignment_id")
+              .from(PaymentReminders::Table, PaymentReminders::AssignmentId)
+              .to(
+                FeesRoomAssignment::Table,
+                FeesRoomAssignment::AssignmentId,
+              )
+              .on_delete(ForeignKeyAction::Cascade),
+          )
+          .to_owned(),
+      )
+      .await?;
+
+    // one reminder per assignment and stage
+    manager
+      .create_index(
+        Index::create()
+          .name("unique_payment_reminders_assignment_id_offset_days")
+          .table(PaymentReminders::Table)
+          .col(PaymentReminders::AssignmentId)
+          .col(PaymentReminders::OffsetDays)
+          .unique()
+          .to_owned(),
+      )
+      .await?;
+
+    Ok(())
+  }
+
+  async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .drop_table(
+        Table::drop()
+          .table(PaymentReminders::Table)
+          .if_exists()
+          .to_owned(),
+      )
+      .await?;
+
+    Ok(())
+  }
+}
@@ -0,0 +1,246 @@
+use extension::postgres::Type;
+use sea_orm::{ActiveEnum, DbBackend, DeriveActiveEnum, EnumIter, Schema};
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20220101_000001_create_users_table::Users;
+use crate::m20240101_000005_create_fees_room_table::FeesRoomAssignment;
+use crate::m20240101_000006_create_transactions_table::Transactions;
+
+#[derive(EnumIter, DeriveActiveEnum)]
+#[sea_orm(
+  rs_type = "String",
+  db_type = "Enum",
+  enum_name = "ledger_account_type"
+)]
+pub enum LedgerAccountType {
+  #[sea_orm(string_value = "asset")]
+  Asset,
+  #[sea_orm(string_value = "liability")]
+  Liability,
+  #[sea_orm(string_value = "income")]
+  Income,
+  #[sea_orm(string_value = "expense")]
+  Expense,
+}
+
+#[derive(EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "ledger_entry_kind")]
+pub enum LedgerEntryKind {
+  #[sea_orm(string_value = "charge")]
+  Charge,
+  #[sea_orm(string_value = "payment")]
+  Payment,
+  #[sea_orm(string_value = "adjustment")]
+  Adjustment,
+  #[sea_orm(string_value = "refund")]
+  Refund,
+}
+
+#[derive(DeriveIden)]
+pub enum LedgerAccounts {
+  Table,
+  Id,
+  Code,
+  Name,
+  AccountType,
+  RoomNumber,
+  CreatedAt,
+}
+
+#[derive(DeriveIden)]
+pub enum LedgerEntries {
+  Table,
+  Id,
+  Kind,
+  Description,
+  AssignmentId,
+  TransactionId,
+  CreatedBy,
+  CreatedAt,
+}
+
+#[derive(DeriveIden)]
+pub enum LedgerPostings {
+  Table,
+  Id,
+  EntryId,
+  AccountId,
+  Debit,
+  Credit,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+  async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    let schema = Schema::new(DbBackend::Postgres);
+    manager
+      .create_type(schema.create_enum_from_active_enum::<LedgerAccountType>())
+      .await?;
+    manager
+      .create_type(schema.create_enum_from_active_enum::<LedgerEntryKind>())
+      .await?;
+
+    // room accounts keep the room number without a foreign key, the history must outlive the room
+    manager
+      .create_table(
+        Table::create()
+          .table(LedgerAccounts::Table)
+          .if_not_exists()
+          .col(pk_auto(LedgerAccounts::Id))
+          .col(string(LedgerAccounts::Code).not_null().unique_key())
+          .col(string(LedgerAccounts::Name).not_null())
+          .col(
+            ColumnDef::new(LedgerAccounts::AccountType)
+              .custom(LedgerAccountType::name())
+              .not_null(),
+          )
+          .col(integer_null(LedgerAccounts::RoomNumber))
+          .col(
+            timestamp(LedgerAccounts::CreatedAt)
+              .not_null()
+              .default(Expr::current_timestamp()),
+          )
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .create_table(
+        Table::create()
+          .table(LedgerEntries::Table)
+          .if_not_exists()
+          .col(pk_auto(LedgerEntries::Id))
+          .col(
+            ColumnDef::new(LedgerEntries::Kind)
+              .custom(LedgerEntryKind::name())
+              .not_null(),
+          )
+          .col(text(LedgerEntries::Description).not_null())
+          .col(integer_null(LedgerEntries::AssignmentId))
+          .col(integer_null(LedgerEntries::TransactionId))
+          .col(integer_null(LedgerEntries::CreatedBy))
+          .col(
+            timestamp(LedgerEntries::CreatedAt)
+              .not_null()
+              .default(Expr::current_timestamp()),
+          )
+          .foreign_key(
+            ForeignKey::create()
+              .name("fk_ledger_entries_assignment_id")
+              .from(LedgerEntries::Table, LedgerEntries::AssignmentId)
+              .to(FeesRoomAssignment::Table, FeesRoomAssignment::AssignmentId)
+              .on_delete(ForeignKeyAction::SetNull),
+          )
+          .foreign_key(
+            ForeignKey::create()
+              .name("fk_ledger_entries_transaction_id")
+              .from(LedgerEntries::Table, LedgerEntries::TransactionId)
+              .to(Transactions::Table, Transactions::Id)
+              .on_delete(ForeignKeyAction::SetNull),
+          )
+          .foreign_key(
+            ForeignKey::create()
+              .name("fk_ledger_entries_created_by")
+              .from(LedgerEntries::Table, LedgerEntries::CreatedBy)
+              .to(Users::Table, Users::Id)
+              .on_delete(ForeignKeyAction::SetNull),
+          )
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .create_table(
+        Table::create()
+          .table(LedgerPostings::Table)
+          .if_not_exists()
+          .col(pk_auto(LedgerPostings::Id))
+          .col(integer(LedgerPostings::EntryId).not_null())
+          .col(integer(LedgerPostings::AccountId).not_null())
+          .col(big_integer(LedgerPostings::Debit).not_null().default(0))
+          .col(big_integer(LedgerPostings::Credit).not_null().default(0))
+          .check(
+            Expr::col(LedgerPostings::Debit)
+              .gte(0)
+              .and(Expr::col(LedgerPostings::Credit).gte(0)),
+          )
+          .foreign_key(
+            ForeignKey::create()
+              .name("fk_ledger_postings_entry_id")
+              .from(LedgerPostings::Table, LedgerPostings::EntryId)
+              .to(LedgerEntries::Table, LedgerEntries::Id)
+              .on_delete(ForeignKeyAction::Cascade),
+          )
+          .foreign_key(
+            ForeignKey::create()
+              .name("fk_ledger_postings_account_id")
+              .from(LedgerPostings::Table, LedgerPostings::AccountId)
+              .to(LedgerAccounts::Table, LedgerAccounts::Id)
+              .on_delete(ForeignKeyAction::Restrict),
+          )
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .create_index(
+        Index::create()
+          .name("idx_ledger_postings_account_id")
+          .table(LedgerPostings::Table)
+          .col(LedgerPostings::AccountId)
+          .to_owned(),
+      )
+      .await?;
+
+    Ok(())
+  }
+
+  async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .drop_table(
+        Table::drop()
+          .table(LedgerPostings::Table)
+          .if_exists()
+          .to_owned(),
+      )
+      .await?;
+    manager
+      .drop_table(
+        Table::drop()
+          .table(LedgerEntries::Table)
+          .if_exists()
+          .to_owned(),
+      )
+      .await?;
+    manager
+      .drop_table(
+        Table::drop()
+          .table(LedgerAccounts::Table)
+          .if_exists()
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .drop_type(
+        Type::drop()
+          .name(LedgerEntryKind::name())
+          .if_exists()
+          .to_owned(),
+      )
+      .await?;
+    manager
+      .drop_type(
+        Type::drop()
+          .name(LedgerAccountType::name())
+          .if_exists()
+          .to_owned(),
+      )
+      .await?;
+
+    Ok(())
+  }
+}
@@ -0,0 +1,73 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20240101_000001_create_fees_table::Fees;
+use crate::m20240101_000005_create_fees_room_table::FeesRoomAssignment;
+use crate::m20240101_000006_create_transactions_table::Transactions;
+
+#[derive(DeriveIden)]
+enum FeesExt {
+  ArchivedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// Point the payments to their assignment with the given delete action.
+async fn replace_transactions_foreign_key(
+  manager: &SchemaManager<'_>,
+  on_delete: ForeignKeyAction,
+) -> Result<(), DbErr> {
+  manager
+    .drop_foreign_key(
+      ForeignKey::drop()
+        .name("fk_transactions_assignment_id")
+        .table(Transactions::Table)
+        .to_owned(),
+    )
+    .await?;
+
+  manager
+    .create_foreign_key(
+      ForeignKey::create()
+        .name("fk_transactions_assignment_id")
+        .from(Transactions::Table, Transactions::AssignmentId)
+        .to(FeesRoomAssignment::Table, FeesRoomAssignment::AssignmentId)
+        .on_delete(on_delete)
+        .to_owned(),
+    )
+    .await
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+  async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .alter_table(
+        Table::alter()
+          .table(Fees::Table)
+          .add_column(timestamp_null(FeesExt::ArchivedAt))
+          .to_owned(),
+      )
+      .await?;
+
+    // deleting a fee must never take its payments with it
+    replace_transactions_foreign_key(manager, ForeignKeyAction::Restrict).await?;
+
+    Ok(())
+  }
+
+  async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    replace_transactions_foreign_key(manager, ForeignKeyAction::Cascade).await?;
+
+    manager
+      .alter_table(
+        Table::alter()
+          .table(Fees::Table)
+          .drop_column(FeesExt::ArchivedAt)
+          .to_owned(),
+      )
+      .await?;
+
+    Ok(())
+  }
+}
@@ -0,0 +1,142 @@
+use sea_orm::ActiveEnum;
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20220101_000001_create_users_table::Users;
+use crate::m20240101_000001_create_fees_table::{Fees, RecurrenceType};
+use crate::m20240101_000005_create_fees_room_table::FeesRoomAssignment;
+
+#[derive(DeriveIden)]
+enum FeesRoomAssignmentExt {
+  FeeName,
+  Amount,
+}
+
+#[derive(DeriveIden)]
+pub enum FeeVersions {
+  Table,
+  Id,
+  FeeId,
+  Version,
+  Name,
+  Amount,
+  IsRequired,
+  DueDate,
+  RecurrenceType,
+  ChangedBy,
+  ChangedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+  async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    // what each room was billed, independent of later edits of the fee
+    manager
+      .alter_table(
+        Table::alter()
+          .table(FeesRoomAssignment::Table)
+          .add_column(string_null(FeesRoomAssignmentExt::FeeName))
+          .add_column(big_integer_null(FeesRoomAssignmentExt::Amount))
+          .to_owned(),
+      )
+      .await?;
+
+    let db = manager.get_connection();
+    db.execute_unprepared(
+      "UPDATE fees_room_assignment SET fee_name = fees.name, amount = fees.amount FROM fees WHERE fees.id = fees_room_assignment.fee_id",
+    )
+    .await?;
+
+    manager
+      .alter_table(
+        Table::alter()
+          .table(FeesRoomAssignment::Table)
+          .modify_column(string(FeesRoomAssignmentExt::FeeName).not_null())
+          .modify_column(big_integer(FeesRoomAssignmentExt::Amount).not_null())
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .create_table(
+        Table::create()
+          .table(FeeVersions::Table)
+          .if_not_exists()
+          .col(pk_auto(FeeVersions::Id))
+          .col(integer(FeeVersions::FeeId).not_null())
+          .col(integer(FeeVersions::Version).not_null())
+          .col(string(FeeVersions::Name).not_null())
+          .col(big_integer(FeeVersions::Amount).not_null())
+          .col(boolean(FeeVersions::IsRequired).not_null())
+          .col(timestamp(FeeVersions::DueDate).not_null())
+          .col(ColumnDef::new(FeeVersions::RecurrenceType).custom(RecurrenceType::name()))
+          .col(integer_null(FeeVersions::ChangedBy))
+          .col(
+            timestamp(FeeVersions::ChangedAt)
+              .not_null()
+              .default(Expr::current_timestamp()),
+          )
+          .foreign_key(
+            ForeignKey::create()
+              .name("fk_fee_versions_fee_id")
+              .from(FeeVersions::Table, FeeVersions::FeeId)
+              .to(Fees::Table, Fees::Id)
+              .on_delete(ForeignKeyAction::Cascade),
+          )
+          .foreign_key(
+            ForeignKey::create()
+              .name("fk_fee_versions_changed_by")
+              .from(FeeVersions::Table, FeeVersions::ChangedBy)
+              .to(Users::Table, Users::Id)
+              .on_delete(ForeignKeyAction::SetNull),
+          )
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .create_index(
+        Index::create()
+          .table(FeeVersions::Table)
+          .name("unique_fee_versions_fee_id_version")
+          .unique()
+          .col(FeeVersions::FeeId)
+          .col(FeeVersions::Version)
+          .to_owned(),
+      )
+      .await?;
+
+    // the current state of existing fees is their first known version
+    db.execute_unprepared(
+      "INSERT INTO fee_versions (fee_id, version, name, amount, is_required, due_date, recurrence_type, changed_at) SELECT id, 1, name, amount, is_required, due_date, recurrence_type, created_at FROM fees",
+    )
+    .await?;
+
+    Ok(())
+  }
+
+  async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .drop_table(
+        Table::drop()
+          .table(FeeVersions::Table)
+          .if_exists()
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .alter_table(
+        Table::alter()
+          .table(FeesRoomAssignment::Table)
+          .drop_column(FeesRoomAssignmentExt::FeeName)
+          .drop_column(FeesRoomAssignmentExt::Amount)
+          .to_owned(),
+      )
+      .await?;
+
+    Ok(())
+  }
+}
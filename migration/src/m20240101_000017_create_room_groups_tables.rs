@@ -0,0 +1,152 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20220101_000001_create_users_table::Users;
+use crate::m20240101_000001_create_fees_table::Fees;
+use crate::m20240101_000003_create_rooms_table::Rooms;
+
+#[derive(DeriveIden)]
+pub enum RoomGroups {
+  Table,
+  Id,
+  Name,
+  Description,
+  CreatedAt,
+}
+
+#[derive(DeriveIden)]
+pub enum RoomGroupMembers {
+  Table,
+  GroupId,
+  RoomNumber,
+}
+
+#[derive(DeriveIden)]
+pub enum FeeAssignmentRules {
+  Table,
+  Id,
+  FeeId,
+  Target,
+  CreatedBy,
+  CreatedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+  async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .create_table(
+        Table::create()
+          .table(RoomGroups::Table)
+          .if_not_exists()
+          .col(pk_auto(RoomGroups::Id))
+          .col(string(RoomGroups::Name).not_null().unique_key())
+          .col(text_null(RoomGroups::Description))
+          .col(
+            timestamp(RoomGroups::CreatedAt)
+              .not_null()
+              .default(Expr::current_timestamp()),
+          )
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .create_table(
+        Table::create()
+          .table(RoomGroupMembers::Table)
+          .if_not_exists()
+          .col(integer(RoomGroupMembers::GroupId).not_null())
+          .col(integer(RoomGroupMembers::RoomNumber).not_null())
+          .primary_key(
+            Index::create()
+              .col(RoomGroupMembers::GroupId)
+              .col(RoomGroupMembers::RoomNumber),
+          )
+          .foreign_key(
+            ForeignKey::create()
+              .name("fk_room_group_members_group_id")
+              .from(RoomGroupMembers::Table, RoomGroupMembers::GroupId)
+              .to(RoomGroups::Table, RoomGroups::Id)
+              .on_delete(ForeignKeyAction::Cascade),
+          )
+          .foreign_key(
+            ForeignKey::create()
+              .name("fk_room_group_members_room_number")
+              .from(RoomGroupMembers::Table, RoomGroupMembers::RoomNumber)
+              .to(Rooms::Table, Rooms::RoomNumber)
+              .on_delete(ForeignKeyAction::Cascade),
+          )
+          .to_owned(),
+      )
+      .await?;
+
+    // the target is resolved again every time the rules are synced
+    manager
+      .create_table(
+        Table::create()
+          .table(FeeAssignmentRules::Table)
+          .if_not_exists()
+          .col(pk_auto(FeeAssignmentRules::Id))
+          .col(integer(FeeAssignmentRules::FeeId).not_null())
+          .col(json_binary(FeeAssignmentRules::Target).not_null())
+          .col(integer_null(FeeAssignmentRules::CreatedBy))
+          .col(
+            timestamp(FeeAssignmentRules::CreatedAt)
+              .not_null()
+              .default(Expr::current_timestamp()),
+          )
+          .foreign_key(
+            ForeignKey::create()
+              .name("fk_fee_assignment_rules_fee_id")
+              .from(FeeAssignmentRules::Table, FeeAssignmentRules::FeeId)
+              .to(Fees::Table, Fees::Id)
+              .on_delete(ForeignKeyAction::Cascade),
+          )
+          .foreign_key(
+            ForeignKey::create()
+              .name("fk_fee_assignment_rules_created_by")
+              .from(FeeAssignmentRules::Table, FeeAssignmentRules::CreatedBy)
+              .to(Users::Table, Users::Id)
+              .on_delete(ForeignKeyAction::SetNull),
+          )
+          .to_owned(),
+      )
+      .await?;
+
+    Ok(())
+  }
+
+  async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .drop_table(
+        Table::drop()
+          .table(FeeAssignmentRules::Table)
+          .if_exists()
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .drop_table(
+        Table::drop()
+          .table(RoomGroupMembers::Table)
+          .if_exists()
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .drop_table(
+        Table::drop()
+          .table(RoomGroups::Table)
+          .if_exists()
+          .to_owned(),
+      )
+      .await?;
+
+    Ok(())
+  }
+}
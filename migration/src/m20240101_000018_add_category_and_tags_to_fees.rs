@@ -0,0 +1,116 @@
+use extension::postgres::Type;
+use sea_orm::{ActiveEnum, DbBackend, DeriveActiveEnum, EnumIter, Schema};
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20240101_000001_create_fees_table::Fees;
+
+#[derive(EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "fee_category")]
+pub enum FeeCategory {
+  #[sea_orm(string_value = "service")]
+  Service,
+  #[sea_orm(string_value = "utilities")]
+  Utilities,
+  #[sea_orm(string_value = "parking")]
+  Parking,
+  #[sea_orm(string_value = "contribution")]
+  Contribution,
+  #[sea_orm(string_value = "penalty")]
+  Penalty,
+}
+
+#[derive(DeriveIden)]
+enum FeesExt {
+  Category,
+}
+
+#[derive(DeriveIden)]
+pub enum FeeTags {
+  Table,
+  FeeId,
+  Tag,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+  async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    let schema = Schema::new(DbBackend::Postgres);
+    manager
+      .create_type(schema.create_enum_from_active_enum::<FeeCategory>())
+      .await?;
+
+    manager
+      .alter_table(
+        Table::alter()
+          .table(Fees::Table)
+          .add_column(
+            ColumnDef::new(FeesExt::Category)
+              .custom(FeeCategory::name())
+              .not_null()
+              .default("service"),
+          )
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .create_table(
+        Table::create()
+          .table(FeeTags::Table)
+          .if_not_exists()
+          .col(integer(FeeTags::FeeId).not_null())
+          .col(string(FeeTags::Tag).not_null())
+          .primary_key(Index::create().col(FeeTags::FeeId).col(FeeTags::Tag))
+          .foreign_key(
+            ForeignKey::create()
+              .name("fk_fee_tags_fee_id")
+              .from(FeeTags::Table, FeeTags::FeeId)
+              .to(Fees::Table, Fees::Id)
+              .on_delete(ForeignKeyAction::Cascade),
+          )
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .create_index(
+        Index::create()
+          .name("idx_fee_tags_tag")
+          .table(FeeTags::Table)
+          .col(FeeTags::Tag)
+          .to_owned(),
+      )
+      .await?;
+
+    Ok(())
+  }
+
+  async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .drop_table(Table::drop().table(FeeTags::Table).if_exists().to_owned())
+      .await?;
+
+    manager
+      .alter_table(
+        Table::alter()
+          .table(Fees::Table)
+          .drop_column(FeesExt::Category)
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .drop_type(
+        Type::drop()
+          .name(FeeCategory::name())
+          .if_exists()
+          .to_owned(),
+      )
+      .await?;
+
+    Ok(())
+  }
+}
@@ -0,0 +1,117 @@
+use extension::postgres::Type;
+use sea_orm::{ActiveEnum, DbBackend, DeriveActiveEnum, EnumIter, Schema};
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20240101_000003_create_rooms_table::Rooms;
+
+#[derive(EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "room_status")]
+pub enum RoomStatus {
+  #[sea_orm(string_value = "vacant")]
+  Vacant,
+  #[sea_orm(string_value = "occupied")]
+  Occupied,
+  #[sea_orm(string_value = "under_renovation")]
+  UnderRenovation,
+}
+
+#[derive(DeriveIden)]
+enum RoomsExt {
+  Building,
+  Floor,
+  Area,
+  RoomType,
+  Status,
+  Notes,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+  async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    let schema = Schema::new(DbBackend::Postgres);
+    manager
+      .create_type(schema.create_enum_from_active_enum::<RoomStatus>())
+      .await?;
+
+    // rooms can now be set up before anyone registers
+    manager
+      .alter_table(
+        Table::alter()
+          .table(Rooms::Table)
+          .modify_column(ColumnDef::new(Rooms::TenantId).integer().null())
+          .add_column(string_null(RoomsExt::Building))
+          .add_column(integer_null(RoomsExt::Floor))
+          .add_column(double_null(RoomsExt::Area))
+          .add_column(string_null(RoomsExt::RoomType))
+          .add_column(
+            ColumnDef::new(RoomsExt::Status)
+              .custom(RoomStatus::name())
+              .not_null()
+              .default("vacant"),
+          )
+          .add_column(text_null(RoomsExt::Notes))
+          .to_owned(),
+      )
+      .await?;
+
+    // existing rooms are numbered by floor, and all of them have a tenant
+    let db = manager.get_connection();
+    db.execute_unprepared(
+      r#"UPDATE "rooms" SET "floor" = "room_number" / 100,
+        "status" = CASE WHEN "tenant_id" IS NULL THEN 'vacant'::room_status ELSE 'occupied'::room_status END"#,
+    )
+    .await?;
+
+    manager
+      .create_index(
+        Index::create()
+          .name("idx_rooms_building_floor")
+          .table(Rooms::Table)
+          .col(RoomsExt::Building)
+          .col(RoomsExt::Floor)
+          .to_owned(),
+      )
+      .await?;
+
+    Ok(())
+  }
+
+  async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    let db = manager.get_connection();
+    db.execute_unprepared(r#"DELETE FROM "rooms" WHERE "tenant_id" IS NULL"#)
+      .await?;
+
+    manager
+      .drop_index(
+        Index::drop()
+          .name("idx_rooms_building_floor")
+          .table(Rooms::Table)
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .alter_table(
+        Table::alter()
+          .table(Rooms::Table)
+          .modify_column(ColumnDef::new(Rooms::TenantId).integer().not_null())
+          .drop_column(RoomsExt::Building)
+          .drop_column(RoomsExt::Floor)
+          .drop_column(RoomsExt::Area)
+          .drop_column(RoomsExt::RoomType)
+          .drop_column(RoomsExt::Status)
+          .drop_column(RoomsExt::Notes)
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .drop_type(Type::drop().name(RoomStatus::name()).if_exists().to_owned())
+      .await?;
+
+    Ok(())
+  }
+}
@@ -0,0 +1,147 @@
+use extension::postgres::Type;
+use sea_orm::{ActiveEnum, DbBackend, DeriveActiveEnum, EnumIter, Schema};
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20220101_000001_create_users_table::Users;
+use crate::m20240101_000003_create_rooms_table::Rooms;
+
+#[derive(EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "room_member_role")]
+pub enum RoomMemberRole {
+  #[sea_orm(string_value = "householder")]
+  Householder,
+  #[sea_orm(string_value = "co_resident")]
+  CoResident,
+  #[sea_orm(string_value = "owner")]
+  Owner,
+  #[sea_orm(string_value = "renter")]
+  Renter,
+}
+
+#[derive(DeriveIden)]
+pub enum RoomMembers {
+  Table,
+  Id,
+  RoomNumber,
+  UserId,
+  Role,
+  InvitedBy,
+  JoinedAt,
+  CreatedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+  async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    let schema = Schema::new(DbBackend::Postgres);
+    manager
+      .create_type(schema.create_enum_from_active_enum::<RoomMemberRole>())
+      .await?;
+
+    // a member without `joined_at` has been invited but has not accepted yet
+    manager
+      .create_table(
+        Table::create()
+          .table(RoomMembers::Table)
+          .if_not_exists()
+          .col(pk_auto(RoomMembers::Id))
+          .col(integer(RoomMembers::RoomNumber).not_null())
+          .col(integer(RoomMembers::UserId).not_null())
+          .col(
+            ColumnDef::new(RoomMembers::Role)
+              .custom(RoomMemberRole::name())
+              .not_null(),
+          )
+          .col(integer_null(RoomMembers::InvitedBy))
+          .col(timestamp_null(RoomMembers::JoinedAt))
+          .col(
+            timestamp(RoomMembers::CreatedAt)
+              .not_null()
+              .default(Expr::current_timestamp()),
+          )
+          .foreign_key(
+            ForeignKey::create()
+              .name("fk_room_members_room_number")
+              .from(RoomMembers::Table, RoomMembers::RoomNumber)
+              .to(Rooms::Table, Rooms::RoomNumber)
+              .on_delete(ForeignKeyAction::Cascade),
+          )
+          .foreign_key(
+            ForeignKey::create()
+              .name("fk_room_members_user_id")
+              .from(RoomMembers::Table, RoomMembers::UserId)
+              .to(Users::Table, Users::Id)
+              .on_delete(ForeignKeyAction::Cascade),
+          )
+          .foreign_key(
+            ForeignKey::create()
+              .name("fk_room_members_invited_by")
+              .from(RoomMembers::Table, RoomMembers::InvitedBy)
+              .to(Users::Table, Users::Id)
+              .on_delete(ForeignKeyAction::SetNull),
+          )
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .create_index(
+        Index::create()
+          .name("idx_room_members_room_number_user_id")
+          .table(RoomMembers::Table)
+          .col(RoomMembers::RoomNumber)
+          .col(RoomMembers::UserId)
+          .unique()
+          .to_owned(),
+      )
+      .await?;
+
+    // one householder per room, and an account lives in one room at a time
+    let db = manager.get_connection();
+    db.execute_unprepared(
+      r#"CREATE UNIQUE INDEX "idx_room_members_householder" ON "room_members" ("room_number")
+        WHERE "role" = 'householder'"#,
+    )
+    .await?;
+    db.execute_unprepared(
+      r#"CREATE UNIQUE INDEX "idx_room_members_joined_user_id" ON "room_members" ("user_id")
+        WHERE "joined_at" IS NOT NULL"#,
+    )
+    .await?;
+
+    // the tenant of a room is its householder
+    db.execute_unprepared(
+      r#"INSERT INTO "room_members" ("room_number", "user_id", "role", "joined_at")
+        SELECT "room_number", "tenant_id", 'householder', CURRENT_TIMESTAMP
+        FROM "rooms" WHERE "tenant_id" IS NOT NULL"#,
+    )
+    .await?;
+
+    Ok(())
+  }
+
+  async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .drop_table(
+        Table::drop()
+          .table(RoomMembers::Table)
+          .if_exists()
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .drop_type(
+        Type::drop()
+          .name(RoomMemberRole::name())
+          .if_exists()
+          .to_owned(),
+      )
+      .await?;
+
+    Ok(())
+  }
+}
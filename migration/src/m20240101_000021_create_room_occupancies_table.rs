@@ -0,0 +1,94 @@
+use sea_orm::ActiveEnum;
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20220101_000001_create_users_table::Users;
+use crate::m20240101_000003_create_rooms_table::Rooms;
+use crate::m20240101_000020_create_room_members_table::RoomMemberRole;
+
+#[derive(DeriveIden)]
+pub enum RoomOccupancies {
+  Table,
+  Id,
+  RoomNumber,
+  UserId,
+  Role,
+  MovedInAt,
+  MovedOutAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+  async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    // who lived in a room when, and with which role; a stay without `moved_out_at` is ongoing
+    manager
+      .create_table(
+        Table::create()
+          .table(RoomOccupancies::Table)
+          .if_not_exists()
+          .col(pk_auto(RoomOccupancies::Id))
+          .col(integer(RoomOccupancies::RoomNumber).not_null())
+          .col(integer(RoomOccupancies::UserId).not_null())
+          .col(
+            ColumnDef::new(RoomOccupancies::Role)
+              .custom(RoomMemberRole::name())
+              .not_null(),
+          )
+          .col(timestamp(RoomOccupancies::MovedInAt).not_null())
+          .col(timestamp_null(RoomOccupancies::MovedOutAt))
+          .foreign_key(
+            ForeignKey::create()
+              .name("fk_room_occupancies_room_number")
+              .from(RoomOccupancies::Table, RoomOccupancies::RoomNumber)
+              .to(Rooms::Table, Rooms::RoomNumber)
+              .on_delete(ForeignKeyAction::Cascade),
+          )
+          .foreign_key(
+            ForeignKey::create()
+              .name("fk_room_occupancies_user_id")
+              .from(RoomOccupancies::Table, RoomOccupancies::UserId)
+              .to(Users::Table, Users::Id)
+              .on_delete(ForeignKeyAction::Cascade),
+          )
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .create_index(
+        Index::create()
+          .name("idx_room_occupancies_room_number")
+          .table(RoomOccupancies::Table)
+          .col(RoomOccupancies::RoomNumber)
+          .col(RoomOccupancies::MovedInAt)
+          .to_owned(),
+      )
+      .await?;
+
+    // the current residents moved in when they joined the room
+    let db = manager.get_connection();
+    db.execute_unprepared(
+      r#"INSERT INTO "room_occupancies" ("room_number", "user_id", "role", "moved_in_at")
+        SELECT "room_number", "user_id", "role", "joined_at"
+        FROM "room_members" WHERE "joined_at" IS NOT NULL"#,
+    )
+    .await?;
+
+    Ok(())
+  }
+
+  async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .drop_table(
+        Table::drop()
+          .table(RoomOccupancies::Table)
+          .if_exists()
+          .to_owned(),
+      )
+      .await?;
+
+    Ok(())
+  }
+}
@@ -0,0 +1,123 @@
+use extension::postgres::Type;
+use sea_orm::{ActiveEnum, DbBackend, DeriveActiveEnum, EnumIter, Schema};
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20220101_000001_create_users_table::Users;
+use crate::m20240101_000003_create_rooms_table::Rooms;
+
+#[derive(EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "room_claim_status")]
+pub enum RoomClaimStatus {
+  #[sea_orm(string_value = "pending")]
+  Pending,
+  #[sea_orm(string_value = "approved")]
+  Approved,
+  #[sea_orm(string_value = "rejected")]
+  Rejected,
+}
+
+#[derive(DeriveIden)]
+pub enum RoomClaims {
+  Table,
+  Id,
+  UserId,
+  RoomNumber,
+  Status,
+  Reason,
+  ReviewedBy,
+  ReviewedAt,
+  CreatedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+  async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    let schema = Schema::new(DbBackend::Postgres);
+    manager
+      .create_type(schema.create_enum_from_active_enum::<RoomClaimStatus>())
+      .await?;
+
+    // a tenant registering for a room, which a manager approves or rejects
+    manager
+      .create_table(
+        Table::create()
+          .table(RoomClaims::Table)
+          .if_not_exists()
+          .col(pk_auto(RoomClaims::Id))
+          .col(integer(RoomClaims::UserId).not_null())
+          .col(integer(RoomClaims::RoomNumber).not_null())
+          .col(
+            ColumnDef::new(RoomClaims::Status)
+              .custom(RoomClaimStatus::name())
+              .not_null()
+              .default("pending"),
+          )
+          .col(text_null(RoomClaims::Reason))
+          .col(integer_null(RoomClaims::ReviewedBy))
+          .col(timestamp_null(RoomClaims::ReviewedAt))
+          .col(
+            timestamp(RoomClaims::CreatedAt)
+              .not_null()
+              .default(Expr::current_timestamp()),
+          )
+          .foreign_key(
+            ForeignKey::create()
+              .name("fk_room_claims_user_id")
+              .from(RoomClaims::Table, RoomClaims::UserId)
+              .to(Users::Table, Users::Id)
+              .on_delete(ForeignKeyAction::Cascade),
+          )
+          .foreign_key(
+            ForeignKey::create()
+              .name("fk_room_claims_room_number")
+              .from(RoomClaims::Table, RoomClaims::RoomNumber)
+              .to(Rooms::Table, Rooms::RoomNumber)
+              .on_delete(ForeignKeyAction::Cascade),
+          )
+          .foreign_key(
+            ForeignKey::create()
+              .name("fk_room_claims_reviewed_by")
+              .from(RoomClaims::Table, RoomClaims::ReviewedBy)
+              .to(Users::Table, Users::Id)
+              .on_delete(ForeignKeyAction::SetNull),
+          )
+          .to_owned(),
+      )
+      .await?;
+
+    // an account waits for at most one room at a time
+    let db = manager.get_connection();
+    db.execute_unprepared(
+      r#"CREATE UNIQUE INDEX "idx_room_claims_pending_user_id" ON "room_claims" ("user_id")
+        WHERE "status" = 'pending'"#,
+    )
+    .await?;
+
+    Ok(())
+  }
+
+  async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .drop_table(
+        Table::drop()
+          .table(RoomClaims::Table)
+          .if_exists()
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .drop_type(
+        Type::drop()
+          .name(RoomClaimStatus::name())
+          .if_exists()
+          .to_owned(),
+      )
+      .await?;
+
+    Ok(())
+  }
+}
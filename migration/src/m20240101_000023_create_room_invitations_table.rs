@@ -0,0 +1,103 @@
+use sea_orm::ActiveEnum;
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20220101_000001_create_users_table::Users;
+use crate::m20240101_000003_create_rooms_table::Rooms;
+use crate::m20240101_000020_create_room_members_table::RoomMemberRole;
+
+#[derive(DeriveIden)]
+pub enum RoomInvitations {
+  Table,
+  Id,
+  RoomNumber,
+  Role,
+  Email,
+  CreatedBy,
+  ExpiresAt,
+  UsedBy,
+  UsedAt,
+  RevokedAt,
+  CreatedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+  async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    // invitation links handed out by managers, each one registers a single account
+    manager
+      .create_table(
+        Table::create()
+          .table(RoomInvitations::Table)
+          .if_not_exists()
+          .col(pk_auto(RoomInvitations::Id))
+          .col(integer(RoomInvitations::RoomNumber).not_null())
+          .col(
+            ColumnDef::new(RoomInvitations::Role)
+              .custom(RoomMemberRole::name())
+              .not_null(),
+          )
+          .col(string_null(RoomInvitations::Email))
+          .col(integer_null(RoomInvitations::CreatedBy))
+          .col(timestamp(RoomInvitations::ExpiresAt).not_null())
+          .col(integer_null(RoomInvitations::UsedBy))
+          .col(timestamp_null(RoomInvitations::UsedAt))
+          .col(timestamp_null(RoomInvitations::RevokedAt))
+          .col(
+            timestamp(RoomInvitations::CreatedAt)
+              .not_null()
+              .default(Expr::current_timestamp()),
+          )
+          .foreign_key(
+            ForeignKey::create()
+              .name("fk_room_invitations_room_number")
+              .from(RoomInvitations::Table, RoomInvitations::RoomNumber)
+              .to(Rooms::Table, Rooms::RoomNumber)
+              .on_delete(ForeignKeyAction::Cascade),
+          )
+          .foreign_key(
+            ForeignKey::create()
+              .name("fk_room_invitations_created_by")
+              .from(RoomInvitations::Table, RoomInvitations::CreatedBy)
+              .to(Users::Table, Users::Id)
+              .on_delete(ForeignKeyAction::SetNull),
+          )
+          .foreign_key(
+            ForeignKey::create()
+              .name("fk_room_invitations_used_by")
+              .from(RoomInvitations::Table, RoomInvitations::UsedBy)
+              .to(Users::Table, Users::Id)
+              .on_delete(ForeignKeyAction::SetNull),
+          )
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .create_index(
+        Index::create()
+          .name("idx_room_invitations_room_number")
+          .table(RoomInvitations::Table)
+          .col(RoomInvitations::RoomNumber)
+          .to_owned(),
+      )
+      .await?;
+
+    Ok(())
+  }
+
+  async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .drop_table(
+        Table::drop()
+          .table(RoomInvitations::Table)
+          .if_exists()
+          .to_owned(),
+      )
+      .await?;
+
+    Ok(())
+  }
+}
@@ -0,0 +1,99 @@
+use extension::postgres::Type;
+use sea_orm::{ActiveEnum, DbBackend, DeriveActiveEnum, EnumIter, Schema};
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20240101_000009_create_family_table::Family;
+
+#[derive(EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "gender")]
+pub enum Gender {
+  #[sea_orm(string_value = "male")]
+  Male,
+  #[sea_orm(string_value = "female")]
+  Female,
+  #[sea_orm(string_value = "other")]
+  Other,
+}
+
+#[derive(DeriveIden)]
+enum FamilyExt {
+  Relationship,
+  Gender,
+  NationalId,
+  Occupation,
+  Phone,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+  async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    let schema = Schema::new(DbBackend::Postgres);
+    manager
+      .create_type(schema.create_enum_from_active_enum::<Gender>())
+      .await?;
+
+    manager
+      .alter_table(
+        Table::alter()
+          .table(Family::Table)
+          .add_column(string_null(FamilyExt::Relationship))
+          .add_column(
+            ColumnDef::new(FamilyExt::Gender)
+              .custom(Gender::name())
+              .null(),
+          )
+          .add_column(string_null(FamilyExt::NationalId))
+          .add_column(string_null(FamilyExt::Occupation))
+          .add_column(string_null(FamilyExt::Phone))
+          .to_owned(),
+      )
+      .await?;
+
+    // a national ID (CCCD) belongs to one person in the whole building
+    manager
+      .create_index(
+        Index::create()
+          .name("idx_family_national_id")
+          .table(Family::Table)
+          .col(FamilyExt::NationalId)
+          .unique()
+          .to_owned(),
+      )
+      .await?;
+
+    Ok(())
+  }
+
+  async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .drop_index(
+        Index::drop()
+          .name("idx_family_national_id")
+          .table(Family::Table)
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .alter_table(
+        Table::alter()
+          .table(Family::Table)
+          .drop_column(FamilyExt::Relationship)
+          .drop_column(FamilyExt::Gender)
+          .drop_column(FamilyExt::NationalId)
+          .drop_column(FamilyExt::Occupation)
+          .drop_column(FamilyExt::Phone)
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .drop_type(Type::drop().name(Gender::name()).if_exists().to_owned())
+      .await?;
+
+    Ok(())
+  }
+}
@@ -0,0 +1,200 @@
+use extension::postgres::Type;
+use sea_orm::{ActiveEnum, DbBackend, DeriveActiveEnum, EnumIter, Schema};
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20220101_000001_create_users_table::Users;
+use crate::m20240101_000003_create_rooms_table::Rooms;
+use crate::m20240101_000009_create_family_table::Family;
+use crate::m20240101_000024_add_details_to_family::Gender;
+
+#[derive(EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "declaration_kind")]
+pub enum DeclarationKind {
+  #[sea_orm(string_value = "temporary_residence")]
+  TemporaryResidence,
+  #[sea_orm(string_value = "temporary_absence")]
+  TemporaryAbsence,
+}
+
+#[derive(EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "declaration_status")]
+pub enum DeclarationStatus {
+  #[sea_orm(string_value = "pending")]
+  Pending,
+  #[sea_orm(string_value = "approved")]
+  Approved,
+  #[sea_orm(string_value = "rejected")]
+  Rejected,
+}
+
+#[derive(DeriveIden)]
+pub enum ResidenceDeclarations {
+  Table,
+  Id,
+  RoomNumber,
+  DeclaredBy,
+  FamilyId,
+  FullName,
+  Birthday,
+  Gender,
+  NationalId,
+  Relationship,
+  Kind,
+  StartDate,
+  EndDate,
+  Reason,
+  Address,
+  Status,
+  ReviewNote,
+  ReviewedBy,
+  ReviewedAt,
+  ExpiryNotifiedAt,
+  CreatedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+  async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    let schema = Schema::new(DbBackend::Postgres);
+    manager
+      .create_type(schema.create_enum_from_active_enum::<DeclarationKind>())
+      .await?;
+    manager
+      .create_type(schema.create_enum_from_active_enum::<DeclarationStatus>())
+      .await?;
+
+    // tạm trú / tạm vắng of a family member, or tạm trú of a guest who is not in `family`. The
+    // person is copied as declared, the register must not change when `family` is edited later
+    manager
+      .create_table(
+        Table::create()
+          .table(ResidenceDeclarations::Table)
+          .if_not_exists()
+          .col(pk_auto(ResidenceDeclarations::Id))
+          .col(integer(ResidenceDeclarations::RoomNumber).not_null())
+          .col(integer(ResidenceDeclarations::DeclaredBy).not_null())
+          .col(integer_null(ResidenceDeclarations::FamilyId))
+          .col(string(ResidenceDeclarations::FullName).not_null())
+          .col(date_null(ResidenceDeclarations::Birthday))
+          .col(
+            ColumnDef::new(ResidenceDeclarations::Gender)
+              .custom(Gender::name())
+              .null(),
+          )
+          .col(string_null(ResidenceDeclarations::NationalId))
+          .col(string_null(ResidenceDeclarations::Relationship))
+          .col(
+            ColumnDef::new(ResidenceDeclarations::Kind)
+              .custom(DeclarationKind::name())
+              .not_null(),
+          )
+          .col(date(ResidenceDeclarations::StartDate).not_null())
+          .col(date(ResidenceDeclarations::EndDate).not_null())
+          .col(text_null(ResidenceDeclarations::Reason))
+          .col(string_null(ResidenceDeclarations::Address))
+          .col(
+            ColumnDef::new(ResidenceDeclarations::Status)
+              .custom(DeclarationStatus::name())
+              .not_null()
+              .default("pending"),
+          )
+          .col(text_null(ResidenceDeclarations::ReviewNote))
+          .col(integer_null(ResidenceDeclarations::ReviewedBy))
+          .col(timestamp_null(ResidenceDeclarations::ReviewedAt))
+          .col(timestamp_null(ResidenceDeclarations::ExpiryNotifiedAt))
+          .col(
+            timestamp(ResidenceDeclarations::CreatedAt)
+              .not_null()
+              .default(Expr::current_timestamp()),
+          )
+          .foreign_key(
+            ForeignKey::create()
+              .name("fk_residence_declarations_room_number")
+              .from(
+                ResidenceDeclarations::Table,
+                ResidenceDeclarations::RoomNumber,
+              )
+              .to(Rooms::Table, Rooms::RoomNumber)
+              .on_delete(ForeignKeyAction::Cascade),
+          )
+          .foreign_key(
+            ForeignKey::create()
+              .name("fk_residence_declarations_declared_by")
+              .from(
+                ResidenceDeclarations::Table,
+                ResidenceDeclarations::DeclaredBy,
+              )
+              .to(Users::Table, Users::Id)
+              .on_delete(ForeignKeyAction::Cascade),
+          )
+          .foreign_key(
+            ForeignKey::create()
+              .name("fk_residence_declarations_family_id")
+              .from(
+                ResidenceDeclarations::Table,
+                ResidenceDeclarations::FamilyId,
+              )
+              .to(Family::Table, Family::Id)
+              .on_delete(ForeignKeyAction::SetNull),
+          )
+          .foreign_key(
+            ForeignKey::create()
+              .name("fk_residence_declarations_reviewed_by")
+              .from(
+                ResidenceDeclarations::Table,
+                ResidenceDeclarations::ReviewedBy,
+              )
+              .to(Users::Table, Users::Id)
+              .on_delete(ForeignKeyAction::SetNull),
+          )
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .create_index(
+        Index::create()
+          .name("idx_residence_declarations_end_date")
+          .table(ResidenceDeclarations::Table)
+          .col(ResidenceDeclarations::Status)
+          .col(ResidenceDeclarations::EndDate)
+          .to_owned(),
+      )
+      .await?;
+
+    Ok(())
+  }
+
+  async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .drop_table(
+        Table::drop()
+          .table(ResidenceDeclarations::Table)
+          .if_exists()
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .drop_type(
+        Type::drop()
+          .name(DeclarationStatus::name())
+          .if_exists()
+          .to_owned(),
+      )
+      .await?;
+    manager
+      .drop_type(
+        Type::drop()
+          .name(DeclarationKind::name())
+          .if_exists()
+          .to_owned(),
+      )
+      .await?;
+
+    Ok(())
+  }
+}
@@ -0,0 +1,175 @@
+use extension::postgres::Type;
+use sea_orm::{ActiveEnum, DbBackend, DeriveActiveEnum, EnumIter, Schema};
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20220101_000001_create_users_table::Users;
+use crate::m20240101_000003_create_rooms_table::Rooms;
+
+#[derive(EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "vehicle_type")]
+pub enum VehicleType {
+  #[sea_orm(string_value = "bicycle")]
+  Bicycle,
+  #[sea_orm(string_value = "electric_bicycle")]
+  ElectricBicycle,
+  #[sea_orm(string_value = "motorbike")]
+  Motorbike,
+  #[sea_orm(string_value = "car")]
+  Car,
+}
+
+#[derive(EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "vehicle_status")]
+pub enum VehicleStatus {
+  #[sea_orm(string_value = "pending")]
+  Pending,
+  #[sea_orm(string_value = "approved")]
+  Approved,
+  #[sea_orm(string_value = "rejected")]
+  Rejected,
+  #[sea_orm(string_value = "removed")]
+  Removed,
+}
+
+#[derive(DeriveIden)]
+pub enum Vehicles {
+  Table,
+  Id,
+  RoomNumber,
+  RegisteredBy,
+  VehicleType,
+  PlateNumber,
+  Brand,
+  Color,
+  ParkingSlot,
+  Status,
+  ReviewNote,
+  ReviewedBy,
+  ReviewedAt,
+  RemovedAt,
+  CreatedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+  async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    let schema = Schema::new(DbBackend::Postgres);
+    manager
+      .create_type(schema.create_enum_from_active_enum::<VehicleType>())
+      .await?;
+    manager
+      .create_type(schema.create_enum_from_active_enum::<VehicleStatus>())
+      .await?;
+
+    // removed vehicles are kept, so that past parking fees can still be traced to them
+    manager
+      .create_table(
+        Table::create()
+          .table(Vehicles::Table)
+          .if_not_exists()
+          .col(pk_auto(Vehicles::Id))
+          .col(integer(Vehicles::RoomNumber).not_null())
+          .col(integer_null(Vehicles::RegisteredBy))
+          .col(
+            ColumnDef::new(Vehicles::VehicleType)
+              .custom(VehicleType::name())
+              .not_null(),
+          )
+          .col(string_null(Vehicles::PlateNumber))
+          .col(string_null(Vehicles::Brand))
+          .col(string_null(Vehicles::Color))
+          .col(string_null(Vehicles::ParkingSlot))
+          .col(
+            ColumnDef::new(Vehicles::Status)
+              .custom(VehicleStatus::name())
+              .not_null()
+              .default("pending"),
+          )
+          .col(text_null(Vehicles::ReviewNote))
+          .col(integer_null(Vehicles::ReviewedBy))
+          .col(timestamp_null(Vehicles::ReviewedAt))
+          .col(timestamp_null(Vehicles::RemovedAt))
+          .col(
+            timestamp(Vehicles::CreatedAt)
+              .not_null()
+              .default(Expr::current_timestamp()),
+          )
+          .foreign_key(
+            ForeignKey::create()
+              .name("fk_vehicles_room_number")
+              .from(Vehicles::Table, Vehicles::RoomNumber)
+              .to(Rooms::Table, Rooms::RoomNumber)
+              .on_delete(ForeignKeyAction::Cascade),
+          )
+          .foreign_key(
+            ForeignKey::create()
+              .name("fk_vehicles_registered_by")
+              .from(Vehicles::Table, Vehicles::RegisteredBy)
+              .to(Users::Table, Users::Id)
+              .on_delete(ForeignKeyAction::SetNull),
+          )
+          .foreign_key(
+            ForeignKey::create()
+              .name("fk_vehicles_reviewed_by")
+              .from(Vehicles::Table, Vehicles::ReviewedBy)
+              .to(Users::Table, Users::Id)
+              .on_delete(ForeignKeyAction::SetNull),
+          )
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .create_index(
+        Index::create()
+          .name("idx_vehicles_room_number")
+          .table(Vehicles::Table)
+          .col(Vehicles::RoomNumber)
+          .to_owned(),
+      )
+      .await?;
+
+    // a plate is registered once, and a parking slot holds one vehicle
+    let db = manager.get_connection();
+    db.execute_unprepared(
+      r#"CREATE UNIQUE INDEX "idx_vehicles_active_plate_number" ON "vehicles" ("plate_number")
+        WHERE "status" IN ('pending', 'approved')"#,
+    )
+    .await?;
+    db.execute_unprepared(
+      r#"CREATE UNIQUE INDEX "idx_vehicles_approved_parking_slot" ON "vehicles" ("parking_slot")
+        WHERE "status" = 'approved'"#,
+    )
+    .await?;
+
+    Ok(())
+  }
+
+  async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .drop_table(Table::drop().table(Vehicles::Table).if_exists().to_owned())
+      .await?;
+
+    manager
+      .drop_type(
+        Type::drop()
+          .name(VehicleStatus::name())
+          .if_exists()
+          .to_owned(),
+      )
+      .await?;
+    manager
+      .drop_type(
+        Type::drop()
+          .name(VehicleType::name())
+          .if_exists()
+          .to_owned(),
+      )
+      .await?;
+
+    Ok(())
+  }
+}
@@ -0,0 +1,61 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20240101_000007_create_notifications_table::Notifications;
+
+#[derive(DeriveIden)]
+enum NotificationsExt {
+  ReadAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+  async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .alter_table(
+        Table::alter()
+          .table(Notifications::Table)
+          .add_column(timestamp_null(NotificationsExt::ReadAt))
+          .to_owned(),
+      )
+      .await?;
+
+    // the notifications of a user are paged from the newest one
+    manager
+      .create_index(
+        Index::create()
+          .name("idx_notifications_to_user_id")
+          .table(Notifications::Table)
+          .col(Notifications::ToUser)
+          .col(Notifications::Id)
+          .to_owned(),
+      )
+      .await?;
+
+    Ok(())
+  }
+
+  async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .drop_index(
+        Index::drop()
+          .name("idx_notifications_to_user_id")
+          .table(Notifications::Table)
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .alter_table(
+        Table::alter()
+          .table(Notifications::Table)
+          .drop_column(NotificationsExt::ReadAt)
+          .to_owned(),
+      )
+      .await?;
+
+    Ok(())
+  }
+}
@@ -0,0 +1,182 @@
+use extension::postgres::Type;
+use sea_orm::{ActiveEnum, DbBackend, DeriveActiveEnum, EnumIter, Schema};
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20220101_000001_create_users_table::Users;
+use crate::m20240101_000007_create_notifications_table::Notifications;
+
+#[derive(EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "delivery_status")]
+pub enum DeliveryStatus {
+  #[sea_orm(string_value = "queued")]
+  Queued,
+  #[sea_orm(string_value = "sent")]
+  Sent,
+  #[sea_orm(string_value = "failed")]
+  Failed,
+}
+
+#[derive(DeriveIden)]
+pub enum NotificationPreferences {
+  Table,
+  UserId,
+  Email,
+  Sms,
+  UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+pub enum NotificationDeliveries {
+  Table,
+  Id,
+  NotificationId,
+  Channel,
+  Status,
+  Attempts,
+  LastError,
+  NextAttemptAt,
+  SentAt,
+  CreatedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+  async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    let schema = Schema::new(DbBackend::Postgres);
+    manager
+      .create_type(schema.create_enum_from_active_enum::<DeliveryStatus>())
+      .await?;
+
+    // users without preferences get the defaults: email on, SMS off
+    manager
+      .create_table(
+        Table::create()
+          .table(NotificationPreferences::Table)
+          .if_not_exists()
+          .col(
+            integer(NotificationPreferences::UserId)
+              .not_null()
+              .primary_key(),
+          )
+          .col(
+            boolean(NotificationPreferences::Email)
+              .not_null()
+              .default(true),
+          )
+          .col(
+            boolean(NotificationPreferences::Sms)
+              .not_null()
+              .default(false),
+          )
+          .col(
+            timestamp(NotificationPreferences::UpdatedAt)
+              .not_null()
+              .default(Expr::current_timestamp()),
+          )
+          .foreign_key(
+            ForeignKey::create()
+              .name("fk_notification_preferences_user_id")
+              .from(
+                NotificationPreferences::Table,
+                NotificationPreferences::UserId,
+              )
+              .to(Users::Table, Users::Id)
+              .on_delete(ForeignKeyAction::Cascade),
+          )
+          .to_owned(),
+      )
+      .await?;
+
+    // one row per notification and external channel, retried until sent or out of attempts
+    manager
+      .create_table(
+        Table::create()
+          .table(NotificationDeliveries::Table)
+          .if_not_exists()
+          .col(pk_auto(NotificationDeliveries::Id))
+          .col(integer(NotificationDeliveries::NotificationId).not_null())
+          .col(string(NotificationDeliveries::Channel).not_null())
+          .col(
+            ColumnDef::new(NotificationDeliveries::Status)
+              .custom(DeliveryStatus::name())
+              .not_null()
+              .default("queued"),
+          )
+          .col(
+            integer(NotificationDeliveries::Attempts)
+              .not_null()
+              .default(0),
+          )
+          .col(text_null(NotificationDeliveries::LastError))
+          .col(
+            timestamp(NotificationDeliveries::NextAttemptAt)
+              .not_null()
+              .default(Expr::current_timestamp()),
+          )
+          .col(timestamp_null(NotificationDeliveries::SentAt))
+          .col(
+            timestamp(NotificationDeliveries::CreatedAt)
+              .not_null()
+              .default(Expr::current_timestamp()),
+          )
+          .foreign_key(
+            ForeignKey::create()
+              .name("fk_notification_deliveries_notification_id")
+              .from(
+                NotificationDeliveries::Table,
+                NotificationDeliveries::NotificationId,
+              )
+              .to(Notifications::Table, Notifications::Id)
+              .on_delete(ForeignKeyAction::Cascade),
+          )
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .create_index(
+        Index::create()
+          .name("idx_notification_deliveries_due")
+          .table(NotificationDeliveries::Table)
+          .col(NotificationDeliveries::Status)
+          .col(NotificationDeliveries::NextAttemptAt)
+          .to_owned(),
+      )
+      .await?;
+
+    Ok(())
+  }
+
+  async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .drop_table(
+        Table::drop()
+          .table(NotificationDeliveries::Table)
+          .if_exists()
+          .to_owned(),
+      )
+      .await?;
+    manager
+      .drop_table(
+        Table::drop()
+          .table(NotificationPreferences::Table)
+          .if_exists()
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .drop_type(
+        Type::drop()
+          .name(DeliveryStatus::name())
+          .if_exists()
+          .to_owned(),
+      )
+      .await?;
+
+    Ok(())
+  }
+}
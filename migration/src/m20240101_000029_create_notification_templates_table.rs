@@ -0,0 +1,159 @@
+use extension::postgres::Type;
+use sea_orm::{ActiveEnum, DbBackend, DeriveActiveEnum, EnumIter, Schema};
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20220101_000001_create_users_table::Users;
+use crate::m20240101_000028_create_notification_delivery_tables::NotificationPreferences;
+
+#[derive(EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "notification_event")]
+pub enum NotificationEvent {
+  #[sea_orm(string_value = "fee_assigned")]
+  FeeAssigned,
+  #[sea_orm(string_value = "payment_reminder_upcoming")]
+  PaymentReminderUpcoming,
+  #[sea_orm(string_value = "payment_reminder_due")]
+  PaymentReminderDue,
+  #[sea_orm(string_value = "payment_reminder_overdue")]
+  PaymentReminderOverdue,
+  #[sea_orm(string_value = "room_invitation")]
+  RoomInvitation,
+  #[sea_orm(string_value = "room_claim_approved")]
+  RoomClaimApproved,
+  #[sea_orm(string_value = "room_claim_rejected")]
+  RoomClaimRejected,
+  #[sea_orm(string_value = "declaration_approved")]
+  DeclarationApproved,
+  #[sea_orm(string_value = "declaration_rejected")]
+  DeclarationRejected,
+  #[sea_orm(string_value = "declaration_expiring")]
+  DeclarationExpiring,
+  #[sea_orm(string_value = "vehicle_approved")]
+  VehicleApproved,
+  #[sea_orm(string_value = "vehicle_rejected")]
+  VehicleRejected,
+}
+
+#[derive(DeriveIden)]
+pub enum NotificationTemplates {
+  Table,
+  Id,
+  Event,
+  Language,
+  Title,
+  Body,
+  UpdatedBy,
+  UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum NotificationPreferencesExt {
+  Language,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+  async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    let schema = Schema::new(DbBackend::Postgres);
+    manager
+      .create_type(schema.create_enum_from_active_enum::<NotificationEvent>())
+      .await?;
+
+    // templates edited by the managers, the built-in ones are used for the others
+    manager
+      .create_table(
+        Table::create()
+          .table(NotificationTemplates::Table)
+          .if_not_exists()
+          .col(pk_auto(NotificationTemplates::Id))
+          .col(
+            ColumnDef::new(NotificationTemplates::Event)
+              .custom(NotificationEvent::name())
+              .not_null(),
+          )
+          .col(string(NotificationTemplates::Language).not_null())
+          .col(text(NotificationTemplates::Title).not_null())
+          .col(text(NotificationTemplates::Body).not_null())
+          .col(integer_null(NotificationTemplates::UpdatedBy))
+          .col(
+            timestamp(NotificationTemplates::UpdatedAt)
+              .not_null()
+              .default(Expr::current_timestamp()),
+          )
+          .foreign_key(
+            ForeignKey::create()
+              .name("fk_notification_templates_updated_by")
+              .from(
+                NotificationTemplates::Table,
+                NotificationTemplates::UpdatedBy,
+              )
+              .to(Users::Table, Users::Id)
+              .on_delete(ForeignKeyAction::SetNull),
+          )
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .create_index(
+        Index::create()
+          .table(NotificationTemplates::Table)
+          .name("unique_notification_templates_event_language")
+          .unique()
+          .col(NotificationTemplates::Event)
+          .col(NotificationTemplates::Language)
+          .to_owned(),
+      )
+      .await?;
+
+    // notifications are rendered in the language of the recipient
+    manager
+      .alter_table(
+        Table::alter()
+          .table(NotificationPreferences::Table)
+          .add_column(
+            string(NotificationPreferencesExt::Language)
+              .not_null()
+              .default("vi"),
+          )
+          .to_owned(),
+      )
+      .await?;
+
+    Ok(())
+  }
+
+  async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .alter_table(
+        Table::alter()
+          .table(NotificationPreferences::Table)
+          .drop_column(NotificationPreferencesExt::Language)
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .drop_table(
+        Table::drop()
+          .table(NotificationTemplates::Table)
+          .if_exists()
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .drop_type(
+        Type::drop()
+          .name(NotificationEvent::name())
+          .if_exists()
+          .to_owned(),
+      )
+      .await?;
+
+    Ok(())
+  }
+}
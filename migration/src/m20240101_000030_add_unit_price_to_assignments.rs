@@ -0,0 +1,41 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20240101_000005_create_fees_room_table::FeesRoomAssignment;
+
+#[derive(DeriveIden)]
+enum FeesRoomAssignmentExt {
+  UnitPrice,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+  async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    // the price per unit a room billed per unit was charged, e.g. per vehicle
+    manager
+      .alter_table(
+        Table::alter()
+          .table(FeesRoomAssignment::Table)
+          .add_column(big_integer_null(FeesRoomAssignmentExt::UnitPrice))
+          .to_owned(),
+      )
+      .await?;
+
+    Ok(())
+  }
+
+  async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .alter_table(
+        Table::alter()
+          .table(FeesRoomAssignment::Table)
+          .drop_column(FeesRoomAssignmentExt::UnitPrice)
+          .to_owned(),
+      )
+      .await?;
+
+    Ok(())
+  }
+}
@@ -0,0 +1,54 @@
+use sea_orm::ActiveEnum;
+use sea_orm_migration::prelude::*;
+
+use crate::m20240101_000016_create_fee_versions_table::FeeVersions;
+use crate::m20240101_000018_add_category_and_tags_to_fees::FeeCategory;
+
+#[derive(DeriveIden)]
+enum FeeVersionsExt {
+  Category,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+  async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .alter_table(
+        Table::alter()
+          .table(FeeVersions::Table)
+          .add_column(
+            ColumnDef::new(FeeVersionsExt::Category)
+              .custom(FeeCategory::name())
+              .not_null()
+              .default("service"),
+          )
+          .to_owned(),
+      )
+      .await?;
+
+    // the category was not kept before, the current one is the best guess
+    let db = manager.get_connection();
+    db.execute_unprepared(
+      "UPDATE fee_versions SET category = fees.category FROM fees WHERE fees.id = fee_versions.fee_id",
+    )
+    .await?;
+
+    Ok(())
+  }
+
+  async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .alter_table(
+        Table::alter()
+          .table(FeeVersions::Table)
+          .drop_column(FeeVersionsExt::Category)
+          .to_owned(),
+      )
+      .await?;
+
+    Ok(())
+  }
+}
@@ -0,0 +1,45 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20240101_000028_create_notification_delivery_tables::NotificationPreferences;
+
+#[derive(DeriveIden)]
+enum NotificationPreferencesExt {
+  Webhook,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+  async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    // the webhook channel was always used before, so users stay opted in
+    manager
+      .alter_table(
+        Table::alter()
+          .table(NotificationPreferences::Table)
+          .add_column(
+            boolean(NotificationPreferencesExt::Webhook)
+              .not_null()
+              .default(true),
+          )
+          .to_owned(),
+      )
+      .await?;
+
+    Ok(())
+  }
+
+  async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .alter_table(
+        Table::alter()
+          .table(NotificationPreferences::Table)
+          .drop_column(NotificationPreferencesExt::Webhook)
+          .to_owned(),
+      )
+      .await?;
+
+    Ok(())
+  }
+}
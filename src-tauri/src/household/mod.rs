@@ -14,6 +14,8 @@ pub struct FeesRoomInfo {
   due_date: NaiveDateTime,
   payment_date: Option<NaiveDateTime>,
   is_paid: bool,
+  #[serde(default)]
+  overdue: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
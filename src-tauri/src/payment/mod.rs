@@ -12,7 +12,7 @@ pub async fn check_payment<R: Runtime>(app: tauri::AppHandle<R>, id: i32) -> Res
   let jwt_access_token = state.access_token.clone().ok_or("Not logged in")?;
 
   let response = client
-    .get(&format!("{}/webhook/payment/{id}", server_url))
+    .get(&format!("{}/payment/{id}", server_url))
     .bearer_auth(jwt_access_token)
     .send()
     .await
@@ -204,21 +204,36 @@ pub async fn get_notifications<R: Runtime>(
   let access_token = state.access_token.clone().ok_or("Not logged in")?;
   let client = &state.client;
 
-  let response: Vec<Notification> = client
-    .get(&format!("{}/user/notifications", server_url))
-    .bearer_auth(access_token)
-    .send()
-    .await
-    .map_err(|e| {
+  // the server returns the notifications a page at a time, newest first
+  let mut response: Vec<Notification> = Vec::new();
+  let mut cursor: Option<String> = None;
+  loop {
+    let mut request = client
+      .get(&format!("{}/user/notifications", server_url))
+      .bearer_auth(&access_token);
+    if let Some(cursor) = &cursor {
+      request = request.query(&[("cursor", cursor)]);
+    }
+    let page = request.send().await.map_err(|e| {
       log::error!("Failed to send get notifications request: {}", e);
       "Failed to send get notifications request".to_string()
-    })?
-    .json()
-    .await
-    .map_err(|e| {
+    })?;
+
+    cursor = page
+      .headers()
+      .get("x-next-cursor")
+      .and_then(|cursor| cursor.to_str().ok())
+      .map(|cursor| cursor.to_string());
+    let notifications: Vec<Notification> = page.json().await.map_err(|e| {
       log::error!("Failed to parse get notifications response: {}", e);
       "Failed to parse get notifications response".to_string()
     })?;
+    response.extend(notifications);
+
+    if cursor.is_none() {
+      break;
+    }
+  }
 
   log::debug!("Notifications: {:?}", &response);
 
@@ -41,6 +41,7 @@ export type FeesRoomInfo = {
   due_date: string;
   payment_date?: string;
   is_paid: boolean;
+  overdue: boolean;
 };
 
 export type PersonalHouseholdInfo = {